@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use idiff::bench;
+
+/// Image sizes (width & height) used for the benchmarks.
+const SIZES: [u32; 3] = [256, 1024, 2048];
+
+/// Block size used for the benchmarks.
+const BLOCK: u32 = 10;
+
+/// Create a pair of images differing in every 7th pixel of every 5th row.
+fn image_pair(size: u32) -> (image::RgbaImage, image::RgbaImage) {
+    let src = image::RgbaImage::from_fn(size, size, |x, y| {
+        image::Rgba([(x % 256) as u8, (y % 256) as u8, 0, 255])
+    });
+    let mut tgt = src.clone();
+    for y in (0..size).step_by(5) {
+        for x in (0..size).step_by(7) {
+            *tgt.get_pixel_mut(x, y) = image::Rgba([255, 255, 255, 255]);
+        }
+    }
+    (src, tgt)
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for size in SIZES {
+        let (src, _) = image_pair(size);
+        let mut png = Vec::new();
+        src.write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+
+        group.throughput(Throughput::Elements(size as u64 * size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &png, |b, png| {
+            b.iter(|| bench::decode(png))
+        });
+    }
+    group.finish();
+}
+
+fn block_difference(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block_difference");
+    for size in SIZES {
+        let (src, tgt) = image_pair(size);
+
+        group.throughput(Throughput::Elements(size as u64 * size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &(src, tgt),
+            |b, (src, tgt)| b.iter(|| bench::block_difference(src, tgt, BLOCK)),
+        );
+    }
+    group.finish();
+}
+
+fn highlight(c: &mut Criterion) {
+    let mut group = c.benchmark_group("highlight");
+    for size in SIZES {
+        let (_, tgt) = image_pair(size);
+
+        group.throughput(Throughput::Elements(size as u64 * size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &tgt, |b, tgt| {
+            b.iter(|| bench::highlight_all(&mut tgt.clone(), BLOCK))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, block_difference, highlight);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::exit::Error;
+
+/// Separator between the archive and the path of the file within the archive (for ex. `baseline.zip::images/a.png`).
+pub(crate) const SEPARATOR: &str = "::";
+
+/// Represents the supported archive formats.
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Zip,
+    Tar,
+}
+
+impl Kind {
+    /// Get the archive format from the extension of the path.
+    fn from_path(path: &Path) -> Option<Kind> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "zip" => Some(Kind::Zip),
+            "tar" => Some(Kind::Tar),
+            _ => None,
+        }
+    }
+}
+
+/// Checks if the path is a (zip / tar) archive.
+pub(crate) fn is_archive(path: &Path) -> bool {
+    Kind::from_path(path).is_some() && path.is_file()
+}
+
+/// Split the path into the archive & the path of the file within the archive (for ex. `baseline.zip::images/a.png`).
+pub(crate) fn split(path: &Path) -> Option<(&Path, &str)> {
+    let (archive, member) = path.to_str()?.split_once(SEPARATOR)?;
+    Kind::from_path(Path::new(archive))?;
+    Some((Path::new(archive), member))
+}
+
+/// Read the file within the archive.
+pub(crate) fn read(archive: &Path, member: &str) -> Result<Vec<u8>, Error> {
+    let error = || {
+        Error::io(format!(
+            "Encountered error while reading {}{}{}.",
+            archive.display(),
+            SEPARATOR,
+            member
+        ))
+    };
+    let file = std::fs::File::open(archive).map_err(|_| error())?;
+
+    let mut data = Vec::new();
+    match Kind::from_path(archive) {
+        Some(Kind::Zip) => {
+            let mut zip = zip::ZipArchive::new(file).map_err(|_| error())?;
+            let mut entry = zip.by_name(member).map_err(|_| error())?;
+            entry.read_to_end(&mut data).map_err(|_| error())?;
+        }
+        Some(Kind::Tar) => {
+            let mut tar = tar::Archive::new(file);
+            let mut entry = tar
+                .entries()
+                .map_err(|_| error())?
+                .flatten()
+                .find(|e| e.path().is_ok_and(|p| p == Path::new(member)))
+                .ok_or_else(error)?;
+            entry.read_to_end(&mut data).map_err(|_| error())?;
+        }
+        None => return Err(error()),
+    }
+
+    Ok(data)
+}
+
+/// List the paths of the files within the archive, sorted by path.
+pub(crate) fn list(archive: &Path) -> Result<Vec<String>, Error> {
+    let error = || {
+        Error::io(format!(
+            "Encountered error while reading {}.",
+            archive.display()
+        ))
+    };
+    let file = std::fs::File::open(archive).map_err(|_| error())?;
+
+    let mut members = Vec::new();
+    match Kind::from_path(archive) {
+        Some(Kind::Zip) => {
+            let zip = zip::ZipArchive::new(file).map_err(|_| error())?;
+            members.extend(
+                zip.file_names()
+                    .filter(|name| !name.ends_with('/'))
+                    .map(String::from),
+            );
+        }
+        Some(Kind::Tar) => {
+            let mut tar = tar::Archive::new(file);
+            for entry in tar.entries().map_err(|_| error())?.flatten() {
+                if entry.header().entry_type().is_file() {
+                    if let Ok(path) = entry.path() {
+                        members.push(path.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        None => return Err(error()),
+    }
+    members.sort();
+
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_split_archive_paths() {
+        assert_eq!(
+            Some((Path::new("/tmp/baseline.zip"), "images/a.png")),
+            split(Path::new("/tmp/baseline.zip::images/a.png"))
+        );
+        assert_eq!(
+            Some((Path::new("baseline.TAR"), "a.png")),
+            split(Path::new("baseline.TAR::a.png"))
+        );
+    }
+
+    #[test]
+    fn should_not_split_other_paths() {
+        assert_eq!(None, split(Path::new("/tmp/baseline.zip")));
+        assert_eq!(None, split(Path::new("/tmp/baseline.png::a.png")));
+    }
+}
@@ -0,0 +1,268 @@
+//! Content-addressable storage of the baselines (golden files): every file is stored by the hash of its content,
+//! and a manifest (`NAME = "HASH"`, toml) records the hashes of the baselines, so that the manifest is versioned
+//! with the tests while the baselines themselves are fetched from the store.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ring::digest;
+
+use crate::exit::Error;
+
+/// Represents a store of the baselines, by the hash of their content.
+pub(crate) trait Store {
+    /// Checks if the content is stored.
+    fn contains(&self, hash: &str) -> Result<bool, Error>;
+    /// Store the content.
+    fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error>;
+    /// Retrieve the content.
+    fn get(&self, hash: &str) -> Result<Vec<u8>, Error>;
+}
+
+/// Represents a store in a directory: the content is stored as `HH/HASH`, by the first 2 characters of its hash.
+struct DirStore(PathBuf);
+
+impl DirStore {
+    fn path(&self, hash: &str) -> PathBuf {
+        self.0.join(&hash[..2]).join(hash)
+    }
+}
+
+impl Store for DirStore {
+    fn contains(&self, hash: &str) -> Result<bool, Error> {
+        Ok(self.path(hash).is_file())
+    }
+
+    fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        let path = self.path(hash);
+        let error = |e: std::io::Error| {
+            Error::io(format!(
+                "Encountered error while storing {}: {}",
+                path.display(),
+                e
+            ))
+        };
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(error)?;
+        // Note: written under a temporary name first, so that an interrupted write is never taken for the content
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, data).map_err(error)?;
+        std::fs::rename(&partial, &path).map_err(error)
+    }
+
+    fn get(&self, hash: &str) -> Result<Vec<u8>, Error> {
+        let path = self.path(hash);
+        std::fs::read(&path).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while reading {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// Open the store: a directory (created, if it does not exist), or an s3 bucket (`s3://BUCKET/PREFIX`, with the
+/// `s3` feature).
+pub(crate) fn open(store: &str) -> Result<Box<dyn Store>, Error> {
+    match store.strip_prefix("s3://") {
+        #[cfg(feature = "s3")]
+        Some(location) => Ok(Box::new(crate::s3::S3Store::new(location)?)),
+        #[cfg(not(feature = "s3"))]
+        Some(_) => Err(Error::from("s3 baseline stores require the 's3' feature.")),
+        None => Ok(Box::new(DirStore(PathBuf::from(store)))),
+    }
+}
+
+/// Content hash of the file (SHA-256, so that distinct baselines cannot collide in a shared store).
+fn hash(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+/// Lowercase hexadecimal digits of the bytes.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Represents the hashes of the baselines, by their file name.
+pub(crate) type Manifest = BTreeMap<String, String>;
+
+/// Read the manifest.
+pub(crate) fn read_manifest(path: &Path) -> Result<Manifest, Error> {
+    let error = |e: String| {
+        Error::io(format!(
+            "Encountered error while reading manifest {}: {}",
+            path.display(),
+            e
+        ))
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| error(e.message().to_string()))?;
+    table
+        .into_iter()
+        .map(|(name, value)| match value {
+            toml::Value::String(hash) if is_hash(&hash) => Ok((name, hash)),
+            _ => Err(error(format!(
+                "'{}' must be the hash of the baseline",
+                name
+            ))),
+        })
+        .collect()
+}
+
+/// Write the manifest.
+pub(crate) fn write_manifest(path: &Path, manifest: &Manifest) -> Result<(), Error> {
+    let table: toml::Table = manifest
+        .iter()
+        .map(|(name, hash)| (name.clone(), toml::Value::String(hash.clone())))
+        .collect();
+    std::fs::write(path, table.to_string()).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while writing manifest {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Checks if the text is a content hash (64 lowercase hexadecimal digits).
+fn is_hash(text: &str) -> bool {
+    text.len() == 64
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+/// Store the files (not stored yet).
+///
+/// Returns the manifest of the files, & the names of the newly stored ones.
+pub(crate) fn push(
+    files: &[(String, PathBuf)],
+    store: &dyn Store,
+) -> Result<(Manifest, Vec<String>), Error> {
+    let mut manifest = Manifest::new();
+    let mut stored = Vec::new();
+    for (name, path) in files {
+        let data = std::fs::read(path).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while reading {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let hash = hash(&data);
+        if !store.contains(&hash)? {
+            store.put(&hash, &data)?;
+            stored.push(name.clone());
+        }
+        manifest.insert(name.clone(), hash);
+    }
+    Ok((manifest, stored))
+}
+
+/// Retrieve the baselines of the manifest into the directory (created, if it does not exist), unless the file
+/// is already up to date.
+///
+/// Returns the names of the retrieved files.
+pub(crate) fn pull(
+    manifest: &Manifest,
+    dir: &Path,
+    store: &dyn Store,
+) -> Result<Vec<String>, Error> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while creating baseline directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    let mut retrieved = Vec::new();
+    for (name, expected) in manifest {
+        // Note: the names are file names within the directory (not paths escaping it)
+        if Path::new(name).file_name() != Some(name.as_ref()) {
+            return Err(format!("'{}' is not a baseline file name.", name).into());
+        }
+        let path = dir.join(name);
+        if std::fs::read(&path).is_ok_and(|data| hash(&data) == *expected) {
+            continue;
+        }
+        let data = store.get(expected)?;
+        if hash(&data) != *expected {
+            return Err(format!(
+                "stored baseline {} ({}) is corrupt (its content does not match the hash).",
+                name, expected
+            )
+            .into());
+        }
+        std::fs::write(&path, data).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while writing {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        retrieved.push(name.clone());
+    }
+    Ok(retrieved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_push_and_pull_baselines_by_their_content() {
+        let dir = std::env::temp_dir().join(format!("idiff_baseline_{}", std::process::id()));
+        let (baselines, pulled) = (dir.join("baselines"), dir.join("pulled"));
+        std::fs::create_dir_all(&baselines).unwrap();
+        std::fs::write(baselines.join("a.png"), b"a").unwrap();
+        std::fs::write(baselines.join("b.png"), b"b").unwrap();
+        std::fs::create_dir_all(&pulled).unwrap();
+        std::fs::write(pulled.join("a.png"), b"a").unwrap();
+        std::fs::write(pulled.join("b.png"), b"outdated").unwrap();
+        let store = open(dir.join("store").to_str().unwrap()).unwrap();
+
+        let files: Vec<_> = ["a.png", "b.png"]
+            .iter()
+            .map(|name| (name.to_string(), baselines.join(name)))
+            .collect();
+        let (manifest, stored) = push(&files, &*store).unwrap();
+        let (_, stored_again) = push(&files, &*store).unwrap();
+        let manifest_file = dir.join("baselines.toml");
+        write_manifest(&manifest_file, &manifest).unwrap();
+        let read = read_manifest(&manifest_file).unwrap();
+        let retrieved = pull(&read, &pulled, &*store).unwrap();
+        let content = std::fs::read(pulled.join("b.png")).unwrap();
+
+        std::fs::write(
+            &manifest_file,
+            "\"../a.png\" = \"0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef\"",
+        )
+        .unwrap();
+        let escaping = pull(&read_manifest(&manifest_file).unwrap(), &pulled, &*store);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(vec!["a.png", "b.png"], stored);
+        assert!(stored_again.is_empty());
+        assert_eq!(manifest, read);
+        assert_eq!(
+            "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb",
+            read["a.png"]
+        );
+        assert_eq!(vec!["b.png"], retrieved);
+        assert_eq!(b"b".to_vec(), content);
+        assert!(escaping.is_err());
+    }
+
+    #[test]
+    fn should_refuse_invalid_manifests() {
+        let path = std::env::temp_dir().join(format!("idiff_manifest_{}.toml", std::process::id()));
+        std::fs::write(&path, "\"a.png\" = 1").unwrap();
+        let error = read_manifest(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error
+            .message
+            .contains("'a.png' must be the hash of the baseline"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::exit::Error;
+
+/// Represents the maximum allowed percentage difference per file, read from a budget file (toml), for ex.
+///
+/// ```toml
+/// # budget of the files not listed below (default 0)
+/// default = 0.1
+///
+/// [files]
+/// "login.png" = 0.5
+/// "dashboard.png" = 2
+/// ```
+#[derive(Debug, PartialEq)]
+pub(crate) struct Budgets {
+    /// budget of the files not listed.
+    default: f32,
+    /// budget per file name.
+    files: BTreeMap<String, f32>,
+}
+
+impl Budgets {
+    /// Read the budget file.
+    pub(crate) fn read(path: &Path) -> Result<Budgets, Error> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while reading budget file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Budgets::parse(&text)
+            .map_err(|e| Error::from(format!("Invalid budget file {}: {}", path.display(), e)))
+    }
+
+    /// Parse the budgets (toml).
+    fn parse(text: &str) -> Result<Budgets, String> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| e.message().to_string())?;
+
+        let percentage = |key: &str, value: &toml::Value| match value {
+            toml::Value::Float(f) if *f >= 0.0 => Ok(*f as f32),
+            toml::Value::Integer(i) if *i >= 0 => Ok(*i as f32),
+            _ => Err(format!(
+                "budget of '{}' must be a non-negative percentage",
+                key
+            )),
+        };
+
+        let mut budgets = Budgets {
+            default: 0.0,
+            files: BTreeMap::new(),
+        };
+        for (key, value) in &table {
+            match (key.as_str(), value) {
+                ("default", value) => budgets.default = percentage(key, value)?,
+                ("files", toml::Value::Table(files)) => {
+                    for (file, value) in files {
+                        budgets.files.insert(file.clone(), percentage(file, value)?);
+                    }
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+        Ok(budgets)
+    }
+
+    /// Get the budget of the file.
+    pub(crate) fn get(&self, file: &str) -> f32 {
+        self.files.get(file).copied().unwrap_or(self.default)
+    }
+
+    /// Get the files with a budget (sorted by name).
+    pub(crate) fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_default_and_per_file_budgets() {
+        let budgets = Budgets::parse(
+            r#"
+            default = 0.1
+
+            [files]
+            "login.png" = 0.5
+            "dashboard.png" = 2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(0.5, budgets.get("login.png"));
+        assert_eq!(2.0, budgets.get("dashboard.png"));
+        assert_eq!(0.1, budgets.get("other.png"));
+        assert_eq!(
+            vec!["dashboard.png", "login.png"],
+            budgets.files().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn should_default_to_zero_budget() {
+        assert_eq!(0.0, Budgets::parse("").unwrap().get("a.png"));
+    }
+
+    #[test]
+    fn should_fail_for_invalid_budgets() {
+        assert!(Budgets::parse("default = -1").is_err());
+        assert!(Budgets::parse("[files]\n\"a.png\" = \"high\"").is_err());
+        assert!(Budgets::parse("unknown = 1").is_err());
+    }
+}
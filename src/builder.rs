@@ -0,0 +1,427 @@
+//! Programmatic (validated) configuration of the comparison, for library users.
+
+use std::sync::Arc;
+
+use image::ImageFormat;
+
+use crate::{
+    comparator, mask, observer, AlphaMode, BlockSize, CancellationToken, CompareOptions, Metric,
+    Normalize, Observer, Region, ToleranceMode,
+};
+
+/// Builds the options of the comparison, validated at `build`.
+///
+/// ```no_run
+/// use idiff::{CompareOptions, Metric, Region};
+///
+/// let options = CompareOptions::builder()
+///     .tolerance(8)
+///     .block(16)
+///     .metric(Metric::Psnr)
+///     .ignore_region(Region { x: 0, y: 0, width: 1920, height: 40 })
+///     .build()
+///     .unwrap();
+/// let result = idiff::diff_with_options("a.png".as_ref(), "b.png".as_ref(), &options).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompareOptionsBuilder {
+    options: CompareOptions,
+}
+
+impl CompareOptions {
+    /// Builder of the options (starting from the defaults of the command line).
+    pub fn builder() -> CompareOptionsBuilder {
+        CompareOptionsBuilder {
+            options: CompareOptions::default(),
+        }
+    }
+}
+
+impl CompareOptionsBuilder {
+    /// Fail if the dimensions of the images are different (instead of comparing the common region).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Size (in pixels) of the blocks the images are compared in (default 10).
+    pub fn block(mut self, block: u32) -> Self {
+        self.options.block = BlockSize::Fixed(block);
+        self.square_blocks()
+    }
+
+    /// Block size proportional to the dimensions of the images (1% of the smaller dimension, clamped to 2-64).
+    pub fn auto_block(mut self) -> Self {
+        self.options.block = BlockSize::Auto;
+        self.square_blocks()
+    }
+
+    /// Rectangular blocks of the width & height (instead of square blocks of the block size).
+    pub fn block_shape(self, width: u32, height: u32) -> Self {
+        let mut builder = self.square_blocks();
+        builder.options.block_shape = Some((width, height));
+        builder
+    }
+
+    /// Compare whole rows (scanlines) of the images, as blocks.
+    pub fn by_row(self) -> Self {
+        let mut builder = self.square_blocks();
+        builder.options.by_row = true;
+        builder
+    }
+
+    /// Compare whole columns of the images, as blocks.
+    pub fn by_column(self) -> Self {
+        let mut builder = self.square_blocks();
+        builder.options.by_column = true;
+        builder
+    }
+
+    /// Note: the last of the block sizes / shapes applies
+    fn square_blocks(mut self) -> Self {
+        self.options.block_shape = None;
+        self.options.by_row = false;
+        self.options.by_column = false;
+        self
+    }
+
+    /// Number of pixels adjacent blocks overlap by.
+    pub fn block_overlap(mut self, overlap: u32) -> Self {
+        self.options.block_overlap = overlap;
+        self
+    }
+
+    /// Percentage (0-100) of the pixels of a block which must differ for the block to be reported as changed.
+    pub fn block_threshold(mut self, percent: f32) -> Self {
+        self.options.block_threshold = percent;
+        self
+    }
+
+    /// Subdivide (quadtree) only the regions with difference, down to blocks no larger than the block size, instead
+    /// of comparing a fixed grid.
+    pub fn adaptive(mut self, adaptive: bool) -> Self {
+        self.options.adaptive = adaptive;
+        self
+    }
+
+    /// Weigh the contribution of every pixel to the percentage difference, from 0 (ignored) to 255 (fully counted).
+    pub fn mask(mut self, weights: image::GrayImage) -> Self {
+        self.options.mask = Some(mask::Mask::new(weights));
+        self
+    }
+
+    /// Count the blocks with difference per 10% bucket of their percentage of differing pixels.
+    pub fn block_histogram(mut self, histogram: bool) -> Self {
+        self.options.block_histogram = histogram;
+        self
+    }
+
+    /// Search the target for the best-matching position of the (smaller) source, and compare the source with the
+    /// matched area (see `DiffResult::found_at`).
+    pub fn find_in_tgt(mut self, find: bool) -> Self {
+        self.options.find_in_tgt = find;
+        self
+    }
+
+    /// Decode the source & target in the formats (if provided), instead of the formats detected from their content.
+    pub fn formats(mut self, src: Option<ImageFormat>, tgt: Option<ImageFormat>) -> Self {
+        self.options.format_src = src;
+        self.options.format_tgt = tgt;
+        self
+    }
+
+    /// Turn the images upright & scale them to their common physical resolution (as per their orientation & DPI
+    /// metadata) before the comparison (see `Warning::Rescaled`).
+    pub fn dpi_aware(mut self, dpi_aware: bool) -> Self {
+        self.options.dpi_aware = dpi_aware;
+        self
+    }
+
+    /// Classify the changed pixels of every block with difference as mostly brighter, darker, hue shifted or mixed
+    /// (see `DiffResult::directions`).
+    pub fn direction(mut self, direction: bool) -> Self {
+        self.options.direction = direction;
+        self
+    }
+
+    /// Classify the differing pixels as added (transparent in the source), removed (transparent in the target) or
+    /// changed (see `DiffStats::pixel_changes`).
+    pub fn classify_pixels(mut self, classify: bool) -> Self {
+        self.options.classify_pixels = classify;
+        self
+    }
+
+    /// Weigh the differing pixels by the visual saliency of the source into a salient percentage difference (see
+    /// `DiffStats::salient_percentage`).
+    pub fn saliency(mut self, saliency: bool) -> Self {
+        self.options.saliency = saliency;
+        self
+    }
+
+    /// Count the pixels with a channel differing by more than `max_channel_diff` (the `tolerance` comparator).
+    pub fn tolerance(mut self, max_channel_diff: u8) -> Self {
+        self.options.comparator = String::from("tolerance");
+        self.options.comparator_threshold = Some(max_channel_diff as f64);
+        self
+    }
+
+    /// Compare the blocks with the (built-in or registered) comparator, configured with the threshold.
+    pub fn comparator(mut self, name: &str, threshold: Option<f64>) -> Self {
+        self.options.comparator = name.to_string();
+        self.options.comparator_threshold = threshold;
+        self
+    }
+
+    /// Report the metric alongside the percentage difference.
+    pub fn metric(mut self, metric: Metric) -> Self {
+        if !self.options.metric.contains(&metric) {
+            self.options.metric.push(metric);
+        }
+        self
+    }
+
+    /// Ignore the region (of the common region of the images) in the comparison.
+    pub fn ignore_region(mut self, region: Region) -> Self {
+        self.options.ignore_region.push(region);
+        self
+    }
+
+    /// How the alpha channel is compared.
+    pub fn alpha_mode(mut self, mode: AlphaMode) -> Self {
+        self.options.alpha_mode = mode;
+        self
+    }
+
+    /// How the brightness / contrast of the target is equalized to the source before the comparison.
+    pub fn normalize(mut self, normalize: Normalize) -> Self {
+        self.options.normalize = normalize;
+        self
+    }
+
+    /// Count the regions only present in one of the images (when the dimensions are different) as difference.
+    pub fn count_size_mismatch(mut self, count: bool) -> Self {
+        self.options.count_size_mismatch = count;
+        self
+    }
+
+    /// Blur both the images (with the standard deviation) before the comparison, to suppress noise.
+    pub fn pre_blur(mut self, sigma: f32) -> Self {
+        self.options.pre_blur = Some(sigma);
+        self
+    }
+
+    /// Count a target pixel as matching if an identical source pixel exists within the number of pixels.
+    pub fn shift_tolerance(mut self, pixels: u32) -> Self {
+        self.options.shift_tolerance = Some(pixels);
+        self
+    }
+
+    /// Ignore the regions (of adjacent pixels with difference) smaller than the number of pixels.
+    pub fn min_cluster_size(mut self, pixels: u32) -> Self {
+        self.options.min_cluster_size = Some(pixels);
+        self
+    }
+
+    /// Ignore the pixels with a luminance difference (0-255) below the delta, even if their color changed.
+    pub fn min_luma_delta(mut self, delta: u8) -> Self {
+        self.options.min_luma_delta = Some(delta);
+        self
+    }
+
+    /// Compare only the fraction (0-1) of the pixels of every block, for a fast approximate result.
+    pub fn sample(mut self, rate: f32) -> Self {
+        self.options.sample = Some(rate);
+        self
+    }
+
+    /// Only find the blocks with difference (by a checksum of every block), instead of counting the differing pixels.
+    pub fn regions_only(mut self, regions_only: bool) -> Self {
+        self.options.regions_only = regions_only;
+        self
+    }
+
+    /// Compare only the region of the source (`a`) with the region of the target (`b`), for ex. two regions of the
+    /// same image.
+    pub fn regions_of_interest(mut self, a: Region, b: Region) -> Self {
+        self.options.roi_a = Some(a);
+        self.options.roi_b = Some(b);
+        self
+    }
+
+    /// Refuse the images with more pixels (width x height) than the limit.
+    pub fn max_pixels(mut self, pixels: u64) -> Self {
+        self.options.max_pixels = Some(pixels);
+        self
+    }
+
+    /// Refuse the images estimated to require more memory (in MB) than the limit to compare.
+    pub fn max_memory(mut self, megabytes: u64) -> Self {
+        self.options.max_memory = Some(megabytes);
+        self
+    }
+
+    /// Compare the full precision (float, for ex. EXR) pixels, tolerating a difference of every channel up to the
+    /// tolerance (of the mode).
+    pub fn float_tolerance(mut self, mode: ToleranceMode, tolerance: f64) -> Self {
+        self.options.tolerance_mode = Some(mode);
+        self.options.float_tolerance = tolerance;
+        self
+    }
+
+    /// Record the time taken by every step of the comparison (in `DiffResult::timings`).
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.options.timings = timings;
+        self
+    }
+
+    /// Cancel the comparison (with the `CANCELLED` error) once the token is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.options.cancellation = Some(token);
+        self
+    }
+
+    /// Notify the observer of the events of the comparison (for ex. to show its progress live).
+    pub fn observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.options.observer = Some(observer::Shared(observer));
+        self
+    }
+
+    /// Validate & build the options.
+    pub fn build(self) -> Result<CompareOptions, String> {
+        let options = self.options;
+        if let Some((width, height)) = options.block_shape {
+            if width == 0 || height == 0 {
+                return Err(String::from("block shape must be positive."));
+            }
+            if options.block_overlap >= width.min(height) {
+                return Err(format!(
+                    "block overlap ({:?}) must be smaller than the block shape ({}x{}).",
+                    options.block_overlap, width, height
+                ));
+            }
+        } else if options.by_row || options.by_column {
+            if options.block_overlap > 0 {
+                return Err(String::from(
+                    "blocks of whole rows / columns cannot overlap.",
+                ));
+            }
+        } else if let BlockSize::Fixed(block) = options.block {
+            if block == 0 {
+                return Err(String::from("block size must be positive."));
+            }
+            if options.block_overlap >= block {
+                return Err(format!(
+                    "block overlap ({:?}) must be smaller than the block size ({:?}).",
+                    options.block_overlap, block
+                ));
+            }
+        }
+        if options.dpi_aware && options.tolerance_mode.is_some() {
+            return Err(String::from(
+                "dpi aware comparison cannot be combined with the tolerance mode.",
+            ));
+        }
+        if options.float_tolerance < 0.0 || !options.float_tolerance.is_finite() {
+            return Err(String::from(
+                "float tolerance must be a non-negative number.",
+            ));
+        }
+        if !(0.0..=100.0).contains(&options.block_threshold) {
+            return Err(String::from("block threshold must be between 0 and 100."));
+        }
+        if options
+            .pre_blur
+            .is_some_and(|sigma| sigma <= 0.0 || !sigma.is_finite())
+        {
+            return Err(String::from("pre blur sigma must be a positive number."));
+        }
+        if options
+            .sample
+            .is_some_and(|rate| rate <= 0.0 || rate > 1.0 || rate.is_nan())
+        {
+            return Err(String::from("sample rate must be above 0, up to 1."));
+        }
+        if options
+            .ignore_region
+            .iter()
+            .any(|r| r.width == 0 || r.height == 0)
+        {
+            return Err(String::from("ignored regions must not be empty."));
+        }
+        if [&options.roi_a, &options.roi_b]
+            .into_iter()
+            .flatten()
+            .any(|r| r.width == 0 || r.height == 0)
+        {
+            return Err(String::from("regions of interest must not be empty."));
+        }
+        comparator::get(&options.comparator, options.comparator_threshold)?;
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_validate_options_at_build() {
+        let options = CompareOptions::builder()
+            .tolerance(8)
+            .block(16)
+            .metric(Metric::Psnr)
+            .metric(Metric::Psnr)
+            .build()
+            .unwrap();
+        assert_eq!("tolerance", options.comparator);
+        assert_eq!(BlockSize::Fixed(16), options.block);
+        assert_eq!(vec![Metric::Psnr], options.metric);
+
+        assert!(CompareOptions::builder().block(0).build().is_err());
+        assert!(CompareOptions::builder()
+            .block(4)
+            .block_overlap(4)
+            .build()
+            .is_err());
+        assert!(CompareOptions::builder().sample(1.5).build().is_err());
+        assert!(CompareOptions::builder()
+            .comparator("unknown", None)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn should_apply_the_last_block_shape() {
+        let options = CompareOptions::builder()
+            .by_row()
+            .block_shape(32, 8)
+            .build()
+            .unwrap();
+        assert_eq!(
+            (Some((32, 8)), false),
+            (options.block_shape, options.by_row)
+        );
+
+        let options = CompareOptions::builder()
+            .block_shape(32, 8)
+            .by_column()
+            .build()
+            .unwrap();
+        assert_eq!((None, true), (options.block_shape, options.by_column));
+
+        assert!(CompareOptions::builder()
+            .block_shape(32, 0)
+            .build()
+            .is_err());
+        assert!(CompareOptions::builder()
+            .block_shape(32, 8)
+            .block_overlap(8)
+            .build()
+            .is_err());
+        assert!(CompareOptions::builder()
+            .by_row()
+            .block_overlap(1)
+            .build()
+            .is_err());
+    }
+}
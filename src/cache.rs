@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+
+/// Represents the content hash of a comparison (of the source, the target & the options).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Key(u128);
+
+impl Key {
+    /// Hash the parts of the comparison (FNV-1a, 128 bit).
+    pub(crate) fn new(parts: &[&[u8]]) -> Key {
+        let mut hash = 0x6c62272e07bb014262b821756295c58d_u128;
+        for part in parts {
+            // Note: the length separates the parts (so that "ab" + "c" != "a" + "bc")
+            for byte in (part.len() as u64).to_le_bytes().iter().chain(part.iter()) {
+                hash = (hash ^ *byte as u128).wrapping_mul(0x0000000001000000000000000000013b);
+            }
+        }
+        Key(hash)
+    }
+
+    /// Path of the cached result in the cache directory.
+    fn path(&self, dir: &Path) -> PathBuf {
+        dir.join(self.to_string())
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// Get the cached percentage difference (if any).
+pub(crate) fn get(dir: &Path, key: Key) -> Option<f32> {
+    std::fs::read_to_string(key.path(dir))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Cache the percentage difference (creating the cache directory, if it does not exist).
+pub(crate) fn put(dir: &Path, key: Key, diff: f32) -> Result<(), Error> {
+    let error = |e: std::io::Error| {
+        Error::io(format!(
+            "Encountered error while writing into cache directory {}: {}",
+            dir.display(),
+            e
+        ))
+    };
+    std::fs::create_dir_all(dir).map_err(error)?;
+    std::fs::write(key.path(dir), diff.to_string()).map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_hash_parts_separately() {
+        assert_eq!(Key::new(&[b"ab", b"c"]), Key::new(&[b"ab", b"c"]));
+        assert_ne!(Key::new(&[b"ab", b"c"]), Key::new(&[b"a", b"bc"]));
+    }
+
+    #[test]
+    fn should_put_and_get_cached_results() {
+        let dir = std::env::temp_dir().join(format!("idiff_cache_{}", std::process::id()));
+        let key = Key::new(&[b"src", b"tgt"]);
+
+        assert_eq!(None, get(&dir, key));
+        put(&dir, key, 0.25).unwrap();
+        assert_eq!(Some(0.25), get(&dir, key));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+//! Cancellation of the comparison (for ex. by a GUI or a server embedding the library).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Error returned by a cancelled comparison.
+pub const CANCELLED: &str = "comparison cancelled.";
+
+/// Represents a token to cancel a comparison from another thread (see `CompareOptionsBuilder::cancellation`).
+///
+/// The comparison checks the token between its steps & its blocks, and returns the `CANCELLED` error once
+/// cancelled.
+///
+/// ```no_run
+/// let token = idiff::CancellationToken::new();
+/// let options = idiff::CompareOptions::builder()
+///     .cancellation(token.clone())
+///     .build()
+///     .unwrap();
+/// let comparison = std::thread::spawn(move || {
+///     idiff::diff_with_options("a.png".as_ref(), "b.png".as_ref(), &options)
+/// });
+/// token.cancel();
+/// assert_eq!(Err(String::from(idiff::CANCELLED)), comparison.join().unwrap());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancel the comparison(s) using the token (or its clones).
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Fail with the `CANCELLED` error if the token (if any) is cancelled.
+pub(crate) fn check(token: Option<&CancellationToken>) -> Result<(), String> {
+    match token.is_some_and(CancellationToken::is_cancelled) {
+        true => Err(String::from(CANCELLED)),
+        false => Ok(()),
+    }
+}
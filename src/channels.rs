@@ -0,0 +1,62 @@
+/// Represents the order the channels of an image are stored in (for ex. `bgra` for GPU readbacks).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ChannelOrder([usize; 4]);
+
+/// Parse the channel order from a permutation of `rgba` (for ex. `bgra` or `argb`), or of `rgb` (alpha last,
+/// for ex. `bgr`).
+pub(crate) fn parse_order(order: &str) -> Result<ChannelOrder, String> {
+    let error = || {
+        format!(
+            "invalid channel order '{}' (expected a permutation of rgba or rgb, for ex. bgr or argb)",
+            order
+        )
+    };
+    let order = order.to_ascii_lowercase();
+    let order = match order.len() {
+        3 => format!("{}a", order),
+        _ => order,
+    };
+    let position = |channel| order.find(channel).ok_or_else(error);
+    let positions = [
+        position('r')?,
+        position('g')?,
+        position('b')?,
+        position('a')?,
+    ];
+    if order.len() != 4 {
+        return Err(error());
+    }
+    Ok(ChannelOrder(positions))
+}
+
+impl ChannelOrder {
+    /// Reorder the channels of the image (stored in this order) into RGBA.
+    pub(crate) fn apply(&self, img: &mut image::RgbaImage) {
+        let ChannelOrder(positions) = *self;
+        for pixel in img.pixels_mut() {
+            let stored = pixel.0;
+            pixel.0 = positions.map(|p| stored[p]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_channel_orders() {
+        assert_eq!(Ok(ChannelOrder([2, 1, 0, 3])), parse_order("bgr"));
+        assert_eq!(Ok(ChannelOrder([1, 2, 3, 0])), parse_order("ARGB"));
+        assert!(parse_order("rgbb").is_err());
+        assert!(parse_order("rgbax").is_err());
+    }
+
+    #[test]
+    fn should_reorder_channels_into_rgba() {
+        let mut img = image::RgbaImage::from_pixel(1, 1, image::Rgba([1, 2, 3, 4]));
+
+        parse_order("argb").unwrap().apply(&mut img);
+        assert_eq!(image::Rgba([2, 3, 4, 1]), *img.get_pixel(0, 0));
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::Bounds;
+
+/// Represents the severity of a cluster of blocks with difference.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Severity {
+    /// magnitude below the minor threshold.
+    Minor,
+    /// magnitude between the minor and major thresholds.
+    Moderate,
+    /// magnitude equal to or above the major threshold.
+    Major,
+}
+
+impl Severity {
+    /// Classify the magnitude of a difference with the thresholds.
+    pub(crate) fn classify(magnitude: u8, minor_threshold: u8, major_threshold: u8) -> Severity {
+        if magnitude < minor_threshold {
+            Severity::Minor
+        } else if magnitude < major_threshold {
+            Severity::Moderate
+        } else {
+            Severity::Major
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Minor => write!(f, "minor"),
+            Severity::Moderate => write!(f, "moderate"),
+            Severity::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// Represents a group of adjacent blocks with difference.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Cluster {
+    /// bounding box of the blocks.
+    pub(crate) bounds: Bounds,
+    /// number of blocks.
+    pub(crate) blocks: usize,
+    /// largest difference of a channel (0-255) within the blocks.
+    pub(crate) magnitude: u8,
+    /// severity of the magnitude.
+    pub(crate) severity: Severity,
+}
+
+impl std::fmt::Display for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} block(s), magnitude: {}): {}",
+            self.severity, self.blocks, self.magnitude, self.bounds
+        )
+    }
+}
+
+/// Group the blocks with difference into clusters of adjacent (incl. diagonally) blocks.
+///
+/// The blocks are expected to be on a grid starting every `step` pixels (as returned by `percentage_difference`).
+///
+/// Returns the clusters sorted by the position (top to bottom, left to right) of their bounding box.
+pub(crate) fn cluster(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    blocks: &[Bounds],
+    step: u32,
+    minor_threshold: u8,
+    major_threshold: u8,
+) -> Vec<Cluster> {
+    let positions: HashMap<(u32, u32), usize> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| ((b.min_width, b.min_height), i))
+        .collect();
+
+    let mut parents: Vec<usize> = (0..blocks.len()).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        // Note: the neighbours before the block are already visited, so only the ones after it need to be joined
+        let neighbours = [
+            (block.min_width.checked_add(step), Some(block.min_height)),
+            (
+                block.min_width.checked_sub(step),
+                block.min_height.checked_add(step),
+            ),
+            (Some(block.min_width), block.min_height.checked_add(step)),
+            (
+                block.min_width.checked_add(step),
+                block.min_height.checked_add(step),
+            ),
+        ];
+        for neighbour in neighbours {
+            if let (Some(x), Some(y)) = neighbour {
+                if let Some(&j) = positions.get(&(x, y)) {
+                    union(&mut parents, i, j);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..blocks.len() {
+        let root = find(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .map(|members| {
+            let bounds = Bounds::new(
+                members.iter().map(|&i| blocks[i].min_width).min().unwrap(),
+                members.iter().map(|&i| blocks[i].max_width).max().unwrap(),
+                members.iter().map(|&i| blocks[i].min_height).min().unwrap(),
+                members.iter().map(|&i| blocks[i].max_height).max().unwrap(),
+            );
+            let magnitude = members
+                .iter()
+                .map(|&i| magnitude(src, tgt, &blocks[i]))
+                .max()
+                .unwrap();
+            Cluster {
+                bounds,
+                blocks: members.len(),
+                magnitude,
+                severity: Severity::classify(magnitude, minor_threshold, major_threshold),
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| a.bounds.cmp(&b.bounds));
+
+    clusters
+}
+
+/// Count the clusters per severity.
+///
+/// Returns the (minor, moderate, major) counts.
+pub(crate) fn count_by_severity(clusters: &[Cluster]) -> (usize, usize, usize) {
+    let count = |severity| clusters.iter().filter(|c| c.severity == severity).count();
+    (
+        count(Severity::Minor),
+        count(Severity::Moderate),
+        count(Severity::Major),
+    )
+}
+
+/// Discard the regions (of adjacent, incl. diagonally, pixels with difference) smaller than `min_size` pixels
+/// within the bounds, by copying the source pixels of those regions onto the target.
+///
+/// Returns the number of discarded pixels.
+pub(crate) fn discard_small_regions(
+    src: &image::RgbaImage,
+    tgt: &mut image::RgbaImage,
+    bounds: &Bounds,
+    min_size: u32,
+) -> usize {
+    let (width, height) = (bounds.max_width, bounds.max_height);
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let differs =
+        |tgt: &image::RgbaImage, x: u32, y: u32| src.get_pixel(x, y) != tgt.get_pixel(x, y);
+
+    let mut visited = vec![false; (width * height) as usize];
+    let mut discarded = 0;
+    for y in bounds.min_height..height {
+        for x in bounds.min_width..width {
+            if visited[index(x, y)] || !differs(tgt, x, y) {
+                continue;
+            }
+
+            // Note: flood fill the region starting from the pixel
+            visited[index(x, y)] = true;
+            let mut region = vec![(x, y)];
+            let mut next = 0;
+            while next < region.len() {
+                let (px, py) = region[next];
+                next += 1;
+                for ny in py.saturating_sub(1)..=(py + 1).min(height - 1) {
+                    for nx in px.saturating_sub(1)..=(px + 1).min(width - 1) {
+                        if nx < bounds.min_width || ny < bounds.min_height {
+                            continue;
+                        }
+                        if !visited[index(nx, ny)] && differs(tgt, nx, ny) {
+                            visited[index(nx, ny)] = true;
+                            region.push((nx, ny));
+                        }
+                    }
+                }
+            }
+
+            if region.len() < min_size as usize {
+                discarded += region.len();
+                for (px, py) in region {
+                    tgt.put_pixel(px, py, *src.get_pixel(px, py));
+                }
+            }
+        }
+    }
+
+    discarded
+}
+
+/// Largest difference of a channel (0-255) between the images within the bounds.
+pub(crate) fn magnitude(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Bounds) -> u8 {
+    (bounds.min_height..bounds.max_height)
+        .flat_map(|y| {
+            crate::row_slice(src, y, bounds)
+                .iter()
+                .zip(crate::row_slice(tgt, y, bounds))
+                .map(|(s, t)| s.abs_diff(*t))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Find the root of the element (with path halving).
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Join the sets of the elements.
+fn union(parents: &mut [usize], i: usize, j: usize) {
+    let (root_i, root_j) = (find(parents, i), find(parents, j));
+    if root_i != root_j {
+        parents[root_j] = root_i;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_classify_magnitude_by_thresholds() {
+        assert_eq!(Severity::Minor, Severity::classify(10, 32, 128));
+        assert_eq!(Severity::Moderate, Severity::classify(32, 32, 128));
+        assert_eq!(Severity::Major, Severity::classify(128, 32, 128));
+    }
+
+    #[test]
+    fn should_group_adjacent_blocks_into_clusters() {
+        let src = image::RgbaImage::new(100, 100);
+        let mut tgt = image::RgbaImage::new(100, 100);
+        *tgt.get_pixel_mut(5, 5) = image::Rgba([10, 0, 0, 0]);
+        *tgt.get_pixel_mut(15, 15) = image::Rgba([200, 0, 0, 0]);
+        *tgt.get_pixel_mut(85, 5) = image::Rgba([64, 0, 0, 0]);
+
+        let blocks = vec![
+            Bounds::new(0, 10, 0, 10),
+            Bounds::new(80, 90, 0, 10),
+            Bounds::new(10, 20, 10, 20),
+        ];
+
+        assert_eq!(
+            vec![
+                Cluster {
+                    bounds: Bounds::new(0, 20, 0, 20),
+                    blocks: 2,
+                    magnitude: 200,
+                    severity: Severity::Major,
+                },
+                Cluster {
+                    bounds: Bounds::new(80, 90, 0, 10),
+                    blocks: 1,
+                    magnitude: 64,
+                    severity: Severity::Moderate,
+                },
+            ],
+            cluster(&src, &tgt, &blocks, 10, 32, 128)
+        );
+    }
+
+    #[test]
+    fn should_count_clusters_by_severity() {
+        let cluster = |severity| Cluster {
+            bounds: Bounds::new(0, 10, 0, 10),
+            blocks: 1,
+            magnitude: 0,
+            severity,
+        };
+        let clusters = vec![
+            cluster(Severity::Minor),
+            cluster(Severity::Major),
+            cluster(Severity::Major),
+        ];
+
+        assert_eq!((1, 0, 2), count_by_severity(&clusters));
+    }
+
+    #[test]
+    fn should_discard_regions_smaller_than_min_size() {
+        let src = image::RgbaImage::new(10, 10);
+        let mut tgt = image::RgbaImage::new(10, 10);
+        // Note: an isolated pixel & a diagonal line of 3 pixels
+        *tgt.get_pixel_mut(0, 0) = image::Rgba([255, 0, 0, 0]);
+        for i in 5..8 {
+            *tgt.get_pixel_mut(i, i) = image::Rgba([255, 0, 0, 0]);
+        }
+
+        assert_eq!(
+            1,
+            discard_small_regions(&src, &mut tgt, &Bounds::new(0, 10, 0, 10), 2)
+        );
+        assert_eq!(image::Rgba([0, 0, 0, 0]), *tgt.get_pixel(0, 0));
+        assert_eq!(image::Rgba([255, 0, 0, 0]), *tgt.get_pixel(6, 6));
+    }
+}
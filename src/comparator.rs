@@ -0,0 +1,664 @@
+//! Comparison of the blocks of the images, pluggable via the `Comparator` trait.
+//!
+//! Custom comparators can be registered by name (with `register`) and are then selectable with the `comparator` option:
+//!
+//! ```no_run
+//! use idiff::comparator::{BlockDiff, Comparator, Region};
+//!
+//! struct RedOnly;
+//!
+//! impl Comparator for RedOnly {
+//!     fn compare_block(&self, src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Region) -> BlockDiff {
+//!         let differing_pixels = bounds
+//!             .pixels()
+//!             .filter(|&(x, y)| src.get_pixel(x, y)[0] != tgt.get_pixel(x, y)[0])
+//!             .count() as u64;
+//!         BlockDiff { differing_pixels }
+//!     }
+//! }
+//!
+//! idiff::comparator::register("red-only", RedOnly).unwrap();
+//! idiff::run();
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+pub use crate::result::Region;
+use crate::Bounds;
+
+/// Names of the built-in comparators.
+pub const BUILT_IN: [&str; 4] = ["exact", "tolerance", "ssim", "delta-e"];
+
+/// Represents the difference within a block.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockDiff {
+    /// number of pixels counted as difference.
+    pub differing_pixels: u64,
+}
+
+/// Compares a block of the images.
+pub trait Comparator: Send + Sync {
+    /// Compare the block of the images (the bounds are within both the images).
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff;
+}
+
+/// Counts the pixels which are not exactly the same (the default).
+pub struct Exact;
+
+impl Comparator for Exact {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        BlockDiff {
+            differing_pixels: crate::pixel_difference(src, tgt, &Bounds::from(bounds)) as u64,
+        }
+    }
+}
+
+/// Counts the pixels with a channel differing by more than `max_channel_diff` (0-255).
+pub struct Tolerance {
+    pub max_channel_diff: u8,
+}
+
+impl Comparator for Tolerance {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let differing_pixels = bounds
+            .pixels()
+            .filter(|&(x, y)| {
+                let (s, t) = (src.get_pixel(x, y), tgt.get_pixel(x, y));
+                s.0.iter()
+                    .zip(t.0.iter())
+                    .any(|(s, t)| s.abs_diff(*t) > self.max_channel_diff)
+            })
+            .count() as u64;
+        BlockDiff { differing_pixels }
+    }
+}
+
+/// Counts the pixels with a perceptual color difference (CIE76 delta E) greater than `threshold`
+/// (or a different alpha).
+pub struct DeltaE {
+    pub threshold: f32,
+}
+
+impl Comparator for DeltaE {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let differing_pixels = bounds
+            .pixels()
+            .filter(|&(x, y)| {
+                let (s, t) = (src.get_pixel(x, y), tgt.get_pixel(x, y));
+                if s == t {
+                    return false;
+                }
+                let (s_lab, t_lab) = (lab(s), lab(t));
+                let delta_e = s_lab
+                    .iter()
+                    .zip(t_lab.iter())
+                    .map(|(s, t)| (s - t).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+                s[3] != t[3] || delta_e > self.threshold
+            })
+            .count() as u64;
+        BlockDiff { differing_pixels }
+    }
+}
+
+/// Counts every pixel of the blocks with a structural similarity (SSIM of the luma) below `min_similarity` (0-1).
+pub struct Ssim {
+    pub min_similarity: f64,
+}
+
+impl Comparator for Ssim {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        if ssim(src, tgt, bounds) >= self.min_similarity {
+            return BlockDiff::default();
+        }
+        BlockDiff {
+            differing_pixels: bounds.width as u64 * bounds.height as u64,
+        }
+    }
+}
+
+impl Region {
+    /// Iterate over the (x, y) coordinates of the pixels within the region.
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+}
+
+impl From<&Region> for Bounds {
+    fn from(region: &Region) -> Bounds {
+        Bounds::new(
+            region.x,
+            region.x + region.width,
+            region.y,
+            region.y + region.height,
+        )
+    }
+}
+
+/// Compares the blocks with the `inner` comparator until the token is cancelled (then skips the remaining
+/// blocks, as without difference).
+pub(crate) struct Cancellable {
+    inner: Arc<dyn Comparator>,
+    token: crate::CancellationToken,
+}
+
+impl Cancellable {
+    pub(crate) fn new(inner: Arc<dyn Comparator>, token: crate::CancellationToken) -> Cancellable {
+        Cancellable { inner, token }
+    }
+}
+
+impl Comparator for Cancellable {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        match self.token.is_cancelled() {
+            true => BlockDiff::default(),
+            false => self.inner.compare_block(src, tgt, bounds),
+        }
+    }
+}
+
+/// Compares a pseudo-random (seeded, so deterministic) subset of the pixels of every block with the `inner`
+/// comparator (pixel by pixel), and extrapolates the differing pixels of the block.
+pub(crate) struct Sampled {
+    inner: Arc<dyn Comparator>,
+    /// fraction (0-1) of the pixels compared.
+    rate: f64,
+    /// total number of the compared pixels.
+    sampled: AtomicU64,
+    /// total number of the compared pixels with difference.
+    differing: AtomicU64,
+}
+
+impl Sampled {
+    /// Seed of the pseudo-random selection of the pixels.
+    const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    pub(crate) fn new(inner: Arc<dyn Comparator>, rate: f64) -> Sampled {
+        Sampled {
+            inner,
+            rate,
+            sampled: AtomicU64::new(0),
+            differing: AtomicU64::new(0),
+        }
+    }
+
+    /// Checks if the pixel is sampled (splitmix64 of the coordinates, compared with the rate).
+    fn is_sampled(&self, x: u32, y: u32) -> bool {
+        let mut z = ((x as u64) << 32 | y as u64) ^ Sampled::SEED;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z as f64 / u64::MAX as f64) < self.rate
+    }
+
+    /// Margin of error (in percentage points, at 95% confidence) of the fraction of the pixels with difference.
+    ///
+    /// Returns `None` if no pixel was compared.
+    pub(crate) fn margin(&self) -> Option<f32> {
+        let sampled = self.sampled.load(Ordering::Relaxed);
+        if sampled == 0 {
+            return None;
+        }
+        let p = self.differing.load(Ordering::Relaxed) as f64 / sampled as f64;
+        Some((1.96 * (p * (1.0 - p) / sampled as f64).sqrt() * 100.0) as f32)
+    }
+}
+
+impl Comparator for Sampled {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let (mut sampled, mut differing) = (0, 0);
+        for (x, y) in bounds.pixels().filter(|&(x, y)| self.is_sampled(x, y)) {
+            let pixel = Region {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            };
+            sampled += 1;
+            differing += self
+                .inner
+                .compare_block(src, tgt, &pixel)
+                .differing_pixels
+                .min(1);
+        }
+        self.sampled.fetch_add(sampled, Ordering::Relaxed);
+        self.differing.fetch_add(differing, Ordering::Relaxed);
+
+        let pixels = bounds.width as u64 * bounds.height as u64;
+        BlockDiff {
+            differing_pixels: match sampled {
+                0 => 0,
+                _ => (differing as f64 * pixels as f64 / sampled as f64).round() as u64,
+            },
+        }
+    }
+}
+
+/// Counts the pixels with a channel differing by more than the re-encoding error expected for its (8x8) jpeg
+/// block, estimated from the quantization tables & the content of both the images (or a different alpha).
+pub(crate) struct JpegAware {
+    /// largest channel difference of every block (row major).
+    tolerances: Vec<u8>,
+    /// number of blocks per row.
+    columns: u32,
+}
+
+impl JpegAware {
+    pub(crate) fn new(
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        src_table: &crate::jpeg::Tables,
+        tgt_table: &crate::jpeg::Tables,
+    ) -> JpegAware {
+        let (src_variances, tgt_variances) = (
+            crate::jpeg::error_variances(src, src_table),
+            crate::jpeg::error_variances(tgt, tgt_table),
+        );
+        let columns = |img: &image::RgbaImage| img.width().div_ceil(8);
+        let (src_columns, tgt_columns) = (columns(src), columns(tgt));
+        let (width, height) = (src.width().min(tgt.width()), src.height().min(tgt.height()));
+
+        // Note: the errors of both the encodings add up, and (as the error is heavy tailed at the edges) 6 standard
+        // deviations (+1 for the rounding of the decoders) cover almost every pixel of a re-encoded block
+        let tolerances = (0..height.div_ceil(8))
+            .flat_map(|row| (0..width.div_ceil(8)).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let variance = src_variances[(row * src_columns + column) as usize]
+                    + tgt_variances[(row * tgt_columns + column) as usize];
+                (6.0 * variance.sqrt() + 1.0).min(255.0) as u8
+            })
+            .collect();
+        JpegAware {
+            tolerances,
+            columns: width.div_ceil(8),
+        }
+    }
+}
+
+impl Comparator for JpegAware {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let differing_pixels = bounds
+            .pixels()
+            .filter(|&(x, y)| {
+                let (s, t) = (src.get_pixel(x, y), tgt.get_pixel(x, y));
+                let tolerance = self.tolerances[((y / 8) * self.columns + x / 8) as usize];
+                s[3] != t[3] || (0..3).any(|c| s[c].abs_diff(t[c]) > tolerance)
+            })
+            .count() as u64;
+        BlockDiff { differing_pixels }
+    }
+}
+
+/// Counts the pixels with a (full precision) channel differing by more than the tolerance (of the mode), comparing
+/// the float pixels of the images instead of the compared (8-bit) ones.
+pub(crate) struct FloatTolerance {
+    src: image::Rgba32FImage,
+    tgt: image::Rgba32FImage,
+    mode: crate::ToleranceMode,
+    tolerance: f64,
+}
+
+impl FloatTolerance {
+    pub(crate) fn new(
+        src: image::Rgba32FImage,
+        tgt: image::Rgba32FImage,
+        mode: crate::ToleranceMode,
+        tolerance: f64,
+    ) -> FloatTolerance {
+        FloatTolerance {
+            src,
+            tgt,
+            mode,
+            tolerance,
+        }
+    }
+}
+
+impl Comparator for FloatTolerance {
+    fn compare_block(
+        &self,
+        _src: &image::RgbaImage,
+        _tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let differing_pixels = bounds
+            .pixels()
+            .filter(|&(x, y)| {
+                let (s, t) = (self.src.get_pixel(x, y), self.tgt.get_pixel(x, y));
+                (0..4).any(|c| !self.mode.within(s[c], t[c], self.tolerance))
+            })
+            .count() as u64;
+        BlockDiff { differing_pixels }
+    }
+}
+
+/// Registry of the custom comparators (by name).
+fn registry() -> &'static RwLock<BTreeMap<String, Arc<dyn Comparator>>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, Arc<dyn Comparator>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(BTreeMap::new()))
+}
+
+/// Register a custom comparator, selectable by its name with the `comparator` option.
+///
+/// Returns an error if the name is of a built-in comparator.
+pub fn register(name: &str, comparator: impl Comparator + 'static) -> Result<(), String> {
+    if BUILT_IN.contains(&name) {
+        return Err(format!("'{}' is a built-in comparator.", name));
+    }
+    registry()
+        .write()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(comparator));
+    Ok(())
+}
+
+/// Get the comparator by its name.
+///
+/// `threshold` configures the built-in comparators (the default is used, if not provided):
+/// `tolerance` - largest channel difference (default 8), `ssim` - smallest similarity (default 0.95)
+/// & `delta-e` - largest color difference (default 2.3).
+pub(crate) fn get(name: &str, threshold: Option<f64>) -> Result<Arc<dyn Comparator>, String> {
+    let comparator: Arc<dyn Comparator> = match name {
+        "exact" => Arc::new(Exact),
+        "tolerance" => Arc::new(Tolerance {
+            max_channel_diff: threshold.unwrap_or(8.0).clamp(0.0, 255.0) as u8,
+        }),
+        "ssim" => Arc::new(Ssim {
+            min_similarity: threshold.unwrap_or(0.95),
+        }),
+        "delta-e" => Arc::new(DeltaE {
+            threshold: threshold.unwrap_or(2.3) as f32,
+        }),
+        _ => {
+            return registry()
+                .read()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .ok_or_else(|| {
+                    let registered = registry()
+                        .read()
+                        .unwrap()
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    format!(
+                        "Unknown comparator '{}' (expected one of: {}).",
+                        name,
+                        BUILT_IN
+                            .iter()
+                            .map(|n| n.to_string())
+                            .chain(registered)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })
+        }
+    };
+    Ok(comparator)
+}
+
+/// Convert the sRGB color into CIE L*a*b* (D65).
+fn lab(pixel: &image::Rgba<u8>) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(pixel[0]), linear(pixel[1]), linear(pixel[2]));
+
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Structural similarity of the luma of the images within the region (as a single window).
+fn ssim(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Region) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let luma =
+        |p: &image::Rgba<u8>| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+
+    let n = bounds.width as f64 * bounds.height as f64;
+    let (mut sum_s, mut sum_t, mut sum_ss, mut sum_tt, mut sum_st) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (x, y) in bounds.pixels() {
+        let (s, t) = (luma(src.get_pixel(x, y)), luma(tgt.get_pixel(x, y)));
+        sum_s += s;
+        sum_t += t;
+        sum_ss += s * s;
+        sum_tt += t * t;
+        sum_st += s * t;
+    }
+
+    let (mean_s, mean_t) = (sum_s / n, sum_t / n);
+    let var_s = sum_ss / n - mean_s * mean_s;
+    let var_t = sum_tt / n - mean_t * mean_t;
+    let covariance = sum_st / n - mean_s * mean_t;
+
+    ((2.0 * mean_s * mean_t + C1) * (2.0 * covariance + C2))
+        / ((mean_s * mean_s + mean_t * mean_t + C1) * (var_s + var_t + C2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_extrapolate_sampled_pixels() {
+        let src = image::RgbaImage::new(100, 100);
+        let tgt = image::RgbaImage::from_pixel(100, 100, image::Rgba([255, 0, 0, 255]));
+        let bounds = Region {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+
+        let sampled = Sampled::new(Arc::new(Exact), 0.1);
+        assert_eq!(
+            10_000,
+            sampled.compare_block(&src, &tgt, &bounds).differing_pixels
+        );
+        assert!((900..1100).contains(&sampled.sampled.load(Ordering::Relaxed)));
+        assert_eq!(Some(0.0), sampled.margin());
+
+        let none = Sampled::new(Arc::new(Exact), 0.1);
+        assert_eq!(0, none.compare_block(&src, &src, &bounds).differing_pixels);
+    }
+
+    #[test]
+    fn should_tolerate_jpeg_re_encoding_error() {
+        let img = image::RgbImage::from_fn(64, 64, |x, y| {
+            let texture = if (x / 2 + y / 3) % 2 == 0 { 0 } else { 40 };
+            image::Rgb([
+                (x * 2 + texture) as u8,
+                (y * 2 + texture) as u8,
+                100 + texture as u8,
+            ])
+        });
+        let encode = |img: &image::RgbImage, quality| {
+            let mut data = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, quality)
+                .encode_image(img)
+                .unwrap();
+            let table = crate::jpeg::tables(&data).unwrap();
+            (image::load_from_memory(&data).unwrap().to_rgba8(), table)
+        };
+        let (src, src_table) = encode(&img, 90);
+        let (tgt, tgt_table) = encode(&img, 70);
+        let mut changed = img.clone();
+        image::imageops::replace(&mut changed, &image::RgbImage::new(16, 16), 24, 24);
+        let (changed, changed_table) = encode(&changed, 70);
+        let bounds = Region {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+        };
+
+        let comparator = JpegAware::new(&src, &tgt, &src_table, &tgt_table);
+        assert_eq!(
+            0,
+            comparator
+                .compare_block(&src, &tgt, &bounds)
+                .differing_pixels
+        );
+        assert!(Exact.compare_block(&src, &tgt, &bounds).differing_pixels > 0);
+
+        let comparator = JpegAware::new(&src, &changed, &src_table, &changed_table);
+        assert!(
+            comparator
+                .compare_block(&src, &changed, &bounds)
+                .differing_pixels
+                >= 100
+        );
+    }
+
+    fn region() -> Region {
+        Region {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 4,
+        }
+    }
+
+    #[test]
+    fn should_count_pixels_beyond_tolerance() {
+        let src = image::RgbaImage::new(4, 4);
+        let mut tgt = image::RgbaImage::new(4, 4);
+        *tgt.get_pixel_mut(0, 0) = image::Rgba([5, 0, 0, 0]);
+        *tgt.get_pixel_mut(1, 0) = image::Rgba([50, 0, 0, 0]);
+
+        assert_eq!(
+            2,
+            Exact.compare_block(&src, &tgt, &region()).differing_pixels
+        );
+        assert_eq!(
+            1,
+            Tolerance {
+                max_channel_diff: 8
+            }
+            .compare_block(&src, &tgt, &region())
+            .differing_pixels
+        );
+    }
+
+    #[test]
+    fn should_ignore_imperceptible_color_differences_with_delta_e() {
+        let src = image::RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(0, 0) = image::Rgba([101, 100, 100, 255]);
+        *tgt.get_pixel_mut(1, 0) = image::Rgba([200, 100, 100, 255]);
+
+        let comparator = DeltaE { threshold: 2.3 };
+        assert_eq!(
+            1,
+            comparator
+                .compare_block(&src, &tgt, &region())
+                .differing_pixels
+        );
+    }
+
+    #[test]
+    fn should_count_whole_block_with_low_ssim() {
+        let src = image::RgbaImage::from_fn(4, 4, |x, _| image::Rgba([x as u8 * 60, 0, 0, 255]));
+        let comparator = Ssim {
+            min_similarity: 0.95,
+        };
+
+        assert_eq!(
+            0,
+            comparator
+                .compare_block(&src, &src, &region())
+                .differing_pixels
+        );
+        let inverted =
+            image::RgbaImage::from_fn(4, 4, |x, _| image::Rgba([(3 - x) as u8 * 60, 0, 0, 255]));
+        assert_eq!(
+            16,
+            comparator
+                .compare_block(&src, &inverted, &region())
+                .differing_pixels
+        );
+    }
+
+    #[test]
+    fn should_get_built_in_and_registered_comparators() {
+        struct Never;
+        impl Comparator for Never {
+            fn compare_block(
+                &self,
+                _: &image::RgbaImage,
+                _: &image::RgbaImage,
+                _: &Region,
+            ) -> BlockDiff {
+                BlockDiff::default()
+            }
+        }
+
+        assert!(get("ssim", None).is_ok());
+        assert!(get("never", None).is_err());
+        assert!(register("exact", Never).is_err());
+
+        register("never", Never).unwrap();
+        assert!(get("never", None).is_ok());
+    }
+}
@@ -0,0 +1,151 @@
+//! Crops of the changed regions, so that the reviewers look at a handful of small images instead of the whole.
+
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+use crate::{cluster, Bounds, Region};
+
+/// Crop the changed regions (the clusters of the blocks with difference, padded by a block for context) of both
+/// the images into the directory (created, if it does not exist), as `region_<N>_src.png` & `region_<N>_tgt.png`
+/// (numbered top to bottom, from 1).
+///
+/// Returns the cropped regions (& the paths of their source & target crops).
+pub(crate) fn write_crops(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    blocks: &[Bounds],
+    step: u32,
+    dir: &Path,
+    force: bool,
+) -> Result<Vec<(Region, PathBuf, PathBuf)>, Error> {
+    let bounds = Bounds::new(
+        0,
+        src.width().min(tgt.width()),
+        0,
+        src.height().min(tgt.height()),
+    );
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while creating crop directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    let mut crops = Vec::new();
+    for (i, cluster) in cluster::cluster(src, tgt, blocks, step, 0, u8::MAX)
+        .iter()
+        .enumerate()
+    {
+        let region = Region::from(
+            &Bounds::new(
+                cluster.bounds.min_width.saturating_sub(step),
+                cluster.bounds.max_width.saturating_add(step),
+                cluster.bounds.min_height.saturating_sub(step),
+                cluster.bounds.max_height.saturating_add(step),
+            )
+            .intersection(&bounds),
+        );
+        let (src_path, tgt_path) =
+            write_pair(src, tgt, &region, dir, &format!("region_{}", i + 1), force)?;
+        crops.push((region, src_path, tgt_path));
+    }
+    Ok(crops)
+}
+
+/// Crop the bounding box of all the changes (the blocks with difference) of both the images into the directory
+/// (created, if it does not exist), as `changes_src.png` & `changes_tgt.png`.
+///
+/// Returns the paths of the source & target crops.
+pub(crate) fn write_changed_area(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    area: &Bounds,
+    dir: &Path,
+    force: bool,
+) -> Result<(PathBuf, PathBuf), Error> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while creating crop directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    write_pair(src, tgt, &Region::from(area), dir, "changes", force)
+}
+
+/// Write the region of both the images, as `<NAME>_src.png` & `<NAME>_tgt.png`.
+fn write_pair(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    region: &Region,
+    dir: &Path,
+    name: &str,
+    force: bool,
+) -> Result<(PathBuf, PathBuf), Error> {
+    let mut paths = Vec::new();
+    for (img, side) in [(src, "src"), (tgt, "tgt")] {
+        let path = dir.join(format!("{}_{}.png", name, side));
+        crate::check_overwrite(&path, force)?;
+        let crop = image::imageops::crop_imm(img, region.x, region.y, region.width, region.height)
+            .to_image();
+        crate::save_image(&crop, &path, None)?;
+        paths.push(path);
+    }
+    let tgt_path = paths.pop().unwrap();
+    Ok((paths.pop().unwrap(), tgt_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_crop_padded_regions_of_both_images() {
+        let dir = std::env::temp_dir().join(format!("idiff_crops_{}", std::process::id()));
+        let src = image::RgbaImage::new(100, 100);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(15, 15) = image::Rgba([255, 0, 0, 255]);
+        *tgt.get_pixel_mut(95, 95) = image::Rgba([255, 0, 0, 255]);
+        let blocks = [Bounds::new(10, 20, 10, 20), Bounds::new(90, 100, 90, 100)];
+
+        let crops = write_crops(&src, &tgt, &blocks, 10, &dir, false).unwrap();
+        assert_eq!(2, crops.len());
+        let (region, src_crop, tgt_crop) = &crops[1];
+        assert_eq!(
+            (80, 80, 20, 20),
+            (region.x, region.y, region.width, region.height)
+        );
+        assert_eq!(dir.join("region_2_src.png"), *src_crop);
+        assert_eq!(
+            (30, 30),
+            image::open(&crops[0].2).unwrap().to_rgba8().dimensions()
+        );
+        assert_eq!(
+            &image::Rgba([255, 0, 0, 255]),
+            image::open(tgt_crop).unwrap().to_rgba8().get_pixel(15, 15)
+        );
+        assert!(write_crops(&src, &tgt, &blocks, 10, &dir, false).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_crop_the_changed_area_of_both_images() {
+        let dir = std::env::temp_dir().join(format!("idiff_changes_{}", std::process::id()));
+        let src = image::RgbaImage::new(100, 100);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(15, 25) = image::Rgba([255, 0, 0, 255]);
+
+        let (src_crop, tgt_crop) =
+            write_changed_area(&src, &tgt, &Bounds::new(10, 60, 20, 40), &dir, false).unwrap();
+        let (src_crop, tgt_crop) = (
+            image::open(src_crop).unwrap().to_rgba8(),
+            image::open(tgt_crop).unwrap().to_rgba8(),
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!((50, 20), src_crop.dimensions());
+        assert_eq!(&image::Rgba([0, 0, 0, 0]), src_crop.get_pixel(5, 5));
+        assert_eq!(&image::Rgba([255, 0, 0, 255]), tgt_crop.get_pixel(5, 5));
+    }
+}
@@ -0,0 +1,78 @@
+//! Classification of the direction of the difference (brighter, darker or hue shifted) of the changed pixels
+//! of a region.
+
+use crate::{row_slice, Bounds, Direction};
+
+/// Smallest change of the luma (0-255) of a pixel to count as brighter / darker (smaller changes of a different
+/// color count as hue shifted).
+const LUMA_THRESHOLD: f64 = 4.0;
+
+/// Smallest fraction of the changed pixels of a region in the same direction, for it to prevail.
+const MAJORITY: f64 = 0.6;
+
+impl Direction {
+    /// Classify the changed pixels (with a different RGB color) of the images within the bounds, by the
+    /// prevailing direction.
+    pub(crate) fn classify(
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Bounds,
+    ) -> Direction {
+        let luma = |p: &[u8]| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+        let (mut brighter, mut darker, mut hue_shifted) = (0u64, 0u64, 0u64);
+        for y in bounds.min_height..bounds.max_height {
+            for (s, t) in row_slice(src, y, bounds)
+                .chunks_exact(4)
+                .zip(row_slice(tgt, y, bounds).chunks_exact(4))
+            {
+                if s[..3] == t[..3] {
+                    continue;
+                }
+                match luma(t) - luma(s) {
+                    d if d >= LUMA_THRESHOLD => brighter += 1,
+                    d if d <= -LUMA_THRESHOLD => darker += 1,
+                    _ => hue_shifted += 1,
+                }
+            }
+        }
+
+        let changed = (brighter + darker + hue_shifted).max(1) as f64;
+        [
+            (brighter, Direction::Brighter),
+            (darker, Direction::Darker),
+            (hue_shifted, Direction::HueShifted),
+        ]
+        .into_iter()
+        .find(|(count, _)| *count as f64 / changed >= MAJORITY)
+        .map_or(Direction::Mixed, |(_, direction)| direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_classify_the_prevailing_direction() {
+        let src = image::RgbaImage::from_pixel(10, 10, image::Rgba([100, 100, 100, 255]));
+        let with = |pixels: &[(u32, [u8; 4])]| {
+            let mut tgt = src.clone();
+            for &(x, color) in pixels {
+                tgt.put_pixel(x, 0, image::Rgba(color));
+            }
+            Direction::classify(&src, &tgt, &Bounds::new(0, 10, 0, 10))
+        };
+
+        assert_eq!(
+            Direction::Darker,
+            with(&[(0, [60, 60, 60, 255]), (1, [90, 90, 90, 255])])
+        );
+        assert_eq!(Direction::Brighter, with(&[(0, [200, 200, 200, 255])]));
+        assert_eq!(Direction::HueShifted, with(&[(0, [102, 99, 100, 255])]));
+        assert_eq!(
+            Direction::Mixed,
+            with(&[(0, [0, 0, 0, 255]), (1, [255, 255, 255, 255])])
+        );
+        assert_eq!(Direction::Mixed, with(&[(0, [100, 100, 100, 0])]));
+    }
+}
@@ -0,0 +1,133 @@
+//! DPI aware comparison: the images are turned upright (as per their EXIF orientation) and scaled to their
+//! common physical resolution (the lower DPI of either image, per axis), so that the same content captured at
+//! different pixel densities (for ex. a 144 DPI retina screenshot & a 72 DPI capture) is compared as such.
+
+use crate::{metadata, Side, Warning};
+
+/// Resolution of the images without a physical resolution in their metadata.
+pub(crate) const DEFAULT_DPI: f64 = 72.0;
+
+/// Represents the physical layout of an (encoded) image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Physical {
+    /// EXIF orientation (1-8, 1 being upright).
+    orientation: u32,
+    /// horizontal & vertical DPI (of the stored, not yet upright, pixels).
+    dpi: (f64, f64),
+}
+
+impl Physical {
+    /// Read the orientation & DPI of the (encoded) image.
+    pub(crate) fn read(data: &[u8]) -> Physical {
+        Physical {
+            orientation: metadata::orientation(data).unwrap_or(1),
+            dpi: metadata::dpi(data).unwrap_or((DEFAULT_DPI, DEFAULT_DPI)),
+        }
+    }
+
+    /// Turn the (decoded) image upright.
+    fn upright(&self, img: image::RgbaImage) -> image::RgbaImage {
+        use image::imageops::{flip_horizontal, flip_vertical, rotate180, rotate270, rotate90};
+        match self.orientation {
+            2 => flip_horizontal(&img),
+            3 => rotate180(&img),
+            4 => flip_vertical(&img),
+            5 => flip_horizontal(&rotate90(&img)),
+            6 => rotate90(&img),
+            7 => flip_horizontal(&rotate270(&img)),
+            8 => rotate270(&img),
+            _ => img,
+        }
+    }
+
+    /// DPI of the upright image (the axes are swapped by the rotations of a quarter turn).
+    fn upright_dpi(&self) -> (f64, f64) {
+        match self.orientation {
+            5..=8 => (self.dpi.1, self.dpi.0),
+            _ => self.dpi,
+        }
+    }
+}
+
+/// Turn the source & target upright and scale them to their common physical resolution.
+///
+/// Returns the images & the warnings of the rescaled images.
+pub(crate) fn normalize(
+    (src, src_physical): (image::RgbaImage, Physical),
+    (tgt, tgt_physical): (image::RgbaImage, Physical),
+) -> (image::RgbaImage, image::RgbaImage, Vec<Warning>) {
+    let (src_dpi, tgt_dpi) = (src_physical.upright_dpi(), tgt_physical.upright_dpi());
+    let common = (src_dpi.0.min(tgt_dpi.0), src_dpi.1.min(tgt_dpi.1));
+    let mut warnings = Vec::new();
+    let mut scale = |side: Side, img: image::RgbaImage, dpi: (f64, f64)| {
+        let size = |length: u32, dpi: f64, common: f64| {
+            ((length as f64 * common / dpi).round() as u32).max(1)
+        };
+        let scaled = (
+            size(img.width(), dpi.0, common.0),
+            size(img.height(), dpi.1, common.1),
+        );
+        if scaled == img.dimensions() {
+            return img;
+        }
+        warnings.push(Warning::Rescaled {
+            side,
+            from: img.dimensions(),
+            to: scaled,
+        });
+        image::imageops::thumbnail(&img, scaled.0, scaled.1)
+    };
+    let src = scale(Side::Src, src_physical.upright(src), src_dpi);
+    let tgt = scale(Side::Tgt, tgt_physical.upright(tgt), tgt_dpi);
+    (src, tgt, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn physical(orientation: u32, dpi: (f64, f64)) -> Physical {
+        Physical { orientation, dpi }
+    }
+
+    #[test]
+    fn should_scale_the_higher_dpi_image_to_the_common_resolution() {
+        let retina = image::RgbaImage::from_pixel(200, 100, image::Rgba([255, 0, 0, 255]));
+        let capture = image::RgbaImage::from_pixel(100, 50, image::Rgba([255, 0, 0, 255]));
+
+        let (src, tgt, warnings) = normalize(
+            (retina, physical(1, (144.0, 144.0))),
+            (capture.clone(), physical(1, (DEFAULT_DPI, DEFAULT_DPI))),
+        );
+        assert_eq!(capture, src);
+        assert_eq!(capture, tgt);
+        assert_eq!(
+            vec![Warning::Rescaled {
+                side: Side::Src,
+                from: (200, 100),
+                to: (100, 50)
+            }],
+            warnings
+        );
+    }
+
+    #[test]
+    fn should_turn_the_images_upright_before_scaling() {
+        // Note: the first 2 (stored) columns are the first 2 rows upright
+        let img = image::RgbaImage::from_fn(100, 50, |x, _| match x < 2 {
+            true => image::Rgba([255, 0, 0, 255]),
+            false => image::Rgba([0, 0, 0, 255]),
+        });
+
+        // Note: rotated a quarter turn clockwise, with the (stored) horizontal DPI being the vertical one upright
+        let (src, tgt, warnings) = normalize(
+            (img.clone(), physical(6, (144.0, 72.0))),
+            (img, physical(1, (72.0, 72.0))),
+        );
+        assert_eq!((50, 50), src.dimensions());
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *src.get_pixel(25, 0));
+        assert_eq!(image::Rgba([0, 0, 0, 255]), *src.get_pixel(25, 1));
+        assert_eq!((100, 50), tgt.dimensions());
+        assert_eq!(1, warnings.len());
+    }
+}
@@ -0,0 +1,128 @@
+//! Exit codes of idiff (a stable contract for the scripts & CI), and the errors carrying them.
+
+/// no difference observed (or within the threshold / tolerance), or the command completed.
+pub(crate) const NO_DIFFERENCE: i32 = 0;
+/// a difference was observed (or an image exceeded its budget).
+pub(crate) const DIFFERENCE: i32 = 1;
+/// invalid command line arguments (or options).
+pub(crate) const USAGE: i32 = 2;
+/// error reading or writing a file (or directory).
+pub(crate) const IO: i32 = 3;
+/// error decoding an image (or an image exceeding the resource limits).
+pub(crate) const DECODE: i32 = 4;
+/// different dimensions of the images, in the strict mode.
+pub(crate) const DIMENSION_MISMATCH: i32 = 5;
+
+/// Exit codes & their description (printed by `--explain-exit-codes`, and documented in the man page).
+pub(crate) const CODES: &[(i32, &str)] = &[
+    (
+        NO_DIFFERENCE,
+        "no difference observed (or within the threshold), or the command completed",
+    ),
+    (
+        DIFFERENCE,
+        "a difference was observed, or an image exceeded its budget (check)",
+    ),
+    (
+        USAGE,
+        "invalid command line arguments or options (usage error)",
+    ),
+    (
+        IO,
+        "error reading or writing a file (for ex. a missing source / target image)",
+    ),
+    (
+        DECODE,
+        "error decoding an image (invalid, unsupported or exceeding the resource limits)",
+    ),
+    (
+        DIMENSION_MISMATCH,
+        "the images do not have the same dimensions (strict mode)",
+    ),
+];
+
+/// Represents an error: its message & its exit code, set where the error is raised (so that the code does not
+/// depend on the wording or the language of the message).
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Error {
+    pub(crate) code: i32,
+    pub(crate) message: String,
+}
+
+impl Error {
+    /// Error reading or writing a file (or directory).
+    pub(crate) fn io(message: impl Into<String>) -> Error {
+        Error {
+            code: IO,
+            message: message.into(),
+        }
+    }
+
+    /// Error decoding an image (or an image exceeding the resource limits).
+    pub(crate) fn decode(message: impl Into<String>) -> Error {
+        Error {
+            code: DECODE,
+            message: message.into(),
+        }
+    }
+
+    /// Different dimensions of the images, in the strict mode.
+    pub(crate) fn dimension_mismatch(message: impl Into<String>) -> Error {
+        Error {
+            code: DIMENSION_MISMATCH,
+            message: message.into(),
+        }
+    }
+
+    /// Same error, with the message prefixed (for ex. with the name of the pair in a batch).
+    pub(crate) fn prefixed(self, prefix: &str) -> Error {
+        Error {
+            message: format!("{}{}", prefix, self.message),
+            ..self
+        }
+    }
+}
+
+/// Note: the errors without a code (invalid arguments, options, ..) are usage errors
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error {
+            code: USAGE,
+            message,
+        }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::from(message.to_string())
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Print the exit codes & their description.
+pub(crate) fn explain() {
+    for (code, description) in CODES {
+        println!("{}  {}", code, description);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_the_code_of_the_errors() {
+        assert_eq!(IO, Error::io("denied").code);
+        assert_eq!(DECODE, Error::decode("invalid").code);
+        assert_eq!(USAGE, Error::from("minor threshold (40) ..").code);
+        let error = Error::dimension_mismatch("(1, 2) & (3, 4)").prefixed("a.png: ");
+        assert_eq!(DIMENSION_MISMATCH, error.code);
+        assert_eq!("a.png: (1, 2) & (3, 4)", error.to_string());
+    }
+}
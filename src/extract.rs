@@ -0,0 +1,262 @@
+//! Extraction of the raster images embedded in documents (pdf & office open xml).
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::exit::Error;
+
+/// Represents an image embedded in a document.
+pub(crate) struct Embedded {
+    /// name of the image within the document (for ex. `word/media/image1.png` or `object 12`).
+    pub(crate) name: String,
+    /// decoded image (or the reason it could not be decoded).
+    pub(crate) image: Result<image::RgbaImage, String>,
+}
+
+/// Extract the raster images embedded in the document, in the order they are stored.
+///
+/// Supports pdf (jpeg & uncompressed / deflated 8-bit images) and office open xml documents (docx, pptx & xlsx).
+pub(crate) fn images(path: &Path) -> Result<Vec<Embedded>, Error> {
+    let error = || {
+        Error::io(format!(
+            "Encountered error while reading {}.",
+            path.display()
+        ))
+    };
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("pdf") => Ok(pdf_images(&std::fs::read(path).map_err(|_| error())?)),
+        Some("docx" | "pptx" | "xlsx") => {
+            let file = std::fs::File::open(path).map_err(|_| error())?;
+            ooxml_images(file).map_err(|_| error())
+        }
+        _ => Err(Error::from(format!(
+            "Unsupported document {} (expected a pdf, docx, pptx or xlsx file).",
+            path.display()
+        ))),
+    }
+}
+
+/// Extract the (raster) media of the office open xml document, in the order of the zip entries.
+fn ooxml_images(file: impl Read + std::io::Seek) -> zip::result::ZipResult<Vec<Embedded>> {
+    let mut zip = zip::ZipArchive::new(file)?;
+    let mut images = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if !entry.name().contains("/media/") {
+            continue;
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        // Note: vector media (for ex. emf / svg) is skipped
+        if image::guess_format(&data).is_err() {
+            continue;
+        }
+        images.push(Embedded {
+            name: entry.name().to_string(),
+            image: image::load_from_memory(&data)
+                .map(|i| i.to_rgba8())
+                .map_err(|_| String::from("Encountered error while decoding the image.")),
+        });
+    }
+    Ok(images)
+}
+
+/// Extract the image xobjects of the pdf, in the order of the objects in the file.
+///
+/// Note: only the objects are scanned (the cross reference table is not read), which is enough for the
+/// images, as streams cannot be stored within object streams.
+fn pdf_images(data: &[u8]) -> Vec<Embedded> {
+    let mut images = Vec::new();
+    let mut position = 0;
+    while let Some(start) = find(data, b" obj", position) {
+        let end_object = find(data, b"endobj", start).unwrap_or(data.len());
+        position = end_object;
+        let Some(stream) = find(&data[..end_object], b"stream", start) else {
+            continue;
+        };
+
+        let dictionary = String::from_utf8_lossy(&data[start..stream]);
+        if name(&dictionary, "/Subtype") != Some("Image") {
+            continue;
+        }
+
+        // Note: the stream data starts after the end of line following the keyword
+        let mut begin = stream + b"stream".len();
+        if data[begin..].starts_with(b"\r\n") {
+            begin += 2;
+        } else if data[begin..].starts_with(b"\n") {
+            begin += 1;
+        }
+        let length = integer(&dictionary, "/Length").map(|l| l as usize);
+        let end = match length {
+            Some(length) if data[begin..].len() >= length => begin + length,
+            _ => find(data, b"endstream", begin).unwrap_or(data.len()),
+        };
+        // Note: the stream data can contain `endobj`
+        position = end;
+
+        images.push(Embedded {
+            name: format!(
+                "object {}",
+                object_number(data, start).unwrap_or(images.len())
+            ),
+            image: decode_pdf_image(&dictionary, &data[begin..end]),
+        });
+    }
+    images
+}
+
+/// Decode the data of the pdf image xobject.
+fn decode_pdf_image(dictionary: &str, data: &[u8]) -> Result<image::RgbaImage, String> {
+    let filter = name(dictionary, "/Filter");
+    if filter == Some("DCTDecode") {
+        return image::load_from_memory(data)
+            .map(|i| i.to_rgba8())
+            .map_err(|_| String::from("Encountered error while decoding the jpeg image."));
+    }
+
+    let unsupported = |what: &str| format!("Unsupported pdf image ({}).", what);
+    let pixels = match filter {
+        None => data.to_vec(),
+        Some("FlateDecode") => {
+            if dictionary.contains("/Predictor") {
+                return Err(unsupported("predictor"));
+            }
+            miniz_oxide::inflate::decompress_to_vec_zlib(data)
+                .map_err(|_| String::from("Encountered error while inflating the image."))?
+        }
+        Some(filter) => return Err(unsupported(filter)),
+    };
+
+    let (width, height) = match (
+        integer(dictionary, "/Width"),
+        integer(dictionary, "/Height"),
+    ) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+        _ => return Err(unsupported("dimensions")),
+    };
+    if integer(dictionary, "/BitsPerComponent") != Some(8) {
+        return Err(unsupported("bits per component"));
+    }
+
+    // Note: the number of components is inferred from the data (the color space can be an indirect reference)
+    let area = width as usize * height as usize;
+    let rgba = |f: fn(&[u8]) -> image::Rgba<u8>, components: usize| {
+        let pixels = pixels[..area * components]
+            .chunks(components)
+            .flat_map(|p| f(p).0);
+        image::RgbaImage::from_raw(width, height, pixels.collect())
+    };
+    let image = match pixels.len() / area {
+        1 => rgba(|p| image::Rgba([p[0], p[0], p[0], 255]), 1),
+        3 => rgba(|p| image::Rgba([p[0], p[1], p[2], 255]), 3),
+        4 => rgba(
+            |p| {
+                let k = 255 - p[3] as u32;
+                let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                image::Rgba([channel(p[0]), channel(p[1]), channel(p[2]), 255])
+            },
+            4,
+        ),
+        _ => None,
+    };
+    image.ok_or_else(|| unsupported("color space"))
+}
+
+/// Find the first occurrence of the needle in the data, starting from `from`.
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| from + i)
+}
+
+/// Number of the object starting at `start` (for ex. `12` for `12 0 obj`).
+fn object_number(data: &[u8], start: usize) -> Option<usize> {
+    let header = String::from_utf8_lossy(&data[start.saturating_sub(20)..start]);
+    let mut parts = header.split_whitespace().rev();
+    parts.next()?;
+    parts.next()?.parse().ok()
+}
+
+/// Rest of the pdf dictionary following the key.
+fn after<'a>(dictionary: &'a str, key: &str) -> Option<&'a str> {
+    let mut rest = dictionary;
+    loop {
+        rest = &rest[rest.find(key)? + key.len()..];
+        // Note: skip the keys with the same prefix (for ex. `/Width` & `/Widths`)
+        if !rest.starts_with(|c: char| c.is_alphanumeric()) {
+            return Some(rest.trim_start().trim_start_matches('[').trim_start());
+        }
+    }
+}
+
+/// Value (token) following the key in the pdf dictionary.
+fn value<'a>(dictionary: &'a str, key: &str) -> Option<&'a str> {
+    let value = after(dictionary, key)?;
+    let end = value
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_whitespace() || "/[]<>()".contains(*c))
+        .map_or(value.len(), |(i, _)| i);
+    Some(&value[..end])
+}
+
+/// Name (without the `/`) following the key in the pdf dictionary.
+fn name<'a>(dictionary: &'a str, key: &str) -> Option<&'a str> {
+    value(dictionary, key)?.strip_prefix('/')
+}
+
+/// Integer following the key in the pdf dictionary (`None` for indirect references).
+fn integer(dictionary: &str, key: &str) -> Option<u32> {
+    let number = value(dictionary, key)?.parse().ok()?;
+    // Note: `12 0 R` is a reference to an object
+    let mut tokens = after(dictionary, key)?.split_whitespace().skip(1);
+    match (tokens.next(), tokens.next()) {
+        (Some(_), Some(r)) if r.starts_with('R') => None,
+        _ => Some(number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_values_of_pdf_dictionary() {
+        let dictionary = "<< /Type /XObject /Subtype/Image /Widths [1] /Width 2 /Length 7 0 R /Filter [/FlateDecode] >>";
+
+        assert_eq!(Some("Image"), name(dictionary, "/Subtype"));
+        assert_eq!(Some("FlateDecode"), name(dictionary, "/Filter"));
+        assert_eq!(Some(2), integer(dictionary, "/Width"));
+        assert_eq!(None, integer(dictionary, "/Length"));
+    }
+
+    #[test]
+    fn should_extract_images_of_pdf() {
+        let pixels = miniz_oxide::deflate::compress_to_vec_zlib(&[255, 0, 0, 0, 0, 255], 6);
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog >>\nendobj\n".to_vec();
+        pdf.extend(
+            format!(
+                "4 0 obj\n<< /Type /XObject /Subtype /Image /Width 2 /Height 1 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+                pixels.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend(&pixels);
+        pdf.extend(b"\nendstream\nendobj\n5 0 obj\n<< /Subtype /Image /Width 1 /Height 1 /BitsPerComponent 8 /Filter /JPXDecode >>\nstream\n\nendstream\nendobj\n%%EOF");
+
+        let images = pdf_images(&pdf);
+        assert_eq!(2, images.len());
+        assert_eq!("object 4", images[0].name);
+        let image = images[0].image.as_ref().unwrap();
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *image.get_pixel(0, 0));
+        assert_eq!(image::Rgba([0, 0, 255, 255]), *image.get_pixel(1, 0));
+        assert!(images[1].image.is_err());
+    }
+}
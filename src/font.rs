@@ -0,0 +1,117 @@
+//! Minimal (3x5 pixels) bitmap font, to draw labels onto the outputs.
+
+/// Glyphs (rows top to bottom, `1` for a set pixel); lowercase letters are drawn as uppercase & unknown
+/// characters as `?`.
+const GLYPHS: &[(char, &str)] = &[
+    ('0', "111101101101111"),
+    ('1', "010110010010111"),
+    ('2', "111001111100111"),
+    ('3', "111001111001111"),
+    ('4', "101101111001001"),
+    ('5', "111100111001111"),
+    ('6', "111100111101111"),
+    ('7', "111001001001001"),
+    ('8', "111101111101111"),
+    ('9', "111101111001111"),
+    ('A', "010101111101101"),
+    ('B', "110101110101110"),
+    ('C', "011100100100011"),
+    ('D', "110101101101110"),
+    ('E', "111100111100111"),
+    ('F', "111100111100100"),
+    ('G', "011100101101011"),
+    ('H', "101101111101101"),
+    ('I', "111010010010111"),
+    ('J', "001001001101010"),
+    ('K', "101101110101101"),
+    ('L', "100100100100111"),
+    ('M', "101111111101101"),
+    ('N', "110101101101101"),
+    ('O', "010101101101010"),
+    ('P', "110101110100100"),
+    ('Q', "010101101110011"),
+    ('R', "110101110101101"),
+    ('S', "011100010001110"),
+    ('T', "111010010010010"),
+    ('U', "101101101101111"),
+    ('V', "101101101101010"),
+    ('W', "101101111111101"),
+    ('X', "101101010101101"),
+    ('Y', "101101010010010"),
+    ('Z', "111001010100111"),
+    (' ', "000000000000000"),
+    ('.', "000000000000010"),
+    (',', "000000000010100"),
+    (':', "000010000010000"),
+    ('%', "101001010100101"),
+    ('<', "001010100010001"),
+    ('>', "100010001010100"),
+    ('=', "000111000111000"),
+    ('-', "000000111000000"),
+    ('_', "000000000000111"),
+    ('/', "001001010100100"),
+    ('(', "010100100100010"),
+    (')', "010001001001010"),
+    ('+', "000010111010000"),
+    ('#', "101111101111101"),
+    ('?', "111001010000010"),
+];
+
+/// Height (in pixels) of the text drawn at the scale.
+pub(crate) fn text_height(scale: u32) -> u32 {
+    5 * scale
+}
+
+/// Width (in pixels) of the text drawn at the scale (a pixel column between the characters).
+pub(crate) fn text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * 4).saturating_sub(1) * scale
+}
+
+/// Draw the text with its top-left corner at (x, y), clipped to the image.
+pub(crate) fn draw_text(
+    img: &mut image::RgbaImage,
+    x: u32,
+    y: u32,
+    text: &str,
+    scale: u32,
+    color: image::Rgba<u8>,
+) {
+    for (i, c) in text.chars().enumerate() {
+        let c = c.to_ascii_uppercase();
+        let glyph = GLYPHS
+            .iter()
+            .find(|(g, _)| *g == c)
+            .or_else(|| GLYPHS.iter().find(|(g, _)| *g == '?'))
+            .map(|(_, rows)| rows.as_bytes())
+            .unwrap();
+        let left = x + i as u32 * 4 * scale;
+        for (bit, _) in glyph.iter().enumerate().filter(|(_, &b)| b == b'1') {
+            let (column, row) = (bit as u32 % 3, bit as u32 / 3);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (px, py) = (left + column * scale + dx, y + row * scale + dy);
+                    if px < img.width() && py < img.height() {
+                        img.put_pixel(px, py, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_draw_text_clipped_to_the_image() {
+        let mut img = image::RgbaImage::new(6, 5);
+        draw_text(&mut img, 0, 0, "1%", 1, image::Rgba([255, 255, 255, 255]));
+
+        let set = |x, y| img.get_pixel(x, y)[3] == 255;
+        assert!(set(1, 0) && set(0, 1) && set(1, 4) && set(2, 4) && !set(0, 0));
+        assert!(set(4, 0) && !set(5, 0));
+        assert_eq!(7, text_width("1%", 1));
+        assert_eq!(10, text_height(2));
+    }
+}
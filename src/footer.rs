@@ -0,0 +1,66 @@
+//! Footer bar appended below the output, describing the comparison (file names, time, difference & version), so
+//! that the outputs shared out of context are self-describing.
+
+use crate::font;
+
+/// Append the footer bar with the lines of text below the image.
+pub(crate) fn append(img: &image::RgbaImage, lines: &[String]) -> image::RgbaImage {
+    // Note: the text is drawn smaller onto the narrow images (& clipped, if still wider than the image)
+    let scale = match img.width() >= 600 {
+        true => 2,
+        false => 1,
+    };
+    let line_height = font::text_height(scale) + 4;
+    let mut output = image::RgbaImage::from_pixel(
+        img.width(),
+        img.height() + lines.len() as u32 * line_height + 4,
+        image::Rgba([32, 32, 32, 255]),
+    );
+    image::imageops::replace(&mut output, img, 0, 0);
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(
+            &mut output,
+            4,
+            img.height() + 4 + i as u32 * line_height,
+            line,
+            scale,
+            image::Rgba([255, 255, 255, 255]),
+        );
+    }
+    output
+}
+
+/// Lines of the footer of the comparison of the files, at the time (UTC, `YYYYMMDDTHHMMSS`).
+pub(crate) fn lines(src: &str, tgt: &str, diff: &str, time: &str) -> Vec<String> {
+    vec![
+        format!("SRC: {}", src),
+        format!("TGT: {}", tgt),
+        format!(
+            "DIFF: {}  TIME: {} UTC  IDIFF {}",
+            diff,
+            time,
+            env!("CARGO_PKG_VERSION")
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_append_the_footer_below_the_image() {
+        let img = image::RgbaImage::from_pixel(100, 40, image::Rgba([0, 0, 255, 255]));
+        let lines = lines("a.png", "b.png", "1.5%", "20240229T235959");
+
+        let output = append(&img, &lines);
+
+        // Note: 3 lines of 5 + 4 pixels, & a margin of 4 pixels
+        assert_eq!((100, 71), output.dimensions());
+        assert_eq!(image::Rgba([0, 0, 255, 255]), *output.get_pixel(99, 39));
+        assert_eq!(image::Rgba([32, 32, 32, 255]), *output.get_pixel(99, 70));
+        // Note: the top-left pixel of the `S` of the first line is not set, the one right of it is
+        assert_eq!(image::Rgba([255, 255, 255, 255]), *output.get_pixel(5, 44));
+        assert!(lines[2].starts_with("DIFF: 1.5%  TIME: 20240229T235959 UTC  IDIFF "));
+    }
+}
@@ -0,0 +1,230 @@
+//! Html gallery of the results of a batch: an `index.html` listing every pair with its status, percentage
+//! difference & a link to its highlighted difference, sortable by difference.
+
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+use crate::{check_overwrite, percent, save_image};
+
+/// Name of the gallery page in the report directory.
+const INDEX: &str = "index.html";
+
+/// Represents the status of a pair (or an unpaired file).
+enum Status {
+    Identical,
+    /// percentage difference & file name of the highlighted difference (in the report directory), if any.
+    Different(f32, Option<String>),
+    Failed(String),
+    OnlyIn(&'static str),
+}
+
+/// Represents the gallery of the results, written into the report directory.
+pub(crate) struct Gallery {
+    dir: PathBuf,
+    rows: Vec<(String, Status)>,
+}
+
+impl Gallery {
+    /// Create the report directory (if it does not exist), refusing to overwrite an existing gallery unless forced.
+    pub(crate) fn create(dir: &Path, force: bool) -> Result<Gallery, Error> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while creating report directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        check_overwrite(&dir.join(INDEX), force)?;
+        Ok(Gallery {
+            dir: dir.to_path_buf(),
+            rows: Vec::new(),
+        })
+    }
+
+    pub(crate) fn identical(&mut self, name: &str) {
+        self.rows.push((name.to_string(), Status::Identical));
+    }
+
+    /// Add the pair with difference, writing its highlighted difference (if any) into the report directory (named
+    /// after the position of the pair, as the names of the pairs can collide once sanitized).
+    pub(crate) fn different(
+        &mut self,
+        name: &str,
+        diff: f32,
+        highlight: Option<&image::RgbaImage>,
+    ) -> Result<(), Error> {
+        let file = match highlight {
+            Some(highlight) => {
+                let file = format!("{:04}_{}.png", self.rows.len(), sanitize(name));
+                save_image(highlight, &self.dir.join(&file), None)?;
+                Some(file)
+            }
+            None => None,
+        };
+        self.rows
+            .push((name.to_string(), Status::Different(diff, file)));
+        Ok(())
+    }
+
+    pub(crate) fn failed(&mut self, name: &str, error: &str) {
+        self.rows
+            .push((name.to_string(), Status::Failed(error.to_string())));
+    }
+
+    pub(crate) fn only_in(&mut self, side: &'static str, name: &str) {
+        self.rows.push((name.to_string(), Status::OnlyIn(side)));
+    }
+
+    /// Write the gallery page (the rows in the order added), returning its path.
+    pub(crate) fn write(&self) -> Result<PathBuf, Error> {
+        let mut rows = String::new();
+        for (name, status) in &self.rows {
+            // Note: the rows without a percentage sort after the ones with difference
+            let (class, sort, status, diff, link) = match status {
+                Status::Identical => (
+                    "identical",
+                    0.0,
+                    String::from("identical"),
+                    "0%".to_string(),
+                    String::new(),
+                ),
+                Status::Different(diff, file) => (
+                    "different",
+                    *diff,
+                    String::from("different"),
+                    percent::render(*diff, None),
+                    file.as_ref()
+                        .map(|file| format!("<a href=\"{}\">diff</a>", file))
+                        .unwrap_or_default(),
+                ),
+                Status::Failed(error) => (
+                    "failed",
+                    -1.0,
+                    format!("error: {}", error),
+                    String::new(),
+                    String::new(),
+                ),
+                Status::OnlyIn(side) => (
+                    "only-in",
+                    -1.0,
+                    format!("only in {}", side),
+                    String::new(),
+                    String::new(),
+                ),
+            };
+            rows.push_str(&format!(
+                "<tr class=\"{}\" data-diff=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                class,
+                sort,
+                escape(name),
+                escape(&status),
+                escape(&diff),
+                link
+            ));
+        }
+
+        let html = format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>idiff batch report</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+th.sortable {{ cursor: pointer; }}
+.different {{ background: #fde8e8; }}
+.failed, .only-in {{ background: #fdf6e3; }}
+</style>
+</head>
+<body>
+<h1>idiff batch report</h1>
+<p>{} pair(s) & unpaired file(s). Click the difference header to sort by difference.</p>
+<table>
+<thead><tr><th>Name</th><th>Status</th><th class=\"sortable\" onclick=\"sortByDiff()\">Difference</th><th>Highlight</th></tr></thead>
+<tbody id=\"rows\">
+{}</tbody>
+</table>
+<script>
+let descending = true;
+function sortByDiff() {{
+  const body = document.getElementById('rows');
+  const rows = Array.from(body.rows);
+  rows.sort((a, b) => (b.dataset.diff - a.dataset.diff) * (descending ? 1 : -1));
+  rows.forEach(row => body.appendChild(row));
+  descending = !descending;
+}}
+</script>
+</body>
+</html>
+",
+            self.rows.len(),
+            rows
+        );
+
+        let index = self.dir.join(INDEX);
+        std::fs::write(&index, html).map_err(|e| {
+            Error::io(format!(
+                "Encountered error while writing report {}: {}",
+                index.display(),
+                e
+            ))
+        })?;
+        Ok(index)
+    }
+}
+
+/// File name safe version of the name (anything but ascii alphanumerics, `-` & `_` replaced with `_`).
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            let safe = c.is_ascii_alphanumeric() || matches!(c, '-' | '_');
+            if safe {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Escape the text for html.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_write_a_row_per_pair_with_links_to_the_differences() {
+        let dir = std::env::temp_dir().join(format!("idiff_gallery_{}", std::process::id()));
+        let mut gallery = Gallery::create(&dir, false).unwrap();
+        gallery.identical("same.png");
+        gallery
+            .different("a/b <c>.png", 1.5, Some(&image::RgbaImage::new(4, 4)))
+            .unwrap();
+        gallery.failed(
+            "broken.png",
+            "Encountered error while opening source / target image.",
+        );
+        gallery.only_in("src", "new.png");
+        let index = gallery.write().unwrap();
+
+        let html = std::fs::read_to_string(&index).unwrap();
+        let linked = dir.join("0001_a_b__c__png.png").exists();
+        assert!(Gallery::create(&dir, false).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(linked);
+        assert!(html.contains("<td>a/b &lt;c&gt;.png</td><td>different</td><td>1.5%</td><td><a href=\"0001_a_b__c__png.png\">diff</a></td>"));
+        assert!(html.contains("data-diff=\"0\"><td>same.png</td><td>identical</td>"));
+        assert!(html.contains("<td>only in src</td>"));
+        assert!(html.contains("error: Encountered error while opening"));
+    }
+}
@@ -0,0 +1,75 @@
+//! Matching of file names with glob patterns (expanded internally, so they work the same in every shell).
+
+/// Checks if the file name is a glob pattern (contains `*`, `?` or `[`).
+pub(crate) fn is_pattern(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
+/// Checks if the file name matches the glob pattern: `*` matches any characters, `?` a single character and
+/// `[...]` a single character of the set (with ranges, for ex. `[a-c]`, negated with `!` or `^`).
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    matches_from(&pattern, &name)
+}
+
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| matches_from(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && matches_from(&pattern[1..], &name[1..]),
+        Some('[') => match (class(&pattern[1..]), name.first()) {
+            (Some((matched, length)), Some(&c)) => {
+                matched(c) && matches_from(&pattern[1 + length..], &name[1..])
+            }
+            // Note: an unclosed `[` matches itself
+            (None, Some('[')) => matches_from(&pattern[1..], &name[1..]),
+            _ => false,
+        },
+        Some(&c) => name.first() == Some(&c) && matches_from(&pattern[1..], &name[1..]),
+    }
+}
+
+/// Parse the character class following a `[`.
+///
+/// Returns the matcher & the length of the class (incl. the closing `]`), or `None` if it is not closed.
+fn class(pattern: &[char]) -> Option<(impl Fn(char) -> bool + '_, usize)> {
+    let negated = matches!(pattern.first(), Some('!' | '^'));
+    let start = negated as usize;
+    // Note: a `]` right after the `[` (or the negation) is a member of the class
+    let end = start + 1 + pattern.get(start + 1..)?.iter().position(|&c| c == ']')?;
+    let members = &pattern[start..end];
+
+    let matcher = move |c: char| {
+        let mut i = 0;
+        let mut found = false;
+        while i < members.len() {
+            if members.get(i + 1) == Some(&'-') && i + 2 < members.len() {
+                found |= (members[i]..=members[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= members[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matcher, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_glob_patterns() {
+        assert!(matches("*.png", "home.png"));
+        assert!(!matches("*.png", "home.png.bak"));
+        assert!(matches("shot_??.png", "shot_01.png"));
+        assert!(!matches("shot_??.png", "shot_1.png"));
+        assert!(matches("[a-c]*", "button.png"));
+        assert!(!matches("[!a-c]*", "button.png"));
+        assert!(matches("*ü*", "menü.png"));
+        assert!(!is_pattern("home.png"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::exit::Error;
+
+/// Represents the result of a comparison recorded in the history file (one json object per line).
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Entry {
+    /// time of the comparison (seconds since the unix epoch).
+    pub(crate) timestamp: u64,
+    /// source file name.
+    pub(crate) src: String,
+    /// target file name.
+    pub(crate) tgt: String,
+    /// percentage difference between the images.
+    pub(crate) diff: f32,
+}
+
+impl Entry {
+    /// Create an entry for a comparison completed now.
+    pub(crate) fn now(src: &Path, tgt: &Path, diff: f32) -> Entry {
+        Entry {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            src: src.display().to_string(),
+            tgt: tgt.display().to_string(),
+            diff,
+        }
+    }
+}
+
+/// Represents the trend of the difference between a pair of files over the recorded runs.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Trend {
+    pub(crate) src: String,
+    pub(crate) tgt: String,
+    /// number of recorded runs.
+    pub(crate) runs: usize,
+    /// difference of the first run.
+    pub(crate) first: f32,
+    /// difference of the last run.
+    pub(crate) last: f32,
+    /// smallest difference.
+    pub(crate) min: f32,
+    /// largest difference.
+    pub(crate) max: f32,
+}
+
+/// Append the entry into the history file (created, if it does not exist).
+pub(crate) fn append(path: &Path, entry: &Entry) -> Result<(), Error> {
+    let error = |e: &dyn std::fmt::Display| {
+        Error::io(format!(
+            "Encountered error while writing history file {}: {}",
+            path.display(),
+            e
+        ))
+    };
+    let line = serde_json::to_string(entry).map_err(|e| error(&e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| error(&e))?;
+    // Note: a single write, so the lines appended by concurrent comparisons (for ex. `batch --jobs`) do not interleave
+    file.write_all(format!("{}\n", line).as_bytes())
+        .map_err(|e| error(&e))
+}
+
+/// Read the entries of the history file (in the recorded order).
+pub(crate) fn read(path: &Path) -> Result<Vec<Entry>, Error> {
+    let file = std::fs::File::open(path).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while reading history file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| {
+            Error::io(format!(
+                "Encountered error while reading history file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Invalid entry in history file {} (line {}): {}",
+                path.display(),
+                i + 1,
+                e
+            )
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Summarize the entries per pair of files.
+///
+/// Returns the trends sorted by the (source, target) file names.
+pub(crate) fn trends(entries: &[Entry]) -> Vec<Trend> {
+    let mut pairs: BTreeMap<(&str, &str), Vec<f32>> = BTreeMap::new();
+    for entry in entries {
+        pairs
+            .entry((&entry.src, &entry.tgt))
+            .or_default()
+            .push(entry.diff);
+    }
+
+    pairs
+        .into_iter()
+        .map(|((src, tgt), diffs)| Trend {
+            src: src.to_string(),
+            tgt: tgt.to_string(),
+            runs: diffs.len(),
+            first: diffs[0],
+            last: diffs[diffs.len() - 1],
+            min: diffs.iter().copied().fold(f32::INFINITY, f32::min),
+            max: diffs.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(src: &str, diff: f32) -> Entry {
+        Entry {
+            timestamp: 0,
+            src: src.to_string(),
+            tgt: String::from("tgt.png"),
+            diff,
+        }
+    }
+
+    #[test]
+    fn should_summarize_trends_per_pair() {
+        let entries = vec![
+            entry("b.png", 0.1),
+            entry("a.png", 0.0),
+            entry("b.png", 0.05),
+            entry("b.png", 0.3),
+        ];
+
+        let trends = trends(&entries);
+
+        assert_eq!(2, trends.len());
+        assert_eq!("a.png", trends[0].src);
+        assert_eq!(
+            Trend {
+                src: String::from("b.png"),
+                tgt: String::from("tgt.png"),
+                runs: 3,
+                first: 0.1,
+                last: 0.3,
+                min: 0.05,
+                max: 0.3,
+            },
+            trends[1]
+        );
+    }
+
+    #[test]
+    fn should_append_and_read_entries() {
+        let path = std::env::temp_dir().join(format!("idiff_history_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        append(&path, &entry("a.png", 0.5)).unwrap();
+        append(&path, &entry("a.png", 1.0)).unwrap();
+
+        assert_eq!(
+            vec![entry("a.png", 0.5), entry("a.png", 1.0)],
+            read(&path).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
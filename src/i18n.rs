@@ -0,0 +1,129 @@
+//! Catalog of the (result & error) messages, by their stable ids, in the supported languages.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Lang {
+    // english
+    #[default]
+    En,
+    // japanese
+    Ja,
+}
+
+/// Messages (templates, the placeholders `{0}`, `{1}`, .. are filled with the arguments) by their stable id.
+const EN: &[(&str, &str)] = &[
+    (
+        "compare.identical",
+        "Comparison Completed. No difference observed between the images!",
+    ),
+    (
+        "compare.difference",
+        "A difference of '{0}' is observed between images.",
+    ),
+    ("compare.differing-pixels", "{0} pixel(s) with difference."),
+    (
+        "compare.highlight-disabled",
+        "(Difference highlighting is currently disabled. Try with 'highlight' flag to highlight the differences)",
+    ),
+    ("output.written", "Output written into {0}"),
+    ("batch.no-difference", "no difference"),
+    ("batch.only-in", "Only in {0}: {1}"),
+    (
+        "batch.completed",
+        "Batch Completed. {0} of {1} image(s) have differences{2}.",
+    ),
+    ("warning", "Warning: {0}"),
+    ("error.count", "{0} error(s):"),
+    (
+        "error.invalid-path",
+        "Invalid values for src/tgt path. Please check and try again.",
+    ),
+    (
+        "error.open",
+        "Encountered error while opening source / target image.",
+    ),
+    (
+        "error.dimension-mismatch",
+        "'src' ({0}) & 'tgt' ({1}) do not have the same dimensions. (Try without 'strict' flag to check the differences)",
+    ),
+];
+
+const JA: &[(&str, &str)] = &[
+    ("compare.identical", "比較完了。画像間に差分はありません！"),
+    ("compare.difference", "画像間に '{0}' の差分があります。"),
+    ("compare.differing-pixels", "差分のあるピクセル: {0}"),
+    (
+        "compare.highlight-disabled",
+        "(差分のハイライトは無効です。'highlight' フラグで差分をハイライトできます)",
+    ),
+    ("output.written", "{0} に出力しました"),
+    ("batch.no-difference", "差分なし"),
+    ("batch.only-in", "{0} のみ: {1}"),
+    (
+        "batch.completed",
+        "バッチ完了。{1} 枚中 {0} 枚の画像に差分があります{2}。",
+    ),
+    ("warning", "警告: {0}"),
+    ("error.count", "{0} 件のエラー:"),
+    (
+        "error.invalid-path",
+        "src / tgt のパスが不正です。確認して再度お試しください。",
+    ),
+    (
+        "error.open",
+        "ソース / ターゲット画像を開けませんでした。",
+    ),
+    (
+        "error.dimension-mismatch",
+        "'src' ({0}) と 'tgt' ({1}) のサイズが異なります。('strict' フラグなしで差分を確認できます)",
+    ),
+];
+
+/// Set the language of the messages (once, the later calls are ignored).
+pub(crate) fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// Message (by its id) in the language set (english by default), filled with the arguments.
+pub(crate) fn text(id: &str, args: &[&dyn Display]) -> String {
+    text_in(LANG.get().copied().unwrap_or_default(), id, args)
+}
+
+/// Note: the messages missing in the catalog of the language fall back to english (& the id, if unknown)
+fn text_in(lang: Lang, id: &str, args: &[&dyn Display]) -> String {
+    let catalog = match lang {
+        Lang::En => EN,
+        Lang::Ja => JA,
+    };
+    let find =
+        |catalog: &[(&str, &'static str)]| catalog.iter().find(|(i, _)| *i == id).map(|(_, t)| *t);
+    let mut text = find(catalog).or_else(|| find(EN)).unwrap_or(id).to_string();
+    for (i, arg) in args.iter().enumerate() {
+        text = text.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_fill_the_placeholders_in_the_order_of_the_language() {
+        assert_eq!(
+            "Batch Completed. 1 of 3 image(s) have differences.",
+            text_in(Lang::En, "batch.completed", &[&1, &3, &""])
+        );
+        assert_eq!(
+            "バッチ完了。3 枚中 1 枚の画像に差分があります。",
+            text_in(Lang::Ja, "batch.completed", &[&1, &3, &""])
+        );
+        assert!(JA.iter().all(|(id, _)| EN.iter().any(|(i, _)| i == id)));
+    }
+}
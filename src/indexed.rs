@@ -0,0 +1,228 @@
+//! Paletted (indexed) png & gif images, compared by their palette indices (instead of their RGBA pixels) when
+//! both the images share the same palette.
+
+use crate::Bounds;
+
+/// Represents an image of palette indices (one byte per pixel, row-major).
+#[derive(Debug, PartialEq)]
+pub(crate) struct Indexed {
+    width: u32,
+    height: u32,
+    /// colors (RGBA, transparent as per the tRNS chunk / transparent index) by index.
+    palette: Vec<[u8; 4]>,
+    indices: Vec<u8>,
+}
+
+impl Indexed {
+    /// Expand the indices into the RGBA pixels.
+    pub(crate) fn to_rgba(&self) -> image::RgbaImage {
+        let mut pixels = Vec::with_capacity(self.indices.len() * 4);
+        for &index in &self.indices {
+            pixels.extend_from_slice(&self.palette[index as usize]);
+        }
+        image::RgbaImage::from_raw(self.width, self.height, pixels).unwrap_or_default()
+    }
+
+    /// Number of the pixels (within the bounds) with different indices.
+    pub(crate) fn differing_pixels(&self, other: &Indexed, bounds: &Bounds) -> u64 {
+        let row = |y: u32| {
+            let start = (y * self.width) as usize;
+            start + bounds.min_width as usize..start + bounds.max_width as usize
+        };
+        (bounds.min_height..bounds.max_height)
+            .map(|y| {
+                self.indices[row(y)]
+                    .iter()
+                    .zip(&other.indices[row(y)])
+                    .filter(|(a, b)| a != b)
+                    .count() as u64
+            })
+            .sum()
+    }
+
+    /// Note: the indices of the duplicated colors are replaced with the first one, so that the same colors have the
+    /// same index
+    fn deduplicate(mut self) -> Indexed {
+        let first: Vec<u8> = (0..self.palette.len())
+            .map(|i| {
+                self.palette
+                    .iter()
+                    .position(|color| *color == self.palette[i])
+                    .unwrap_or(i) as u8
+            })
+            .collect();
+        for index in &mut self.indices {
+            *index = first[*index as usize];
+        }
+        self
+    }
+
+    /// Checks every index is within the palette.
+    fn is_valid(&self) -> bool {
+        self.indices.len() == (self.width * self.height) as usize
+            && self
+                .indices
+                .iter()
+                .all(|&i| (i as usize) < self.palette.len())
+    }
+}
+
+/// Decode the paletted png or gif (its first frame, if it covers the whole image), or `None` if the image is not
+/// paletted (or not decodable as such).
+fn decode(data: &[u8]) -> Option<Indexed> {
+    let indexed = decode_png(data).or_else(|| decode_gif(data))?;
+    indexed.is_valid().then(|| indexed.deduplicate())
+}
+
+fn decode_png(data: &[u8]) -> Option<Indexed> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().ok()?;
+    let info = reader.info();
+    if info.color_type != png::ColorType::Indexed {
+        return None;
+    }
+    let (width, height) = (info.width, info.height);
+    let trns = info.trns.as_deref().unwrap_or_default();
+    let palette = info
+        .palette
+        .as_deref()?
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(i, c)| [c[0], c[1], c[2], trns.get(i).copied().unwrap_or(255)])
+        .collect();
+    let bits = info.bit_depth as usize;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).ok()?;
+    // Note: the indices of the lower bit depths are packed (most significant bits first) in every row
+    let mut indices = Vec::with_capacity((width * height) as usize);
+    for row in buffer.chunks(frame.line_size).take(height as usize) {
+        for x in 0..width as usize {
+            let bit = x * bits;
+            let byte = *row.get(bit / 8)?;
+            let shift = 8 - bits - bit % 8;
+            indices.push((byte >> shift) & ((1u16 << bits) - 1) as u8);
+        }
+    }
+    Some(Indexed {
+        width,
+        height,
+        palette,
+        indices,
+    })
+}
+
+fn decode_gif(data: &[u8]) -> Option<Indexed> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(data).ok()?;
+    let (width, height) = (decoder.width(), decoder.height());
+    let global = decoder.global_palette().map(<[u8]>::to_vec);
+    let frame = decoder.read_next_frame().ok()??;
+    if (frame.left, frame.top, frame.width, frame.height) != (0, 0, width, height) {
+        return None;
+    }
+    let palette = frame
+        .palette
+        .as_ref()
+        .or(global.as_ref())?
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(i, c)| {
+            let alpha = match frame.transparent == Some(i as u8) {
+                true => 0,
+                false => 255,
+            };
+            [c[0], c[1], c[2], alpha]
+        })
+        .collect();
+    Some(Indexed {
+        width: width as u32,
+        height: height as u32,
+        palette,
+        indices: frame.buffer.to_vec(),
+    })
+}
+
+/// Decode the source & target as paletted images, if both are paletted with the same palette (& dimensions).
+pub(crate) fn pair(src: &[u8], tgt: &[u8]) -> Option<(Indexed, Indexed)> {
+    let (src, tgt) = (decode(src)?, decode(tgt)?);
+    let same = src.palette == tgt.palette && (src.width, src.height) == (tgt.width, tgt.height);
+    same.then_some((src, tgt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(indices: &[u8], bit_depth: png::BitDepth, palette: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 4, 2);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(bit_depth);
+        encoder.set_palette(palette.to_vec());
+        encoder.set_trns(vec![255, 0]);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(indices)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn should_decode_paletted_png_and_compare_the_indices() {
+        let palette = [255, 0, 0, 0, 0, 255, 0, 255, 0];
+        // Note: 2 bits per index, a row of 4 indices per byte
+        let src = encode_png(
+            &[0b00_01_10_00, 0b00_00_00_00],
+            png::BitDepth::Two,
+            &palette,
+        );
+        let tgt = encode_png(
+            &[0b00_01_10_00, 0b00_00_01_10],
+            png::BitDepth::Two,
+            &palette,
+        );
+
+        let (src, tgt) = pair(&src, &tgt).unwrap();
+        assert_eq!(vec![0, 1, 2, 0, 0, 0, 0, 0], src.indices);
+        assert_eq!(2, src.differing_pixels(&tgt, &Bounds::new(0, 4, 0, 2)));
+        assert_eq!(0, src.differing_pixels(&tgt, &Bounds::new(0, 4, 0, 1)));
+
+        let rgba = src.to_rgba();
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *rgba.get_pixel(0, 0));
+        assert_eq!(image::Rgba([0, 0, 255, 0]), *rgba.get_pixel(1, 0));
+        assert_eq!(image::Rgba([0, 255, 0, 255]), *rgba.get_pixel(2, 0));
+    }
+
+    #[test]
+    fn should_only_pair_images_with_the_same_palette() {
+        let indices = [0b00_01_00_00, 0];
+        let src = encode_png(&indices, png::BitDepth::Two, &[255, 0, 0, 0, 0, 255]);
+        let other = encode_png(&indices, png::BitDepth::Two, &[255, 0, 0, 0, 0, 254]);
+        assert!(pair(&src, &src).is_some());
+        assert!(pair(&src, &other).is_none());
+
+        let mut rgba = Vec::new();
+        image::RgbaImage::new(4, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut rgba),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        assert!(pair(&src, &rgba).is_none());
+    }
+
+    #[test]
+    fn should_match_duplicated_colors() {
+        // Note: the first two colors differ by their transparency, the last two are the same
+        let palette = [0, 0, 255, 0, 0, 255, 255, 0, 0, 255, 0, 0];
+        let src = encode_png(&[2, 2, 0, 0, 0, 0, 0, 0], png::BitDepth::Eight, &palette);
+        let tgt = encode_png(&[3, 3, 0, 0, 0, 0, 0, 0], png::BitDepth::Eight, &palette);
+
+        let (src, tgt) = pair(&src, &tgt).unwrap();
+        assert_eq!(0, src.differing_pixels(&tgt, &Bounds::new(0, 4, 0, 2)));
+    }
+}
@@ -0,0 +1,339 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::codecs;
+use image::{ColorType, ImageDecoder, ImageFormat, ImageResult};
+
+use crate::exit::Error;
+use crate::raw::{self, RawFormat};
+use crate::{archive, i18n, page, uncompressed, CompareOptions, Dimensions};
+
+/// Represents an input image, either a local file (read once, when loaded), a downloaded URL (with the `net`
+/// feature) / extracted file or the encoded image provided by the library user.
+pub(crate) enum Input<'a> {
+    File(&'a Path, Vec<u8>),
+    Memory(Vec<u8>),
+    Slice(&'a [u8]),
+}
+
+impl<'a> Input<'a> {
+    /// Get the input for the path (reading it from the archive / downloading it, if it is a file within an archive / URL).
+    pub(crate) fn load(path: &'a Path, options: &CompareOptions) -> Result<Input<'a>, Error> {
+        if let Some((archive, member)) = archive::split(path) {
+            return archive::read(archive, member).map(Input::Memory);
+        }
+        if !is_url(path) {
+            let data = std::fs::read(path).map_err(|_| {
+                Error::io(format!(
+                    "Encountered error while reading {}.",
+                    path.display()
+                ))
+            })?;
+            return Ok(Input::File(path, data));
+        }
+
+        #[cfg(feature = "net")]
+        return download(
+            path.to_str().unwrap(),
+            std::time::Duration::from_secs(options.timeout),
+            options.max_download_size,
+        )
+        .map(Input::Memory);
+
+        #[cfg(not(feature = "net"))]
+        {
+            let _ = options;
+            Err(Error::from("URL inputs require the 'net' feature."))
+        }
+    }
+
+    /// Decode the image into RGBA (as a raw frame, if the raw format & dimensions are provided,
+    /// or only the page / frame, if selected), in the format (if provided, see `Input::open`).
+    pub(crate) fn decode(
+        &self,
+        raw: Option<(RawFormat, Dimensions)>,
+        page: Option<usize>,
+        format: Option<ImageFormat>,
+    ) -> Result<image::RgbaImage, Error> {
+        if let Some((format, dimension)) = raw {
+            return Ok(raw::decode(self.bytes(), format, dimension)?);
+        }
+        if let Some(page) = page {
+            return page::decode(self.bytes(), page);
+        }
+
+        // Note: the uncompressed formats are converted straight from the encoded image, without an extra copy
+        if format.is_none() {
+            if let Some(img) = uncompressed::decode(self.bytes()) {
+                return Ok(img);
+            }
+        }
+        self.open(format).map(|i| i.to_rgba8())
+    }
+
+    /// Decode the image of the format (if provided), or else of the format detected from its content (its magic
+    /// bytes, for ex. a webp image named `.png`), falling back to the extension of the file for the formats
+    /// without any (for ex. tga).
+    fn open(&self, format: Option<ImageFormat>) -> Result<image::DynamicImage, Error> {
+        let error = || Error::decode(i18n::text("error.open", &[]));
+        let mut reader = image::io::Reader::new(Cursor::new(self.bytes()))
+            .with_guessed_format()
+            .map_err(|_| error())?;
+        let fallback = match self {
+            Input::File(path, _) => ImageFormat::from_path(path).ok(),
+            _ => None,
+        };
+        if let Some(format) = format.or(reader.format()).or(fallback) {
+            reader.set_format(format);
+        }
+        reader.decode().map_err(|_| error())
+    }
+
+    /// Decode the image into full precision (float) RGBA, for ex. the linear values of an EXR (the raw frames &
+    /// the selected pages are converted from 8-bit).
+    pub(crate) fn decode_float(
+        &self,
+        raw: Option<(RawFormat, Dimensions)>,
+        page: Option<usize>,
+        format: Option<ImageFormat>,
+    ) -> Result<image::Rgba32FImage, Error> {
+        if raw.is_some() || page.is_some() {
+            let img = self.decode(raw, page, format)?;
+            return Ok(image::DynamicImage::ImageRgba8(img).to_rgba32f());
+        }
+        self.open(format).map(|i| i.to_rgba32f())
+    }
+
+    /// Read the header of the image (as a raw frame, if the raw format & dimensions are provided, or else of the
+    /// format, if provided), without decoding the pixels.
+    pub(crate) fn header(
+        &self,
+        raw: Option<(RawFormat, Dimensions)>,
+        format: Option<ImageFormat>,
+    ) -> Result<Header, Error> {
+        let bytes = self.bytes();
+        if let Some((format, Dimensions(width, height))) = raw {
+            raw::decode(bytes, format, Dimensions(width, height))?;
+            return Ok(Header {
+                format: format!("raw {:?}", format).to_lowercase(),
+                dimensions: (width, height),
+                color: None,
+            });
+        }
+
+        let error = || {
+            Error::decode("Encountered error while reading the header of source / target image.")
+        };
+        let mut reader = image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|_| error())?;
+        if let Some(format) = format {
+            reader.set_format(format);
+        }
+        let format = reader.format().ok_or_else(error)?;
+        let dimensions = reader.into_dimensions().map_err(|_| error())?;
+        Ok(Header {
+            format: format.extensions_str()[0].to_string(),
+            dimensions,
+            color: color_type(bytes, format),
+        })
+    }
+
+    /// Get the encoded image.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        match self {
+            Input::File(_, data) | Input::Memory(data) => data,
+            Input::Slice(data) => data,
+        }
+    }
+}
+
+/// Represents the header of an image.
+pub(crate) struct Header {
+    /// format (for ex. `png`).
+    pub(crate) format: String,
+    /// dimensions (width, height).
+    pub(crate) dimensions: (u32, u32),
+    /// color type (if known for the format).
+    pub(crate) color: Option<ColorType>,
+}
+
+impl Header {
+    /// Channels of the color type (for ex. `rgba`), if known.
+    pub(crate) fn channels(&self) -> Option<&'static str> {
+        self.color.map(|color| match color.channel_count() {
+            1 => "gray",
+            2 => "gray+alpha",
+            3 => "rgb",
+            _ => "rgba",
+        })
+    }
+
+    /// Bit depth (per channel) of the color type, if known.
+    pub(crate) fn depth(&self) -> Option<u16> {
+        self.color
+            .map(|color| color.bytes_per_pixel() as u16 * 8 / color.channel_count() as u16)
+    }
+}
+
+impl std::fmt::Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.dimensions;
+        write!(f, "{}, {}x{}", self.format, width, height)?;
+        if let (Some(depth), Some(channels)) = (self.depth(), self.channels()) {
+            write!(f, ", {}-bit {}", depth, channels)?;
+        }
+        Ok(())
+    }
+}
+
+/// Color type of the encoded image (from the header of the common formats).
+fn color_type(data: &[u8], format: ImageFormat) -> Option<ColorType> {
+    fn of<'a>(decoder: ImageResult<impl ImageDecoder<'a>>) -> Option<ColorType> {
+        decoder.ok().map(|d| d.color_type())
+    }
+    let data = Cursor::new(data);
+    match format {
+        ImageFormat::Png => of(codecs::png::PngDecoder::new(data)),
+        ImageFormat::Jpeg => of(codecs::jpeg::JpegDecoder::new(data)),
+        ImageFormat::Gif => of(codecs::gif::GifDecoder::new(data)),
+        ImageFormat::Bmp => of(codecs::bmp::BmpDecoder::new(data)),
+        ImageFormat::Tiff => of(codecs::tiff::TiffDecoder::new(data)),
+        ImageFormat::WebP => of(codecs::webp::WebPDecoder::new(data)),
+        ImageFormat::Tga => of(codecs::tga::TgaDecoder::new(data)),
+        ImageFormat::Pnm => of(codecs::pnm::PnmDecoder::new(data)),
+        ImageFormat::Ico => of(codecs::ico::IcoDecoder::new(data)),
+        ImageFormat::OpenExr => of(codecs::openexr::OpenExrDecoder::new(data)),
+        ImageFormat::Qoi => of(codecs::qoi::QoiDecoder::new(data)),
+        _ => None,
+    }
+}
+
+/// Checks if the path is a http(s) URL.
+pub(crate) fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|p| p.starts_with("http://") || p.starts_with("https://"))
+}
+
+/// Get a local path for the input, used for naming the files derived from it (for ex. the highlight output).
+///
+/// A file within an archive is placed next to the archive, a URL in the current directory
+/// & the page / frame selector is dropped.
+pub(crate) fn local_path(path: &Path) -> PathBuf {
+    if let Some((file, _)) = page::split(path) {
+        return local_path(file);
+    }
+    if let Some((archive, member)) = archive::split(path) {
+        let file_name = Path::new(member).file_name().unwrap_or_default();
+        return archive.with_file_name(file_name);
+    }
+    if is_url(path) {
+        let url = path.to_str().unwrap();
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        return PathBuf::from(url.rsplit('/').next().unwrap_or_default());
+    }
+    path.to_path_buf()
+}
+
+/// Download the URL into memory.
+#[cfg(feature = "net")]
+fn download(url: &str, timeout: std::time::Duration, max_size: u64) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let response = agent.get(url).call().map_err(|e| {
+        Error::io(format!(
+            "Encountered error while downloading {}: {}",
+            url, e
+        ))
+    })?;
+
+    let mut data = Vec::new();
+    // Note: read one byte more than the limit to detect responses exceeding it
+    response
+        .into_reader()
+        .take(max_size + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| {
+            Error::io(format!(
+                "Encountered error while downloading {}: {}",
+                url, e
+            ))
+        })?;
+    if data.len() as u64 > max_size {
+        return Err(Error::from(format!(
+            "{} exceeds the maximum download size ({} bytes).",
+            url, max_size
+        )));
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_http_and_https_urls() {
+        assert!(is_url(Path::new("http://example.com/a.png")));
+        assert!(is_url(Path::new("https://example.com/a.png")));
+        assert!(!is_url(Path::new("/tmp/http/a.png")));
+        assert!(!is_url(Path::new("ftp://example.com/a.png")));
+    }
+
+    #[test]
+    fn should_return_local_path_for_archives_and_urls() {
+        assert_eq!(
+            PathBuf::from("/tmp/baseline/a.png"),
+            local_path(Path::new("/tmp/baseline/images.zip::dir/a.png"))
+        );
+        assert_eq!(
+            PathBuf::from("a.png"),
+            local_path(Path::new("https://example.com/dir/a.png?version=1"))
+        );
+        assert_eq!(
+            PathBuf::from("/tmp/a.png"),
+            local_path(Path::new("/tmp/a.png"))
+        );
+        assert_eq!(
+            PathBuf::from("/tmp/scan.tif"),
+            local_path(Path::new("/tmp/scan.tif#3"))
+        );
+    }
+
+    #[test]
+    fn should_read_header_without_decoding() {
+        let mut data = Vec::new();
+        image::DynamicImage::ImageLuma16(image::ImageBuffer::new(3, 2))
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+
+        let header = Input::Memory(data).header(None, None).unwrap();
+        assert_eq!("png, 3x2, 16-bit gray", header.to_string());
+    }
+
+    #[test]
+    fn should_decode_by_the_content_or_the_provided_format() {
+        let encode = |format| {
+            let mut data = Vec::new();
+            image::DynamicImage::ImageRgba8(image::RgbaImage::new(3, 2))
+                .write_to(&mut Cursor::new(&mut data), format)
+                .unwrap();
+            data
+        };
+        let gif = Input::Memory(encode(ImageFormat::Gif));
+        assert_eq!((3, 2), gif.decode(None, None, None).unwrap().dimensions());
+        assert!(gif.decode(None, None, Some(ImageFormat::Png)).is_err());
+
+        // Note: tga images have no magic bytes
+        let tga = Input::Memory(encode(ImageFormat::Tga));
+        assert!(tga.decode(None, None, None).is_err());
+        let decoded = tga.decode(None, None, Some(ImageFormat::Tga)).unwrap();
+        assert_eq!((3, 2), decoded.dimensions());
+        assert_eq!(
+            "tga",
+            tga.header(None, Some(ImageFormat::Tga)).unwrap().format
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Estimation of the quantization error of jpeg images (for the jpeg aware comparison).
+
+/// Natural (row major) index of the coefficients of a quantization table stored in zigzag order.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Represents a quantization table (in the natural order).
+pub(crate) type QuantizationTable = [u16; 64];
+
+/// Represents the quantization tables of a jpeg image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Tables {
+    /// table of the luma (the table 0).
+    pub(crate) luma: QuantizationTable,
+    /// table of the chroma (the table 1, or the luma table if the image has no other table).
+    pub(crate) chroma: QuantizationTable,
+}
+
+/// Read the quantization tables of the jpeg image.
+///
+/// Returns `None` if the image is not a jpeg (or has no table 0).
+pub(crate) fn tables(data: &[u8]) -> Option<Tables> {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+
+    let (mut luma, mut chroma) = (None, None);
+    let mut position = 2;
+    while let (Some(&0xff), Some(&marker)) = (data.get(position), data.get(position + 1)) {
+        // Note: the tables precede the start of scan (& the entropy coded data)
+        if marker == 0xda {
+            break;
+        }
+        let length = u16::from_be_bytes([*data.get(position + 2)?, *data.get(position + 3)?]);
+        let segment = data.get(position + 4..position + 2 + length as usize)?;
+        if marker == 0xdb {
+            let mut rest = segment;
+            while let Some((&info, values)) = rest.split_first() {
+                let (precision, id) = (info >> 4, info & 0x0f);
+                let size = if precision == 0 { 64 } else { 128 };
+                let values = values.get(..size)?;
+                let mut table = [0; 64];
+                for (i, &natural) in ZIGZAG.iter().enumerate() {
+                    table[natural] = match precision {
+                        0 => values[i] as u16,
+                        _ => u16::from_be_bytes([values[2 * i], values[2 * i + 1]]),
+                    };
+                }
+                match id {
+                    0 => luma = Some(table),
+                    1 => chroma = Some(table),
+                    _ => {}
+                }
+                rest = &rest[1 + size..];
+            }
+        }
+        position += 2 + length as usize;
+    }
+    luma.map(|luma| Tables {
+        luma,
+        chroma: chroma.unwrap_or(luma),
+    })
+}
+
+/// Expected variance of the quantization error of a channel (of the RGB, the largest) of every 8x8 block of the
+/// image (row major).
+///
+/// The error of a DCT coefficient is uniform within the quantization step (`q² / 12`), unless the coefficient
+/// itself is smaller (then it is quantized to 0, and the error is at most the coefficient), so flat blocks
+/// expect a smaller error than textured blocks.
+///
+/// Note: the chroma subsampling is not modelled (the chroma is mostly smooth).
+pub(crate) fn error_variances(img: &image::RgbaImage, tables: &Tables) -> Vec<f32> {
+    let (columns, rows) = (img.width().div_ceil(8), img.height().div_ceil(8));
+    let cosines: Vec<f32> = (0..64)
+        .map(|i| {
+            let (frequency, position) = ((i / 8) as f32, (i % 8) as f32);
+            let scale = if frequency == 0.0 {
+                (1.0f32 / 8.0).sqrt()
+            } else {
+                0.5
+            };
+            scale * ((2.0 * position + 1.0) * frequency * std::f32::consts::PI / 16.0).cos()
+        })
+        .collect();
+
+    // Variance of the error of the block (of a YCbCr channel) quantized with the table.
+    let variance = |block: &[f32], table: &QuantizationTable| {
+        let mut variance = 0.0;
+        for v in 0..8 {
+            for u in 0..8 {
+                let mut coefficient = 0.0;
+                for y in 0..8 {
+                    for x in 0..8 {
+                        coefficient += block[y * 8 + x] * cosines[u * 8 + x] * cosines[v * 8 + y];
+                    }
+                }
+                let q = table[v * 8 + u] as f32;
+                variance += (q * q / 12.0).min(coefficient * coefficient);
+            }
+        }
+        variance / 64.0
+    };
+
+    let mut variances = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            // Note: the pixels beyond the edges repeat the last row / column (as the encoders pad)
+            let ycbcr: Vec<[f32; 3]> = (0..64)
+                .map(|i| {
+                    let p = img.get_pixel(
+                        (column * 8 + i % 8).min(img.width() - 1),
+                        (row * 8 + i / 8).min(img.height() - 1),
+                    );
+                    let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+                    [
+                        0.299 * r + 0.587 * g + 0.114 * b,
+                        -0.1687 * r - 0.3313 * g + 0.5 * b,
+                        0.5 * r - 0.4187 * g - 0.0813 * b,
+                    ]
+                })
+                .collect();
+            let channel = |c: usize| ycbcr.iter().map(|p| p[c]).collect::<Vec<_>>();
+            let (y, cb, cr) = (
+                variance(&channel(0), &tables.luma),
+                variance(&channel(1), &tables.chroma),
+                variance(&channel(2), &tables.chroma),
+            );
+            // Note: the errors of the channels are independent (R = Y + 1.402 Cr, B = Y + 1.772 Cb)
+            variances.push((y + 1.402 * 1.402 * cr).max(y + 1.772 * 1.772 * cb));
+        }
+    }
+    variances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a jpeg (with the quality) of a gradient.
+    fn jpeg(quality: u8) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 16) as u8, 0])
+        });
+        let mut data = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, quality)
+            .encode_image(&img)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn should_read_tables_of_jpeg() {
+        let (high, low) = (tables(&jpeg(95)).unwrap(), tables(&jpeg(50)).unwrap());
+
+        assert!(high.luma.iter().sum::<u16>() < low.luma.iter().sum::<u16>());
+        assert_ne!(low.luma, low.chroma);
+        assert_eq!(None, tables(b"\x89PNG"));
+    }
+
+    #[test]
+    fn should_expect_smaller_error_for_flat_blocks() {
+        let tables = Tables {
+            luma: [16; 64],
+            chroma: [16; 64],
+        };
+        let flat = image::RgbaImage::from_pixel(8, 8, image::Rgba([100, 100, 100, 255]));
+        let textured = image::RgbaImage::from_fn(8, 8, |x, y| {
+            let v = if (x + y) % 2 == 0 { 0 } else { 255 };
+            image::Rgba([v, v, v, 255])
+        });
+
+        let (flat, textured) = (
+            error_variances(&flat, &tables),
+            error_variances(&textured, &tables),
+        );
+        assert_eq!(1, flat.len());
+        assert!(flat[0] < 1.0);
+        assert!(textured[0] > flat[0]);
+    }
+}
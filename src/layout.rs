@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use crate::comparator::Comparator;
+use crate::{Bounds, Region};
+
+/// Represents the named regions of the images (for ex. `header` or `sidebar`), read from a layout file.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Layout(pub(crate) BTreeMap<String, Region>);
+
+/// Read the layout file, a json object of the regions by name
+/// (for ex. `{"header": {"x": 0, "y": 0, "width": 1920, "height": 120}}`).
+pub(crate) fn read(path: &str) -> Result<Layout, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Encountered error while reading layout file {}: {}",
+            path, e
+        )
+    })?;
+    parse(&content).map_err(|e| format!("Invalid layout file {}: {}", path, e))
+}
+
+/// Parse the layout from json.
+fn parse(content: &str) -> Result<Layout, String> {
+    let regions: BTreeMap<String, Region> =
+        serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if let Some((name, _)) = regions.iter().find(|(_, r)| r.width == 0 || r.height == 0) {
+        return Err(format!("region '{}' is empty", name));
+    }
+    Ok(Layout(regions))
+}
+
+/// Percentage of the pixels with difference within every named region (sorted by name).
+///
+/// Only the part of a region within the (common) bounds is compared.
+pub(crate) fn attribute(
+    layout: &Layout,
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    comparator: &dyn Comparator,
+) -> Vec<(String, f32)> {
+    layout
+        .0
+        .iter()
+        .map(|(name, region)| {
+            let clipped = Bounds::new(
+                region.x.clamp(bounds.min_width, bounds.max_width),
+                (region.x.saturating_add(region.width)).clamp(bounds.min_width, bounds.max_width),
+                region.y.clamp(bounds.min_height, bounds.max_height),
+                (region.y.saturating_add(region.height))
+                    .clamp(bounds.min_height, bounds.max_height),
+            );
+            let diff = match clipped.area() {
+                0 => 0.0,
+                area => {
+                    let differing = comparator
+                        .compare_block(src, tgt, &Region::from(&clipped))
+                        .differing_pixels;
+                    (differing as f64 / area as f64 * 100.0) as f32
+                }
+            };
+            (name.clone(), diff)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_attribute_differences_to_named_regions() {
+        let layout = parse(
+            r#"{"header": {"x": 0, "y": 0, "width": 10, "height": 2}, "body": {"x": 0, "y": 2, "width": 10, "height": 8}}"#,
+        )
+        .unwrap();
+        let src = image::RgbaImage::new(10, 10);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(3, 1) = image::Rgba([255, 0, 0, 255]);
+
+        assert_eq!(
+            vec![(String::from("body"), 0.0), (String::from("header"), 5.0)],
+            attribute(
+                &layout,
+                &src,
+                &tgt,
+                &Bounds::new(0, 10, 0, 10),
+                &crate::comparator::Exact
+            )
+        );
+    }
+
+    #[test]
+    fn should_fail_for_empty_regions() {
+        assert!(parse(r#"{"header": {"x": 0, "y": 0, "width": 0, "height": 2}}"#).is_err());
+    }
+}
@@ -1,412 +1,5697 @@
+mod archive;
+mod baseline;
+mod budget;
+mod builder;
+mod cache;
+mod cancel;
+mod channels;
+mod cluster;
+pub mod comparator;
+mod crop;
+mod direction;
+mod dpi;
+mod exit;
+mod extract;
+mod font;
+mod footer;
+mod gallery;
+mod glob;
+#[cfg(feature = "serde")]
+mod history;
+mod i18n;
+mod indexed;
+mod input;
+mod jpeg;
+#[cfg(feature = "serde")]
+mod layout;
+mod limits;
+mod mask;
+mod matrix;
+mod metadata;
+mod montage;
+mod observer;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod page;
+mod parallel;
+mod percent;
+mod presence;
+mod pseudo;
+mod raw;
+pub mod renderer;
+mod result;
+#[cfg(feature = "s3")]
+mod s3;
+mod saliency;
+mod sequence;
+#[cfg(feature = "server")]
+mod server;
+mod severity;
+mod similarity;
+mod stream;
+mod template;
+#[cfg(feature = "idiff-test")]
+pub mod testing;
+mod texture;
+mod thumbnail;
+mod tiles;
+mod transform;
+mod tty;
+mod uncompressed;
+#[cfg(feature = "video")]
+mod video;
+
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+pub use builder::CompareOptionsBuilder;
+pub use cancel::{CancellationToken, CANCELLED};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use cluster::Cluster;
 use colored::*;
-use image::GenericImage;
+use input::Input;
+use metadata::MetadataChange;
+pub use observer::Observer;
+pub use renderer::{Report, ReportRenderer};
+pub use result::{
+    DiffResult, DiffStats, Direction, PixelChanges, Region, Side, TextChange, Timing, Transform,
+    Warning, SCHEMA_VERSION,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(arg_required_else_help = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// print the exit codes and their meaning
+    #[arg(long, exclusive = true)]
+    explain_exit_codes: bool,
+
+    /// language of the result & error messages
+    #[arg(long, global = true, value_enum, default_value_t)]
+    lang: i18n::Lang,
+
+    /// number of decimals of the rendered percentage difference
+    #[arg(long, global = true, value_name = "DECIMALS")]
+    precision: Option<usize>,
+
+    /// how the percentage difference is rendered
+    #[arg(long, global = true, value_enum, default_value_t)]
+    percent_format: percent::PercentFormat,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// compare the source and target images (default, when no command is specified)
+    Compare(CompareArgs),
+
+    /// compare the images with matching file names in the source and target directories
+    Batch(BatchArgs),
+
+    /// compare the source and target images and list the regions with difference
+    Report(ReportArgs),
+
+    /// compare the images in the source and target directories against their budget (fails if any exceeds it)
+    Check(CheckArgs),
+
+    /// approve the target image as the new source image
+    Approve(ApproveArgs),
+
+    /// store & retrieve the baselines by their content hash
+    #[command(subcommand)]
+    Baseline(BaselineCommands),
+
+    /// compare every mip level of the source and target textures (dds & ktx2)
+    Mips(MipsArgs),
+
+    /// compare the images embedded in the source and target documents (pdf, docx, pptx & xlsx) by their index
+    ExtractCompare(ExtractCompareArgs),
+
+    /// compare the frames of the source and target videos
+    #[cfg(feature = "video")]
+    Video(VideoArgs),
+
+    /// summarize the results recorded in a history file
+    #[cfg(feature = "serde")]
+    #[command(subcommand)]
+    History(HistoryCommands),
+
+    /// serve the comparison over HTTP (POST /compare returns the json result, POST /highlight the highlighted image)
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
+
+    /// print the json schema of the results (the json output of the report command)
+    #[cfg(feature = "schema")]
+    Schema,
+
+    /// print the shell completion script
+    Completions(CompletionsArgs),
+
+    /// print the man page (roff)
+    Man,
+}
+
+#[derive(Args)]
+struct CompareArgs {
     /// source file name
     #[arg(long, value_name = "SOURCE_FILE_NAME")]
     src: PathBuf,
 
-    /// target file name
-    #[arg(long, value_name = "TARGET_FILE_NAME")]
-    tgt: PathBuf,
+    /// target file name (or a generated 'color:#RRGGBB' of the source dimensions / transparent 'blank:WxH' image)
+    #[arg(
+        long,
+        value_name = "TARGET_FILE_NAME",
+        required_unless_present = "self_compare"
+    )]
+    tgt: Option<PathBuf>,
 
-    /// strict comparison (exits if dimensions are different)
-    #[arg(long)]
-    strict: bool,
+    /// compare two regions (roi-a & roi-b) of the source image itself, for ex. to verify the symmetry or tiling of a texture
+    #[arg(long, conflicts_with = "tgt", requires_all = ["roi_a", "roi_b"])]
+    self_compare: bool,
+
+    #[command(flatten)]
+    options: CompareOptions,
 
     /// highlight differences in a new file
     #[arg(long)]
     highlight: bool,
 
-    /// pixel block size for highlighting difference
-    #[arg(long, requires = "highlight", default_value_t = 10)]
-    block: u32,
-
-    /// optional output file name (without extension)
+    /// optional output file name, next to the target file unless it has directories (created if missing), for ex. artifacts/run42/login_diff (uses the extension of the target file, if not provided)
     #[arg(short, long, value_name = "OUTPUT_FILE_NAME", requires = "highlight")]
     output: Option<String>,
+
+    /// directory to write the output into (created, if it does not exist), instead of next to the target
+    #[arg(long, value_name = "OUTPUT_DIRECTORY_NAME", requires = "highlight")]
+    output_dir: Option<PathBuf>,
+
+    /// directory to write the output into as a Deep Zoom image (a pyramid of tiles, for ex. for OpenSeadragon to pan & zoom), instead of a single file
+    #[arg(long, value_name = "TILE_DIRECTORY_NAME", requires = "highlight", conflicts_with_all = ["output_dir", "quality"])]
+    tile_output: Option<PathBuf>,
+
+    /// size (in pixels) of the square tiles of the tile output
+    #[arg(long, value_name = "SIZE", requires = "tile_output", value_parser = tiles::parse_tile_size, default_value = "256")]
+    tile_size: u32,
+
+    /// output quality (1-100) for the lossy formats (jpeg, webp & avif)
+    #[arg(long, requires = "highlight", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// overwrite the existing output files (refused, otherwise)
+    #[arg(long)]
+    force: bool,
+
+    /// append a timestamp (UTC, YYYYMMDDTHHMMSS) to the names of the output files
+    #[arg(long)]
+    timestamp_output: bool,
+
+    /// append a footer bar (file names, time, difference & version) below the output
+    #[arg(long, requires = "highlight")]
+    stamp: bool,
+
+    /// write thumbnails (of the size, in pixels, of the longer side) of the source, target & output next to the output
+    #[arg(long, value_name = "SIZE", requires = "highlight", value_parser = thumbnail::parse_size)]
+    thumbnails: Option<u32>,
+
+    /// how the differences are shown in the output
+    #[arg(long, alias = "highlight-mode", value_enum, requires = "highlight", default_value_t = OutputFormat::Highlight)]
+    output_format: OutputFormat,
+
+    /// opacity (0-1) of the target composited over the source for the blend output
+    #[arg(long, value_name = "ALPHA", requires = "highlight", value_parser = parse_alpha, default_value_t = 0.5)]
+    blend_alpha: f32,
+
+    /// image the differences are shown over in the output (both writes an output per image)
+    #[arg(long, value_enum, requires = "highlight", default_value_t = HighlightBase::Tgt)]
+    highlight_base: HighlightBase,
+
+    /// colors of the differences shown in the output (& the terminal preview)
+    #[arg(long, value_enum, default_value_t = Palette::Default)]
+    palette: Palette,
+
+    /// render a downscaled output (with the differences shown) in the terminal
+    #[arg(long, value_enum, value_name = "PROTOCOL", num_args = 0..=1, default_missing_value = "auto")]
+    preview_tty: Option<tty::TtyProtocol>,
+
+    /// save every changed region (padded by a block) cropped from the source & target into the directory
+    #[arg(long, value_name = "CROP_DIRECTORY_NAME")]
+    crop_regions: Option<PathBuf>,
+
+    /// save the bounding box of all the changes cropped from the source & target into the directory
+    #[arg(long, value_name = "CROP_DIRECTORY_NAME")]
+    crop_to_changes: Option<PathBuf>,
+
+    /// write the difference magnitudes (largest channel difference, 0-255) of the common region as a matrix (.csv or .npy)
+    #[arg(long, value_name = "MATRIX_FILE_NAME", value_parser = matrix::parse_output)]
+    matrix_output: Option<PathBuf>,
+
+    /// cells of the matrix output
+    #[arg(long, value_enum, requires = "matrix_output", default_value_t = matrix::MatrixLevel::Block)]
+    matrix_level: matrix::MatrixLevel,
+
+    /// wait (up to the number of seconds) for the inputs to exist and stop changing in size, before comparing
+    #[arg(long, value_name = "TIMEOUT")]
+    wait_for_files: Option<u64>,
+
+    /// validate the inputs (reading only their headers), print what would be compared & where the output would be written, and exit
+    #[arg(long)]
+    dry_run: bool,
+
+    /// print the format, color type, bit depth, dimensions, frame count & ICC profile presence of both inputs, and exit (without comparing)
+    #[arg(long, conflicts_with = "dry_run")]
+    info: bool,
 }
 
-pub fn run() {
-    let cli = Cli::parse();
+/// Represents how the differences are shown in the output image.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum OutputFormat {
+    // Note: the variants of the value enums (of the options, here & in the other modules) have plain comments
+    // (instead of doc comments), to keep the possible values in the short help
+    // target with the blocks with difference outlined
+    Highlight,
+    // target composited over the source (onion skin), with the pixels with difference tinted
+    Blend,
+    // target with the blocks with difference in full color, and the rest desaturated & darkened
+    Cutout,
+}
 
-    if !cli.src.exists() || !cli.tgt.exists() {
-        eprintln!(
-            "{}",
-            "Invalid values for src/tgt path. Please check and try again.".red()
-        );
-        std::process::exit(1);
+/// Represents the colors the differences are shown with in the output (the outline of the highlight & the tint
+/// of the blend output).
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Palette {
+    // red outline & magenta tint
+    Default,
+    // blue outline & orange tint (red-green safe)
+    Deuteranopia,
+    // blue outline & yellow tint (red-green safe, without dark reds)
+    Protanopia,
+    // vermilion outline & bluish green tint (blue-yellow safe)
+    Tritanopia,
+    // dashed black & white outline & yellow tint
+    HighContrast,
+}
+
+impl Palette {
+    /// Color of the outline at the position along it (the high contrast outline alternates every 4 pixels, to
+    /// be visible over both dark & light content).
+    fn outline(&self, position: u32) -> image::Rgba<u8> {
+        image::Rgba(match self {
+            Palette::Default => [255, 0, 0, 255],
+            Palette::Deuteranopia | Palette::Protanopia => [0, 114, 178, 255],
+            Palette::Tritanopia => [213, 94, 0, 255],
+            Palette::HighContrast if (position / 4).is_multiple_of(2) => [0, 0, 0, 255],
+            Palette::HighContrast => [255, 255, 255, 255],
+        })
     }
 
-    let (src, tgt) = match (image::open(&cli.src), image::open(&cli.tgt)) {
-        (Ok(s), Ok(t)) => (s.to_rgba8(), t.to_rgba8()),
-        (_, _) => {
-            eprintln!(
-                "{}",
-                "Encountered error while opening source / target image.".red()
-            );
-            std::process::exit(1);
-        }
-    };
+    /// Color the pixels with difference are tinted with.
+    fn tint(&self) -> image::Rgba<u8> {
+        image::Rgba(match self {
+            Palette::Default => [255, 0, 255, 255],
+            Palette::Deuteranopia => [230, 159, 0, 255],
+            Palette::Protanopia | Palette::HighContrast => [240, 228, 66, 255],
+            Palette::Tritanopia => [0, 158, 115, 255],
+        })
+    }
+}
 
-    let src_dimension: Dimensions = Dimensions::from(src.dimensions());
-    let tgt_dimension: Dimensions = Dimensions::from(tgt.dimensions());
+/// Represents the image(s) the differences are shown over in the output.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum HighlightBase {
+    // source image (shows the content removed from the source)
+    Src,
+    // target image
+    Tgt,
+    // an output per image
+    Both,
+}
 
-    if cli.strict && !Dimensions::same(&src_dimension, &tgt_dimension) {
-        eprintln!("{}",
-            format!("'src' ({:?}) & 'tgt' ({:?}) do not have the same dimensions. (Try without 'strict' flag to check the differences)", src_dimension, tgt_dimension)
-            .red());
-        std::process::exit(1);
-    }
+/// Represents how the files of the source and target directories are paired.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum PairBy {
+    // matching file names
+    Name,
+    // identical content (for ex. renamed files)
+    Hash,
+    // matching capture time (exif DateTimeOriginal, to the second)
+    ExifTime,
+    // position in the sorted file names
+    Order,
+    // closest perceptual hash (for ex. screenshots named with random IDs)
+    Similarity,
+}
 
-    let bounds = match Bounds::get_max_bounds(src_dimension, tgt_dimension) {
-        Ok(b) => b,
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
-        }
-    };
+#[derive(Args)]
+struct BatchArgs {
+    /// source directory name (or frame file name pattern, with range)
+    #[arg(long, value_name = "SOURCE_DIRECTORY_NAME")]
+    src: PathBuf,
 
-    if !bounds.is_greater_than(cli.block * cli.block) {
-        eprintln!(
-            "{}",
-            format!(
-                "block size ({:?}) cannot be greater than the max bound (height: {:?},  width: {:?}).",
-                cli.block, bounds.max_height, bounds.max_width
-            )
-            .red()
-        );
-        std::process::exit(1);
-    }
+    /// target directory name (or frame file name pattern, with range)
+    #[arg(long, value_name = "TARGET_DIRECTORY_NAME")]
+    tgt: PathBuf,
 
-    let (diff, bounds_with_diff) = percentage_difference(&src, &tgt, &bounds, cli.block);
+    #[command(flatten)]
+    options: CompareOptions,
 
-    if diff == 0.0 {
-        println!(
-            "{}",
-            "Comparison Completed. No difference observed between the images!".green()
-        );
-        std::process::exit(0);
-    } else {
-        println!(
-            "A difference of '{:.5}{}' is observed between images.",
-            diff.to_string().red(),
-            "%".red()
-        );
-        if !cli.highlight {
-            println!("{}", "(Difference highlighting is currently disabled. Try with 'highlight' flag to highlight the differences)".yellow());
-            std::process::exit(0);
-        }
+    /// file name pattern of the numbered frames in the directories, with a %d (or zero padded, for ex. %04d) placeholder of the frame number (for ex. 'frame_%04d.png')
+    #[arg(long, value_name = "PATTERN", value_parser = sequence::parse_pattern, requires = "range")]
+    sequence: Option<String>,
+
+    /// compare the numbered frames of the range (inclusive, for ex. 1..500) instead of the files with matching names, reporting the first diverging frame
+    #[arg(long, value_name = "START..END", value_parser = sequence::parse_range, conflicts_with_all = ["include", "exclude", "extensions"])]
+    range: Option<sequence::FrameRange>,
+
+    /// highlight differences in a new file for every pair with difference
+    #[arg(long)]
+    highlight: bool,
+
+    /// directory to write the outputs into (created, if it does not exist), instead of next to the targets
+    #[arg(long, value_name = "OUTPUT_DIRECTORY_NAME", requires = "highlight")]
+    output_dir: Option<PathBuf>,
+
+    /// overwrite the existing output files (refused, otherwise)
+    #[arg(long)]
+    force: bool,
+
+    /// append a timestamp (UTC, YYYYMMDDTHHMMSS) to the names of the output files
+    #[arg(long)]
+    timestamp_output: bool,
+
+    /// directory to cache the results in, so that only the changed pairs are compared again
+    #[arg(long, value_name = "CACHE_DIRECTORY_NAME", conflicts_with_all = ["highlight", "compare_metadata", "clusters", "montage", "report_dir"])]
+    cache_dir: Option<PathBuf>,
+
+    /// colors of the differences shown in the outputs
+    #[arg(long, value_enum, requires = "highlight", default_value_t = Palette::Default)]
+    palette: Palette,
+
+    /// report the errors of the pairs (for ex. unreadable files) together at the end (& fail), instead of exiting at the first one
+    #[arg(long)]
+    keep_going: bool,
+
+    /// how the source and target files are paired
+    #[arg(long, value_enum, default_value_t = PairBy::Name)]
+    pair_by: PairBy,
+
+    /// write a contact sheet of the pairs with difference (a row of src, tgt & highlighted difference thumbnails each)
+    #[arg(long, value_name = "MONTAGE_FILE_NAME")]
+    montage: Option<PathBuf>,
+
+    /// directory to write an html gallery (index.html) into, listing every pair with its status, percentage difference & highlighted difference (sortable by difference)
+    #[arg(long, value_name = "REPORT_DIRECTORY_NAME")]
+    report_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    filter: FileFilter,
+
+    /// number of pairs compared concurrently (the results are still printed in order) [default: number of cores]
+    #[arg(long, value_name = "N")]
+    jobs: Option<std::num::NonZeroUsize>,
+}
+
+/// Represents the filters of the file names paired in the directories.
+#[derive(Args, Default)]
+struct FileFilter {
+    /// only pair the files matching one of the glob patterns (comma separated, for ex. 'login_*,home_*')
+    #[arg(long, value_name = "PATTERN", value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// skip the files matching one of the glob patterns (comma separated)
+    #[arg(long, value_name = "PATTERN", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// only pair the files with one of the extensions (comma separated, case insensitive, for ex. 'png,webp')
+    #[arg(long, value_name = "EXTENSION", value_delimiter = ',')]
+    extensions: Vec<String>,
+}
+
+impl FileFilter {
+    /// Checks if the file name passes the filters (included, not excluded & with one of the extensions).
+    fn allows(&self, name: &str) -> bool {
+        let extension = Path::new(name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        (self.include.is_empty() || self.include.iter().any(|p| glob::matches(p, name)))
+            && !self.exclude.iter().any(|p| glob::matches(p, name))
+            && (self.extensions.is_empty()
+                || self.extensions.iter().any(|e| {
+                    extension.as_deref() == Some(e.trim_start_matches('.').to_lowercase().as_str())
+                }))
     }
+}
 
-    let mut tgt_copy = match copy_image(&tgt) {
-        Ok(t) => t,
-        Err(_) => {
-            eprintln!(
-                "{}",
-                "Encountered error while creating a copy of target image for highlighting.".red()
-            );
-            std::process::exit(1);
-        }
-    };
+#[derive(Args)]
+struct ReportArgs {
+    /// source file name
+    #[arg(long, value_name = "SOURCE_FILE_NAME")]
+    src: PathBuf,
+
+    /// target file name
+    #[arg(long, value_name = "TARGET_FILE_NAME")]
+    tgt: PathBuf,
 
-    highlight(&mut tgt_copy, bounds_with_diff);
+    #[command(flatten)]
+    options: CompareOptions,
 
-    let output = generate_output_file_name(cli.output, &cli.tgt).unwrap();
-    tgt_copy.save(&output).unwrap();
-    println!(
-        "{}",
-        format!("Output written into {}", &output.to_str().unwrap()).green()
-    );
+    /// format of the report (text, json, junit, html, github or a registered one)
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    format: String,
+
+    /// print the report as json (same as `--format json`)
+    #[cfg(feature = "serde")]
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
 }
 
-/// Creates a copy of the image.
-fn copy_image(img: &image::RgbaImage) -> Result<image::RgbaImage, image::error::ImageError> {
-    let mut img_copy: image::RgbaImage =
-        image::ImageBuffer::new(img.dimensions().0, img.dimensions().1);
-    img_copy.copy_from(img, 0, 0)?;
-    Ok(img_copy)
+#[derive(Args)]
+struct CheckArgs {
+    /// source directory name
+    #[arg(long, value_name = "SOURCE_DIRECTORY_NAME")]
+    src: PathBuf,
+
+    /// target directory name
+    #[arg(long, value_name = "TARGET_DIRECTORY_NAME")]
+    tgt: PathBuf,
+
+    /// budget file (toml) with the maximum allowed percentage difference per file
+    #[arg(long, value_name = "BUDGET_FILE_NAME")]
+    budget: PathBuf,
+
+    /// directory to cache the results in, so that only the changed pairs are compared again
+    #[arg(long, value_name = "CACHE_DIRECTORY_NAME")]
+    cache_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    options: CompareOptions,
 }
 
-/// Compare the pixel difference for every pixel for the specified bounds between the images and calculate the percentage difference.
-///
-/// Returns the percentage difference and Vec\<Bounds\> where the difference was observed.
-///
-/// Logic: `(mismatching pixels / total pixels ) * 100`
-fn percentage_difference(
-    src: &image::RgbaImage,
-    tgt: &image::RgbaImage,
-    bounds: &Bounds,
+#[derive(Args)]
+struct ApproveArgs {
+    /// source file name (replaced by the target)
+    #[arg(long, value_name = "SOURCE_FILE_NAME")]
+    src: PathBuf,
+
+    /// target file name
+    #[arg(long, value_name = "TARGET_FILE_NAME")]
+    tgt: PathBuf,
+}
+
+#[derive(Args)]
+struct MipsArgs {
+    /// source texture name
+    #[arg(long, value_name = "SOURCE_FILE_NAME")]
+    src: PathBuf,
+
+    /// target texture name
+    #[arg(long, value_name = "TARGET_FILE_NAME")]
+    tgt: PathBuf,
+
+    #[command(flatten)]
+    options: CompareOptions,
+}
+
+#[derive(Args)]
+struct ExtractCompareArgs {
+    /// source document name
+    #[arg(long, value_name = "SOURCE_FILE_NAME")]
+    src: PathBuf,
+
+    /// target document name
+    #[arg(long, value_name = "TARGET_FILE_NAME")]
+    tgt: PathBuf,
+
+    /// extract and compare the embedded raster images
+    #[arg(long, required = true)]
+    images: bool,
+
+    #[command(flatten)]
+    options: CompareOptions,
+}
+
+#[derive(Subcommand)]
+enum BaselineCommands {
+    /// store the baselines of the directory & record their content hashes in the manifest
+    Push(BaselineArgs),
+    /// retrieve the baselines recorded in the manifest into the directory
+    Pull(BaselineArgs),
+}
+
+#[derive(Args)]
+struct BaselineArgs {
+    /// baseline directory name
+    #[arg(long, value_name = "BASELINE_DIRECTORY_NAME")]
+    dir: PathBuf,
+
+    /// store of the baselines (a directory, or s3://BUCKET/PREFIX with the s3 feature)
+    #[arg(long, value_name = "STORE")]
+    store: String,
+
+    /// manifest file name (the content hashes of the baselines, by file name)
+    #[arg(
+        long,
+        value_name = "MANIFEST_FILE_NAME",
+        default_value = "baselines.toml"
+    )]
+    manifest: PathBuf,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// show the trend of the difference of every pair of files
+    Show(HistoryShowArgs),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Args)]
+struct HistoryShowArgs {
+    /// history file name (written with the history option)
+    #[arg(long, value_name = "HISTORY_FILE_NAME")]
+    history: PathBuf,
+}
+
+#[cfg(feature = "server")]
+#[derive(Args)]
+struct ServeArgs {
+    /// port to listen on (0 for any free port)
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: String,
+
+    /// maximum size (in bytes) of a request body (the uploaded images)
+    #[arg(long, default_value_t = 100 * 1024 * 1024)]
+    max_body_size: usize,
+
+    /// maximum number of pixels of an uploaded image (lower limits allowed in the query)
+    #[arg(long, value_name = "N", default_value_t = 100_000_000)]
+    max_pixels: u64,
+
+    /// maximum estimated memory (in MB) of a comparison (lower limits allowed in the query)
+    #[arg(long, value_name = "MB", default_value_t = 4096)]
+    max_memory: u64,
+
+    /// time (in seconds) allowed to read a request / write a response
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    timeout: u64,
+
+    /// maximum number of requests handled at once (the others wait in the queue)
+    #[arg(long, value_name = "N", default_value_t = 8, value_parser = clap::value_parser!(u64).range(1..))]
+    max_connections: u64,
+}
+
+#[derive(Args)]
+struct CompletionsArgs {
+    /// shell to generate the completion script for
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+#[cfg(feature = "video")]
+#[derive(Args)]
+struct VideoArgs {
+    /// source video file name
+    #[arg(long, value_name = "SOURCE_FILE_NAME")]
+    src: PathBuf,
+
+    /// target video file name
+    #[arg(long, value_name = "TARGET_FILE_NAME")]
+    tgt: PathBuf,
+
+    /// pixel block size for highlighting difference
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
     block: u32,
-) -> (f32, Vec<Bounds>) {
-    let mut total_diff = 0;
-    let mut bounds_with_difference = Vec::new();
 
-    for start_height in (bounds.min_height..bounds.max_height).step_by(block as usize) {
-        for start_width in (bounds.min_width..bounds.max_width).step_by(block as usize) {
-            // Note: max width & height should not exceed the overall bounds
-            let max_width = std::cmp::min(start_width + block, bounds.max_width);
-            let max_height = std::cmp::min(start_height + block, bounds.max_height);
+    /// percentage difference of a frame above which it is reported
+    #[arg(long, default_value_t = 0.0)]
+    threshold: f32,
+
+    /// directory to write the reported target frames (with the differences highlighted) into
+    #[arg(long, value_name = "DIRECTORY_NAME")]
+    dump_dir: Option<PathBuf>,
+
+    /// colors of the highlighted differences
+    #[arg(long, value_enum, requires = "dump_dir", default_value_t = Palette::Default)]
+    palette: Palette,
+}
+
+/// Options of the comparison (shared by the commands comparing images), built with `CompareOptions::builder`
+/// by library users.
+#[derive(Args, Clone, Debug)]
+pub struct CompareOptions {
+    /// strict comparison (exits if dimensions are different)
+    #[arg(long)]
+    strict: bool,
+
+    /// pixel block size for highlighting difference (or auto, for 1% of the smaller dimension)
+    #[arg(long, value_name = "BLOCK", default_value = "10", value_parser = parse_block)]
+    block: BlockSize,
+
+    /// rectangular blocks (instead of square blocks of the block size)
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_block_shape, conflicts_with_all = ["block", "by_row", "by_column", "adaptive"])]
+    block_shape: Option<(u32, u32)>,
+
+    /// compare whole rows (scanlines) of the images, as blocks
+    #[arg(long, conflicts_with_all = ["block", "by_column", "adaptive"])]
+    by_row: bool,
+
+    /// compare whole columns of the images, as blocks
+    #[arg(long, conflicts_with_all = ["block", "adaptive"])]
+    by_column: bool,
+
+    /// number of pixels adjacent blocks overlap by
+    #[arg(long, default_value_t = 0)]
+    block_overlap: u32,
+
+    /// count the regions only present in one of the images (when dimensions are different) as difference
+    #[arg(long)]
+    count_size_mismatch: bool,
+
+    /// standard deviation (sigma) of the gaussian blur applied to both the images before the comparison (to suppress noise)
+    #[arg(long, value_name = "SIGMA", value_parser = parse_sigma)]
+    pre_blur: Option<f32>,
+
+    /// region of the images to ignore in the comparison (repeatable)
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_region)]
+    ignore_region: Vec<Region>,
+
+    /// mask image weighing the contribution of every pixel to the percentage difference by its alpha channel (or gray value, if opaque), from 0 (ignored) to 255 (fully counted)
+    #[arg(long, value_name = "MASK_FILE_NAME", value_parser = mask::read)]
+    mask: Option<mask::Mask>,
+
+    /// how the alpha channel is compared
+    #[arg(long, value_enum, default_value_t = AlphaMode::Straight)]
+    alpha_mode: AlphaMode,
+
+    /// equalize the brightness / contrast of the target to the source before the comparison (for ex. photos taken under a slightly different exposure)
+    #[arg(long, value_enum, default_value_t = Normalize::None)]
+    normalize: Normalize,
+
+    /// percentage (0-100) of the pixels of a block which must differ for the block to be reported (& highlighted) as changed
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent, default_value_t = 0.0)]
+    block_threshold: f32,
+
+    /// subdivide (quadtree) only the regions with difference, down to blocks no larger than the block size, instead of comparing a fixed grid (tighter regions, faster on mostly identical images)
+    #[arg(long, conflicts_with_all = ["block_overlap", "clusters"])]
+    adaptive: bool,
+
+    /// check whether the target is a rotated (90, 180 or 270°) or mirrored source, to report the transform instead of the difference
+    #[arg(long)]
+    detect_transform: bool,
+
+    /// compare after undoing the detected transform of the target
+    #[arg(long, requires = "detect_transform")]
+    undo_transform: bool,
+
+    /// search the target for the best-matching position of the (smaller) source, and compare the source with the matched area
+    #[arg(long, conflicts_with = "detect_transform")]
+    find_in_tgt: bool,
+
+    /// region of the source to compare (instead of the whole source)
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_region, conflicts_with = "find_in_tgt")]
+    roi_a: Option<Region>,
+
+    /// region of the target to compare with (instead of the whole target)
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_region, conflicts_with = "find_in_tgt")]
+    roi_b: Option<Region>,
+
+    /// count a target pixel as matching if an identical source pixel exists within the given number of pixels
+    #[arg(long, value_name = "PIXELS")]
+    shift_tolerance: Option<u32>,
+
+    /// ignore the regions (of adjacent pixels with difference) smaller than the given number of pixels
+    #[arg(long, value_name = "PIXELS")]
+    min_cluster_size: Option<u32>,
+
+    /// ignore the pixels with a luminance difference (0-255) below the given delta, even if their color changed
+    #[arg(long, value_name = "DELTA")]
+    min_luma_delta: Option<u8>,
+
+    /// block comparator (exact, tolerance, ssim, delta-e or a registered one)
+    #[arg(long, value_name = "NAME", default_value = "exact")]
+    comparator: String,
+
+    /// threshold of the comparator (tolerance: largest channel difference, ssim: smallest similarity, delta-e: largest color difference)
+    #[arg(long, value_name = "THRESHOLD")]
+    comparator_threshold: Option<f64>,
+
+    /// compare the full precision (float, for ex. EXR) pixels, tolerating a difference of every channel up to float-tolerance
+    #[arg(long, value_enum, conflicts_with_all = ["comparator", "jpeg_aware", "preview_scale", "pre_blur", "shift_tolerance", "min_cluster_size", "min_luma_delta", "raw_format"])]
+    tolerance_mode: Option<ToleranceMode>,
+
+    /// largest tolerated difference of a channel in the tolerance mode (absolute: in linear units, relative: as a fraction of the larger value, ulps: in units in the last place)
+    #[arg(long, value_name = "TOLERANCE", requires = "tolerance_mode", value_parser = parse_tolerance, default_value_t = 0.0)]
+    float_tolerance: f64,
+
+    /// tolerate the expected re-encoding error of jpeg inputs (estimated per 8x8 block from their quantization tables)
+    #[arg(long, conflicts_with_all = ["comparator", "preview_scale"])]
+    jpeg_aware: bool,
+
+    /// downscale both the images by the factor (0-1) before the comparison, for a fast approximate result
+    #[arg(long, value_name = "FACTOR", value_parser = parse_scale)]
+    preview_scale: Option<f32>,
+
+    /// turn the images upright & scale them to their common physical resolution (as per their orientation & DPI metadata, or else 72 DPI) before the comparison
+    #[arg(long, conflicts_with_all = ["tolerance_mode", "jpeg_aware", "raw_format"])]
+    dpi_aware: bool,
+
+    /// additional metrics of the difference (comma separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    metric: Vec<Metric>,
+
+    /// token to cancel the comparison (library only)
+    #[arg(skip)]
+    cancellation: Option<CancellationToken>,
+
+    /// observer of the events of the comparison (library only)
+    #[arg(skip)]
+    observer: Option<observer::Shared>,
+
+    /// print the progress of the comparison (completed stages, compared blocks & found regions) into stderr
+    #[arg(long)]
+    progress: bool,
+
+    /// compare only the (pseudo-randomly, but deterministically selected) fraction (0-1) of the pixels of every block, for a fast approximate result
+    #[arg(long, value_name = "RATE", value_parser = parse_scale)]
+    sample: Option<f32>,
+
+    /// only find the blocks with difference (by a checksum of every block), instead of counting the differing pixels: the percentage is of the area of the blocks with difference
+    #[arg(long, conflicts_with_all = ["comparator", "comparator_threshold", "tolerance_mode", "jpeg_aware", "sample", "adaptive", "mask", "block_threshold"])]
+    regions_only: bool,
+
+    /// denominator of the percentage difference (default: overlap, or union with count-size-mismatch)
+    #[arg(long, value_enum)]
+    percentage_base: Option<PercentageBase>,
+
+    /// compare the metadata (EXIF, XMP, ICC profile & PNG text chunks) of the images as well
+    #[arg(long)]
+    compare_metadata: bool,
+
+    /// group the blocks with difference into clusters and classify their severity (minor / moderate / major)
+    #[arg(long)]
+    clusters: bool,
+
+    /// print the distribution (in 10% buckets) of the percentage of differing pixels of the blocks with difference
+    #[arg(long)]
+    block_histogram: bool,
+
+    /// color the highlighted blocks by their percentage of differing pixels (yellow below 1%, orange below 10%, red from 10%), with a legend strip below the output
+    #[arg(long)]
+    severity_colors: bool,
+
+    /// classify the changed pixels of every block with difference as mostly brighter, darker, hue shifted or mixed
+    #[arg(long)]
+    direction: bool,
+
+    /// classify the differing pixels as added (transparent in the source), removed (transparent in the target) or changed
+    #[arg(long, conflicts_with = "regions_only")]
+    classify_pixels: bool,
+
+    /// weigh the differing pixels by the visual saliency of the source (near the top & the center, of high contrast) into a salient percentage difference
+    #[arg(long)]
+    saliency: bool,
+
+    /// largest channel difference (0-255) of a cluster below which it is minor
+    #[arg(long, requires = "clusters", default_value_t = 32)]
+    minor_threshold: u8,
+
+    /// largest channel difference (0-255) of a cluster from which it is major
+    #[arg(long, requires = "clusters", default_value_t = 128)]
+    major_threshold: u8,
+
+    /// timeout (in seconds) for downloading URL inputs
+    #[cfg(feature = "net")]
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// maximum size (in bytes) of downloaded URL inputs
+    #[cfg(feature = "net")]
+    #[arg(long, default_value_t = 100 * 1024 * 1024)]
+    max_download_size: u64,
+
+    /// refuse the images with more pixels (width x height) than the limit, before decoding them
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_pixels: Option<u64>,
+
+    /// refuse the images estimated to require more memory (in MB) than the limit to compare, before decoding them
+    #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u64).range(1..))]
+    max_memory: Option<u64>,
+
+    /// order the channels of the target are stored in, to reorder them into RGBA (for ex. bgr, argb or any permutation of rgba)
+    #[arg(long, value_name = "ORDER", value_parser = channels::parse_order)]
+    swap_channels: Option<channels::ChannelOrder>,
+
+    /// order the channels of the source are stored in (see swap-channels)
+    #[arg(long, value_name = "ORDER", value_parser = channels::parse_order)]
+    swap_src_channels: Option<channels::ChannelOrder>,
+
+    /// format of raw (headerless) src / tgt frames
+    #[arg(long, value_enum, requires = "raw_size")]
+    raw_format: Option<raw::RawFormat>,
+
+    /// dimensions of raw (headerless) src / tgt frames
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = raw::parse_size, requires = "raw_format")]
+    raw_size: Option<Dimensions>,
+
+    /// format of the src image (for ex. webp), instead of the format detected from its content
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format, conflicts_with = "raw_format")]
+    format_src: Option<image::ImageFormat>,
+
+    /// format of the tgt image (for ex. webp), instead of the format detected from its content
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format, conflicts_with = "raw_format")]
+    format_tgt: Option<image::ImageFormat>,
+
+    /// append the result of every comparison into the history file (json lines)
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "HISTORY_FILE_NAME")]
+    history: Option<PathBuf>,
+
+    /// recognize the text of the changed regions (with the tesseract command), to report the text before & after
+    #[cfg(feature = "ocr")]
+    #[arg(long)]
+    ocr: bool,
+
+    /// layout file (json) naming regions of the images, to report the difference within every named region
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "LAYOUT_FILE_NAME", value_parser = layout::read)]
+    layout: Option<layout::Layout>,
+
+    /// print the time taken by every step (for ex. decode, diff, highlight & encode) into stderr (& include them in the json output)
+    #[arg(long, alias = "bench-mode")]
+    timings: bool,
+}
+
+impl Default for CompareOptions {
+    /// Options with the default values of the command line arguments.
+    fn default() -> Self {
+        let command = CompareOptions::augment_args(clap::Command::new("idiff"));
+        CompareOptions::from_arg_matches(&command.get_matches_from(["idiff"])).unwrap()
+    }
+}
+
+/// Parse a (downscale) factor between 0 (exclusive) and 1.
+fn parse_scale(scale: &str) -> Result<f32, String> {
+    match scale.parse::<f32>() {
+        Ok(s) if s > 0.0 && s <= 1.0 => Ok(s),
+        _ => Err(format!(
+            "invalid factor '{}' (expected above 0, up to 1)",
+            scale
+        )),
+    }
+}
+
+/// Parse an image format by its extension (for ex. `png`).
+fn parse_format(format: &str) -> Result<image::ImageFormat, String> {
+    image::ImageFormat::from_extension(format).ok_or_else(|| {
+        format!(
+            "unknown image format '{}' (expected an extension, for ex. png or webp)",
+            format
+        )
+    })
+}
+
+/// Parse a percentage between 0 and 100.
+fn parse_percent(percent: &str) -> Result<f32, String> {
+    match percent.parse::<f32>() {
+        Ok(p) if (0.0..=100.0).contains(&p) => Ok(p),
+        _ => Err(format!(
+            "invalid percentage '{}' (expected 0 to 100)",
+            percent
+        )),
+    }
+}
+
+/// Parse a (non-negative) tolerance.
+fn parse_tolerance(tolerance: &str) -> Result<f64, String> {
+    match tolerance.parse::<f64>() {
+        Ok(t) if t >= 0.0 && t.is_finite() => Ok(t),
+        _ => Err(format!(
+            "invalid tolerance '{}' (expected a non-negative number)",
+            tolerance
+        )),
+    }
+}
+
+/// Parse an opacity between 0 and 1.
+fn parse_alpha(alpha: &str) -> Result<f32, String> {
+    match alpha.parse::<f32>() {
+        Ok(a) if (0.0..=1.0).contains(&a) => Ok(a),
+        _ => Err(format!("invalid alpha '{}' (expected 0 to 1)", alpha)),
+    }
+}
+
+/// Parse the (positive) sigma of a gaussian blur.
+fn parse_sigma(sigma: &str) -> Result<f32, String> {
+    match sigma.parse::<f32>() {
+        Ok(s) if s > 0.0 && s.is_finite() => Ok(s),
+        _ => Err(format!(
+            "invalid sigma '{}' (expected a positive number)",
+            sigma
+        )),
+    }
+}
+
+/// Parse a region (`X,Y,WIDTH,HEIGHT`, for ex. `0,0,1920,40`).
+fn parse_region(region: &str) -> Result<Region, String> {
+    let values: Vec<u32> = region
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Region {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!(
+            "invalid region '{}' (expected X,Y,WIDTH,HEIGHT, for ex. 0,0,1920,40)",
+            region
+        )),
+    }
+}
+
+/// Represents the size of the blocks the images are compared in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BlockSize {
+    Fixed(u32),
+    /// proportional to the dimensions of the images (see `BlockSize::resolve`).
+    Auto,
+}
+
+impl BlockSize {
+    /// Get the block size for the bounds: 1% of the smaller dimension (clamped to 2-64 pixels) for `Auto`.
+    fn resolve(&self, bounds: &Bounds) -> u32 {
+        match self {
+            BlockSize::Fixed(block) => *block,
+            BlockSize::Auto => {
+                let smaller = std::cmp::min(bounds.max_width, bounds.max_height);
+                (smaller / 100).clamp(2, 64)
+            }
+        }
+    }
+}
+
+/// Parse the (positive) block size or `auto`.
+fn parse_block(block: &str) -> Result<BlockSize, String> {
+    match block {
+        "auto" => Ok(BlockSize::Auto),
+        _ => match block.parse() {
+            Ok(b) if b > 0 => Ok(BlockSize::Fixed(b)),
+            _ => Err(format!(
+                "invalid block size '{}' (expected a positive number or auto)",
+                block
+            )),
+        },
+    }
+}
+
+/// Parse the (positive) dimensions of the rectangular blocks (`WIDTHxHEIGHT`).
+fn parse_block_shape(shape: &str) -> Result<(u32, u32), String> {
+    match shape.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+        Some((Ok(width), Ok(height))) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!(
+            "invalid block shape '{}' (expected WIDTHxHEIGHT, for ex. 32x8)",
+            shape
+        )),
+    }
+}
+
+/// Represents the metrics of the difference reported alongside the percentage difference.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Metric {
+    // peak signal-to-noise ratio (dB)
+    Psnr,
+    // mean squared error of the RGB channels
+    Mse,
+    // mean absolute error of the RGB channels (exposes gradual global shifts, for ex. gamma changes)
+    Mae,
+}
+
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Metric::Psnr => write!(f, "PSNR"),
+            Metric::Mse => write!(f, "MSE"),
+            Metric::Mae => write!(f, "MAE"),
+        }
+    }
+}
+
+/// Represents how the alpha channel is compared.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum AlphaMode {
+    // compare the alpha like the color channels
+    Straight,
+    // compare only the color channels
+    Ignore,
+    // compare the colors multiplied by the alpha (transparent pixels match regardless of their color)
+    Premultiplied,
+}
+
+impl AlphaMode {
+    /// Convert the image for the comparison in this mode (`None` for straight, compared as is).
+    fn apply(&self, img: &image::RgbaImage) -> Option<image::RgbaImage> {
+        let mut img = img.clone();
+        match self {
+            AlphaMode::Straight => return None,
+            AlphaMode::Ignore => img.pixels_mut().for_each(|p| p[3] = 255),
+            AlphaMode::Premultiplied => img.pixels_mut().for_each(|p| {
+                for c in 0..3 {
+                    p[c] = ((p[c] as u32 * p[3] as u32 + 127) / 255) as u8;
+                }
+                p[3] = 255;
+            }),
+        }
+        Some(img)
+    }
+}
+
+/// Represents how the brightness / contrast of the target is equalized to the source.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Normalize {
+    // compare the images as is
+    None,
+    // match the histogram of every color channel
+    Histogram,
+    // match the mean & standard deviation of every color channel
+    MeanStd,
+}
+
+impl Normalize {
+    /// Equalize the color channels of the target to the source (by their values within the bounds), for the
+    /// comparison in this mode (`None` for none, compared as is).
+    fn apply(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Bounds,
+    ) -> Option<image::RgbaImage> {
+        if *self == Normalize::None {
+            return None;
+        }
+        let (src_histograms, tgt_histograms) = (
+            channel_histograms(src, bounds),
+            channel_histograms(tgt, bounds),
+        );
+        let mut lookup = [[0u8; 256]; 3];
+        for c in 0..3 {
+            lookup[c] = match self {
+                Normalize::None => unreachable!(),
+                Normalize::Histogram => match_histogram(&src_histograms[c], &tgt_histograms[c]),
+                Normalize::MeanStd => match_mean_std(&src_histograms[c], &tgt_histograms[c]),
+            };
+        }
+        let mut tgt = tgt.clone();
+        tgt.pixels_mut().for_each(|p| {
+            for c in 0..3 {
+                p[c] = lookup[c][p[c] as usize];
+            }
+        });
+        Some(tgt)
+    }
+}
+
+/// Count the values of every color channel of the pixels within the bounds.
+fn channel_histograms(img: &image::RgbaImage, bounds: &Bounds) -> [[u64; 256]; 3] {
+    let mut histograms = [[0u64; 256]; 3];
+    for y in bounds.min_height..bounds.max_height {
+        for p in row_slice(img, y, bounds).chunks_exact(4) {
+            for c in 0..3 {
+                histograms[c][p[c] as usize] += 1;
+            }
+        }
+    }
+    histograms
+}
+
+/// Map every target value to the smallest source value whose cumulative frequency reaches the one of the
+/// target value.
+fn match_histogram(src: &[u64; 256], tgt: &[u64; 256]) -> [u8; 256] {
+    let cumulative = |histogram: &[u64; 256]| {
+        let total = histogram.iter().sum::<u64>().max(1) as f64;
+        let mut sum = 0;
+        histogram.map(|count| {
+            sum += count;
+            sum as f64 / total
+        })
+    };
+    let (src, tgt) = (cumulative(src), cumulative(tgt));
+    let mut lookup = [0u8; 256];
+    for (value, mapped) in lookup.iter_mut().enumerate() {
+        *mapped = src.iter().position(|&c| c >= tgt[value]).unwrap_or(255) as u8;
+    }
+    lookup
+}
+
+/// Map every target value linearly, so that the mean & standard deviation of the target match the source.
+fn match_mean_std(src: &[u64; 256], tgt: &[u64; 256]) -> [u8; 256] {
+    let mean_std = |histogram: &[u64; 256]| {
+        let total = histogram.iter().sum::<u64>().max(1) as f64;
+        let mean = histogram
+            .iter()
+            .enumerate()
+            .map(|(v, &count)| v as f64 * count as f64)
+            .sum::<f64>()
+            / total;
+        let variance = histogram
+            .iter()
+            .enumerate()
+            .map(|(v, &count)| (v as f64 - mean).powi(2) * count as f64)
+            .sum::<f64>()
+            / total;
+        (mean, variance.sqrt())
+    };
+    let ((src_mean, src_std), (tgt_mean, tgt_std)) = (mean_std(src), mean_std(tgt));
+    // Note: a flat target channel is only shifted
+    let scale = if tgt_std > 0.0 {
+        src_std / tgt_std
+    } else {
+        1.0
+    };
+    let mut lookup = [0u8; 256];
+    for (value, mapped) in lookup.iter_mut().enumerate() {
+        *mapped = ((value as f64 - tgt_mean) * scale + src_mean)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    lookup
+}
+
+/// Represents how the difference of the full precision (float) channels is tolerated.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ToleranceMode {
+    // difference of the values
+    Absolute,
+    // difference relative to the larger (absolute) value
+    Relative,
+    // number of representable floats between the values
+    Ulps,
+}
+
+impl ToleranceMode {
+    /// Checks if the values are within the tolerance (NaNs only match NaNs).
+    fn within(&self, src: f32, tgt: f32, tolerance: f64) -> bool {
+        if src.is_nan() || tgt.is_nan() {
+            return src.is_nan() && tgt.is_nan();
+        }
+        let difference = (src as f64 - tgt as f64).abs();
+        match self {
+            ToleranceMode::Absolute => difference <= tolerance,
+            ToleranceMode::Relative => {
+                difference <= tolerance * (src.abs() as f64).max(tgt.abs() as f64)
+            }
+            ToleranceMode::Ulps => {
+                // Note: the bits of the floats, ordered as integers (the negative values reversed, -0 = +0)
+                let ordered = |v: f32| {
+                    let bits = v.to_bits() as i32;
+                    match bits < 0 {
+                        true => i32::MIN as i64 - bits as i64,
+                        false => bits as i64,
+                    }
+                };
+                (ordered(src) - ordered(tgt)).unsigned_abs() as f64 <= tolerance
+            }
+        }
+    }
+}
+
+/// Represents the region of the images the percentage difference is relative to.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum PercentageBase {
+    // region common to both the images
+    Overlap,
+    // whole source image
+    Src,
+    // whole target image
+    Tgt,
+    // region covered by either of the images
+    Union,
+}
+
+pub fn run() {
+    let cli = Cli::parse_from(with_default_command(std::env::args_os().collect()));
+    i18n::set_lang(cli.lang);
+    percent::set_format(cli.percent_format, cli.precision);
+
+    let Some(command) = cli.command else {
+        exit::explain();
+        return;
+    };
+    match command {
+        Commands::Compare(args) => run_compare(args),
+        Commands::Batch(args) => run_batch(args),
+        Commands::Report(args) => run_report(args),
+        Commands::Check(args) => run_check(args),
+        Commands::Approve(args) => run_approve(args),
+        Commands::Baseline(BaselineCommands::Push(args)) => run_baseline_push(args),
+        Commands::Baseline(BaselineCommands::Pull(args)) => run_baseline_pull(args),
+        Commands::Mips(args) => run_mips(args),
+        Commands::ExtractCompare(args) => run_extract_compare(args),
+        #[cfg(feature = "video")]
+        Commands::Video(args) => run_video(args),
+        #[cfg(feature = "serde")]
+        Commands::History(HistoryCommands::Show(args)) => run_history_show(args),
+        #[cfg(feature = "server")]
+        Commands::Serve(args) => run_serve(args),
+        #[cfg(feature = "schema")]
+        Commands::Schema => run_schema(),
+        Commands::Completions(args) => run_completions(args),
+        Commands::Man => run_man(),
+    }
+}
+
+/// Insert the `compare` command when the arguments start with an option (other than help / version),
+/// so that `idiff --src <SOURCE_FILE_NAME> --tgt <TARGET_FILE_NAME>` keeps working.
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    let starts_with_option = match args.get(1).and_then(|a| a.to_str()) {
+        Some("-h" | "--help" | "-V" | "--version" | "--explain-exit-codes") => false,
+        Some(a) => a.starts_with('-'),
+        None => false,
+    };
+
+    if starts_with_option {
+        args.insert(1, OsString::from("compare"));
+    }
+    args
+}
+
+/// Print the error message and exit (with the exit code of the error, `exit::USAGE` unless set where it was raised).
+fn exit_with_error(error: impl Into<exit::Error>) -> ! {
+    let error = error.into();
+    eprintln!("{}", error.message.red());
+    std::process::exit(error.code);
+}
+
+/// Exit (with `exit::DIFFERENCE`) if a difference was observed.
+fn exit_if_different(different: bool) {
+    if different {
+        std::process::exit(exit::DIFFERENCE);
+    }
+}
+
+impl CompareArgs {
+    /// Target file name (the source itself, if self compared).
+    fn tgt(&self) -> &Path {
+        self.tgt.as_deref().unwrap_or(&self.src)
+    }
+}
+
+fn run_compare(args: CompareArgs) {
+    if glob_pattern(&args.src).is_some() || glob_pattern(args.tgt()).is_some() {
+        run_compare_globs(args);
+        return;
+    }
+    if let Some(timeout) = args.wait_for_files {
+        if let Err(e) = wait_for_files(&args.src, args.tgt(), Duration::from_secs(timeout)) {
+            exit_with_error(e);
+        }
+    }
+    if args.dry_run {
+        if let Err(e) = dry_run(&args) {
+            exit_with_error(e);
+        }
+        std::process::exit(0);
+    }
+    if args.info {
+        if let Err(e) = print_info(&args) {
+            exit_with_error(e);
+        }
+        std::process::exit(0);
+    }
+
+    let mut comparison = match compare(&args.src, args.tgt(), &args.options) {
+        Ok(c) => c,
+        Err(e) => exit_with_error(e),
+    };
+
+    if args.options.timings {
+        print_timings(&comparison.timings);
+    }
+    print!("{}", renderer::Text::summary(&comparison));
+    // Note: the (raw) difference of a transformed target is not meaningful, unless undone
+    if matches!(comparison.transform, Some((_, false))) {
+        std::process::exit(exit::DIFFERENCE);
+    }
+
+    let stamp = args.timestamp_output.then(timestamp);
+    if let Some(output) = &args.matrix_output {
+        let (src, tgt) = (&comparison.src, &comparison.tgt);
+        let bounds = Bounds::new(
+            0,
+            src.width().min(tgt.width()),
+            0,
+            src.height().min(tgt.height()),
+        );
+        let matrix = matrix::Matrix::new(
+            src,
+            tgt,
+            &bounds,
+            args.matrix_level,
+            comparison.block_shape,
+            args.options.block_overlap,
+        );
+        let output = stamped(output, stamp.as_deref());
+        match check_overwrite(&output, args.force).and_then(|_| matrix.write(&output)) {
+            Ok(()) => println!("Matrix written into {}", output.display()),
+            Err(e) => exit_with_error(e),
+        }
+    }
+
+    if let Some(dir) = &args.crop_regions {
+        let step = comparison.block - args.options.block_overlap;
+        let dir = stamped(dir, stamp.as_deref());
+        match crop::write_crops(
+            &comparison.src,
+            &comparison.tgt,
+            &comparison.bounds_with_diff,
+            step,
+            &dir,
+            args.force,
+        ) {
+            Ok(crops) => {
+                for (region, src, tgt) in crops {
+                    println!(
+                        "Region {}x{} at {}, {} cropped into {} & {}",
+                        region.width,
+                        region.height,
+                        region.x,
+                        region.y,
+                        src.display(),
+                        tgt.display()
+                    );
+                }
+            }
+            Err(e) => exit_with_error(e),
+        }
+    }
+
+    if let Some(dir) = &args.crop_to_changes {
+        if let Some(area) = Bounds::enclosing(&comparison.bounds_with_diff) {
+            let dir = stamped(dir, stamp.as_deref());
+            match crop::write_changed_area(
+                &comparison.src,
+                &comparison.tgt,
+                &area,
+                &dir,
+                args.force,
+            ) {
+                Ok((src, tgt)) => println!(
+                    "Changed area cropped into {} & {}",
+                    src.display(),
+                    tgt.display()
+                ),
+                Err(e) => exit_with_error(e),
+            }
+        }
+    }
+
+    if comparison.diff == 0.0 {
+        std::process::exit(exit::NO_DIFFERENCE);
+    }
+    if let Some(protocol) = args.preview_tty {
+        let on_src = args.highlight_base == HighlightBase::Src;
+        match render_output(
+            &mut comparison,
+            on_src,
+            args.output_format,
+            args.blend_alpha,
+            args.palette,
+        )
+        .and_then(|img| tty::render(&img, protocol))
+        {
+            Ok(preview) => print!("{}", preview),
+            Err(e) => exit_with_error(e),
+        }
+    }
+    if !args.highlight {
+        println!("{}", i18n::text("compare.highlight-disabled", &[]).yellow());
+        std::process::exit(exit::DIFFERENCE);
+    }
+
+    let footer = args.stamp.then(|| {
+        footer::lines(
+            &args.src.display().to_string(),
+            &args.tgt().display().to_string(),
+            &percent::render(comparison.diff, None),
+            &timestamp(),
+        )
+    });
+    // Note: the thumbnails of the inputs are downscaled once (before the outputs are drawn onto the inputs)
+    let thumbnails = args.thumbnails.map(|size| {
+        (
+            size,
+            thumbnail::resize(&comparison.src, size),
+            thumbnail::resize(&comparison.tgt, size),
+        )
+    });
+    let mut timings = Vec::new();
+    for (on_src, file, requested) in outputs(&args) {
+        let start = Instant::now();
+        match render_output(
+            &mut comparison,
+            on_src,
+            args.output_format,
+            args.blend_alpha,
+            args.palette,
+        )
+        .and_then(|img| {
+            let img = match &footer {
+                Some(lines) => Rendered::Owned(footer::append(&img, lines)),
+                None => img,
+            };
+            timings.push(("highlight", start.elapsed()));
+            let start = Instant::now();
+            let output = match &args.tile_output {
+                Some(dir) => {
+                    let descriptor =
+                        tile_descriptor(requested.clone(), dir, file, stamp.as_deref());
+                    tiles::write(&img, &descriptor, args.tile_size, args.force).map(|_| descriptor)
+                }
+                None => write_highlight(
+                    &img,
+                    requested.clone(),
+                    args.output_dir.as_deref(),
+                    file,
+                    args.quality,
+                    stamp.as_deref(),
+                    args.force,
+                ),
+            };
+            timings.push(("encode", start.elapsed()));
+            let output = output?;
+            let written = match &thumbnails {
+                Some((size, src, tgt)) => {
+                    thumbnail::write(&output, &img, (src, tgt), *size, args.force)?
+                }
+                None => Vec::new(),
+            };
+            Ok((output, written))
+        }) {
+            Ok((output, thumbnails)) => {
+                print_warnings(output_warning(&requested, &output).as_slice());
+                println!(
+                    "{}",
+                    i18n::text("output.written", &[&output.display()]).green()
+                );
+                for thumbnail in thumbnails {
+                    println!("Thumbnail written into {}", thumbnail.display());
+                }
+            }
+            Err(e) => exit_with_error(e),
+        }
+    }
+    if args.options.timings {
+        print_timings(&timings);
+    }
+    std::process::exit(exit::DIFFERENCE);
+}
+
+/// Compare the files matching the glob patterns of `src` / `tgt` (for ex. `shots/*.png`), paired by file name
+/// (as the batch command).
+fn run_compare_globs(args: CompareArgs) {
+    let unsupported = [
+        ("output", args.output.is_some()),
+        ("quality", args.quality.is_some()),
+        (
+            "output-format",
+            args.output_format != OutputFormat::Highlight,
+        ),
+        ("highlight-base", args.highlight_base != HighlightBase::Tgt),
+        ("preview-tty", args.preview_tty.is_some()),
+        ("dry-run", args.dry_run),
+        ("info", args.info),
+        ("matrix-output", args.matrix_output.is_some()),
+        ("tile-output", args.tile_output.is_some()),
+        ("self-compare", args.self_compare),
+        ("crop-regions", args.crop_regions.is_some()),
+        ("crop-to-changes", args.crop_to_changes.is_some()),
+        ("stamp", args.stamp),
+        ("thumbnails", args.thumbnails.is_some()),
+        ("wait-for-files", args.wait_for_files.is_some()),
+    ];
+    if let Some((option, _)) = unsupported.iter().find(|(_, used)| *used) {
+        exit_with_error(format!(
+            "'{}' cannot be used with glob patterns (every matching pair is compared as with the batch command).",
+            option
+        ));
+    }
+
+    let tgt = args.tgt().to_path_buf();
+    run_batch(BatchArgs {
+        src: args.src,
+        tgt,
+        options: args.options,
+        sequence: None,
+        range: None,
+        highlight: args.highlight,
+        output_dir: args.output_dir,
+        cache_dir: None,
+        force: args.force,
+        timestamp_output: args.timestamp_output,
+        palette: args.palette,
+        pair_by: PairBy::Name,
+        keep_going: false,
+        montage: None,
+        report_dir: None,
+        filter: FileFilter::default(),
+        jobs: None,
+    });
+}
+
+/// Outputs to write: whether the output is over the source, the input it is named after and the requested
+/// name.
+fn outputs(args: &CompareArgs) -> Vec<(bool, &Path, Option<String>)> {
+    let bases: &[bool] = match args.highlight_base {
+        HighlightBase::Src => &[true],
+        HighlightBase::Tgt => &[false],
+        HighlightBase::Both => &[true, false],
+    };
+    bases
+        .iter()
+        .map(|&on_src| match (on_src, args.highlight_base) {
+            (true, HighlightBase::Both) => (
+                on_src,
+                args.src.as_path(),
+                src_output_name(&args.output, &args.src),
+            ),
+            (true, _) => (on_src, args.src.as_path(), args.output.clone()),
+            // Note: the outputs over a pseudo-target (for ex. `color:#000000`) are named after the source
+            (false, _) if pseudo::Pseudo::parse(args.tgt()).is_some() => {
+                (on_src, args.src.as_path(), args.output.clone())
+            }
+            (false, _) => (on_src, args.tgt(), args.output.clone()),
+        })
+        .collect()
+}
+
+/// Print the format, color type, bit depth, dimensions, frame count & ICC profile presence of the inputs (the
+/// frames of animated images are decoded to be counted).
+fn print_info(args: &CompareArgs) -> Result<(), exit::Error> {
+    let ((src, _), (tgt, _)) = (split_page(&args.src), split_page(args.tgt()));
+    check_inputs_exist(src, tgt)?;
+
+    let raw = args.options.raw_format.zip(args.options.raw_size);
+    let formats = [args.options.format_src, args.options.format_tgt];
+    for ((label, path), format) in [("Source", src), ("Target", tgt)].into_iter().zip(formats) {
+        let input = Input::load(path, &args.options)?;
+        let header = input.header(raw, format)?;
+        let bytes = input.bytes();
+        let (frames, icc) = match raw {
+            Some(_) => (1, false),
+            None => (
+                page::count(bytes),
+                metadata::read(bytes).is_ok_and(|m| m.contains_key("ICC")),
+            ),
+        };
+        let unknown = || String::from("unknown");
+        println!("{}: {}", label, path.display());
+        println!("  format: {}", header.format);
+        println!(
+            "  color type: {}",
+            header.channels().map_or_else(unknown, String::from)
+        );
+        println!(
+            "  bit depth: {}",
+            header.depth().map_or_else(unknown, |d| d.to_string())
+        );
+        println!(
+            "  dimensions: {}x{}",
+            header.dimensions.0, header.dimensions.1
+        );
+        println!("  frames: {}", frames);
+        println!("  ICC profile: {}", if icc { "yes" } else { "no" });
+    }
+    Ok(())
+}
+
+/// Validate the inputs (reading only their headers) and print what would be compared & where the output would
+/// be written.
+fn dry_run(args: &CompareArgs) -> Result<(), exit::Error> {
+    let ((src, src_page), (tgt, tgt_page)) = (split_page(&args.src), split_page(args.tgt()));
+    check_inputs_exist(src, tgt)?;
+
+    let raw = args.options.raw_format.zip(args.options.raw_size);
+    let (src_header, tgt_header) = (
+        Input::load(src, &args.options)?.header(raw, args.options.format_src)?,
+        Input::load(tgt, &args.options)?.header(raw, args.options.format_tgt)?,
+    );
+    let page = |page: Option<usize>| match page {
+        Some(page) => format!(", page {}", page),
+        None => String::new(),
+    };
+    println!(
+        "Source: {} ({}{})",
+        src.display(),
+        src_header,
+        page(src_page)
+    );
+    println!(
+        "Target: {} ({}{})",
+        tgt.display(),
+        tgt_header,
+        page(tgt_page)
+    );
+
+    // Note: the dimensions of a selected page are only known once decoded
+    if src_page.is_none() && tgt_page.is_none() && src_header.dimensions != tgt_header.dimensions {
+        if args.options.strict {
+            return Err(exit::Error::dimension_mismatch(i18n::text(
+                "error.dimension-mismatch",
+                &[
+                    &format!("{:?}", Dimensions::from(src_header.dimensions)),
+                    &format!("{:?}", Dimensions::from(tgt_header.dimensions)),
+                ],
+            )));
+        }
+        print_warnings(&[Warning::DimensionMismatch {
+            src: src_header.dimensions,
+            tgt: tgt_header.dimensions,
+        }]);
+    }
+
+    if !args.highlight {
+        println!("No output would be written (difference highlighting is disabled).");
+    }
+    for (_, file, requested) in outputs(args).into_iter().filter(|_| args.highlight) {
+        let stamp = args.timestamp_output.then(timestamp);
+        let output = match &args.tile_output {
+            Some(dir) => tile_descriptor(requested, dir, file, stamp.as_deref()),
+            None => output_path(
+                requested,
+                args.output_dir.as_deref(),
+                file,
+                stamp.as_deref(),
+            ),
+        };
+        println!(
+            "Output would be written into {} (if a difference is observed).",
+            output.display()
+        );
+    }
+    println!("{}", "Dry run: the images were not compared.".green());
+    Ok(())
+}
+
+/// Print the time taken by the steps of the comparison (into stderr, to keep stdout unchanged).
+fn print_timings(timings: &[(&'static str, Duration)]) {
+    for (step, duration) in timings {
+        eprintln!("[timings] {}: {:.3?}", step, duration);
+    }
+}
+
+/// Size of the blocks (`SIZE` if square, `WIDTHxHEIGHT` otherwise).
+fn block_size_text((width, height): (u32, u32)) -> String {
+    match width == height {
+        true => width.to_string(),
+        false => format!("{}x{}", width, height),
+    }
+}
+
+/// Print the warnings of the comparison.
+fn print_warnings(warnings: &[Warning]) {
+    print!("{}", renderer::warnings_text(warnings));
+}
+
+/// Warning when the output is written with a different extension than requested.
+fn output_warning(requested: &Option<String>, written: &Path) -> Option<Warning> {
+    let requested = requested.as_deref()?;
+    let name = written.file_name()?.to_str()?;
+    match Path::new(requested).file_name()?.to_str()? == name {
+        true => None,
+        false => Some(Warning::OutputExtensionChanged {
+            requested: requested.to_string(),
+            written: name.to_string(),
+        }),
+    }
+}
+
+fn run_batch(args: BatchArgs) {
+    // Note: the frames of a sequence are paired by their number (the order of all the frames is kept)
+    let paired = match args.range {
+        Some(range) => sequence::pair_frames(&args.src, &args.tgt, args.sequence.as_deref(), range)
+            .map(|(pairs, unpaired, order)| (pairs, unpaired, Some(order))),
+        None => pair_files(
+            &args.src,
+            &args.tgt,
+            args.pair_by,
+            &args.filter,
+            &args.options,
+        )
+        .map(|(pairs, unpaired)| (pairs, unpaired, None)),
+    };
+    let (pairs, (only_in_src, only_in_tgt), frames) = match paired {
+        Ok(p) => p,
+        Err(e) => exit_with_error(e),
+    };
+
+    if let Some(scale) = args.options.preview_scale {
+        println!(
+            "{}",
+            format!(
+                "Approximate results (compared at {}% scale).",
+                scale * 100.0
+            )
+            .yellow()
+        );
+    }
+    if let Some(rate) = args.options.sample {
+        println!(
+            "{}",
+            format!(
+                "Approximate results (sampled {}% of the pixels).",
+                rate * 100.0
+            )
+            .yellow()
+        );
+    }
+
+    let (mut with_difference, mut cached) = (0, 0);
+    let mut diverging = std::collections::HashSet::new();
+    let mut montage = montage::Montage::default();
+    let stamp = args.timestamp_output.then(timestamp);
+    let mut gallery = match &args.report_dir {
+        Some(dir) => match gallery::Gallery::create(dir, args.force) {
+            Ok(gallery) => Some(gallery),
+            Err(e) => exit_with_error(e),
+        },
+        None => None,
+    };
+    let mut errors = Vec::new();
+    let jobs = args
+        .jobs
+        .map_or_else(parallel::default_jobs, std::num::NonZeroUsize::get);
+    // Note: only the percentage difference is cached (the cache conflicts with the options needing more)
+    let work = |(_, src, tgt): &FilePair| match &args.cache_dir {
+        Some(dir) => cached_diff(src, tgt, &args.options, dir).map(|(diff, hit)| (diff, None, hit)),
+        None => compare(src, tgt, &args.options).map(|c| (c.diff, Some(c), false)),
+    };
+    parallel::for_each_ordered(&pairs, jobs, work, |(name, _, tgt), result| {
+        let (diff, comparison) = match result {
+            Ok((diff, comparison, hit)) => {
+                cached += hit as usize;
+                (diff, comparison)
+            }
+            Err(e) => {
+                if let Some(gallery) = &mut gallery {
+                    gallery.failed(name, &e.message);
+                }
+                fail_pair(&mut errors, args.keep_going, name, e);
+                return;
+            }
+        };
+
+        if let Some(comparison) = &comparison {
+            if args.options.timings {
+                eprintln!("[timings] {}", name);
+                print_timings(&comparison.timings);
+            }
+        }
+        print!("{}", renderer::Text::pair(name, diff, comparison.as_ref()));
+        if comparison
+            .as_ref()
+            .is_some_and(|c| matches!(c.transform, Some((_, false))))
+        {
+            with_difference += 1;
+            diverging.insert(name.clone());
+            if let Some(Err(e)) = gallery.as_mut().map(|g| g.different(name, diff, None)) {
+                fail_pair(&mut errors, args.keep_going, name, e);
+            }
+            return;
+        }
+
+        if diff == 0.0 {
+            if let Some(gallery) = &mut gallery {
+                gallery.identical(name);
+            }
+            return;
+        }
+
+        with_difference += 1;
+        diverging.insert(name.clone());
+        if let Some(mut comparison) = comparison {
+            if args.montage.is_some() {
+                match render_output(
+                    &mut comparison,
+                    false,
+                    OutputFormat::Highlight,
+                    0.0,
+                    args.palette,
+                )
+                .map(|diff| montage::thumbnail(&diff))
+                {
+                    Ok(diff) => montage.add(&comparison.src, &comparison.tgt, &diff),
+                    Err(e) => fail_pair(&mut errors, args.keep_going, name, e),
+                }
+            }
+            if let Some(gallery) = &mut gallery {
+                match render_output(
+                    &mut comparison,
+                    false,
+                    OutputFormat::Highlight,
+                    0.0,
+                    args.palette,
+                )
+                .and_then(|img| gallery.different(name, diff, Some(&img)))
+                {
+                    Ok(()) => {}
+                    Err(e) => fail_pair(&mut errors, args.keep_going, name, e),
+                }
+            }
+            if args.highlight {
+                match render_output(
+                    &mut comparison,
+                    false,
+                    OutputFormat::Highlight,
+                    0.0,
+                    args.palette,
+                )
+                .and_then(|img| {
+                    write_highlight(
+                        &img,
+                        None,
+                        args.output_dir.as_deref(),
+                        tgt,
+                        None,
+                        stamp.as_deref(),
+                        args.force,
+                    )
+                }) {
+                    Ok(output) => {
+                        println!("  {}", i18n::text("output.written", &[&output.display()]))
+                    }
+                    Err(e) => fail_pair(&mut errors, args.keep_going, name, e),
+                }
+            }
+        }
+    });
+
+    if let Some(output) = &args.montage {
+        if montage.is_empty() {
+            println!("No montage written (no pair has differences).");
+        } else {
+            let output = stamped(output, stamp.as_deref());
+            match check_overwrite(&output, args.force)
+                .and_then(|_| save_image(&montage.render(), &output, None))
+            {
+                Ok(()) => println!("Montage written into {}", output.display()),
+                Err(e) => exit_with_error(e),
+            }
+        }
+    }
+
+    for (side, names) in [("src", &only_in_src), ("tgt", &only_in_tgt)] {
+        for name in names {
+            println!("{}", i18n::text("batch.only-in", &[&side, name]).yellow());
+            if let Some(gallery) = &mut gallery {
+                gallery.only_in(side, name);
+            }
+        }
+    }
+    if let Some(gallery) = &gallery {
+        match gallery.write() {
+            Ok(index) => println!("Report written into {}", index.display()),
+            Err(e) => exit_with_error(e),
+        }
+    }
+    if let Some(frames) = &frames {
+        let first = frames.iter().find(|name| {
+            diverging.contains(*name) || only_in_src.contains(name) || only_in_tgt.contains(name)
+        });
+        match first {
+            Some(name) => println!("{}", format!("First diverging frame: {}", name).red()),
+            None => println!("{}", "No diverging frame.".green()),
+        }
+    }
+
+    let cached = match args.cache_dir {
+        Some(_) => format!(" ({} from the cache)", cached),
+        None => String::new(),
+    };
+    println!(
+        "{}",
+        i18n::text(
+            "batch.completed",
+            &[&with_difference, &pairs.len(), &cached]
+        )
+        .green()
+    );
+
+    if !errors.is_empty() {
+        eprintln!("{}", i18n::text("error.count", &[&errors.len()]).red());
+        for error in &errors {
+            eprintln!("  {}", error.message.red());
+        }
+        std::process::exit(errors[0].code);
+    }
+    exit_if_different(with_difference > 0 || !only_in_src.is_empty() || !only_in_tgt.is_empty());
+}
+
+/// Record the error of the pair (printed into stderr right away) to be reported at the end, if keep going, or
+/// exit with it.
+fn fail_pair(errors: &mut Vec<exit::Error>, keep_going: bool, name: &str, error: exit::Error) {
+    let error = error.prefixed(&format!("{}: ", name));
+    if !keep_going {
+        exit_with_error(error);
+    }
+    eprintln!("{}", error.message.red());
+    errors.push(error);
+}
+
+/// Get the percentage difference of the images from the cache, or compare them (and cache the result).
+///
+/// Returns the percentage difference & whether it was cached.
+fn cached_diff(
+    src: &Path,
+    tgt: &Path,
+    options: &CompareOptions,
+    cache_dir: &Path,
+) -> Result<(f32, bool), exit::Error> {
+    let key = cache_key(src, tgt, options)?;
+    if let Some(diff) = cache::get(cache_dir, key) {
+        return Ok((diff, true));
+    }
+
+    let diff = compare(src, tgt, options)?.diff;
+    cache::put(cache_dir, key, diff)?;
+    Ok((diff, false))
+}
+
+/// Content hash of the comparison of the source and target images (with the options & the version of idiff).
+fn cache_key(src: &Path, tgt: &Path, options: &CompareOptions) -> Result<cache::Key, exit::Error> {
+    let content = |path: &Path| -> Result<(Vec<u8>, String), exit::Error> {
+        let (file, page) = match page::split(path) {
+            Some((file, page)) => (file, page.to_string()),
+            None => (path, String::new()),
+        };
+        Ok((Input::load(file, options)?.bytes().to_vec(), page))
+    };
+    let ((src, src_page), (tgt, tgt_page)) = (content(src)?, content(tgt)?);
+    let options = cached_options(options);
+
+    Ok(cache::Key::new(&[
+        &src,
+        src_page.as_bytes(),
+        &tgt,
+        tgt_page.as_bytes(),
+        options.as_bytes(),
+        env!("CARGO_PKG_VERSION").as_bytes(),
+    ]))
+}
+
+/// Version of the options hashed into the cache key (bumped when the hashed options, or their meaning, change).
+const CACHED_OPTIONS_VERSION: u32 = 1;
+
+/// Options affecting the cached percentage difference, as `NAME=VALUE` lines (of the version).
+///
+/// Note: the other options (for ex. the progress, the timings, the observer or the cancellation) are left out,
+/// so that they do not invalidate the cache
+fn cached_options(options: &CompareOptions) -> String {
+    let o = options;
+    let fields: &[(&str, &dyn std::fmt::Debug)] = &[
+        ("version", &CACHED_OPTIONS_VERSION),
+        ("strict", &o.strict),
+        ("block", &o.block),
+        ("block-shape", &o.block_shape),
+        ("by-row", &o.by_row),
+        ("by-column", &o.by_column),
+        ("block-overlap", &o.block_overlap),
+        ("count-size-mismatch", &o.count_size_mismatch),
+        ("pre-blur", &o.pre_blur),
+        ("ignore-region", &o.ignore_region),
+        ("mask", &o.mask.as_ref().map(|m| m.hash())),
+        ("alpha-mode", &o.alpha_mode),
+        ("normalize", &o.normalize),
+        ("block-threshold", &o.block_threshold),
+        ("adaptive", &o.adaptive),
+        ("detect-transform", &o.detect_transform),
+        ("undo-transform", &o.undo_transform),
+        ("find-in-tgt", &o.find_in_tgt),
+        ("roi-a", &o.roi_a),
+        ("roi-b", &o.roi_b),
+        ("shift-tolerance", &o.shift_tolerance),
+        ("min-cluster-size", &o.min_cluster_size),
+        ("min-luma-delta", &o.min_luma_delta),
+        ("comparator", &o.comparator),
+        ("comparator-threshold", &o.comparator_threshold),
+        ("tolerance-mode", &o.tolerance_mode),
+        ("float-tolerance", &o.float_tolerance),
+        ("jpeg-aware", &o.jpeg_aware),
+        ("preview-scale", &o.preview_scale),
+        ("dpi-aware", &o.dpi_aware),
+        ("sample", &o.sample),
+        ("regions-only", &o.regions_only),
+        ("percentage-base", &o.percentage_base),
+        ("swap-channels", &o.swap_channels),
+        ("swap-src-channels", &o.swap_src_channels),
+        ("raw-format", &o.raw_format),
+        ("raw-size", &o.raw_size),
+        ("format-src", &o.format_src),
+        ("format-tgt", &o.format_tgt),
+    ];
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={:?}\n", name, value))
+        .collect()
+}
+
+fn run_report(args: ReportArgs) {
+    let comparison = match compare(&args.src, &args.tgt, &args.options) {
+        Ok(c) => c,
+        Err(e) => exit_with_error(e),
+    };
+
+    if args.options.timings {
+        print_timings(&comparison.timings);
+    }
+
+    #[cfg(feature = "serde")]
+    let format = match args.json {
+        true => "json",
+        false => args.format.as_str(),
+    };
+    #[cfg(not(feature = "serde"))]
+    let format = args.format.as_str();
+    let renderer = match renderer::get(format) {
+        Ok(r) => r,
+        Err(e) => exit_with_error(e),
+    };
+    let result = diff_result(&comparison, &args.options);
+    let report = Report::new(&args.src, &args.tgt, &result, &comparison);
+    match renderer.render(&report) {
+        Ok(text) => print!("{}", text),
+        Err(e) => exit_with_error(e),
+    }
+    exit_if_different(comparison.diff != 0.0);
+}
+
+#[cfg(feature = "video")]
+fn run_video(args: VideoArgs) {
+    if let Some(dir) = &args.dump_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            exit_with_error(exit::Error::io(format!(
+                "Encountered error while creating {}: {}",
+                dir.display(),
+                e
+            )));
+        }
+    }
+
+    let comparison = match video::compare(
+        &args.src,
+        &args.tgt,
+        args.block,
+        args.threshold,
+        args.dump_dir.as_ref().map(|_| args.palette),
+    ) {
+        Ok(c) => c,
+        Err(e) => exit_with_error(e),
+    };
+
+    for frame in &comparison.frames {
+        println!(
+            "frame {}: {}",
+            frame.index,
+            percent::render(frame.diff, None).red()
+        );
+        if let (Some(dir), Some(img)) = (&args.dump_dir, &frame.highlighted) {
+            let output = dir.join(format!("frame_{:06}.png", frame.index));
+            if let Err(e) = img.save(&output) {
+                exit_with_error(exit::Error::io(format!(
+                    "Encountered error while creating output file: {}",
+                    e
+                )));
+            }
+            println!("  {}", i18n::text("output.written", &[&output.display()]));
+        }
+    }
+
+    if comparison.only_in_src > 0 || comparison.only_in_tgt > 0 {
+        println!(
+            "{}",
+            format!(
+                "Frame count mismatch: {} frame(s) only in src, {} frame(s) only in tgt.",
+                comparison.only_in_src, comparison.only_in_tgt
+            )
+            .yellow()
+        );
+    }
+    if let Some((index, diff)) = comparison.max_diff {
+        println!(
+            "Mean difference: {:.5}%, largest difference: {:.5}% (frame {}).",
+            comparison.mean_diff, diff, index
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "Video Comparison Completed. {} of {} frame(s) have differences above {}%.",
+            comparison.frames.len(),
+            comparison.compared,
+            args.threshold
+        )
+        .green()
+    );
+    exit_if_different(!comparison.frames.is_empty());
+}
+
+#[cfg(feature = "server")]
+fn run_serve(args: ServeArgs) {
+    let address = format!("{}:{}", args.bind, args.port);
+    let limits = server::Limits {
+        max_body_size: args.max_body_size,
+        max_pixels: args.max_pixels,
+        max_memory: args.max_memory,
+        timeout: Duration::from_secs(args.timeout),
+        max_connections: args.max_connections as usize,
+    };
+    if let Err(e) = server::serve(&address, limits) {
+        exit_with_error(e);
+    }
+}
+
+#[cfg(feature = "schema")]
+fn run_schema() {
+    match serde_json::to_string_pretty(&schemars::schema_for!(DiffResult)) {
+        Ok(schema) => println!("{}", schema),
+        Err(e) => exit_with_error(format!("Encountered error while writing json: {}", e)),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn run_history_show(args: HistoryShowArgs) {
+    let entries = match history::read(&args.history) {
+        Ok(e) => e,
+        Err(e) => exit_with_error(e),
+    };
+
+    for trend in history::trends(&entries) {
+        let change = trend.last - trend.first;
+        let change = if change > 0.0 {
+            format!("+{}%", change).red()
+        } else {
+            format!("{}%", change).green()
+        };
+        println!(
+            "{} -> {}: {} run(s), first: {}%, last: {}%, min: {}%, max: {}%, change: {}",
+            trend.src, trend.tgt, trend.runs, trend.first, trend.last, trend.min, trend.max, change
+        );
+    }
+}
+
+fn run_completions(args: CompletionsArgs) {
+    clap_complete::generate(
+        args.shell,
+        &mut Cli::command(),
+        "idiff",
+        &mut std::io::stdout(),
+    );
+}
+
+fn run_man() {
+    let command = Cli::command();
+    let mut roff = Vec::new();
+    let mut render = || -> Result<(), std::io::Error> {
+        let man = clap_mangen::Man::new(command.clone());
+        man.render_title(&mut roff)?;
+        man.render_name_section(&mut roff)?;
+        man.render_synopsis_section(&mut roff)?;
+        man.render_description_section(&mut roff)?;
+        man.render_options_section(&mut roff)?;
+        man.render_subcommands_section(&mut roff)?;
+
+        // Note: the options of the commands are rendered under their own heading (for ex. COMPARE OPTIONS)
+        for subcommand in command.get_subcommands() {
+            let name = subcommand.get_name().to_string();
+            let subcommand = subcommand
+                .clone()
+                .disable_help_flag(true)
+                .mut_args(|arg| arg.help_heading(format!("{} options", name)));
+            clap_mangen::Man::new(subcommand).render_options_section(&mut roff)?;
+        }
+
+        let mut exit_status = clap_mangen::roff::Roff::new();
+        exit_status.control("SH", ["EXIT STATUS"]);
+        for (code, description) in exit::CODES {
+            exit_status
+                .control("TP", [])
+                .text([clap_mangen::roff::bold(code.to_string())])
+                .text([clap_mangen::roff::roman(*description)]);
+        }
+        exit_status.to_writer(&mut roff)?;
+
+        man.render_version_section(&mut roff)
+    };
+
+    if let Err(e) = render() {
+        exit_with_error(exit::Error::io(format!(
+            "Encountered error while generating the man page: {}",
+            e
+        )));
+    }
+    print!("{}", String::from_utf8_lossy(&roff));
+}
+
+fn run_check(args: CheckArgs) {
+    let budgets = match budget::Budgets::read(&args.budget) {
+        Ok(b) => b,
+        Err(e) => exit_with_error(e),
+    };
+    let filter = FileFilter::default();
+    let (pairs, _) = match pair_files(&args.src, &args.tgt, PairBy::Name, &filter, &args.options) {
+        Ok(p) => p,
+        Err(e) => exit_with_error(e),
+    };
+
+    for file in budgets.files() {
+        if !pairs.iter().any(|(name, _, _)| name == file) {
+            println!(
+                "{}",
+                format!("{}: not found in both the directories", file).yellow()
+            );
+        }
+    }
+
+    let mut over_budget = Vec::new();
+    for (name, src, tgt) in &pairs {
+        let diff = match &args.cache_dir {
+            Some(dir) => cached_diff(src, tgt, &args.options, dir).map(|(diff, _)| diff),
+            None => compare(src, tgt, &args.options).map(|c| c.diff),
+        };
+        let diff = match diff {
+            Ok(d) => d,
+            Err(e) => exit_with_error(e.prefixed(&format!("{}: ", name))),
+        };
+
+        let budget = budgets.get(name);
+        if diff > budget {
+            println!(
+                "{}: {} (budget: {}%)",
+                name,
+                percent::render(diff, None).red(),
+                budget
+            );
+            over_budget.push((name, diff, budget));
+        } else {
+            println!(
+                "{}: {}% (budget: {}%)",
+                name,
+                diff.to_string().green(),
+                budget
+            );
+        }
+    }
+
+    if !over_budget.is_empty() {
+        eprintln!(
+            "{}",
+            format!(
+                "Check Failed. {} of {} image(s) exceed their budget:",
+                over_budget.len(),
+                pairs.len()
+            )
+            .red()
+        );
+        for (name, diff, budget) in over_budget {
+            eprintln!("  {}: {}% (budget: {}%)", name, diff, budget);
+        }
+        std::process::exit(exit::DIFFERENCE);
+    }
+    println!(
+        "{}",
+        format!(
+            "Check Completed. All {} image(s) are within their budget.",
+            pairs.len()
+        )
+        .green()
+    );
+}
+
+fn run_mips(args: MipsArgs) {
+    let (src, tgt) = match (
+        texture::mip_levels(&args.src),
+        texture::mip_levels(&args.tgt),
+    ) {
+        (Ok(src), Ok(tgt)) => (src, tgt),
+        (Err(e), _) | (_, Err(e)) => exit_with_error(e),
+    };
+
+    let mut with_difference = 0;
+    for (level, (src, tgt)) in src.iter().zip(&tgt).enumerate() {
+        let (width, height) = tgt.dimensions();
+        let name = format!("mip {} ({}x{})", level, width, height);
+
+        // Note: the blocks of the smaller levels are limited to the level
+        let mut options = args.options.clone();
+        let smaller = width.min(height).min(src.width()).min(src.height());
+        let block = BlockSize::Fixed(
+            args.options
+                .block
+                .resolve(&Bounds::new(0, width, 0, height))
+                .min(smaller),
+        );
+        options.block = block;
+        options.block_overlap = options.block_overlap.min(smaller - 1);
+
+        match compare_decoded(
+            src.clone(),
+            tgt.clone(),
+            &options,
+            Encoded::default(),
+            Vec::new(),
+        ) {
+            Ok(c) => {
+                with_difference += (c.diff != 0.0) as usize;
+                print!("{}", renderer::Text::pair(&name, c.diff, Some(&c)));
+            }
+            Err(e) => exit_with_error(e.prefixed(&format!("{}: ", name))),
+        }
+    }
+
+    for (levels, name) in [(&src, "src"), (&tgt, "tgt")] {
+        for (level, img) in levels.iter().enumerate().skip(src.len().min(tgt.len())) {
+            let (width, height) = img.dimensions();
+            println!(
+                "{}",
+                format!("mip {} ({}x{}): only in {}", level, width, height, name).yellow()
+            );
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Mips Completed. {} of {} mip level(s) have differences ({} in src, {} in tgt).",
+            with_difference,
+            src.len().min(tgt.len()),
+            src.len(),
+            tgt.len()
+        )
+        .green()
+    );
+    exit_if_different(with_difference > 0 || src.len() != tgt.len());
+}
+
+fn run_extract_compare(args: ExtractCompareArgs) {
+    let (src, tgt) = match (extract::images(&args.src), extract::images(&args.tgt)) {
+        (Ok(src), Ok(tgt)) => (src, tgt),
+        (Err(e), _) | (_, Err(e)) => exit_with_error(e),
+    };
+
+    let mut with_difference = 0;
+    for (index, (src, tgt)) in src.iter().zip(&tgt).enumerate() {
+        let name = if src.name == tgt.name {
+            format!("#{} ({})", index, src.name)
+        } else {
+            format!("#{} ({} / {})", index, src.name, tgt.name)
+        };
+
+        let comparison = match (&src.image, &tgt.image) {
+            (Ok(src), Ok(tgt)) => compare_decoded(
+                src.clone(),
+                tgt.clone(),
+                &args.options,
+                Encoded::default(),
+                Vec::new(),
+            ),
+            (Err(e), _) | (_, Err(e)) => Err(exit::Error::decode(e.clone())),
+        };
+        match comparison {
+            Ok(c) => {
+                with_difference += (c.diff != 0.0) as usize;
+                print!("{}", renderer::Text::pair(&name, c.diff, Some(&c)));
+            }
+            Err(e) => println!("{}: {}", name, format!("skipped ({})", e).yellow()),
+        }
+    }
+
+    for (documents, name) in [(&src, "src"), (&tgt, "tgt")] {
+        let compared = src.len().min(tgt.len());
+        for (index, embedded) in documents.iter().enumerate().skip(compared) {
+            println!(
+                "{}",
+                format!("#{} ({}): only in {}", index, embedded.name, name).yellow()
+            );
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Extract Compare Completed. {} of {} embedded image(s) have differences ({} in src, {} in tgt).",
+            with_difference,
+            src.len().min(tgt.len()),
+            src.len(),
+            tgt.len()
+        )
+        .green()
+    );
+    exit_if_different(with_difference > 0 || src.len() != tgt.len());
+}
+
+fn run_approve(args: ApproveArgs) {
+    if !args.tgt.is_file() {
+        exit_with_error(exit::Error::io(
+            "Invalid value for tgt path. Please check and try again.",
+        ));
+    }
+
+    if let Err(e) = std::fs::copy(&args.tgt, &args.src) {
+        exit_with_error(exit::Error::io(format!(
+            "Encountered error while copying target image over source image: {}",
+            e
+        )));
+    }
+    println!(
+        "{}",
+        format!(
+            "Approved {} as the new {}",
+            args.tgt.display(),
+            args.src.display()
+        )
+        .green()
+    );
+}
+
+fn run_baseline_push(args: BaselineArgs) {
+    let store = match baseline::open(&args.store) {
+        Ok(s) => s,
+        Err(e) => exit_with_error(e),
+    };
+    let mut files = match list_files(&args.dir) {
+        Ok(f) => f,
+        Err(e) => exit_with_error(e),
+    };
+    // Note: the manifest itself is not a baseline (if written into the directory)
+    files.retain(|(_, path)| path.canonicalize().ok() != args.manifest.canonicalize().ok());
+
+    let (manifest, stored) = match baseline::push(&files, &*store) {
+        Ok(p) => p,
+        Err(e) => exit_with_error(e),
+    };
+    for name in &stored {
+        println!("Stored {} ({})", name, manifest[name]);
+    }
+    if let Err(e) = baseline::write_manifest(&args.manifest, &manifest) {
+        exit_with_error(e);
+    }
+    println!(
+        "{}",
+        format!(
+            "{} baseline(s) recorded in {} ({} newly stored)",
+            manifest.len(),
+            args.manifest.display(),
+            stored.len()
+        )
+        .green()
+    );
+}
+
+fn run_baseline_pull(args: BaselineArgs) {
+    let store = match baseline::open(&args.store) {
+        Ok(s) => s,
+        Err(e) => exit_with_error(e),
+    };
+    let manifest = match baseline::read_manifest(&args.manifest) {
+        Ok(m) => m,
+        Err(e) => exit_with_error(e),
+    };
+    let retrieved = match baseline::pull(&manifest, &args.dir, &*store) {
+        Ok(r) => r,
+        Err(e) => exit_with_error(e),
+    };
+    for name in &retrieved {
+        println!("Retrieved {} ({})", name, manifest[name]);
+    }
+    println!(
+        "{}",
+        format!(
+            "{} baseline(s) in {} ({} retrieved, {} up to date)",
+            manifest.len(),
+            args.dir.display(),
+            retrieved.len(),
+            manifest.len() - retrieved.len()
+        )
+        .green()
+    );
+}
+
+/// Represents the result of comparing two images.
+struct Comparison {
+    /// percentage difference between the images.
+    diff: f32,
+    /// number of pixels with difference (estimated, if sampled).
+    differing_pixels: u64,
+    /// bounds where the difference was observed (sorted, see `Bounds`'s `Ord`).
+    bounds_with_diff: Vec<Bounds>,
+    /// bounds only present in the source image (sorted).
+    only_in_src: Vec<Bounds>,
+    /// bounds only present in the target image (sorted).
+    only_in_tgt: Vec<Bounds>,
+    /// differences in the metadata (if compared, sorted by key).
+    metadata_changes: Option<Vec<MetadataChange>>,
+    /// factor the images were downscaled by (if previewed, the result is approximate).
+    preview_scale: Option<f32>,
+    /// whether only the blocks with difference were found (the differing pixels are the area of the blocks).
+    regions_only: bool,
+    /// requested metrics (& their values) of the difference.
+    metrics: Vec<(Metric, f64)>,
+    /// fraction of the compared pixels & margin of error (percentage points, at 95% confidence), if sampled.
+    sample: Option<(f32, f32)>,
+    /// size of the compared blocks (resolved, if `auto`; the smaller dimension, if rectangular).
+    block: u32,
+    /// width & height of the compared blocks.
+    block_shape: (u32, u32),
+    /// clusters of the blocks with difference (if requested, sorted by their bounds).
+    clusters: Option<Vec<Cluster>>,
+    /// number of blocks with difference per 10% bucket of their percentage of differing pixels (if requested).
+    block_histogram: Option<[usize; 10]>,
+    /// percentage of differing pixels of every block with difference (in the order of `bounds_with_diff`), if
+    /// colored by severity.
+    block_percentages: Option<Vec<f64>>,
+    /// prevailing direction of the difference of every block with difference (in the order of
+    /// `bounds_with_diff`), if requested.
+    directions: Option<Vec<Direction>>,
+    /// differing pixels classified by their presence in the images (if requested).
+    pixel_changes: Option<PixelChanges>,
+    /// percentage difference weighted by the visual saliency of the source (if requested).
+    salient_diff: Option<f32>,
+    /// transform of the source the target matches (if detected), & whether the images were compared after undoing it.
+    transform: Option<(Transform, bool)>,
+    /// position (x, y) of the source within the target (if searched), the compared area of the target.
+    found_at: Option<(u32, u32)>,
+    /// percentage difference within every named region of the layout (if provided, sorted by name).
+    named_regions: Option<Vec<(String, f32)>>,
+    /// regions with different text (if recognized, sorted by position).
+    text_changes: Vec<TextChange>,
+    /// behaviors of the comparison which can change the result.
+    warnings: Vec<Warning>,
+    /// time taken by every step of the comparison.
+    timings: Vec<(&'static str, Duration)>,
+    /// source image.
+    src: image::RgbaImage,
+    /// target image.
+    tgt: image::RgbaImage,
+}
+
+/// Compare the source and target images (with the default options).
+///
+/// ```no_run
+/// let result = idiff::diff("a.png".as_ref(), "b.png".as_ref()).unwrap();
+/// println!("{}%", result.stats.diff_percentage);
+/// ```
+pub fn diff(src: &Path, tgt: &Path) -> Result<DiffResult, String> {
+    diff_with_options(src, tgt, &CompareOptions::default())
+}
+
+/// Compare the source and target images with the options (see `CompareOptions::builder`).
+pub fn diff_with_options(
+    src: &Path,
+    tgt: &Path,
+    options: &CompareOptions,
+) -> Result<DiffResult, String> {
+    compare(src, tgt, options)
+        .map(|c| diff_result(&c, options))
+        .map_err(|e| e.message)
+}
+
+/// Compare the encoded source and target images in memory (for ex. uploads), without the filesystem.
+///
+/// ```no_run
+/// let (src, tgt) = (std::fs::read("a.png").unwrap(), std::fs::read("b.png").unwrap());
+/// let result = idiff::compare_from_bytes(&src, &tgt, &idiff::CompareOptions::default()).unwrap();
+/// ```
+pub fn compare_from_bytes(
+    src: &[u8],
+    tgt: &[u8],
+    options: &CompareOptions,
+) -> Result<DiffResult, String> {
+    let (src, tgt) = (Input::Slice(src), Input::Slice(tgt));
+    compare_inputs((&src, None), (&tgt, None), options, Vec::new())
+        .map(|c| diff_result(&c, options))
+        .map_err(|e| e.message)
+}
+
+/// Compare the encoded source and target images read from the streams (for ex. sockets or archive entries),
+/// without intermediate files: the png, jpeg & gif images are decoded incrementally (the others are read into
+/// memory first) in the `format` (or else the format guessed from their first bytes).
+///
+/// The options reading the encoded images (for ex. `compare-metadata` or `jpeg-aware`) read both the streams
+/// into memory (as `compare_from_bytes`).
+///
+/// ```no_run
+/// let (src, tgt) = (std::fs::File::open("a.png").unwrap(), std::fs::File::open("b.png").unwrap());
+/// let options = idiff::CompareOptions::default();
+/// let result = idiff::compare_readers(src, tgt, Some(image::ImageFormat::Png), &options).unwrap();
+/// ```
+pub fn compare_readers(
+    mut src: impl std::io::Read,
+    mut tgt: impl std::io::Read,
+    format: Option<image::ImageFormat>,
+    options: &CompareOptions,
+) -> Result<DiffResult, String> {
+    if options.reads_encoded() {
+        let read = |reader: &mut dyn std::io::Read| {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).map_err(|e| e.to_string())?;
+            Ok::<_, String>(data)
+        };
+        return compare_from_bytes(&read(&mut src)?, &read(&mut tgt)?, options);
+    }
+    compare_streams(src, tgt, format, options).map_err(|e| e.message)
+}
+
+/// Compare the source and target streams, decoded from their headers (see `compare_readers`).
+fn compare_streams(
+    src: impl std::io::Read,
+    tgt: impl std::io::Read,
+    format: Option<image::ImageFormat>,
+    options: &CompareOptions,
+) -> Result<DiffResult, exit::Error> {
+    let mut timings = Vec::new();
+    let start = Instant::now();
+    let (src, tgt) = (
+        stream::Stream::open(src, format)?,
+        stream::Stream::open(tgt, format)?,
+    );
+    // Note: the limits are checked with the headers, before the pixels are decoded
+    if options.max_pixels.is_some() || options.max_memory.is_some() {
+        limits::check(
+            src.dimensions()?,
+            tgt.dimensions()?,
+            options.max_pixels,
+            options.max_memory,
+        )?;
+    }
+    let (src, tgt) = (src.decode()?, tgt.decode()?);
+    options.complete_stage(&mut timings, "decode", start.elapsed());
+    cancel::check(options.cancellation.as_ref())?;
+
+    let encoded = Encoded {
+        float_pixels: options
+            .tolerance_mode
+            .map(|_| (src.to_rgba32f(), tgt.to_rgba32f())),
+        ..Encoded::default()
+    };
+    let comparison = compare_decoded(src.to_rgba8(), tgt.to_rgba8(), options, encoded, timings)?;
+    cancel::check(options.cancellation.as_ref())?;
+    Ok(diff_result(&comparison, options))
+}
+
+/// Compare the decoded source and target images (the metadata is not compared).
+pub fn compare_images(
+    src: &image::DynamicImage,
+    tgt: &image::DynamicImage,
+    options: &CompareOptions,
+) -> Result<DiffResult, String> {
+    let float_pixels = options
+        .tolerance_mode
+        .map(|_| (src.to_rgba32f(), tgt.to_rgba32f()));
+    let encoded = Encoded {
+        jpeg_tables: None,
+        float_pixels,
+        indexed: None,
+    };
+    let comparison = compare_decoded(src.to_rgba8(), tgt.to_rgba8(), options, encoded, Vec::new())
+        .map_err(|e| e.message)?;
+    cancel::check(options.cancellation.as_ref())?;
+    Ok(diff_result(&comparison, options))
+}
+
+/// Convert the comparison into its result, with the time taken by every step (if requested).
+fn diff_result(comparison: &Comparison, options: &CompareOptions) -> DiffResult {
+    let mut result = DiffResult::from(comparison);
+    if options.timings {
+        result.timings = Some(
+            comparison
+                .timings
+                .iter()
+                .map(|(step, duration)| Timing {
+                    step: step.to_string(),
+                    seconds: duration.as_secs_f64(),
+                })
+                .collect(),
+        );
+    }
+    result
+}
+
+impl CompareOptions {
+    /// Observer of the comparison (the progress printer of the CLI, if requested).
+    fn observer(&self) -> Option<Arc<dyn Observer>> {
+        static PROGRESS: OnceLock<Arc<dyn Observer>> = OnceLock::new();
+        match &self.observer {
+            Some(observer) => Some(observer.0.clone()),
+            None => self.progress.then(|| {
+                PROGRESS
+                    .get_or_init(|| Arc::new(observer::Progress::default()))
+                    .clone()
+            }),
+        }
+    }
+
+    /// Width & height of the compared blocks within the bounds: the block shape, the whole rows / columns, or
+    /// square blocks of the (resolved) block size.
+    fn block_dimensions(&self, bounds: &Bounds) -> (u32, u32) {
+        if self.by_row {
+            (bounds.max_width - bounds.min_width, 1)
+        } else if self.by_column {
+            (1, bounds.max_height - bounds.min_height)
+        } else if let Some(shape) = self.block_shape {
+            shape
+        } else {
+            let block = self.block.resolve(bounds);
+            (block, block)
+        }
+    }
+
+    /// Whether the comparison reads the encoded images (besides their pixels), for ex. their metadata.
+    fn reads_encoded(&self) -> bool {
+        self.compare_metadata || self.jpeg_aware || self.dpi_aware || self.raw_format.is_some()
+    }
+
+    /// Whether paletted images (sharing the same palette) can be compared by their indices: none of the options
+    /// changes the pixels before they are compared, and the comparator tells the exact pixel difference.
+    fn compares_indices(&self) -> bool {
+        self.swap_src_channels.is_none()
+            && self.swap_channels.is_none()
+            && !self.undo_transform
+            && !self.find_in_tgt
+            && self.roi_a.is_none()
+            && self.roi_b.is_none()
+            && self.preview_scale.is_none()
+            && !self.dpi_aware
+            && !self.adaptive
+            && !self.jpeg_aware
+            && self.tolerance_mode.is_none()
+            && self.sample.is_none()
+            && self.comparator == "exact"
+    }
+
+    /// Record the time taken by the stage of the comparison, and notify the observer (if any).
+    fn complete_stage(
+        &self,
+        timings: &mut Vec<(&'static str, Duration)>,
+        stage: &'static str,
+        duration: Duration,
+    ) {
+        if let Some(observer) = self.observer() {
+            observer.on_stage_complete(stage, duration);
+        }
+        timings.push((stage, duration));
+    }
+}
+
+/// Open and compare the source and target images.
+fn compare(src: &Path, tgt: &Path, options: &CompareOptions) -> Result<Comparison, exit::Error> {
+    #[cfg(feature = "serde")]
+    let arguments = (src, tgt);
+    // Note: a pseudo-target (for ex. `color:#000000`) is generated, instead of loaded
+    let pseudo = pseudo::Pseudo::parse(tgt).transpose()?;
+    let (src, src_page) = split_page(src);
+    let (tgt, tgt_page) = match pseudo {
+        Some(_) => (src, None),
+        None => split_page(tgt),
+    };
+    check_inputs_exist(src, tgt)?;
+
+    let mut timings = Vec::new();
+
+    let start = Instant::now();
+    let src = Input::load(src, options)?;
+    let tgt = match &pseudo {
+        Some(pseudo) => {
+            let raw = options.raw_format.zip(options.raw_size);
+            Input::Memory(pseudo.encode(src.header(raw, options.format_src)?.dimensions)?)
+        }
+        None => Input::load(tgt, options)?,
+    };
+    if matches!(src, Input::Memory(_)) || (pseudo.is_none() && matches!(tgt, Input::Memory(_))) {
+        options.complete_stage(&mut timings, "download", start.elapsed());
+    }
+
+    let comparison = compare_inputs((&src, src_page), (&tgt, tgt_page), options, timings)?;
+
+    #[cfg(feature = "serde")]
+    if let Some(path) = &options.history {
+        history::append(
+            path,
+            &history::Entry::now(arguments.0, arguments.1, comparison.diff),
+        )?;
+    }
+
+    Ok(comparison)
+}
+
+/// Compare the (loaded) source and target inputs, or only their selected page / frame.
+fn compare_inputs(
+    (src, src_page): (&Input, Option<usize>),
+    (tgt, tgt_page): (&Input, Option<usize>),
+    options: &CompareOptions,
+    mut timings: Vec<(&'static str, Duration)>,
+) -> Result<Comparison, exit::Error> {
+    let start = Instant::now();
+    let metadata_changes = if options.compare_metadata {
+        let changes = metadata::diff(&metadata::read(src.bytes())?, &metadata::read(tgt.bytes())?);
+        options.complete_stage(&mut timings, "metadata", start.elapsed());
+        Some(changes)
+    } else {
+        None
+    };
+
+    let raw = options.raw_format.zip(options.raw_size);
+    let mut warnings = Vec::new();
+    if raw.is_none() {
+        for (side, input, page) in [(Side::Src, &src, src_page), (Side::Tgt, &tgt, tgt_page)] {
+            let bytes = input.bytes();
+            if page.is_none() && page::has_more_pages(bytes) {
+                warnings.push(Warning::FramesIgnored(side));
+            }
+            if metadata::read(bytes).is_ok_and(|m| m.contains_key("ICC")) {
+                warnings.push(Warning::IccProfileIgnored(side));
+            }
+        }
+    }
+
+    // Note: the inputs without a readable header (for ex. a pdf) are only checked once decoded
+    if options.max_pixels.is_some() || options.max_memory.is_some() {
+        let dimensions = match raw {
+            Some((_, Dimensions(width, height))) => Some(((width, height), (width, height))),
+            None => src
+                .header(None, options.format_src)
+                .ok()
+                .zip(tgt.header(None, options.format_tgt).ok())
+                .map(|(src, tgt)| (src.dimensions, tgt.dimensions)),
+        };
+        if let Some((src, tgt)) = dimensions {
+            limits::check(src, tgt, options.max_pixels, options.max_memory)?;
+        }
+    }
+
+    let float_pixels = match options.tolerance_mode {
+        Some(_) => Some((
+            src.decode_float(raw, src_page, options.format_src)?,
+            tgt.decode_float(raw, tgt_page, options.format_tgt)?,
+        )),
+        None => None,
+    };
+    let jpeg_tables = if options.jpeg_aware {
+        jpeg::tables(src.bytes()).zip(jpeg::tables(tgt.bytes()))
+    } else {
+        None
+    };
+
+    cancel::check(options.cancellation.as_ref())?;
+    let start = Instant::now();
+    // Note: the paletted images sharing the same palette are compared by their indices, the RGBA pixels are only
+    // expanded from the palette (instead of decoded) for the outputs
+    let unpaged = raw.is_none() && src_page.is_none() && tgt_page.is_none();
+    let indexed = match unpaged && options.compares_indices() {
+        true => indexed::pair(src.bytes(), tgt.bytes()),
+        false => None,
+    };
+    let (decoded_src, decoded_tgt) = match &indexed {
+        Some((src, tgt)) => (src.to_rgba(), tgt.to_rgba()),
+        None => (
+            src.decode(raw, src_page, options.format_src)?,
+            tgt.decode(raw, tgt_page, options.format_tgt)?,
+        ),
+    };
+    options.complete_stage(&mut timings, "decode", start.elapsed());
+    let (src, tgt) = match options.dpi_aware {
+        true => {
+            let start = Instant::now();
+            let (src, tgt, mut rescaled) = dpi::normalize(
+                (decoded_src, dpi::Physical::read(src.bytes())),
+                (decoded_tgt, dpi::Physical::read(tgt.bytes())),
+            );
+            warnings.append(&mut rescaled);
+            options.complete_stage(&mut timings, "dpi", start.elapsed());
+            (src, tgt)
+        }
+        false => (decoded_src, decoded_tgt),
+    };
+    cancel::check(options.cancellation.as_ref())?;
+
+    let encoded = Encoded {
+        jpeg_tables,
+        float_pixels,
+        indexed,
+    };
+    let mut comparison = compare_decoded(src, tgt, options, encoded, timings)?;
+    cancel::check(options.cancellation.as_ref())?;
+    comparison.metadata_changes = metadata_changes;
+    warnings.append(&mut comparison.warnings);
+    comparison.warnings = warnings;
+    Ok(comparison)
+}
+
+/// Split the page / frame selector from the path (if any).
+fn split_page(path: &Path) -> (&Path, Option<usize>) {
+    match page::split(path) {
+        Some((file, page)) => (file, Some(page)),
+        None => (path, None),
+    }
+}
+
+/// Check that the source and target inputs exist (URLs are only checked when downloaded).
+fn check_inputs_exist(src: &Path, tgt: &Path) -> Result<(), exit::Error> {
+    let exists = |path: &Path| match archive::split(path) {
+        Some((archive, _)) => archive.is_file(),
+        None => input::is_url(path) || path.exists(),
+    };
+    if !exists(src) || !exists(tgt) {
+        return Err(exit::Error::io(i18n::text("error.invalid-path", &[])));
+    }
+    Ok(())
+}
+
+/// Wait until the (local) source and target files exist and their size is unchanged since the previous check
+/// (polled every 250ms), for ex. while a screenshot is still being written.
+fn wait_for_files(src: &Path, tgt: &Path, timeout: Duration) -> Result<(), String> {
+    let size = |path: &Path| {
+        let file = split_page(path).0;
+        let file = archive::split(file).map_or(file, |(archive, _)| archive);
+        match input::is_url(file) {
+            true => Some(0),
+            false => std::fs::metadata(file).ok().map(|m| m.len()),
+        }
+    };
+
+    let start = Instant::now();
+    let mut previous = None;
+    loop {
+        let sizes = size(src).zip(size(tgt));
+        if sizes.is_some() && sizes == previous {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(format!(
+                "Timed out after {}s waiting for the src/tgt files (missing or still being written).",
+                timeout.as_secs()
+            ));
+        }
+        previous = sizes;
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Replace the ignored regions (clipped to the bounds) of the target with the source, so they match.
+fn replace_ignored_regions<P: image::Pixel>(
+    src: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    tgt: &mut image::ImageBuffer<P, Vec<P::Subpixel>>,
+    regions: &[Region],
+    bounds: &Bounds,
+) {
+    for region in regions {
+        let clipped = Bounds::from(region).intersection(bounds);
+        for y in clipped.min_height..clipped.max_height {
+            for x in clipped.min_width..clipped.max_width {
+                tgt.put_pixel(x, y, *src.get_pixel(x, y));
+            }
+        }
+    }
+}
+
+/// Represents what the comparison needs of the encoded source and target images, besides their (8-bit) pixels.
+#[derive(Default)]
+struct Encoded {
+    /// quantization tables of the (jpeg) images, for the jpeg aware comparison.
+    jpeg_tables: Option<(jpeg::Tables, jpeg::Tables)>,
+    /// full precision pixels of the (for ex. EXR) images, for the tolerance mode.
+    float_pixels: Option<(image::Rgba32FImage, image::Rgba32FImage)>,
+    /// palette indices of the (paletted) images sharing the same palette, compared instead of their pixels.
+    indexed: Option<(indexed::Indexed, indexed::Indexed)>,
+}
+
+/// Compare the decoded source and target images (the metadata is not compared).
+///
+/// `encoded` is what some options need of the encoded images, and `timings` are the time taken by the steps
+/// before the comparison (for ex. decoding).
+fn compare_decoded(
+    mut src: image::RgbaImage,
+    mut tgt: image::RgbaImage,
+    options: &CompareOptions,
+    encoded: Encoded,
+    mut timings: Vec<(&'static str, Duration)>,
+) -> Result<Comparison, exit::Error> {
+    limits::check(
+        src.dimensions(),
+        tgt.dimensions(),
+        options.max_pixels,
+        options.max_memory,
+    )?;
+    // Note: the regions of interest are compared (& highlighted) instead of the images, so every region is in
+    // their coordinates
+    if let Some(roi) = &options.roi_a {
+        src = crop_roi(&src, roi, "roi-a", "source")?;
+    }
+    if let Some(roi) = &options.roi_b {
+        tgt = crop_roi(&tgt, roi, "roi-b", "target")?;
+    }
+    if let Some(order) = options.swap_src_channels {
+        order.apply(&mut src);
+    }
+    if let Some(order) = options.swap_channels {
+        order.apply(&mut tgt);
+    }
+
+    let transform = options
+        .detect_transform
+        .then(|| transform::detect(&src, &tgt))
+        .flatten();
+    if let Some(transform) = transform.filter(|_| options.undo_transform) {
+        tgt = transform.inverse().apply(&tgt);
+    }
+
+    // Note: the matched area of the target is compared (& highlighted), so every region is in its coordinates
+    let found_at = match options.find_in_tgt {
+        true => {
+            let start = Instant::now();
+            let (x, y) = template::find(&src, &tgt)?;
+            tgt = image::imageops::crop_imm(&tgt, x, y, src.width(), src.height()).to_image();
+            options.complete_stage(&mut timings, "find", start.elapsed());
+            Some((x, y))
+        }
+        false => None,
+    };
+
+    // Note: the preview is compared (& highlighted) instead of the images, so every region is in its coordinates
+    let (src, tgt) = match options.preview_scale {
+        Some(scale) => {
+            let start = Instant::now();
+            let preview = (downscale(&src, scale), downscale(&tgt, scale));
+            options.complete_stage(&mut timings, "preview", start.elapsed());
+            preview
+        }
+        None => (src, tgt),
+    };
+
+    let src_dimension: Dimensions = Dimensions::from(src.dimensions());
+    let tgt_dimension: Dimensions = Dimensions::from(tgt.dimensions());
+
+    if options.strict && !Dimensions::same(&src_dimension, &tgt_dimension) {
+        return Err(exit::Error::dimension_mismatch(i18n::text(
+            "error.dimension-mismatch",
+            &[
+                &format!("{:?}", src_dimension),
+                &format!("{:?}", tgt_dimension),
+            ],
+        )));
+    }
+
+    let bounds = Bounds::get_max_bounds(src_dimension, tgt_dimension)?;
+    let mut warnings = Vec::new();
+    if !Dimensions::same(&src_dimension, &tgt_dimension) {
+        warnings.push(Warning::DimensionMismatch {
+            src: src.dimensions(),
+            tgt: tgt.dimensions(),
+        });
+    }
+
+    let block_shape = options.block_dimensions(&bounds);
+    // Note: the smaller dimension of the rectangular blocks, to step / pad by (for ex. the clusters & the crops)
+    let block = block_shape.0.min(block_shape.1);
+    let overlap = options.block_overlap;
+    if !bounds.is_greater_than((block_shape.0 as u64 * block_shape.1 as u64).saturating_sub(1)) {
+        return Err(format!(
+            "block size ({}) cannot be greater than the max bound (height: {:?},  width: {:?}).",
+            block_size_text(block_shape),
+            bounds.max_height,
+            bounds.max_width
+        )
+        .into());
+    }
+    if overlap >= block {
+        return Err(format!(
+            "block overlap ({:?}) must be smaller than the block size ({}).",
+            overlap,
+            block_size_text(block_shape)
+        )
+        .into());
+    }
+
+    if options.minor_threshold > options.major_threshold {
+        return Err(format!(
+            "minor threshold ({:?}) cannot be greater than the major threshold ({:?}).",
+            options.minor_threshold, options.major_threshold
+        )
+        .into());
+    }
+
+    if options.adaptive && (overlap > 0 || options.clusters) {
+        return Err(
+            "adaptive comparison cannot be combined with block overlap or clusters.".into(),
+        );
+    }
+
+    // Note: the converted (& blurred) images are only compared, the original target is still highlighted
+    let converted = options
+        .alpha_mode
+        .apply(&src)
+        .zip(options.alpha_mode.apply(&tgt));
+    let (compared_src, compared_tgt) = match &converted {
+        Some((src, tgt)) => (src, tgt),
+        None => (&src, &tgt),
+    };
+
+    let normalized = options.normalize.apply(compared_src, compared_tgt, &bounds);
+    let compared_tgt = normalized.as_ref().unwrap_or(compared_tgt);
+
+    let ignored = (!options.ignore_region.is_empty()).then(|| {
+        let mut ignored = compared_tgt.clone();
+        replace_ignored_regions(compared_src, &mut ignored, &options.ignore_region, &bounds);
+        ignored
+    });
+    let compared_tgt = ignored.as_ref().unwrap_or(compared_tgt);
+
+    if let Some(mask) = &options.mask {
+        mask.check(&bounds)?;
+    }
+    let masked = options.mask.as_ref().map(|mask| {
+        let mut masked = compared_tgt.clone();
+        mask.ignore(compared_src, &mut masked, &bounds);
+        masked
+    });
+    let compared_tgt = masked.as_ref().unwrap_or(compared_tgt);
+
+    let blurred = options.pre_blur.map(|sigma| {
+        let start = Instant::now();
+        let blurred = (
+            image::imageops::blur(compared_src, sigma),
+            image::imageops::blur(compared_tgt, sigma),
+        );
+        options.complete_stage(&mut timings, "blur", start.elapsed());
+        blurred
+    });
+    let (compared_src, compared_tgt) = match &blurred {
+        Some((src, tgt)) => (src, tgt),
+        None => (compared_src, compared_tgt),
+    };
+
+    let shifted = options.shift_tolerance.map(|shift| {
+        let start = Instant::now();
+        let mut shifted = compared_tgt.clone();
+        match_shifted_pixels(compared_src, &mut shifted, &bounds, shift);
+        options.complete_stage(&mut timings, "shift tolerance", start.elapsed());
+        shifted
+    });
+    let compared_tgt = shifted.as_ref().unwrap_or(compared_tgt);
+
+    let luma_matched = options.min_luma_delta.map(|delta| {
+        let start = Instant::now();
+        let mut matched = compared_tgt.clone();
+        match_similar_luma(compared_src, &mut matched, &bounds, delta);
+        options.complete_stage(&mut timings, "min luma delta", start.elapsed());
+        matched
+    });
+    let compared_tgt = luma_matched.as_ref().unwrap_or(compared_tgt);
+
+    let filtered = options.min_cluster_size.map(|min_size| {
+        let start = Instant::now();
+        let mut filtered = compared_tgt.clone();
+        cluster::discard_small_regions(compared_src, &mut filtered, &bounds, min_size);
+        options.complete_stage(&mut timings, "min cluster size", start.elapsed());
+        filtered
+    });
+    let compared_tgt = filtered.as_ref().unwrap_or(compared_tgt);
+
+    let comparator = if options.jpeg_aware {
+        let (src_table, tgt_table) = encoded.jpeg_tables.ok_or_else(|| {
+            String::from("jpeg aware comparison requires jpeg src and tgt images.")
+        })?;
+        Arc::new(comparator::JpegAware::new(
+            compared_src,
+            compared_tgt,
+            &src_table,
+            &tgt_table,
+        ))
+    } else if let Some(((float_src, mut float_tgt), mode)) =
+        encoded.float_pixels.zip(options.tolerance_mode)
+    {
+        replace_ignored_regions(&float_src, &mut float_tgt, &options.ignore_region, &bounds);
+        if let Some(mask) = &options.mask {
+            mask.ignore(&float_src, &mut float_tgt, &bounds);
+        }
+        Arc::new(comparator::FloatTolerance::new(
+            float_src,
+            float_tgt,
+            mode,
+            options.float_tolerance,
+        ))
+    } else {
+        comparator::get(&options.comparator, options.comparator_threshold)?
+    };
+    let sampled = options
+        .sample
+        .map(|rate| Arc::new(comparator::Sampled::new(comparator.clone(), rate as f64)));
+    let comparator: Arc<dyn comparator::Comparator> = match &sampled {
+        Some(sampled) => sampled.clone(),
+        None => comparator,
+    };
+    let comparator: Arc<dyn comparator::Comparator> = match &options.cancellation {
+        Some(token) => Arc::new(comparator::Cancellable::new(comparator, token.clone())),
+        None => comparator,
+    };
+    // Note: only the blocks of the difference are observed (not the ones compared again for the statistics)
+    let observer = options.observer();
+    let observed: Arc<dyn comparator::Comparator> = match &observer {
+        Some(observer) => Arc::new(observer::Observed::new(
+            comparator.clone(),
+            observer.clone(),
+        )),
+        None => comparator.clone(),
+    };
+
+    // Note: the indices are only compared if no option changed the compared pixels
+    let indexed = encoded
+        .indexed
+        .filter(|_| std::ptr::eq(compared_src, &src) && std::ptr::eq(compared_tgt, &tgt));
+
+    let start = Instant::now();
+    let (differing_pixels, bounds_with_diff) = if options.regions_only {
+        // Note: every pixel of a block with difference is counted (the block is not compared pixel by pixel)
+        block_difference(&bounds, block_shape, overlap, 0.0, |bounds| {
+            let diff = match block_checksum(compared_src, bounds)
+                == block_checksum(compared_tgt, bounds)
+            {
+                true => 0,
+                false => bounds.area(),
+            };
+            if let Some(observer) = &observer {
+                observer.on_block_compared(&Region::from(bounds), diff);
+            }
+            diff
+        })
+    } else if let Some((src_indices, tgt_indices)) = &indexed {
+        block_difference(
+            &bounds,
+            block_shape,
+            overlap,
+            options.block_threshold,
+            |bounds| {
+                let diff = src_indices.differing_pixels(tgt_indices, bounds);
+                if let Some(observer) = &observer {
+                    observer.on_block_compared(&Region::from(bounds), diff);
+                }
+                diff
+            },
+        )
+    } else if options.adaptive {
+        quadtree_difference(
+            compared_src,
+            compared_tgt,
+            &bounds,
+            block,
+            options.block_threshold,
+            &*observed,
+        )
+    } else {
+        difference(
+            compared_src,
+            compared_tgt,
+            &bounds,
+            block_shape,
+            overlap,
+            options.block_threshold,
+            &*observed,
+        )
+    };
+    if let Some(observer) = &observer {
+        for bound in &bounds_with_diff {
+            observer.on_region_found(&Region::from(bound));
+        }
+    }
+    options.complete_stage(&mut timings, "diff", start.elapsed());
+    cancel::check(options.cancellation.as_ref())?;
+    // Note: the differing pixels are still counted as is, only the percentage is weighted
+    let weighted_pixels = match &options.mask {
+        Some(mask) => {
+            differing_pixels as f64
+                - mask.discount(compared_src, compared_tgt, &bounds, &*comparator)
+        }
+        None => differing_pixels as f64,
+    };
+    let sample = options
+        .sample
+        .zip(sampled)
+        .map(|(rate, sampled)| (rate, sampled.margin().unwrap_or(0.0)));
+
+    #[cfg(feature = "serde")]
+    let named_regions = options
+        .layout
+        .as_ref()
+        .map(|layout| layout::attribute(layout, compared_src, compared_tgt, &bounds, &*comparator));
+    #[cfg(not(feature = "serde"))]
+    let named_regions = None;
+
+    let clusters = if options.clusters {
+        let start = Instant::now();
+        let clusters = cluster::cluster(
+            compared_src,
+            compared_tgt,
+            &bounds_with_diff,
+            block - overlap,
+            options.minor_threshold,
+            options.major_threshold,
+        );
+        options.complete_stage(&mut timings, "clusters", start.elapsed());
+        Some(clusters)
+    } else {
+        None
+    };
+
+    let block_percentages = (options.block_histogram || options.severity_colors)
+        .then(|| block_percentages(compared_src, compared_tgt, &bounds_with_diff, &*comparator));
+    let block_histogram = options
+        .block_histogram
+        .then(|| block_histogram(block_percentages.as_deref().unwrap_or_default()));
+    let block_percentages = block_percentages.filter(|_| options.severity_colors);
+    let directions = options.direction.then(|| {
+        bounds_with_diff
+            .iter()
+            .map(|bound| Direction::classify(compared_src, compared_tgt, bound))
+            .collect()
+    });
+    let pixel_changes = options
+        .classify_pixels
+        .then(|| PixelChanges::count(compared_src, compared_tgt, &bounds));
+    let salient_diff = options
+        .saliency
+        .then(|| saliency::percentage(compared_src, compared_tgt, &bounds, &*comparator));
+
+    #[cfg(feature = "ocr")]
+    let text_changes = if options.ocr {
+        let start = Instant::now();
+        let changes = ocr::text_changes(&src, &tgt, &bounds, &bounds_with_diff, block - overlap)?;
+        options.complete_stage(&mut timings, "ocr", start.elapsed());
+        changes
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "ocr"))]
+    let text_changes = Vec::new();
+
+    let metrics = if options.metric.is_empty() {
+        Vec::new()
+    } else {
+        let start = Instant::now();
+        let metrics = options
+            .metric
+            .iter()
+            .map(|&metric| match metric {
+                Metric::Mse => (metric, mean_squared_error(&src, &tgt, &bounds)),
+                Metric::Psnr => (metric, psnr(mean_squared_error(&src, &tgt, &bounds))),
+                Metric::Mae => (metric, mean_absolute_error(&src, &tgt, &bounds)),
+            })
+            .collect();
+        options.complete_stage(&mut timings, "metrics", start.elapsed());
+        metrics
+    };
+
+    let only_in_src = bounds.excluded_from(&Dimensions::from(src.dimensions()));
+    let only_in_tgt = bounds.excluded_from(&Dimensions::from(tgt.dimensions()));
+
+    let area = |bounds: &[Bounds]| bounds.iter().map(Bounds::area).sum::<u64>();
+    let default_base = if options.count_size_mismatch {
+        PercentageBase::Union
+    } else {
+        PercentageBase::Overlap
+    };
+    // Note: only the mismatching regions within the base can be counted as difference
+    let (mismatch, base) = match options.percentage_base.unwrap_or(default_base) {
+        PercentageBase::Overlap => (0, bounds.area()),
+        PercentageBase::Src => (area(&only_in_src), src_dimension.area()),
+        PercentageBase::Tgt => (area(&only_in_tgt), tgt_dimension.area()),
+        PercentageBase::Union => {
+            let mismatch = area(&only_in_src) + area(&only_in_tgt);
+            (mismatch, bounds.area() + mismatch)
+        }
+    };
+    let mismatch = if options.count_size_mismatch {
+        mismatch
+    } else {
+        0
+    };
+    let diff = ((weighted_pixels as f32) / (bounds.area() as f32)) * 100.0;
+    let diff = rebase_percentage(diff, bounds.area(), mismatch, base);
+
+    Ok(Comparison {
+        diff,
+        differing_pixels: differing_pixels + mismatch,
+        bounds_with_diff,
+        only_in_src,
+        only_in_tgt,
+        metadata_changes: None,
+        preview_scale: options.preview_scale,
+        regions_only: options.regions_only,
+        metrics,
+        sample,
+        block,
+        block_shape,
+        clusters,
+        block_histogram,
+        block_percentages,
+        directions,
+        pixel_changes,
+        salient_diff,
+        transform: transform.map(|t| (t, options.undo_transform)),
+        found_at,
+        named_regions,
+        text_changes,
+        warnings,
+        timings,
+        src,
+        tgt,
+    })
+}
+
+/// Crop the region of interest from the image, failing if it does not lie within the image.
+fn crop_roi(
+    img: &image::RgbaImage,
+    roi: &Region,
+    option: &str,
+    side: &str,
+) -> Result<image::RgbaImage, String> {
+    let (width, height) = img.dimensions();
+    let within = roi
+        .x
+        .checked_add(roi.width)
+        .is_some_and(|right| right <= width)
+        && roi
+            .y
+            .checked_add(roi.height)
+            .is_some_and(|bottom| bottom <= height);
+    if !within {
+        return Err(format!(
+            "{} ({},{},{},{}) must lie within the {} image ({}x{}).",
+            option, roi.x, roi.y, roi.width, roi.height, side, width, height
+        ));
+    }
+    Ok(image::imageops::crop_imm(img, roi.x, roi.y, roi.width, roi.height).to_image())
+}
+
+/// Write the output (see `render_output`) of the differences over the image `file` into the output file.
+///
+/// The output is written next to `file` (& named after it, if `output` is not provided, see
+/// `generate_output_file_name`), unless `output_dir` is provided. The missing directories are created.
+///
+/// Returns the output file name.
+fn write_highlight(
+    img: &image::RgbaImage,
+    output: Option<String>,
+    output_dir: Option<&Path>,
+    file: &Path,
+    quality: Option<u8>,
+    stamp: Option<&str>,
+    force: bool,
+) -> Result<PathBuf, exit::Error> {
+    let output = output_path(output, output_dir, file, stamp);
+    check_overwrite(&output, force)?;
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return Err(exit::Error::io(format!(
+                "Encountered error while creating output directory {}: {}",
+                dir.display(),
+                e
+            )));
+        }
+    }
+    save_image(img, &output, quality)?;
+    Ok(output)
+}
+
+/// Path of the output named after the input `file` (within `output_dir`, if provided, and with the timestamp
+/// `stamp` appended to the name, if provided).
+fn output_path(
+    output: Option<String>,
+    output_dir: Option<&Path>,
+    file: &Path,
+    stamp: Option<&str>,
+) -> PathBuf {
+    let output = generate_output_file_name(output, &input::local_path(file)).unwrap();
+    let output = match output_dir {
+        Some(dir) => dir.join(output.file_name().unwrap()),
+        None => output,
+    };
+    stamped(&output, stamp)
+}
+
+/// Path of the Deep Zoom descriptor of the tile output (see `tiles::write`), named as the output file (see
+/// `output_path`) within the tile directory.
+fn tile_descriptor(
+    output: Option<String>,
+    dir: &Path,
+    file: &Path,
+    stamp: Option<&str>,
+) -> PathBuf {
+    output_path(output, Some(dir), file, stamp).with_extension("dzi")
+}
+
+/// Append the timestamp (if any) to the file name, before the extension.
+fn stamped(path: &Path, stamp: Option<&str>) -> PathBuf {
+    let (Some(stamp), Some(stem)) = (stamp, path.file_stem()) else {
+        return path.to_path_buf();
+    };
+    let mut name = stem.to_os_string();
+    name.push(format!("_{}", stamp));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// Current time (UTC) as `YYYYMMDDTHHMMSS`, for the names of the outputs.
+fn timestamp() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_timestamp(seconds)
+}
+
+/// Format the seconds since the unix epoch as `YYYYMMDDTHHMMSS` (UTC).
+fn format_timestamp(seconds: u64) -> String {
+    // Note: the civil date of the days since the epoch (Howard Hinnant's algorithm)
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    let time = seconds % 86400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Fail if the output file exists, unless forced to overwrite it.
+pub(crate) fn check_overwrite(output: &Path, force: bool) -> Result<(), exit::Error> {
+    if !force && output.exists() {
+        return Err(format!(
+            "Output file {} already exists (use --force to overwrite it, or --timestamp-output).",
+            output.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Name of the source output when an output is written per image (to not overwrite the target output).
+fn src_output_name(output: &Option<String>, src: &Path) -> Option<String> {
+    match output {
+        Some(output) => {
+            let output = Path::new(output);
+            let stem = output.file_stem()?.to_str()?;
+            match output.extension().and_then(|e| e.to_str()) {
+                Some(ext) => Some(format!("{}_src.{}", stem, ext)),
+                None => Some(format!("{}_src", stem)),
+            }
+        }
+        None => Some(format!(
+            "{}_src_diff",
+            input::local_path(src).file_stem()?.to_str()?
+        )),
+    }
+}
+
+/// Render the output image over the target (or the source, if `on_src`): with the blocks with difference
+/// outlined, blended over the other image (with the opacity `blend_alpha`) or with only the blocks with
+/// difference in full color (the outline & the tint are of the palette).
+///
+/// The outline is drawn onto the image of the comparison itself (restored once the output is dropped), instead
+/// of a copy, so that highlighting a large image does not double the memory used.
+fn render_output(
+    comparison: &mut Comparison,
+    on_src: bool,
+    format: OutputFormat,
+    blend_alpha: f32,
+    palette: Palette,
+) -> Result<Rendered<'_>, exit::Error> {
+    let (base, other) = match on_src {
+        true => (&mut comparison.src, &comparison.tgt),
+        false => (&mut comparison.tgt, &comparison.src),
+    };
+    match format {
+        OutputFormat::Blend => Ok(Rendered::Owned(blend(other, base, blend_alpha, palette))),
+        OutputFormat::Cutout => Ok(Rendered::Owned(cutout(base, &comparison.bounds_with_diff))),
+        // Note: the blocks colored by severity replace the outline of the palette
+        OutputFormat::Highlight => match &comparison.block_percentages {
+            Some(percentages) => Ok(Rendered::Owned(severity::highlight(
+                base,
+                &comparison.bounds_with_diff,
+                percentages,
+            ))),
+            None => {
+                let overwritten = highlight(base, &comparison.bounds_with_diff, palette);
+                Ok(Rendered::Drawn(base, overwritten))
+            }
+        },
+    }
+}
+
+/// Represents a rendered output: a new image, or the outline drawn onto an image of the comparison (with the
+/// overwritten pixels, restored when dropped).
+enum Rendered<'a> {
+    Owned(image::RgbaImage),
+    Drawn(&'a mut image::RgbaImage, Vec<(u32, u32, image::Rgba<u8>)>),
+}
+
+impl std::ops::Deref for Rendered<'_> {
+    type Target = image::RgbaImage;
+
+    fn deref(&self) -> &image::RgbaImage {
+        match self {
+            Rendered::Owned(img) => img,
+            Rendered::Drawn(img, _) => img,
+        }
+    }
+}
+
+impl Drop for Rendered<'_> {
+    fn drop(&mut self) {
+        if let Rendered::Drawn(img, overwritten) = self {
+            // Note: in the reverse order, as the corners of overlapping bounds are overwritten more than once
+            for &(x, y, pixel) in overwritten.iter().rev() {
+                img.put_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+/// Write the image into the output file, in the format matching its extension.
+///
+/// `quality` (1-100) is only used for the lossy formats (jpeg, webp & avif).
+pub(crate) fn save_image(
+    img: &image::RgbaImage,
+    output: &Path,
+    quality: Option<u8>,
+) -> Result<(), exit::Error> {
+    let format = match image::ImageFormat::from_path(output) {
+        Ok(f) => f,
+        Err(_) => {
+            return Err(format!("Unsupported output format for {}.", output.display()).into())
+        }
+    };
+
+    #[cfg(not(feature = "webp"))]
+    if format == image::ImageFormat::WebP {
+        return Err("Writing webp output requires the 'webp' feature.".into());
+    }
+    #[cfg(not(feature = "avif"))]
+    if format == image::ImageFormat::Avif {
+        return Err("Writing avif output requires the 'avif' feature.".into());
+    }
+
+    let file = match std::fs::File::create(output) {
+        Ok(f) => std::io::BufWriter::new(f),
+        Err(e) => {
+            return Err(exit::Error::io(format!(
+                "Encountered error while creating output file {}: {}",
+                output.display(),
+                e
+            )))
+        }
+    };
+
+    let result = match format {
+        image::ImageFormat::Jpeg => {
+            // Note: jpeg does not support the alpha channel (converted without an intermediate RGBA copy)
+            let rgb: image::RgbImage = image::buffer::ConvertBuffer::convert(img);
+            let mut encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(file, quality.unwrap_or(75));
+            encoder.encode_image(&rgb)
+        }
+        #[cfg(feature = "webp")]
+        image::ImageFormat::WebP => {
+            let webp_quality = match quality {
+                Some(q) => image::codecs::webp::WebPQuality::lossy(q),
+                None => image::codecs::webp::WebPQuality::lossless(),
+            };
+            image::codecs::webp::WebPEncoder::new_with_quality(file, webp_quality).encode(
+                img,
+                img.width(),
+                img.height(),
+                image::ColorType::Rgba8,
+            )
+        }
+        #[cfg(feature = "avif")]
+        image::ImageFormat::Avif => {
+            use image::ImageEncoder;
+            let encoder = match quality {
+                Some(q) => image::codecs::avif::AvifEncoder::new_with_speed_quality(file, 4, q),
+                None => image::codecs::avif::AvifEncoder::new(file),
+            };
+            encoder.write_image(img, img.width(), img.height(), image::ColorType::Rgba8)
+        }
+        _ => {
+            let mut file = file;
+            img.write_to(&mut file, format)
+        }
+    };
+
+    result.map_err(|e| {
+        exit::Error::io(format!(
+            "Encountered error while writing output file {}: {}",
+            output.display(),
+            e
+        ))
+    })
+}
+
+/// Pair the files in the source and target directories (or archives), or matching the glob patterns of the file
+/// names (for ex. `shots/*.png`), by name, content hash, capture time or order.
+///
+/// Returns the (file name, source path, target path) for every pair, sorted by (source) file name, where the
+/// name is `SOURCE_NAME -> TARGET_NAME` if the names are different, and the names of the (filtered) files left
+/// unpaired in the source & target.
+fn pair_files(
+    src: &Path,
+    tgt: &Path,
+    pair_by: PairBy,
+    filter: &FileFilter,
+    options: &CompareOptions,
+) -> Result<(Vec<FilePair>, Unpaired), exit::Error> {
+    let is_container = |path: &Path| {
+        path.is_dir()
+            || archive::is_archive(path)
+            || glob_pattern(path).is_some_and(|(dir, _)| dir.is_dir())
+    };
+    if !is_container(src) || !is_container(tgt) {
+        return Err(exit::Error::io(
+            "Invalid values for src/tgt directory. Please check and try again.",
+        ));
+    }
+
+    let (mut src_files, mut tgt_files) = (list_files(src)?, list_files(tgt)?);
+    src_files.retain(|(name, _)| filter.allows(name));
+    tgt_files.retain(|(name, _)| filter.allows(name));
+    src_files.sort();
+    tgt_files.sort();
+    let names = |files: &[NamedFile]| files.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+    let (mut only_in_src, mut only_in_tgt) = (names(&src_files), names(&tgt_files));
+
+    let content = |path: &Path| -> Result<Vec<u8>, exit::Error> {
+        let file = page::split(path).map_or(path, |(file, _)| file);
+        Ok(Input::load(file, options)?.bytes().to_vec())
+    };
+    let pairs = match pair_by {
+        PairBy::Name => pair_by_key(src_files, tgt_files, |name, _| Ok(Some(name.to_string())))?,
+        PairBy::Hash => pair_by_key(src_files, tgt_files, |_, path| {
+            Ok(Some(cache::Key::new(&[&content(path)?])))
+        })?,
+        PairBy::ExifTime => pair_by_key(src_files, tgt_files, |_, path| {
+            Ok(metadata::capture_time(&content(path)?))
+        })?,
+        PairBy::Order => src_files.into_iter().zip(tgt_files).collect(),
+        PairBy::Similarity => pair_by_similarity(src_files, tgt_files, options)?,
+    };
+    only_in_src.retain(|name| !pairs.iter().any(|((n, _), _)| n == name));
+    only_in_tgt.retain(|name| !pairs.iter().any(|(_, (n, _))| n == name));
+
+    let pairs = pairs
+        .into_iter()
+        .map(|((src_name, src_file), (tgt_name, tgt_file))| {
+            let name = match src_name == tgt_name {
+                true => src_name,
+                false => format!("{} -> {}", src_name, tgt_name),
+            };
+            (name, src_file, tgt_file)
+        })
+        .collect();
+    Ok((pairs, (only_in_src, only_in_tgt)))
+}
+
+/// Represents the (name, source path, target path) of a paired file.
+type FilePair = (String, PathBuf, PathBuf);
+
+/// Represents the (name, path) of a listed file.
+type NamedFile = (String, PathBuf);
+
+/// Represents the names of the files only in the source & only in the target (unpaired).
+type Unpaired = (Vec<String>, Vec<String>);
+
+/// Pair the (name, path) of the source & target files with the same key (each target file at most once, the
+/// first by name), skipping the files without a key.
+fn pair_by_key<K: Eq + std::hash::Hash>(
+    src_files: Vec<NamedFile>,
+    tgt_files: Vec<NamedFile>,
+    key: impl Fn(&str, &Path) -> Result<Option<K>, exit::Error>,
+) -> Result<Vec<(NamedFile, NamedFile)>, exit::Error> {
+    let mut tgt_by_key = std::collections::HashMap::new();
+    for (name, path) in tgt_files {
+        if let Some(k) = key(&name, &path)? {
+            tgt_by_key.entry(k).or_insert((name, path));
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (name, path) in src_files {
+        if let Some(tgt) = key(&name, &path)?.and_then(|k| tgt_by_key.remove(&k)) {
+            pairs.push(((name, path), tgt));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Pair the (name, path) of the source & target files with the closest perceptual hashes (see
+/// `similarity::pair`), skipping the files which cannot be decoded.
+///
+/// Note: the ambiguous pairs are printed (as warnings), the files without a close enough candidate are left unpaired
+fn pair_by_similarity(
+    src_files: Vec<NamedFile>,
+    tgt_files: Vec<NamedFile>,
+    options: &CompareOptions,
+) -> Result<Vec<(NamedFile, NamedFile)>, exit::Error> {
+    let hashed = |files: Vec<NamedFile>, format| -> Result<Vec<(NamedFile, u64)>, exit::Error> {
+        let mut hashed = Vec::new();
+        for (name, path) in files {
+            if let Ok(img) = Input::load(&path, options)?.decode(None, None, format) {
+                let hash = similarity::hash(&img);
+                hashed.push(((name, path), hash));
+            }
+        }
+        Ok(hashed)
+    };
+    let matches = similarity::pair(
+        hashed(src_files, options.format_src)?,
+        hashed(tgt_files, options.format_tgt)?,
+    );
+    for m in &matches {
+        if let Some((other, distance)) = &m.ambiguous_with {
+            println!(
+                "{}",
+                format!(
+                    "Ambiguous pair {} -> {} (hash distance {}, {} at {}).",
+                    m.src.0, m.tgt.0, m.distance, other, distance
+                )
+                .yellow()
+            );
+        }
+    }
+    Ok(matches.into_iter().map(|m| (m.src, m.tgt)).collect())
+}
+
+/// Split the path into the directory & the glob pattern of the file name (if it is one).
+fn glob_pattern(path: &Path) -> Option<(&Path, &str)> {
+    let pattern = path.file_name()?.to_str()?;
+    if !glob::is_pattern(pattern) || path.exists() {
+        return None;
+    }
+    match path.parent() {
+        Some(dir) if dir != Path::new("") => Some((dir, pattern)),
+        _ => Some((Path::new("."), pattern)),
+    }
+}
+
+/// List the files in the directory (or archive), or the files matching the glob pattern.
+///
+/// Returns the (file name, path) for every file, where the path of a file within an archive is `ARCHIVE::FILE_NAME`.
+fn list_files(path: &Path) -> Result<Vec<(String, PathBuf)>, exit::Error> {
+    if let Some((dir, pattern)) = glob_pattern(path) {
+        let mut files = list_files(dir)?;
+        files.retain(|(name, _)| glob::matches(pattern, name));
+        return Ok(files);
+    }
+    if archive::is_archive(path) {
+        return Ok(archive::list(path)?
+            .into_iter()
+            .map(|name| {
+                let file =
+                    PathBuf::from(format!("{}{}{}", path.display(), archive::SEPARATOR, name));
+                (name, file)
+            })
+            .collect());
+    }
+
+    let entries = match std::fs::read_dir(path) {
+        Ok(e) => e,
+        Err(_) => {
+            return Err(exit::Error::io(format!(
+                "Encountered error while reading directory {}.",
+                path.display()
+            )))
+        }
+    };
+
+    Ok(entries
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            )
+        })
+        .collect())
+}
+
+/// Internal functions exposed for the benchmarks (`benches/`), not a stable API.
+#[doc(hidden)]
+pub mod bench {
+    /// Decode the encoded image into RGBA.
+    pub fn decode(data: &[u8]) -> image::RgbaImage {
+        image::load_from_memory(data).unwrap().to_rgba8()
+    }
+
+    /// Calculate the percentage difference (and the blocks with difference) over the common bounds of the images.
+    ///
+    /// Returns the percentage difference and the number of blocks with difference.
+    pub fn block_difference(
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        block: u32,
+    ) -> (f32, usize) {
+        let bounds = super::Bounds::get_max_bounds(
+            super::Dimensions::from(src.dimensions()),
+            super::Dimensions::from(tgt.dimensions()),
+        )
+        .unwrap();
+        let (diff, bounds_with_diff) = super::percentage_difference(
+            src,
+            tgt,
+            &bounds,
+            block,
+            0,
+            0.0,
+            &super::comparator::Exact,
+        );
+        (diff, bounds_with_diff.len())
+    }
+
+    /// Highlight every block of the image (the worst case, when every block has difference).
+    pub fn highlight_all(img: &mut image::RgbaImage, block: u32) {
+        let (width, height) = img.dimensions();
+        let bounds: Vec<_> = (0..height)
+            .step_by(block as usize)
+            .flat_map(|y| {
+                (0..width).step_by(block as usize).map(move |x| {
+                    super::Bounds::new(
+                        x,
+                        std::cmp::min(x + block, width),
+                        y,
+                        std::cmp::min(y + block, height),
+                    )
+                })
+            })
+            .collect();
+        super::highlight(img, &bounds, super::Palette::Default);
+    }
+}
+
+/// Treat the target pixels (within the bounds) as matching, if an identical source pixel exists within `shift` pixels
+/// (in both the directions), by copying the source pixel onto the target.
+///
+/// Returns the number of matched pixels.
+fn match_shifted_pixels(
+    src: &image::RgbaImage,
+    tgt: &mut image::RgbaImage,
+    bounds: &Bounds,
+    shift: u32,
+) -> usize {
+    let mut matched = 0;
+    for y in bounds.min_height..bounds.max_height {
+        for x in bounds.min_width..bounds.max_width {
+            let pixel = *tgt.get_pixel(x, y);
+            if *src.get_pixel(x, y) == pixel {
+                continue;
+            }
+
+            let (min_x, max_x) = (
+                x.saturating_sub(shift).max(bounds.min_width),
+                (x + shift).min(bounds.max_width - 1),
+            );
+            let (min_y, max_y) = (
+                y.saturating_sub(shift).max(bounds.min_height),
+                (y + shift).min(bounds.max_height - 1),
+            );
+            let found =
+                (min_y..=max_y).any(|sy| (min_x..=max_x).any(|sx| *src.get_pixel(sx, sy) == pixel));
+            if found {
+                tgt.put_pixel(x, y, *src.get_pixel(x, y));
+                matched += 1;
+            }
+        }
+    }
+    matched
+}
+
+/// Match the target pixels (within the bounds) with a luminance difference below `min_delta` from the source
+/// pixel (of the same alpha), by copying the source pixel onto the target.
+///
+/// Returns the number of matched pixels.
+fn match_similar_luma(
+    src: &image::RgbaImage,
+    tgt: &mut image::RgbaImage,
+    bounds: &Bounds,
+    min_delta: u8,
+) -> usize {
+    let luma =
+        |p: &image::Rgba<u8>| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+    let mut matched = 0;
+    for y in bounds.min_height..bounds.max_height {
+        for x in bounds.min_width..bounds.max_width {
+            let (src_pixel, tgt_pixel) = (src.get_pixel(x, y), tgt.get_pixel(x, y));
+            // Note: a change of the transparency is not a change of the color, so it is never ignored
+            if src_pixel == tgt_pixel || src_pixel[3] != tgt_pixel[3] {
+                continue;
+            }
+            if (luma(src_pixel) - luma(tgt_pixel)).abs() < min_delta as f64 {
+                tgt.put_pixel(x, y, *src_pixel);
+                matched += 1;
+            }
+        }
+    }
+    matched
+}
+
+/// Downscale the image by the factor (to at least 1x1 pixels), averaging the pixels.
+fn downscale(img: &image::RgbaImage, scale: f32) -> image::RgbaImage {
+    let size = |length: u32| ((length as f32 * scale).round() as u32).max(1);
+    image::imageops::thumbnail(img, size(img.width()), size(img.height()))
+}
+
+/// Composite the target over the source with the opacity `alpha` (onion skin), tinting the pixels with difference
+/// (with the tint of the palette).
+///
+/// The output has the dimensions of the target (the regions only present in the target are kept as is).
+fn blend(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    alpha: f32,
+    palette: Palette,
+) -> image::RgbaImage {
+    let tint = palette.tint();
+    let mix = |a: &image::Rgba<u8>, b: &image::Rgba<u8>, t: f32| {
+        image::Rgba(std::array::from_fn(|i| {
+            (a[i] as f32 * (1.0 - t) + b[i] as f32 * t).round() as u8
+        }))
+    };
+
+    image::RgbaImage::from_fn(tgt.width(), tgt.height(), |x, y| {
+        let t = tgt.get_pixel(x, y);
+        if x >= src.width() || y >= src.height() {
+            return *t;
+        }
+        let s = src.get_pixel(x, y);
+        let blended = mix(s, t, alpha);
+        if s == t {
+            blended
+        } else {
+            mix(&blended, &tint, 0.5)
+        }
+    })
+}
+
+/// Desaturate & darken (to half the luminance) the target, except the blocks with difference.
+fn cutout(tgt: &image::RgbaImage, bounds: &[Bounds]) -> image::RgbaImage {
+    let mut output = image::RgbaImage::from_fn(tgt.width(), tgt.height(), |x, y| {
+        let p = tgt.get_pixel(x, y);
+        let luma = (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 2000;
+        image::Rgba([luma as u8, luma as u8, luma as u8, p[3]])
+    });
+    for bound in bounds {
+        for y in bound.min_height..bound.max_height {
+            for x in bound.min_width..bound.max_width {
+                output.put_pixel(x, y, *tgt.get_pixel(x, y));
+            }
+        }
+    }
+    output
+}
+
+/// Compare the pixel difference for every pixel for the specified bounds between the images and calculate the percentage difference.
+///
+/// Blocks are `block` pixels wide and consecutive blocks overlap by `overlap` pixels.
+/// Every pixel is counted only once towards the total, even when it falls within several blocks.
+///
+/// Every block is compared with the `comparator`, and is returned as a block with difference only if more than
+/// `block_threshold` percent of its pixels differ (every differing pixel is still counted towards the total).
+///
+/// Returns the percentage difference and Vec\<Bounds\> where the difference was observed (sorted, top to bottom, left to right).
+///
+/// Logic: `(mismatching pixels / total pixels ) * 100`
+fn percentage_difference(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    block: u32,
+    overlap: u32,
+    block_threshold: f32,
+    comparator: &dyn comparator::Comparator,
+) -> (f32, Vec<Bounds>) {
+    let (total_diff, bounds_with_difference) = difference(
+        src,
+        tgt,
+        bounds,
+        (block, block),
+        overlap,
+        block_threshold,
+        comparator,
+    );
+    let diff_percentage =
+        ((total_diff as f32) / ((bounds.max_height * bounds.max_width) as f32)) * 100.0;
+    (diff_percentage, bounds_with_difference)
+}
+
+/// Compare the blocks (of the width & height) for the specified bounds between the images (see
+/// `percentage_difference`).
+///
+/// Returns the number of mismatching pixels and the bounds where the difference was observed (sorted).
+fn difference(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    block: (u32, u32),
+    overlap: u32,
+    block_threshold: f32,
+    comparator: &dyn comparator::Comparator,
+) -> (u64, Vec<Bounds>) {
+    block_difference(bounds, block, overlap, block_threshold, |bounds| {
+        comparator
+            .compare_block(src, tgt, &Region::from(bounds))
+            .differing_pixels
+    })
+}
+
+/// Count the differing pixels of the blocks (see `difference`) for the specified bounds with `compare_block`.
+fn block_difference(
+    bounds: &Bounds,
+    (block_width, block_height): (u32, u32),
+    overlap: u32,
+    block_threshold: f32,
+    compare_block: impl Fn(&Bounds) -> u64,
+) -> (u64, Vec<Bounds>) {
+    let mut total_diff = 0;
+    let mut bounds_with_difference = Vec::new();
+
+    let height_starts = block_starts(
+        bounds.min_height,
+        bounds.max_height,
+        block_height,
+        block_height - overlap,
+    );
+    let width_starts = block_starts(
+        bounds.min_width,
+        bounds.max_width,
+        block_width,
+        block_width - overlap,
+    );
+
+    for (i, &start_height) in height_starts.iter().enumerate() {
+        for (j, &start_width) in width_starts.iter().enumerate() {
+            // Note: max width & height should not exceed the overall bounds
+            let max_width = std::cmp::min(start_width + block_width, bounds.max_width);
+            let max_height = std::cmp::min(start_height + block_height, bounds.max_height);
+
+            // Note: the part of the block which is not shared with the next block (to avoid counting a pixel twice)
+            let owned_width = width_starts.get(j + 1).copied().unwrap_or(max_width);
+            let owned_height = height_starts.get(i + 1).copied().unwrap_or(max_height);
+
+            let current_bound = Bounds::new(start_width, max_width, start_height, max_height);
+            let owned_bound = Bounds::new(start_width, owned_width, start_height, owned_height);
+            let diff = compare_block(&current_bound);
+            if diff != 0 {
+                total_diff += if owned_bound == current_bound {
+                    diff
+                } else {
+                    compare_block(&owned_bound)
+                };
+                if diff as f64 * 100.0 > block_threshold as f64 * current_bound.area() as f64 {
+                    bounds_with_difference.push(current_bound);
+                }
+            }
+        }
+    }
+    (total_diff, bounds_with_difference)
+}
+
+/// Compare the specified bounds between the images as a quadtree: a region with difference is split into
+/// (up to) 4 quadrants, until it is no larger than `block` pixels (in both dimensions), while a region without
+/// difference is skipped at once.
+///
+/// Every leaf (region no larger than `block`) is returned as a block with difference only if more than
+/// `block_threshold` percent of its pixels differ.
+///
+/// Returns the number of mismatching pixels and the bounds where the difference was observed (sorted).
+fn quadtree_difference(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    block: u32,
+    block_threshold: f32,
+    comparator: &dyn comparator::Comparator,
+) -> (u64, Vec<Bounds>) {
+    let mut total_diff = 0;
+    let mut bounds_with_difference = Vec::new();
+    let mut regions = vec![Bounds::new(
+        bounds.min_width,
+        bounds.max_width,
+        bounds.min_height,
+        bounds.max_height,
+    )];
+    while let Some(region) = regions.pop() {
+        let diff = comparator
+            .compare_block(src, tgt, &Region::from(&region))
+            .differing_pixels;
+        if diff == 0 {
+            continue;
+        }
+        let (width, height) = (
+            region.max_width - region.min_width,
+            region.max_height - region.min_height,
+        );
+        if width <= block && height <= block {
+            total_diff += diff;
+            if diff as f64 * 100.0 > block_threshold as f64 * region.area() as f64 {
+                bounds_with_difference.push(region);
+            }
+            continue;
+        }
+        // Note: only the dimensions larger than the block are split
+        let mid_width = region.min_width + if width > block { width / 2 } else { width };
+        let mid_height = region.min_height + if height > block { height / 2 } else { height };
+        for (min_width, max_width) in [(region.min_width, mid_width), (mid_width, region.max_width)]
+        {
+            for (min_height, max_height) in [
+                (region.min_height, mid_height),
+                (mid_height, region.max_height),
+            ] {
+                if min_width < max_width && min_height < max_height {
+                    regions.push(Bounds::new(min_width, max_width, min_height, max_height));
+                }
+            }
+        }
+    }
+    bounds_with_difference.sort();
+    (total_diff, bounds_with_difference)
+}
+
+/// Percentage of differing pixels of every block with difference.
+fn block_percentages(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds_with_diff: &[Bounds],
+    comparator: &dyn comparator::Comparator,
+) -> Vec<f64> {
+    bounds_with_diff
+        .iter()
+        .map(|bound| {
+            let diff = comparator
+                .compare_block(src, tgt, &Region::from(bound))
+                .differing_pixels;
+            diff as f64 * 100.0 / bound.area() as f64
+        })
+        .collect()
+}
+
+/// Count the blocks with difference per 10% bucket of their percentage of differing pixels (the first bucket is
+/// above 0% up to 10%, the last above 90% up to 100%).
+fn block_histogram(percentages: &[f64]) -> [usize; 10] {
+    let mut histogram = [0; 10];
+    for percent in percentages {
+        let bucket = (percent / 10.0).ceil() as usize;
+        histogram[bucket.clamp(1, 10) - 1] += 1;
+    }
+    histogram
+}
+
+/// Mean squared error of the RGB channels of the pixels within the bounds.
+fn mean_squared_error(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Bounds) -> f64 {
+    let mut total = 0u64;
+    for y in bounds.min_height..bounds.max_height {
+        for (s, t) in row_slice(src, y, bounds)
+            .chunks_exact(4)
+            .zip(row_slice(tgt, y, bounds).chunks_exact(4))
+        {
+            for c in 0..3 {
+                let d = s[c] as i64 - t[c] as i64;
+                total += (d * d) as u64;
+            }
+        }
+    }
+    total as f64 / (bounds.area() * 3) as f64
+}
+
+/// Mean absolute error of the RGB channels of the images within the bounds (0 to 255).
+fn mean_absolute_error(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Bounds) -> f64 {
+    let mut total = 0u64;
+    for y in bounds.min_height..bounds.max_height {
+        for (s, t) in row_slice(src, y, bounds)
+            .chunks_exact(4)
+            .zip(row_slice(tgt, y, bounds).chunks_exact(4))
+        {
+            for c in 0..3 {
+                total += s[c].abs_diff(t[c]) as u64;
+            }
+        }
+    }
+    total as f64 / (bounds.area() * 3) as f64
+}
+
+/// Peak signal-to-noise ratio (dB) of the mean squared error of 8-bit channels (infinite, if no error).
+fn psnr(mse: f64) -> f64 {
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Recalculate the percentage difference of the common bounds (with `area` pixels) relative to `base` pixels,
+/// counting the `mismatch` pixels only present in one of the images as difference.
+///
+/// Logic: `((mismatching pixels + mismatch) / base) * 100`
+fn rebase_percentage(diff: f32, area: u64, mismatch: u64, base: u64) -> f32 {
+    ((diff as f64 * area as f64 + 100.0 * mismatch as f64) / base as f64) as f32
+}
+
+/// Calculate the start positions of the blocks between min and max, moving by step.
+///
+/// Stops once a block reaches max, so that no block is completely covered by the previous one.
+fn block_starts(min: u32, max: u32, block: u32, step: u32) -> Vec<u32> {
+    let mut starts = Vec::new();
+    let mut start = min;
+    while start < max {
+        starts.push(start);
+        if start + block >= max {
+            break;
+        }
+        start += step;
+    }
+    starts
+}
+
+/// Compare the pixel difference for the specified bounds between the images.
+///
+/// Note: compares the rows of the raw buffers as slices (instead of `get_pixel` for every pixel), as the images can have different widths.
+fn pixel_difference(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Bounds) -> u32 {
+    let mut diff = 0;
+
+    for y in bounds.min_height..bounds.max_height {
+        let src_row = row_slice(src, y, bounds);
+        let tgt_row = row_slice(tgt, y, bounds);
+        diff += src_row
+            .chunks_exact(4)
+            .zip(tgt_row.chunks_exact(4))
+            .filter(|(s, t)| s != t)
+            .count() as u32;
+    }
+
+    diff
+}
+
+/// Checksum of the raw (RGBA) subpixels of the image within the bounds (cheaper than comparing them pixel by pixel,
+/// as 8 subpixels are hashed at once).
+fn block_checksum(img: &image::RgbaImage, bounds: &Bounds) -> u64 {
+    let mut checksum = 0_u64;
+    for y in bounds.min_height..bounds.max_height {
+        let row = row_slice(img, y, bounds);
+        let mut words = row.chunks_exact(8);
+        for word in &mut words {
+            let word = u64::from_le_bytes(word.try_into().unwrap());
+            checksum = (checksum.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+        }
+        for &byte in words.remainder() {
+            checksum =
+                (checksum.rotate_left(5) ^ byte as u64).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+        }
+    }
+    checksum
+}
+
+/// Get the raw (RGBA) subpixels of row `y` of the image between the min & max width of the bounds.
+fn row_slice<'a>(img: &'a image::RgbaImage, y: u32, bounds: &Bounds) -> &'a [u8] {
+    let row_start = y as usize * img.width() as usize * 4;
+    &img.as_raw()
+        [row_start + bounds.min_width as usize * 4..row_start + bounds.max_width as usize * 4]
+}
+
+/// Highlight the specified bounds in the image (with the outline of the palette).
+///
+/// Returns the overwritten pixels (x, y & the original color), in the order they were drawn.
+fn highlight(
+    img: &mut image::RgbaImage,
+    bounds: &[Bounds],
+    palette: Palette,
+) -> Vec<(u32, u32, image::Rgba<u8>)> {
+    let mut overwritten = Vec::new();
+    let mut draw = |x, y, color| {
+        overwritten.push((x, y, *img.get_pixel(x, y)));
+        img.put_pixel(x, y, color);
+    };
+    for bound in bounds {
+        for x in bound.min_width..bound.max_width {
+            draw(x, bound.min_height, palette.outline(x));
+            draw(x, bound.max_height - 1, palette.outline(x));
+        }
+
+        for y in bound.min_height..bound.max_height {
+            draw(bound.min_width, y, palette.outline(y));
+            draw(bound.max_width - 1, y, palette.outline(y));
+        }
+    }
+    overwritten
+}
+
+/// Generate output file name with extension if one is provided else use the backup file.
+///
+/// A provided file name is placed next to the backup file, unless it has directory components (for ex.
+/// `artifacts/run42/login_diff`), kept as is then.
+///
+/// The extension of the provided file name is kept if it is a known image format, else the extension of the backup file is used
+/// (or `png`, if the backup file is not an image, for ex. a raw frame).
+fn generate_output_file_name(output: Option<String>, backup_file: &Path) -> Option<PathBuf> {
+    let file_name = match output {
+        Some(f) => f,
+        None => format!("{}_diff", backup_file.file_stem()?.to_str()?.to_owned()),
+    };
+
+    let mut output = match Path::new(&file_name).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => PathBuf::from(file_name),
+        _ => backup_file.with_file_name(file_name),
+    };
+    if image::ImageFormat::from_path(&output).is_ok() {
+        return Some(output);
+    }
+    match backup_file.extension() {
+        Some(ext) if image::ImageFormat::from_extension(ext).is_some() => {
+            output.set_extension(ext);
+        }
+        _ => {
+            output.set_extension("png");
+        }
+    }
+
+    Some(output)
+}
+
+/// Represents the Dimension (width, height).
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Dimensions(u32, u32);
+
+impl Dimensions {
+    /// Create Dimensions from a tuple.
+    fn from(d: (u32, u32)) -> Dimensions {
+        Dimensions(d.0, d.1)
+    }
+
+    /// Checks if the Dimensions are same.
+    fn same(d1: &Dimensions, d2: &Dimensions) -> bool {
+        matches!(d1.cmp(d2), std::cmp::Ordering::Equal)
+    }
+
+    /// Number of pixels.
+    fn area(&self) -> u64 {
+        self.0 as u64 * self.1 as u64
+    }
+}
+
+/// Represents the Bound consisting of min/max width and min/max height.
+#[derive(Debug, Eq, PartialEq)]
+struct Bounds {
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+impl std::fmt::Display for Bounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "width: {}..{}, height: {}..{}",
+            self.min_width, self.max_width, self.min_height, self.max_height
+        )
+    }
+}
+
+/// Note: bounds are ordered top to bottom, then left to right (i.e. row-major by their top-left corner),
+/// which is the order every list of bounds is reported in.
+impl Ord for Bounds {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (
+            self.min_height,
+            self.min_width,
+            self.max_height,
+            self.max_width,
+        )
+            .cmp(&(
+                other.min_height,
+                other.min_width,
+                other.max_height,
+                other.max_width,
+            ))
+    }
+}
+
+impl PartialOrd for Bounds {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Bounds {
+    /// Creates a new Bounds.
+    fn new(min_width: u32, max_width: u32, min_height: u32, max_height: u32) -> Bounds {
+        Bounds {
+            min_width,
+            max_width,
+            min_height,
+            max_height,
+        }
+    }
+    /// Get the max bounds from the provided Dimensions (width & height).
+    fn get_max_bounds(src: Dimensions, tgt: Dimensions) -> Result<Bounds, String> {
+        let Dimensions(w1, h1) = src;
+        let Dimensions(w2, h2) = tgt;
+
+        let max_width = std::cmp::min(w1, w2);
+        let max_height = std::cmp::min(h1, h2);
+
+        if max_width == 0 || max_height == 0 {
+            return Err(String::from("Maximum width / height cannot be ZERO (0)."));
+        }
+
+        Ok(Bounds {
+            min_width: 0,
+            max_width,
+            min_height: 0,
+            max_height,
+        })
+    }
+
+    /// Number of pixels within the bounds.
+    fn area(&self) -> u64 {
+        (self.max_width - self.min_width) as u64 * (self.max_height - self.min_height) as u64
+    }
+
+    /// Get the bounds of the image with the provided Dimensions (width & height) lying outside of these bounds.
+    ///
+    /// The part to the right of the bounds is returned first, followed by the part below the bounds.
+    fn excluded_from(&self, dimension: &Dimensions) -> Vec<Bounds> {
+        let Dimensions(width, height) = *dimension;
+        let mut excluded = Vec::new();
+
+        if width > self.max_width {
+            excluded.push(Bounds::new(self.max_width, width, 0, height));
+        }
+        if height > self.max_height {
+            excluded.push(Bounds::new(
+                0,
+                std::cmp::min(width, self.max_width),
+                self.max_height,
+                height,
+            ));
+        }
+
+        excluded
+    }
+
+    /// Get the smallest bounds enclosing all the bounds (if any).
+    fn enclosing(bounds: &[Bounds]) -> Option<Bounds> {
+        let first = bounds.first()?;
+        Some(bounds[1..].iter().fold(
+            Bounds::new(
+                first.min_width,
+                first.max_width,
+                first.min_height,
+                first.max_height,
+            ),
+            |a, b| {
+                Bounds::new(
+                    a.min_width.min(b.min_width),
+                    a.max_width.max(b.max_width),
+                    a.min_height.min(b.min_height),
+                    a.max_height.max(b.max_height),
+                )
+            },
+        ))
+    }
+
+    /// Get the part of these bounds within the other bounds (empty, if they do not overlap).
+    fn intersection(&self, other: &Bounds) -> Bounds {
+        let (min_width, min_height) = (
+            self.min_width.max(other.min_width),
+            self.min_height.max(other.min_height),
+        );
+        Bounds::new(
+            min_width,
+            self.max_width.min(other.max_width).max(min_width),
+            min_height,
+            self.max_height.min(other.max_height).max(min_height),
+        )
+    }
+
+    /// Checks if the max bound (bounds.max_width * bounds.max_height) is greater than the parameter.
+    fn is_greater_than(&self, other: u64) -> bool {
+        (self.max_width as u64 * self.max_height as u64) > other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_return_true_for_matching_dimensions() {
+        let src = Dimensions(1, 1);
+        let tgt = Dimensions(1, 1);
+
+        assert!(Dimensions::same(&src, &tgt));
+    }
+
+    #[test]
+    fn should_return_false_for_mismatching_dimensions() {
+        let src = Dimensions(0, 0);
+        let tgt = Dimensions(1, 1);
+
+        assert!(!Dimensions::same(&src, &tgt));
+    }
+
+    #[test]
+    fn should_return_zero_for_matching_images() {
+        let src = image::ImageBuffer::new(100, 100);
+        let tgt = image::ImageBuffer::new(100, 100);
+        let bounds = Bounds::new(0, 100, 0, 100);
+
+        assert_eq!(0, pixel_difference(&src, &tgt, &bounds));
+    }
+
+    #[test]
+    fn should_return_non_zero_value_for_mismatching_images() {
+        let src = image::ImageBuffer::new(100, 100);
+
+        let mut tgt = image::ImageBuffer::new(100, 100);
+        *tgt.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+        *tgt.get_pixel_mut(20, 20) = image::Rgba([10, 10, 10, 255]);
+
+        let bounds = Bounds::new(0, 100, 0, 100);
+
+        assert_eq!(2, pixel_difference(&src, &tgt, &bounds));
+    }
+
+    #[test]
+    fn should_return_pixel_difference_for_images_with_different_widths() {
+        let mut src = image::ImageBuffer::new(50, 100);
+        *src.get_pixel_mut(49, 10) = image::Rgba([10, 10, 10, 255]);
+
+        let mut tgt = image::ImageBuffer::new(100, 100);
+        *tgt.get_pixel_mut(20, 20) = image::Rgba([10, 10, 10, 255]);
+        *tgt.get_pixel_mut(60, 20) = image::Rgba([10, 10, 10, 255]);
+
+        let bounds = Bounds::new(0, 50, 0, 100);
+
+        assert_eq!(2, pixel_difference(&src, &tgt, &bounds));
+    }
+
+    #[test]
+    fn should_return_ok_for_non_zero_bounds() {
+        let src = Dimensions::from((10, 100));
+        let tgt = Dimensions::from((100, 10));
+
+        assert_eq!(
+            Ok(Bounds::new(0, 10, 0, 10)),
+            Bounds::get_max_bounds(src, tgt)
+        );
+    }
+
+    #[test]
+    fn should_return_err_for_zero_bounds() {
+        let src = Dimensions::from((0, 0));
+        let tgt = Dimensions::from((1, 1));
+
+        assert_eq!(
+            Err(String::from("Maximum width / height cannot be ZERO (0).")),
+            Bounds::get_max_bounds(src, tgt)
+        );
+    }
+
+    #[test]
+    fn should_generate_name_from_backup_if_option_is_none() {
+        assert_eq!(
+            Some(PathBuf::from("/target_test_diff.png")),
+            generate_output_file_name(None, &PathBuf::from("/target_test.png"))
+        );
+    }
+
+    #[test]
+    fn should_generate_name_from_option_if_option_is_some() {
+        assert_eq!(
+            Some(PathBuf::from("/custom_output_file.png")),
+            generate_output_file_name(
+                Some(String::from("custom_output_file")),
+                &PathBuf::from("/target_test.png"),
+            )
+        );
+    }
+
+    #[test]
+    fn should_keep_directories_of_option() {
+        assert_eq!(
+            Some(PathBuf::from("artifacts/run42/login_diff.png")),
+            generate_output_file_name(
+                Some(String::from("artifacts/run42/login_diff")),
+                &PathBuf::from("/target_test.png"),
+            )
+        );
+    }
+
+    #[test]
+    fn should_generate_png_name_if_backup_is_not_an_image() {
+        assert_eq!(
+            Some(PathBuf::from("/frame_diff.png")),
+            generate_output_file_name(None, &PathBuf::from("/frame.yuv"))
+        );
+    }
+
+    #[test]
+    fn should_keep_extension_from_option_if_it_is_an_image_format() {
+        assert_eq!(
+            Some(PathBuf::from("/custom_output_file.webp")),
+            generate_output_file_name(
+                Some(String::from("custom_output_file.webp")),
+                &PathBuf::from("/target_test.png"),
+            )
+        );
+    }
+
+    #[test]
+    pub fn should_return_zero_value_tuple_when_differences_are_observed() {
+        let src = image::ImageBuffer::new(100, 100);
+        let tgt = image::ImageBuffer::new(100, 100);
+
+        let bounds = Bounds::new(0, 20, 0, 20);
+
+        let (diff, bounds_with_diff) =
+            percentage_difference(&src, &tgt, &bounds, 10, 0, 0.0, &comparator::Exact);
+
+        assert_eq!(0.0, diff);
+        assert_eq!(Vec::<Bounds>::new(), bounds_with_diff);
+    }
+
+    #[test]
+    pub fn should_return_non_zero_tuple_when_differences_are_observed() {
+        let src = image::ImageBuffer::new(100, 100);
+
+        let mut tgt = image::ImageBuffer::new(100, 100);
+        *tgt.get_pixel_mut(15, 15) = image::Rgba([10, 10, 10, 255]);
+        *tgt.get_pixel_mut(55, 55) = image::Rgba([10, 10, 10, 255]);
+
+        let bounds = Bounds::new(0, 20, 0, 20);
+
+        let (diff, bounds_with_diff) =
+            percentage_difference(&src, &tgt, &bounds, 10, 0, 0.0, &comparator::Exact);
+
+        assert_eq!(0.25, diff);
+        assert_eq!(vec![Bounds::new(10, 20, 10, 20)], bounds_with_diff);
+    }
+
+    #[test]
+    pub fn should_highlight_only_the_specified_bounds() {
+        let img = image::ImageBuffer::new(100, 100);
+
+        let mut img_clone1 = img.clone();
+        let bounds = vec![Bounds::new(10, 20, 10, 20), Bounds::new(50, 60, 50, 60)];
+        highlight(&mut img_clone1, &bounds, Palette::Default);
+
+        let mut img_clone2 = img.clone();
+        for i in 10..20 {
+            *img_clone2.get_pixel_mut(i, 10) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(i, 19) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(10, i) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(19, i) = image::Rgba([255, 0, 0, 255]);
+        }
+        for i in 50..60 {
+            *img_clone2.get_pixel_mut(i, 50) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(i, 59) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(50, i) = image::Rgba([255, 0, 0, 255]);
+            *img_clone2.get_pixel_mut(59, i) = image::Rgba([255, 0, 0, 255]);
+        }
+
+        assert_ne!(img, img_clone1);
+        assert_eq!(img_clone2, img_clone1);
+    }
+
+    #[test]
+    fn should_calculate_block_starts_without_overlap() {
+        assert_eq!(vec![0, 10], block_starts(0, 20, 10, 10));
+        assert_eq!(vec![0, 10, 20], block_starts(0, 25, 10, 10));
+    }
+
+    #[test]
+    fn should_calculate_block_starts_with_overlap() {
+        assert_eq!(vec![0, 6, 12], block_starts(0, 20, 10, 6));
+        assert_eq!(vec![0], block_starts(0, 8, 10, 6));
+    }
+
+    #[test]
+    pub fn should_count_pixels_in_overlapping_blocks_only_once() {
+        let src = image::ImageBuffer::new(100, 100);
+
+        let mut tgt = image::ImageBuffer::new(100, 100);
+        *tgt.get_pixel_mut(9, 9) = image::Rgba([10, 10, 10, 255]);
+
+        let bounds = Bounds::new(0, 20, 0, 20);
+
+        let (diff, bounds_with_diff) =
+            percentage_difference(&src, &tgt, &bounds, 10, 4, 0.0, &comparator::Exact);
+
+        assert_eq!(0.25, diff);
+        assert_eq!(
+            vec![
+                Bounds::new(0, 10, 0, 10),
+                Bounds::new(6, 16, 0, 10),
+                Bounds::new(0, 10, 6, 16),
+                Bounds::new(6, 16, 6, 16),
+            ],
+            bounds_with_diff
+        );
+    }
+
+    #[test]
+    fn should_insert_compare_command_when_arguments_start_with_an_option() {
+        assert_eq!(
+            vec!["idiff", "compare", "--src", "a.png"],
+            with_default_command(
+                vec!["idiff", "--src", "a.png"]
+                    .into_iter()
+                    .map(OsString::from)
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn should_not_insert_compare_command_for_subcommands_and_help() {
+        for args in [
+            vec!["idiff", "batch", "--src", "a"],
+            vec!["idiff", "--help"],
+            vec!["idiff", "--explain-exit-codes"],
+            vec!["idiff"],
+        ] {
+            let args: Vec<OsString> = args.into_iter().map(OsString::from).collect();
+            assert_eq!(args, with_default_command(args.clone()));
+        }
+    }
+
+    #[test]
+    fn should_return_no_excluded_bounds_for_same_dimensions() {
+        let bounds = Bounds::new(0, 10, 0, 10);
+
+        assert_eq!(
+            Vec::<Bounds>::new(),
+            bounds.excluded_from(&Dimensions(10, 10))
+        );
+    }
+
+    #[test]
+    fn should_enclose_all_the_bounds() {
+        assert_eq!(None, Bounds::enclosing(&[]));
+        assert_eq!(
+            Some(Bounds::new(10, 60, 0, 40)),
+            Bounds::enclosing(&[
+                Bounds::new(10, 20, 30, 40),
+                Bounds::new(50, 60, 0, 10),
+                Bounds::new(30, 40, 10, 20),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_order_bounds_top_to_bottom_then_left_to_right() {
+        let mut bounds = vec![
+            Bounds::new(10, 20, 10, 20),
+            Bounds::new(0, 10, 10, 20),
+            Bounds::new(10, 20, 0, 10),
+        ];
+        bounds.sort();
+
+        assert_eq!(
+            vec![
+                Bounds::new(10, 20, 0, 10),
+                Bounds::new(0, 10, 10, 20),
+                Bounds::new(10, 20, 10, 20),
+            ],
+            bounds
+        );
+    }
+
+    #[test]
+    fn should_return_bounds_with_difference_in_sorted_order() {
+        let src = image::RgbaImage::new(30, 30);
+        let mut tgt = image::RgbaImage::new(30, 30);
+        for (x, y) in [(25, 25), (5, 15), (25, 5), (15, 15)] {
+            *tgt.get_pixel_mut(x, y) = image::Rgba([255, 0, 0, 0]);
+        }
+
+        let (_, bounds) = percentage_difference(
+            &src,
+            &tgt,
+            &Bounds::new(0, 30, 0, 30),
+            10,
+            0,
+            0.0,
+            &comparator::Exact,
+        );
+
+        assert_eq!(4, bounds.len());
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn should_return_excluded_bounds_for_larger_dimensions() {
+        let bounds = Bounds::new(0, 10, 0, 10);
+
+        assert_eq!(
+            vec![Bounds::new(10, 15, 0, 20), Bounds::new(0, 10, 10, 20)],
+            bounds.excluded_from(&Dimensions(15, 20))
+        );
+    }
 
-            let current_bound = Bounds::new(start_width, max_width, start_height, max_height);
-            let diff = pixel_difference(src, tgt, &current_bound);
-            if diff != 0 {
-                total_diff += diff;
-                bounds_with_difference.push(current_bound);
-            }
-        }
+    #[test]
+    fn should_count_size_mismatch_as_difference() {
+        assert_eq!(50.0, rebase_percentage(0.0, 100, 100, 200));
+        assert_eq!(75.0, rebase_percentage(50.0, 100, 100, 200));
+        assert_eq!(10.0, rebase_percentage(10.0, 100, 0, 100));
     }
-    let diff_percentage =
-        ((total_diff as f32) / ((bounds.max_height * bounds.max_width) as f32)) * 100.0;
-    (diff_percentage, bounds_with_difference)
-}
 
-/// Compare the pixel difference for the specified bounds between the images.
-fn pixel_difference(src: &image::RgbaImage, tgt: &image::RgbaImage, bounds: &Bounds) -> u32 {
-    let mut diff = 0;
+    #[test]
+    fn should_parse_positive_sigma_only() {
+        assert_eq!(Ok(1.5), parse_sigma("1.5"));
+        assert!(parse_sigma("0").is_err());
+        assert!(parse_sigma("-1").is_err());
+        assert!(parse_sigma("abc").is_err());
+    }
 
-    for y in bounds.min_height..bounds.max_height {
-        for x in bounds.min_width..bounds.max_width {
-            if src.get_pixel(x, y) != tgt.get_pixel(x, y) {
-                diff += 1;
-            }
-        }
+    #[test]
+    fn should_rebase_percentage_to_a_larger_image() {
+        assert_eq!(25.0, rebase_percentage(50.0, 100, 0, 200));
+        assert_eq!(50.0, rebase_percentage(50.0, 100, 50, 200));
     }
 
-    diff
-}
+    #[test]
+    fn should_blend_target_over_source_and_tint_differences() {
+        let mut src = image::RgbaImage::from_pixel(2, 1, image::Rgba([100, 100, 100, 255]));
+        let mut tgt = image::RgbaImage::from_pixel(3, 1, image::Rgba([100, 100, 100, 255]));
+        *src.get_pixel_mut(1, 0) = image::Rgba([0, 0, 0, 255]);
+        *tgt.get_pixel_mut(1, 0) = image::Rgba([200, 200, 200, 255]);
+        *tgt.get_pixel_mut(2, 0) = image::Rgba([1, 2, 3, 4]);
 
-/// Highlight the specified bounds in the image.
-fn highlight(img: &mut image::RgbaImage, bounds: Vec<Bounds>) {
-    for bound in bounds {
-        for x in bound.min_width..bound.max_width {
-            *img.get_pixel_mut(x, bound.min_height) = image::Rgba([255, 0, 0, 255]);
-            *img.get_pixel_mut(x, bound.max_height - 1) = image::Rgba([255, 0, 0, 255]);
-        }
+        let blended = blend(&src, &tgt, 0.5, Palette::Default);
 
-        for y in bound.min_height..bound.max_height {
-            *img.get_pixel_mut(bound.min_width, y) = image::Rgba([255, 0, 0, 255]);
-            *img.get_pixel_mut(bound.max_width - 1, y) = image::Rgba([255, 0, 0, 255]);
-        }
+        assert_eq!(image::Rgba([100, 100, 100, 255]), *blended.get_pixel(0, 0));
+        assert_eq!(image::Rgba([178, 50, 178, 255]), *blended.get_pixel(1, 0));
+        assert_eq!(image::Rgba([1, 2, 3, 4]), *blended.get_pixel(2, 0));
     }
-}
 
-/// Generate output file name with extension if one is provided else use the backup file.
-fn generate_output_file_name(output: Option<String>, backup_file: &Path) -> Option<PathBuf> {
-    let file_name = match output {
-        Some(f) => f,
-        None => format!("{}_diff", backup_file.file_stem()?.to_str()?.to_owned()),
-    };
+    #[test]
+    fn should_use_the_default_values_of_the_arguments_for_default_options() {
+        let options = CompareOptions::default();
 
-    let mut output = backup_file.with_file_name(file_name);
-    if let Some(ext) = backup_file.extension() {
-        output.set_extension(ext);
+        assert_eq!(BlockSize::Fixed(10), options.block);
+        assert_eq!(32, options.minor_threshold);
+        assert!(!options.strict);
     }
 
-    Some(output)
-}
+    #[test]
+    fn should_downscale_to_at_least_one_pixel() {
+        let img = image::RgbaImage::new(100, 3);
 
-/// Represents the Dimension (width, height).
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
-struct Dimensions(u32, u32);
+        assert_eq!((25, 1), downscale(&img, 0.25).dimensions());
+        assert!(parse_scale("0").is_err());
+        assert!(parse_scale("2").is_err());
+    }
 
-impl Dimensions {
-    /// Create Dimensions from a tuple.
-    fn from(d: (u32, u32)) -> Dimensions {
-        Dimensions(d.0, d.1)
+    #[test]
+    fn should_match_pixels_shifted_within_tolerance() {
+        let mut src = image::RgbaImage::new(10, 1);
+        let mut tgt = image::RgbaImage::new(10, 1);
+        // Note: a pixel shifted by 1 & a pixel shifted by 3
+        *src.get_pixel_mut(1, 0) = image::Rgba([255, 0, 0, 255]);
+        *tgt.get_pixel_mut(2, 0) = image::Rgba([255, 0, 0, 255]);
+        *src.get_pixel_mut(5, 0) = image::Rgba([0, 255, 0, 255]);
+        *tgt.get_pixel_mut(8, 0) = image::Rgba([0, 255, 0, 255]);
+
+        let bounds = Bounds::new(0, 10, 0, 1);
+        // Note: the shifted pixels are matched on both the (old & new) positions
+        assert_eq!(3, match_shifted_pixels(&src, &mut tgt, &bounds, 1));
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *tgt.get_pixel(1, 0));
+        assert_eq!(image::Rgba([0, 0, 0, 0]), *tgt.get_pixel(2, 0));
+        assert_eq!(image::Rgba([0, 255, 0, 255]), *tgt.get_pixel(8, 0));
     }
 
-    /// Checks if the Dimensions are same.
-    fn same(d1: &Dimensions, d2: &Dimensions) -> bool {
-        matches!(d1.cmp(d2), std::cmp::Ordering::Equal)
+    #[test]
+    fn should_match_pixels_with_a_luminance_difference_below_the_delta() {
+        let src = image::RgbaImage::from_pixel(4, 1, image::Rgba([100, 100, 100, 255]));
+        let mut tgt = src.clone();
+        // Note: a hue shift of (almost) the same luminance, a darker pixel & a more transparent pixel
+        *tgt.get_pixel_mut(0, 0) = image::Rgba([110, 96, 102, 255]);
+        *tgt.get_pixel_mut(1, 0) = image::Rgba([80, 80, 80, 255]);
+        *tgt.get_pixel_mut(2, 0) = image::Rgba([100, 100, 100, 128]);
+
+        let bounds = Bounds::new(0, 4, 0, 1);
+        assert_eq!(1, match_similar_luma(&src, &mut tgt, &bounds, 5));
+        assert_eq!(src.get_pixel(0, 0), tgt.get_pixel(0, 0));
+        assert_eq!(image::Rgba([80, 80, 80, 255]), *tgt.get_pixel(1, 0));
+        assert_eq!(image::Rgba([100, 100, 100, 128]), *tgt.get_pixel(2, 0));
     }
-}
 
-/// Represents the Bound consisting of min/max width and min/max height.
-#[derive(Debug, PartialEq)]
-struct Bounds {
-    min_width: u32,
-    max_width: u32,
-    min_height: u32,
-    max_height: u32,
-}
+    #[test]
+    fn should_resolve_auto_block_size_from_the_smaller_dimension() {
+        assert_eq!(Ok(BlockSize::Auto), parse_block("auto"));
+        assert!(parse_block("0").is_err());
 
-impl Bounds {
-    /// Creates a new Bounds.
-    fn new(min_width: u32, max_width: u32, min_height: u32, max_height: u32) -> Bounds {
-        Bounds {
-            min_width,
-            max_width,
-            min_height,
-            max_height,
-        }
+        assert_eq!(21, BlockSize::Auto.resolve(&Bounds::new(0, 3840, 0, 2160)));
+        assert_eq!(2, BlockSize::Auto.resolve(&Bounds::new(0, 32, 0, 32)));
+        assert_eq!(7, BlockSize::Fixed(7).resolve(&Bounds::new(0, 32, 0, 32)));
     }
-    /// Get the max bounds from the provided Dimensions (width & height).
-    fn get_max_bounds(src: Dimensions, tgt: Dimensions) -> Result<Bounds, String> {
-        let Dimensions(w1, h1) = src;
-        let Dimensions(w2, h2) = tgt;
 
-        let max_width = std::cmp::min(w1, w2);
-        let max_height = std::cmp::min(h1, h2);
+    #[test]
+    fn should_keep_only_blocks_with_difference_in_color_for_cutout() {
+        let tgt = image::RgbaImage::from_pixel(4, 1, image::Rgba([200, 100, 50, 255]));
 
-        if max_width == 0 || max_height == 0 {
-            return Err(String::from("Maximum width / height cannot be ZERO (0)."));
-        }
+        let output = cutout(&tgt, &[Bounds::new(2, 4, 0, 1)]);
+        assert_eq!(image::Rgba([62, 62, 62, 255]), *output.get_pixel(0, 0));
+        assert_eq!(image::Rgba([200, 100, 50, 255]), *output.get_pixel(3, 0));
+    }
 
-        Ok(Bounds {
-            min_width: 0,
-            max_width,
-            min_height: 0,
-            max_height,
-        })
+    #[test]
+    fn should_name_source_output_apart_from_target_output() {
+        let src = Path::new("/baseline/a.png");
+
+        assert_eq!(
+            Some(String::from("a_src_diff")),
+            src_output_name(&None, src)
+        );
+        assert_eq!(
+            Some(String::from("out_src.png")),
+            src_output_name(&Some(String::from("out.png")), src)
+        );
     }
 
-    /// Checks if the max bound (bounds.max_width * bounds.max_height) is greater than the parameter.
-    fn is_greater_than(&self, other: u32) -> bool {
-        (self.max_width * self.max_height) > other
+    #[test]
+    fn should_warn_when_output_extension_is_changed() {
+        assert_eq!(
+            Some(Warning::OutputExtensionChanged {
+                requested: String::from("out.txt"),
+                written: String::from("out.png"),
+            }),
+            output_warning(&Some(String::from("out.txt")), Path::new("/tmp/out.png"))
+        );
+        assert_eq!(
+            None,
+            output_warning(&Some(String::from("out.png")), Path::new("/tmp/out.png"))
+        );
+        assert_eq!(None, output_warning(&None, Path::new("/tmp/b_diff.png")));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn should_calculate_mse_and_psnr() {
+        let src = image::RgbaImage::new(2, 1);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(0, 0) = image::Rgba([6, 0, 0, 255]);
+
+        let mse = mean_squared_error(&src, &tgt, &Bounds::new(0, 2, 0, 1));
+        assert_eq!(6.0, mse);
+        assert!((psnr(mse) - 40.3).abs() < 0.1);
+        assert_eq!(f64::INFINITY, psnr(0.0));
+    }
 
     #[test]
-    fn should_return_true_for_matching_dimensions() {
-        let src = Dimensions(1, 1);
-        let tgt = Dimensions(1, 1);
+    fn should_calculate_mae_of_a_global_shift() {
+        let src = image::RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+        let tgt = image::RgbaImage::from_pixel(4, 4, image::Rgba([103, 97, 103, 255]));
 
-        assert!(Dimensions::same(&src, &tgt));
+        assert_eq!(
+            3.0,
+            mean_absolute_error(&src, &tgt, &Bounds::new(0, 4, 0, 4))
+        );
+        assert_eq!(
+            0.0,
+            mean_absolute_error(&src, &src, &Bounds::new(0, 4, 0, 4))
+        );
     }
 
     #[test]
-    fn should_return_false_for_mismatching_dimensions() {
-        let src = Dimensions(0, 0);
-        let tgt = Dimensions(1, 1);
+    fn should_highlight_with_colors_of_palette() {
+        let mut img = image::RgbaImage::new(20, 20);
+        highlight(
+            &mut img,
+            &[Bounds::new(0, 20, 0, 20)],
+            Palette::Deuteranopia,
+        );
+        assert_eq!(image::Rgba([0, 114, 178, 255]), *img.get_pixel(0, 5));
 
-        assert!(!Dimensions::same(&src, &tgt));
+        highlight(
+            &mut img,
+            &[Bounds::new(0, 20, 0, 20)],
+            Palette::HighContrast,
+        );
+        assert_eq!(image::Rgba([0, 0, 0, 255]), *img.get_pixel(3, 0));
+        assert_eq!(image::Rgba([255, 255, 255, 255]), *img.get_pixel(4, 0));
     }
 
     #[test]
-    fn should_return_zero_for_matching_images() {
-        let src = image::ImageBuffer::new(100, 100);
-        let tgt = image::ImageBuffer::new(100, 100);
-        let bounds = Bounds::new(0, 100, 0, 100);
+    fn should_not_count_ignored_regions_and_transparent_colors() {
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(1, 1) = image::Rgba([255, 0, 0, 255]);
+        *tgt.get_pixel_mut(15, 15) = image::Rgba([255, 0, 0, 0]);
 
-        assert_eq!(0, pixel_difference(&src, &tgt, &bounds));
+        let diff = |options: CompareOptions| {
+            compare_decoded(
+                src.clone(),
+                tgt.clone(),
+                &options,
+                Encoded::default(),
+                Vec::new(),
+            )
+            .unwrap()
+            .diff
+        };
+        assert_eq!(0.5, diff(CompareOptions::default()));
+        let options = CompareOptions::builder()
+            .ignore_region(Region {
+                x: 0,
+                y: 0,
+                width: 5,
+                height: 5,
+            })
+            .alpha_mode(AlphaMode::Premultiplied)
+            .build()
+            .unwrap();
+        assert_eq!(0.0, diff(options));
+        assert!(parse_region("0,0,0,5").is_err());
     }
 
     #[test]
-    fn should_return_non_zero_value_for_mismatching_images() {
-        let src = image::ImageBuffer::new(100, 100);
+    fn should_only_return_blocks_with_difference_above_block_threshold() {
+        let src = image::RgbaImage::new(20, 10);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(1, 1) = image::Rgba([255, 0, 0, 255]);
+        for x in 10..15 {
+            *tgt.get_pixel_mut(x, 1) = image::Rgba([255, 0, 0, 255]);
+        }
 
-        let mut tgt = image::ImageBuffer::new(100, 100);
-        *tgt.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
-        *tgt.get_pixel_mut(20, 20) = image::Rgba([10, 10, 10, 255]);
+        let (diff, bounds) = percentage_difference(
+            &src,
+            &tgt,
+            &Bounds::new(0, 20, 0, 10),
+            10,
+            0,
+            2.0,
+            &comparator::Exact,
+        );
+        assert_eq!(3.0, diff);
+        assert_eq!(vec![Bounds::new(10, 20, 0, 10)], bounds);
+        assert!(parse_percent("101").is_err());
+    }
 
-        let bounds = Bounds::new(0, 100, 0, 100);
+    #[test]
+    fn should_wait_for_files_to_exist() {
+        let dir = std::env::temp_dir().join(format!("idiff_wait_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (src, tgt) = (dir.join("src.png"), dir.join("tgt.png"));
+        std::fs::write(&src, b"src").unwrap();
 
-        assert_eq!(2, pixel_difference(&src, &tgt, &bounds));
+        assert!(wait_for_files(&src, &tgt, Duration::ZERO).is_err());
+
+        let writer = {
+            let tgt = tgt.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                std::fs::write(tgt, b"tgt").unwrap();
+            })
+        };
+        assert_eq!(Ok(()), wait_for_files(&src, &tgt, Duration::from_secs(5)));
+        writer.join().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
     }
 
     #[test]
-    fn should_return_ok_for_non_zero_bounds() {
-        let src = Dimensions::from((10, 100));
-        let tgt = Dimensions::from((100, 10));
+    fn should_count_blocks_with_difference_per_bucket() {
+        let src = image::RgbaImage::new(20, 10);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(1, 1) = image::Rgba([255, 0, 0, 255]);
+        for x in 10..20 {
+            *tgt.get_pixel_mut(x, 1) = image::Rgba([255, 0, 0, 255]);
+            *tgt.get_pixel_mut(x, 2) = image::Rgba([255, 0, 0, 255]);
+        }
+
+        let bounds = [Bounds::new(0, 10, 0, 10), Bounds::new(10, 20, 0, 10)];
+        let histogram =
+            block_histogram(&block_percentages(&src, &tgt, &bounds, &comparator::Exact));
+        assert_eq!([1, 1, 0, 0, 0, 0, 0, 0, 0, 0], histogram);
+    }
+
+    #[test]
+    fn should_fail_cancelled_comparison() {
+        let (src, tgt) = (image::RgbaImage::new(20, 20), image::RgbaImage::new(20, 20));
+        let token = CancellationToken::new();
+        let options = CompareOptions::builder()
+            .cancellation(token.clone())
+            .build()
+            .unwrap();
+        assert!(compare_decoded(
+            src.clone(),
+            tgt.clone(),
+            &options,
+            Encoded::default(),
+            Vec::new()
+        )
+        .is_ok());
 
+        token.cancel();
         assert_eq!(
-            Ok(Bounds::new(0, 10, 0, 10)),
-            Bounds::get_max_bounds(src, tgt)
+            Some(String::from(CANCELLED)),
+            compare_decoded(src, tgt, &options, Encoded::default(), Vec::new())
+                .err()
+                .map(|e| e.message)
         );
     }
 
     #[test]
-    fn should_return_err_for_zero_bounds() {
-        let src = Dimensions::from((0, 0));
-        let tgt = Dimensions::from((1, 1));
+    fn should_only_hash_the_options_affecting_the_cached_difference() {
+        let options = CompareOptions::default();
+        let unaffected = CompareOptions {
+            progress: true,
+            timings: true,
+            cancellation: Some(CancellationToken::new()),
+            ..CompareOptions::default()
+        };
+        let affected = CompareOptions {
+            min_luma_delta: Some(4),
+            ..CompareOptions::default()
+        };
+        assert_eq!(cached_options(&options), cached_options(&unaffected));
+        assert_ne!(cached_options(&options), cached_options(&affected));
+        assert!(cached_options(&options).starts_with("version=1\n"));
+    }
 
+    #[test]
+    fn should_append_timestamp_to_output_names() {
+        assert_eq!("19700101T000000", format_timestamp(0));
+        assert_eq!("20240229T235959", format_timestamp(1709251199));
         assert_eq!(
-            Err(String::from("Maximum width / height cannot be ZERO (0).")),
-            Bounds::get_max_bounds(src, tgt)
+            PathBuf::from("out/home_diff_20240229T235959.png"),
+            stamped(Path::new("out/home_diff.png"), Some("20240229T235959"))
+        );
+        assert_eq!(
+            PathBuf::from("home_diff.png"),
+            stamped(Path::new("home_diff.png"), None)
         );
     }
 
     #[test]
-    fn should_generate_name_from_backup_if_option_is_none() {
+    fn should_tolerate_float_differences_by_mode() {
+        assert!(ToleranceMode::Absolute.within(1.0, 1.0005, 1e-3));
+        assert!(!ToleranceMode::Absolute.within(1000.0, 1000.5, 1e-3));
+        assert!(ToleranceMode::Relative.within(1000.0, 1000.5, 1e-3));
+        assert!(!ToleranceMode::Relative.within(0.001, 0.002, 1e-3));
+        assert!(ToleranceMode::Ulps.within(1.0, f32::from_bits(1.0f32.to_bits() + 2), 2.0));
+        assert!(ToleranceMode::Ulps.within(-0.0, 0.0, 0.0));
+        assert!(!ToleranceMode::Ulps.within(-f32::MIN_POSITIVE, f32::MIN_POSITIVE, 1.0));
+        assert!(ToleranceMode::Absolute.within(f32::NAN, f32::NAN, 0.0));
+        assert!(!ToleranceMode::Absolute.within(f32::NAN, 0.0, 1.0));
+
+        let src = image::Rgba32FImage::from_pixel(20, 20, image::Rgba([4.0, 2.0, 1.0, 1.0]));
+        let mut tgt = src.clone();
+        tgt.put_pixel(1, 1, image::Rgba([4.001, 2.0, 1.0, 1.0]));
+        tgt.put_pixel(15, 15, image::Rgba([4.1, 2.0, 1.0, 1.0]));
+        let encoded = Encoded {
+            jpeg_tables: None,
+            float_pixels: Some((src, tgt)),
+            indexed: None,
+        };
+        let options = CompareOptions::builder()
+            .float_tolerance(ToleranceMode::Relative, 1e-3)
+            .build()
+            .unwrap();
+        let (rgba8, rgba8_copy) = (image::RgbaImage::new(20, 20), image::RgbaImage::new(20, 20));
+        let comparison = compare_decoded(rgba8, rgba8_copy, &options, encoded, Vec::new()).unwrap();
+        assert_eq!(0.25, comparison.diff);
+    }
+
+    #[test]
+    fn should_subdivide_only_the_regions_with_difference() {
+        let src = image::RgbaImage::new(200, 100);
+        let mut tgt = src.clone();
+        tgt.put_pixel(150, 30, image::Rgba([10, 10, 10, 255]));
+        tgt.put_pixel(151, 30, image::Rgba([10, 10, 10, 255]));
+        let bounds = Bounds::new(0, 200, 0, 100);
+
+        let (diff, bounds_with_diff) =
+            quadtree_difference(&src, &tgt, &bounds, 10, 0.0, &comparator::Exact);
+
+        assert_eq!(2, diff);
+        assert_eq!(vec![Bounds::new(150, 156, 25, 31)], bounds_with_diff);
         assert_eq!(
-            Some(PathBuf::from("/target_test_diff.png")),
-            generate_output_file_name(None, &PathBuf::from("/target_test.png"))
+            (0, vec![]),
+            quadtree_difference(&src, &src, &bounds, 10, 0.0, &comparator::Exact)
         );
     }
 
     #[test]
-    fn should_generate_name_from_option_if_option_is_some() {
+    fn should_equalize_brightness_of_target() {
+        let src = image::RgbaImage::from_fn(16, 16, |x, y| {
+            let v = (x * 8 + y) as u8;
+            image::Rgba([v, v, v, 255])
+        });
+        let mut tgt = src.clone();
+        tgt.pixels_mut().for_each(|p| {
+            for c in 0..3 {
+                p[c] += 40;
+            }
+        });
+        let bounds = Bounds::new(0, 16, 0, 16);
+
+        assert_eq!(None, Normalize::None.apply(&src, &tgt, &bounds));
         assert_eq!(
-            Some(PathBuf::from("/custom_output_file.png")),
-            generate_output_file_name(
-                Some(String::from("custom_output_file")),
-                &PathBuf::from("/target_test.png"),
+            Some(src.clone()),
+            Normalize::MeanStd.apply(&src, &tgt, &bounds)
+        );
+        assert_eq!(
+            Some(src.clone()),
+            Normalize::Histogram.apply(&src, &tgt, &bounds)
+        );
+    }
+
+    #[test]
+    fn should_compare_images_in_memory() {
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        tgt.put_pixel(5, 5, image::Rgba([255, 0, 0, 255]));
+        let encode = |img: &image::RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageOutputFormat::Png,
             )
+            .unwrap();
+            bytes
+        };
+        let options = CompareOptions::default();
+
+        let result = compare_from_bytes(&encode(&src), &encode(&tgt), &options).unwrap();
+        assert_eq!(0.25, result.stats.diff_percentage);
+        assert_eq!(1, result.regions.len());
+
+        let (src, tgt) = (
+            image::DynamicImage::ImageRgba8(src),
+            image::DynamicImage::ImageRgba8(tgt),
         );
+        assert_eq!(result, compare_images(&src, &tgt, &options).unwrap());
+        assert!(compare_from_bytes(b"not an image", b"not an image", &options).is_err());
     }
 
     #[test]
-    pub fn should_return_zero_value_tuple_when_differences_are_observed() {
-        let src = image::ImageBuffer::new(100, 100);
-        let tgt = image::ImageBuffer::new(100, 100);
+    fn should_compare_images_from_readers() {
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        tgt.put_pixel(5, 5, image::Rgba([255, 0, 0, 255]));
+        let encode = |img: &image::RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+            bytes
+        };
+        let (src, tgt) = (encode(&src), encode(&tgt));
+        let options = CompareOptions::default();
 
-        let bounds = Bounds::new(0, 20, 0, 20);
+        let result = compare_readers(src.as_slice(), tgt.as_slice(), None, &options).unwrap();
+        assert_eq!(compare_from_bytes(&src, &tgt, &options).unwrap(), result);
+        let hinted = compare_readers(
+            src.as_slice(),
+            tgt.as_slice(),
+            Some(image::ImageFormat::Png),
+            &options,
+        );
+        assert_eq!(Ok(result), hinted);
 
-        let (diff, bounds_with_diff) = percentage_difference(&src, &tgt, &bounds, 10);
+        let limited = CompareOptions::builder().max_pixels(100).build().unwrap();
+        let error = compare_readers(src.as_slice(), tgt.as_slice(), None, &limited).unwrap_err();
+        assert!(error.starts_with("source image (20x20, 400 pixels) exceeds the limit"));
 
-        assert_eq!(0.0, diff);
-        assert_eq!(Vec::<Bounds>::new(), bounds_with_diff);
+        // Note: the formats read into memory (instead of decoded incrementally) are also checked by their headers,
+        // before the (here truncated) pixels are decoded
+        let mut bmp = Vec::new();
+        image::RgbaImage::new(20, 20)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bmp),
+                image::ImageOutputFormat::Bmp,
+            )
+            .unwrap();
+        let bmp = &bmp[..bmp.len() / 2];
+        assert!(compare_readers(bmp, bmp, None, &options).is_err());
+        let error = compare_readers(bmp, bmp, None, &limited).unwrap_err();
+        assert!(error.starts_with("source image (20x20, 400 pixels) exceeds the limit"));
     }
 
     #[test]
-    pub fn should_return_non_zero_tuple_when_differences_are_observed() {
-        let src = image::ImageBuffer::new(100, 100);
-
-        let mut tgt = image::ImageBuffer::new(100, 100);
-        *tgt.get_pixel_mut(15, 15) = image::Rgba([10, 10, 10, 255]);
-        *tgt.get_pixel_mut(55, 55) = image::Rgba([10, 10, 10, 255]);
+    fn should_filter_file_names() {
+        let filter = FileFilter {
+            include: vec![String::from("login_*")],
+            exclude: vec![String::from("*_draft.*")],
+            extensions: vec![String::from(".png"), String::from("webp")],
+        };
+        assert!(filter.allows("login_1.PNG"));
+        assert!(filter.allows("login_2.webp"));
+        assert!(!filter.allows("login_draft.png"));
+        assert!(!filter.allows("login_3.jpg"));
+        assert!(!filter.allows("home.png"));
+        assert!(FileFilter::default().allows("home"));
+    }
 
-        let bounds = Bounds::new(0, 20, 0, 20);
+    #[test]
+    fn should_restore_the_image_highlighted_in_place() {
+        let src = image::RgbaImage::from_fn(40, 40, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(5, 5) = image::Rgba([255, 255, 255, 255]);
+        *tgt.get_pixel_mut(30, 30) = image::Rgba([255, 255, 255, 255]);
+        let options = CompareOptions::builder().block(10).build().unwrap();
+        let mut comparison =
+            compare_decoded(src, tgt.clone(), &options, Encoded::default(), Vec::new()).unwrap();
 
-        let (diff, bounds_with_diff) = percentage_difference(&src, &tgt, &bounds, 10);
+        let output = render_output(
+            &mut comparison,
+            false,
+            OutputFormat::Highlight,
+            0.0,
+            Palette::Default,
+        )
+        .unwrap();
+        assert!(matches!(output, Rendered::Drawn(..)));
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *output.get_pixel(0, 0));
+        drop(output);
 
-        assert_eq!(0.25, diff);
-        assert_eq!(vec![Bounds::new(10, 20, 10, 20)], bounds_with_diff);
+        assert_eq!(tgt, comparison.tgt);
     }
 
     #[test]
-    pub fn should_highlight_only_the_specified_bounds() {
-        let img = image::ImageBuffer::new(100, 100);
+    fn should_notify_the_observer_while_comparing() {
+        #[derive(Default)]
+        struct Recorder(std::sync::Mutex<Vec<String>>);
 
-        let mut img_clone1 = img.clone();
-        let bounds = vec![Bounds::new(10, 20, 10, 20), Bounds::new(50, 60, 50, 60)];
-        highlight(&mut img_clone1, bounds);
+        impl Observer for Recorder {
+            fn on_region_found(&self, region: &Region) {
+                let event = format!("region {},{}", region.x, region.y);
+                self.0.lock().unwrap().push(event);
+            }
 
-        let mut img_clone2 = img.clone();
-        for i in 10..20 {
-            *img_clone2.get_pixel_mut(i, 10) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(i, 19) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(10, i) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(19, i) = image::Rgba([255, 0, 0, 255]);
-        }
-        for i in 50..60 {
-            *img_clone2.get_pixel_mut(i, 50) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(i, 59) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(50, i) = image::Rgba([255, 0, 0, 255]);
-            *img_clone2.get_pixel_mut(59, i) = image::Rgba([255, 0, 0, 255]);
+            fn on_stage_complete(&self, stage: &str, _duration: Duration) {
+                self.0.lock().unwrap().push(format!("stage {}", stage));
+            }
         }
 
-        assert_ne!(img, img_clone1);
-        assert_eq!(img_clone2, img_clone1);
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(15, 5) = image::Rgba([255, 0, 0, 255]);
+        let recorder = Arc::new(Recorder::default());
+        let options = CompareOptions::builder()
+            .block(10)
+            .observer(recorder.clone())
+            .build()
+            .unwrap();
+        compare_decoded(src, tgt, &options, Encoded::default(), Vec::new()).unwrap();
+
+        assert_eq!(
+            vec!["region 10,0", "stage diff"],
+            *recorder.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_compare_paletted_images_by_their_indices() {
+        let encode = |indices: &[u8]| {
+            let mut data = Vec::new();
+            let mut encoder = png::Encoder::new(&mut data, 20, 20);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(vec![0, 0, 0, 255, 0, 0]);
+            encoder
+                .write_header()
+                .unwrap()
+                .write_image_data(indices)
+                .unwrap();
+            data
+        };
+        let src = vec![0; 400];
+        let mut tgt = src.clone();
+        tgt[5 * 20 + 15] = 1;
+        let (src, tgt) = (encode(&src), encode(&tgt));
+        let options = CompareOptions::builder().block(10).build().unwrap();
+
+        let indexed = compare_from_bytes(&src, &tgt, &options).unwrap();
+        let decoded = compare_images(
+            &image::load_from_memory(&src).unwrap(),
+            &image::load_from_memory(&tgt).unwrap(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            decoded.stats.differing_pixels,
+            indexed.stats.differing_pixels
+        );
+        assert_eq!(decoded.regions, indexed.regions);
+        assert_eq!(1, indexed.regions.len());
+    }
+
+    #[test]
+    fn should_count_the_blocks_with_difference_when_regions_only() {
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(15, 5) = image::Rgba([255, 0, 0, 255]);
+        let options = CompareOptions::builder()
+            .block(10)
+            .regions_only(true)
+            .build()
+            .unwrap();
+        let comparison =
+            compare_decoded(src, tgt, &options, Encoded::default(), Vec::new()).unwrap();
+
+        assert_eq!(
+            vec![Bounds::new(10, 20, 0, 10)],
+            comparison.bounds_with_diff
+        );
+        assert_eq!(100, comparison.differing_pixels);
+        assert_eq!(25.0, comparison.diff);
+        assert!(comparison.regions_only);
+        assert_ne!(
+            block_checksum(&comparison.src, &Bounds::new(10, 20, 0, 10)),
+            block_checksum(&comparison.tgt, &Bounds::new(10, 20, 0, 10))
+        );
+    }
+
+    #[test]
+    fn should_compare_the_regions_of_interest() {
+        // Note: a texture of 2 tiles, the right one with a changed pixel
+        let mut texture = image::RgbaImage::from_fn(40, 20, |x, y| {
+            image::Rgba([(x % 20 * 10) as u8, (y * 10) as u8, 0, 255])
+        });
+        let (left, right) = (
+            Region {
+                x: 0,
+                y: 0,
+                width: 20,
+                height: 20,
+            },
+            Region {
+                x: 20,
+                y: 0,
+                width: 20,
+                height: 20,
+            },
+        );
+        let options = CompareOptions::builder()
+            .block(10)
+            .regions_of_interest(left.clone(), right.clone())
+            .build()
+            .unwrap();
+        let compare = |texture: &image::RgbaImage| {
+            compare_decoded(
+                texture.clone(),
+                texture.clone(),
+                &options,
+                Encoded::default(),
+                Vec::new(),
+            )
+        };
+        assert_eq!(0, compare(&texture).unwrap().differing_pixels);
+
+        *texture.get_pixel_mut(35, 15) = image::Rgba([0, 0, 255, 255]);
+        let comparison = compare(&texture).unwrap();
+        assert_eq!(
+            vec![Bounds::new(10, 20, 10, 20)],
+            comparison.bounds_with_diff
+        );
+        assert_eq!((20, 20), comparison.tgt.dimensions());
+
+        let outside = Region { x: 30, ..right };
+        let options = CompareOptions::builder()
+            .regions_of_interest(left, outside)
+            .build()
+            .unwrap();
+        let error = compare_decoded(
+            texture.clone(),
+            texture,
+            &options,
+            Encoded::default(),
+            Vec::new(),
+        );
+        assert!(error.is_err_and(|e| e
+            .message
+            .starts_with("roi-b (30,0,20,20) must lie within the target image")));
+    }
+
+    #[test]
+    fn should_compare_rectangular_blocks_rows_and_columns() {
+        let src = image::DynamicImage::ImageRgba8(image::RgbaImage::new(40, 20));
+        let mut tgt = image::RgbaImage::new(40, 20);
+        tgt.put_pixel(35, 12, image::Rgba([255, 0, 0, 255]));
+        let tgt = image::DynamicImage::ImageRgba8(tgt);
+        let regions = |options: CompareOptions| {
+            compare_images(&src, &tgt, &options)
+                .unwrap()
+                .regions
+                .into_iter()
+                .map(|r| (r.x, r.y, r.width, r.height))
+                .collect::<Vec<_>>()
+        };
+
+        let shape = CompareOptions::builder()
+            .block_shape(16, 4)
+            .build()
+            .unwrap();
+        assert_eq!(vec![(32, 12, 8, 4)], regions(shape));
+        let rows = CompareOptions::builder().by_row().build().unwrap();
+        assert_eq!(vec![(0, 12, 40, 1)], regions(rows));
+        let columns = CompareOptions::builder().by_column().build().unwrap();
+        assert_eq!(vec![(35, 0, 1, 20)], regions(columns));
+        let large = CompareOptions::builder()
+            .block_shape(70000, 70000)
+            .build()
+            .unwrap();
+        let error = compare_images(&src, &tgt, &large).unwrap_err();
+        assert!(error.starts_with("block size (70000) cannot be greater"));
+        assert_eq!("16x4", block_size_text((16, 4)));
+        assert_eq!("10", block_size_text((10, 10)));
     }
 }
@@ -0,0 +1,67 @@
+//! Resource limits of a comparison: the inputs exceeding the number of pixels (or the estimated memory) are
+//! refused before they are decoded, so that a malformed (for ex. 100000x100000) image cannot exhaust the memory.
+
+use crate::exit::Error;
+
+/// Bytes per pixel of the estimated memory: the decoded image & its RGBA conversion, 4 bytes per pixel each.
+const BYTES_PER_PIXEL: u64 = 8;
+
+/// Check the dimensions of the source & target against the largest number of pixels (of either image) & the
+/// largest estimated memory (in MB, of both the images).
+pub(crate) fn check(
+    src: (u32, u32),
+    tgt: (u32, u32),
+    max_pixels: Option<u64>,
+    max_memory: Option<u64>,
+) -> Result<(), Error> {
+    let pixels = |(width, height): (u32, u32)| width as u64 * height as u64;
+    if let Some(max_pixels) = max_pixels {
+        for (side, dimensions) in [("source", src), ("target", tgt)] {
+            if pixels(dimensions) > max_pixels {
+                return Err(Error::decode(format!(
+                    "{} image ({}x{}, {} pixels) exceeds the limit of {} pixels (max-pixels).",
+                    side,
+                    dimensions.0,
+                    dimensions.1,
+                    pixels(dimensions),
+                    max_pixels
+                )));
+            }
+        }
+    }
+    if let Some(max_memory) = max_memory {
+        let memory = (pixels(src) + pixels(tgt)) * BYTES_PER_PIXEL;
+        let megabytes = memory.div_ceil(1024 * 1024);
+        if megabytes > max_memory {
+            return Err(Error::decode(format!(
+                "comparing the images ({}x{} & {}x{}) requires about {} MB, which exceeds the limit of {} MB (max-memory).",
+                src.0, src.1, tgt.0, tgt.1, megabytes, max_memory
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_refuse_images_exceeding_the_limits() {
+        assert!(check((100, 100), (100, 100), None, None).is_ok());
+        assert!(check((100, 100), (100, 100), Some(10_000), Some(1)).is_ok());
+
+        let error = check((100, 100), (100, 101), Some(10_000), None).unwrap_err();
+        assert_eq!(crate::exit::DECODE, error.code);
+        assert!(error
+            .message
+            .starts_with("target image (100x101, 10100 pixels) exceeds the limit"));
+
+        // Note: 2 images of 1024x512 pixels, 8 bytes each
+        assert!(check((1024, 512), (1024, 512), None, Some(8)).is_ok());
+        let error = check((1024, 512), (1024, 513), None, Some(8)).unwrap_err();
+        assert!(error
+            .message
+            .contains("requires about 9 MB, which exceeds the limit of 8 MB"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Weight masks: a grayscale image (or the alpha channel of an image) scaling the contribution of every pixel
+//! to the percentage difference, from 0 (ignored) to 255 (fully counted).
+
+use std::sync::Arc;
+
+use crate::comparator::Comparator;
+use crate::{cache, Bounds, Region};
+
+/// Represents the weights of the pixels, read from a mask image.
+#[derive(Clone)]
+pub(crate) struct Mask {
+    weights: Arc<image::GrayImage>,
+    /// content hash of the weights (so that the cache key differs by mask).
+    hash: cache::Key,
+}
+
+/// Note: the weights are summarized (instead of listing every pixel)
+impl std::fmt::Debug for Mask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mask")
+            .field("dimensions", &self.weights.dimensions())
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+impl Mask {
+    pub(crate) fn new(weights: image::GrayImage) -> Mask {
+        let hash = cache::Key::new(&[weights.as_raw()]);
+        Mask {
+            weights: Arc::new(weights),
+            hash,
+        }
+    }
+
+    /// Content hash of the weights.
+    pub(crate) fn hash(&self) -> cache::Key {
+        self.hash
+    }
+
+    /// Check the mask covers the compared bounds.
+    pub(crate) fn check(&self, bounds: &Bounds) -> Result<(), String> {
+        let (width, height) = self.weights.dimensions();
+        if width < bounds.max_width || height < bounds.max_height {
+            return Err(format!(
+                "mask ({}x{}) must cover the compared region of the images ({}x{}).",
+                width, height, bounds.max_width, bounds.max_height
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replace the pixels of the target weighing 0 (within the bounds) with the source, so they match.
+    pub(crate) fn ignore<P: image::Pixel>(
+        &self,
+        src: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+        tgt: &mut image::ImageBuffer<P, Vec<P::Subpixel>>,
+        bounds: &Bounds,
+    ) {
+        for y in bounds.min_height..bounds.max_height {
+            for x in bounds.min_width..bounds.max_width {
+                if self.weights.get_pixel(x, y)[0] == 0 {
+                    tgt.put_pixel(x, y, *src.get_pixel(x, y));
+                }
+            }
+        }
+    }
+
+    /// Number of differing pixels (within the bounds) discounted by their weight: a differing pixel weighing `w`
+    /// only counts as `w / 255` of a pixel.
+    ///
+    /// Note: the partially weighted pixels are compared one by one, the others are counted as is
+    pub(crate) fn discount(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Bounds,
+        comparator: &dyn Comparator,
+    ) -> f64 {
+        let mut discount = 0.0;
+        for y in bounds.min_height..bounds.max_height {
+            for x in bounds.min_width..bounds.max_width {
+                let weight = self.weights.get_pixel(x, y)[0];
+                if weight == 0 || weight == 255 {
+                    continue;
+                }
+                let pixel = Region {
+                    x,
+                    y,
+                    width: 1,
+                    height: 1,
+                };
+                if comparator.compare_block(src, tgt, &pixel).differing_pixels != 0 {
+                    discount += (255 - weight) as f64 / 255.0;
+                }
+            }
+        }
+        discount
+    }
+}
+
+/// Read the mask image: its alpha channel (if any) or its grayscale values are the weights.
+pub(crate) fn read(path: &str) -> Result<Mask, String> {
+    let img = image::open(path)
+        .map_err(|e| format!("Encountered error while decoding mask {}: {}", path, e))?;
+    let weights = if img.color().has_alpha() {
+        let img = img.to_rgba8();
+        image::GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            image::Luma([img.get_pixel(x, y)[3]])
+        })
+    } else {
+        img.to_luma8()
+    };
+    Ok(Mask::new(weights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparator;
+
+    #[test]
+    fn should_ignore_and_discount_pixels_by_their_weight() {
+        let mut weights = image::GrayImage::from_pixel(4, 1, image::Luma([255]));
+        weights.put_pixel(0, 0, image::Luma([0]));
+        weights.put_pixel(1, 0, image::Luma([51]));
+        let mask = Mask::new(weights);
+        let bounds = Bounds::new(0, 4, 0, 1);
+
+        let src = image::RgbaImage::new(4, 1);
+        let mut tgt = image::RgbaImage::from_pixel(4, 1, image::Rgba([9, 9, 9, 255]));
+        mask.ignore(&src, &mut tgt, &bounds);
+        assert_eq!(src.get_pixel(0, 0), tgt.get_pixel(0, 0));
+        assert_ne!(src.get_pixel(1, 0), tgt.get_pixel(1, 0));
+
+        let comparator = comparator::get("exact", None).unwrap();
+        let discount = mask.discount(&src, &tgt, &bounds, &*comparator);
+        assert!((discount - 0.8).abs() < 1e-9);
+        assert!(mask.check(&bounds).is_ok());
+        assert!(mask.check(&Bounds::new(0, 4, 0, 2)).is_err());
+    }
+}
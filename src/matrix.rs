@@ -0,0 +1,152 @@
+//! Export of the difference magnitudes as a matrix (csv or NumPy array), for post-processing.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+use crate::{cluster, Bounds};
+
+/// Represents the cells of the matrix.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub(crate) enum MatrixLevel {
+    // a cell per block
+    Block,
+    // a cell per pixel
+    Pixel,
+}
+
+/// Represents the largest channel difference (0-255) of every cell (block or pixel) of the common region.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Matrix {
+    rows: usize,
+    columns: usize,
+    /// values of the cells (row major).
+    values: Vec<u8>,
+}
+
+/// Parse the file name of the matrix output (a `.csv` or `.npy` file).
+pub(crate) fn parse_output(output: &str) -> Result<PathBuf, String> {
+    match Path::new(output).extension().and_then(|e| e.to_str()) {
+        Some("csv" | "npy") => Ok(PathBuf::from(output)),
+        _ => Err(format!(
+            "invalid matrix output '{}' (expected a .csv or .npy file)",
+            output
+        )),
+    }
+}
+
+impl Matrix {
+    /// Compute the matrix of the common region (`bounds`) of the images, with the blocks of the comparison.
+    pub(crate) fn new(
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Bounds,
+        level: MatrixLevel,
+        (block_width, block_height): (u32, u32),
+        overlap: u32,
+    ) -> Matrix {
+        let (block_width, block_height, overlap) = match level {
+            MatrixLevel::Block => (block_width, block_height, overlap),
+            MatrixLevel::Pixel => (1, 1, 0),
+        };
+        let rows = crate::block_starts(
+            bounds.min_height,
+            bounds.max_height,
+            block_height,
+            block_height - overlap,
+        );
+        let columns = crate::block_starts(
+            bounds.min_width,
+            bounds.max_width,
+            block_width,
+            block_width - overlap,
+        );
+
+        let values = rows
+            .iter()
+            .flat_map(|&y| columns.iter().map(move |&x| (x, y)))
+            .map(|(x, y)| {
+                let cell = Bounds::new(
+                    x,
+                    (x + block_width).min(bounds.max_width),
+                    y,
+                    (y + block_height).min(bounds.max_height),
+                );
+                cluster::magnitude(src, tgt, &cell)
+            })
+            .collect();
+        Matrix {
+            rows: rows.len(),
+            columns: columns.len(),
+            values,
+        }
+    }
+
+    /// Write the matrix as csv (a line per row) or as a NumPy array (`.npy`, of `uint8`), by the extension.
+    pub(crate) fn write(&self, output: &Path) -> Result<(), Error> {
+        let data = match output.extension().and_then(|e| e.to_str()) {
+            Some("npy") => self.npy(),
+            _ => self.csv().into_bytes(),
+        };
+        std::fs::File::create(output)
+            .and_then(|mut f| f.write_all(&data))
+            .map_err(|e| {
+                Error::io(format!(
+                    "Encountered error while writing matrix {}: {}",
+                    output.display(),
+                    e
+                ))
+            })
+    }
+
+    fn csv(&self) -> String {
+        self.values
+            .chunks(self.columns.max(1))
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                row.join(",") + "\n"
+            })
+            .collect()
+    }
+
+    /// Encode the matrix in the NumPy format (version 1.0).
+    fn npy(&self) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '|u1', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows, self.columns
+        );
+        // Note: the magic, version, header length & header (ending with a new line) are aligned to 64 bytes
+        let length = 10 + header.len() + 1;
+        header.push_str(&" ".repeat(length.next_multiple_of(64) - length));
+        header.push('\n');
+
+        let mut data = b"\x93NUMPY\x01\x00".to_vec();
+        data.extend((header.len() as u16).to_le_bytes());
+        data.extend(header.as_bytes());
+        data.extend(&self.values);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_export_magnitudes_of_blocks_and_pixels() {
+        let src = image::RgbaImage::new(4, 2);
+        let mut tgt = src.clone();
+        *tgt.get_pixel_mut(3, 1) = image::Rgba([0, 20, 0, 0]);
+        let bounds = Bounds::new(0, 4, 0, 2);
+
+        let blocks = Matrix::new(&src, &tgt, &bounds, MatrixLevel::Block, (2, 2), 0);
+        assert_eq!("0,20\n", blocks.csv());
+
+        let pixels = Matrix::new(&src, &tgt, &bounds, MatrixLevel::Pixel, (2, 2), 0);
+        assert_eq!("0,0,0,0\n0,0,0,20\n", pixels.csv());
+        let npy = pixels.npy();
+        assert_eq!(0, (npy.len() - 8) % 64);
+        assert_eq!(&[0, 0, 0, 20], &npy[npy.len() - 4..]);
+        assert!(String::from_utf8_lossy(&npy).contains("'shape': (2, 4)"));
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::BTreeMap;
+
+use crate::exit::Error;
+
+/// Represents the metadata of an image as (key, value) pairs, sorted by key.
+pub(crate) type Metadata = BTreeMap<String, String>;
+
+/// PNG file signature.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// PNG text chunk keyword used for XMP packets.
+const PNG_XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// JPEG APP1 segment identifier used for XMP packets.
+const JPEG_XMP_IDENTIFIER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// JPEG APP2 segment identifier used for ICC profiles.
+const JPEG_ICC_IDENTIFIER: &[u8] = b"ICC_PROFILE\0";
+
+/// Represents a difference in the metadata between the source and target images.
+#[derive(Debug, PartialEq)]
+pub(crate) enum MetadataChange {
+    /// key (& value) only present in the target image.
+    Added(String, String),
+    /// key (& value) only present in the source image.
+    Removed(String, String),
+    /// key present in both the images with different values (source, target).
+    Changed(String, String, String),
+}
+
+impl MetadataChange {
+    /// Key of the changed metadata.
+    fn key(&self) -> &str {
+        match self {
+            MetadataChange::Added(key, _)
+            | MetadataChange::Removed(key, _)
+            | MetadataChange::Changed(key, _, _) => key,
+        }
+    }
+}
+
+impl std::fmt::Display for MetadataChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MetadataChange::Added(key, value) => write!(f, "+ {}: {}", key, value),
+            MetadataChange::Removed(key, value) => write!(f, "- {}: {}", key, value),
+            MetadataChange::Changed(key, src, tgt) => write!(f, "~ {}: {} -> {}", key, src, tgt),
+        }
+    }
+}
+
+/// Read the EXIF, XMP & ICC metadata (and the text chunks for PNG) of the (encoded) image.
+pub(crate) fn read(data: &[u8]) -> Result<Metadata, Error> {
+    let mut metadata = Metadata::new();
+    read_exif(data, &mut metadata);
+
+    if data.starts_with(&PNG_SIGNATURE) {
+        read_png(data, &mut metadata)?;
+    } else if data.starts_with(&[0xff, 0xd8]) {
+        read_jpeg(data, &mut metadata);
+    }
+
+    Ok(metadata)
+}
+
+/// Compare the metadata of the images.
+///
+/// Returns the changes sorted by key.
+pub(crate) fn diff(src: &Metadata, tgt: &Metadata) -> Vec<MetadataChange> {
+    let mut changes = Vec::new();
+
+    for (key, src_value) in src {
+        match tgt.get(key) {
+            None => changes.push(MetadataChange::Removed(key.clone(), src_value.clone())),
+            Some(tgt_value) if tgt_value != src_value => changes.push(MetadataChange::Changed(
+                key.clone(),
+                src_value.clone(),
+                tgt_value.clone(),
+            )),
+            Some(_) => {}
+        }
+    }
+    for (key, tgt_value) in tgt {
+        if !src.contains_key(key) {
+            changes.push(MetadataChange::Added(key.clone(), tgt_value.clone()));
+        }
+    }
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+
+    changes
+}
+
+/// Read the capture time (the EXIF `DateTimeOriginal`, or else `DateTime`) of the (encoded) image, as
+/// `YYYY:MM:DD HH:MM:SS`.
+pub(crate) fn capture_time(data: &[u8]) -> Option<String> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .iter()
+        .find_map(
+            |&tag| match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+                exif::Value::Ascii(values) => Some(latin1(values.first()?)),
+                _ => None,
+            },
+        )
+}
+
+/// Read the EXIF orientation (1-8, 1 being upright) of the (encoded) image.
+pub(crate) fn orientation(data: &[u8]) -> Option<u32> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Read the physical resolution (horizontal & vertical DPI) of the (encoded) image: the `pHYs` chunk of a PNG,
+/// the JFIF density of a JPEG, or else the EXIF resolution.
+pub(crate) fn dpi(data: &[u8]) -> Option<(f64, f64)> {
+    const INCH: f64 = 0.0254;
+    let resolution = if data.starts_with(&PNG_SIGNATURE) {
+        png_chunks(data)
+            .into_iter()
+            .find(|(chunk_type, _)| *chunk_type == b"pHYs")
+            .and_then(|(_, chunk)| {
+                let ppu =
+                    |i: usize| Some(u32::from_be_bytes(chunk.get(i..i + 4)?.try_into().ok()?));
+                // Note: the unit 1 is the meter (& 0 is only the aspect ratio)
+                match chunk.get(8) {
+                    Some(1) => Some((ppu(0)? as f64 * INCH, ppu(4)? as f64 * INCH)),
+                    _ => None,
+                }
+            })
+    } else if data.starts_with(&[0xff, 0xd8]) {
+        jpeg_segments(data)
+            .into_iter()
+            .find(|(marker, segment)| *marker == 0xe0 && segment.starts_with(b"JFIF\0"))
+            .and_then(|(_, segment)| {
+                // Note: the version (2 bytes) is followed by the unit (1: inch, 2: centimeter) & the densities
+                let density =
+                    |i: usize| Some(u16::from_be_bytes(segment.get(i..i + 2)?.try_into().ok()?));
+                let scale = match segment.get(7) {
+                    Some(1) => 1.0,
+                    Some(2) => 2.54,
+                    _ => return None,
+                };
+                Some((density(8)? as f64 * scale, density(10)? as f64 * scale))
+            })
+    } else {
+        None
+    };
+    resolution
+        .or_else(|| exif_dpi(data))
+        .filter(|(x, y)| *x > 0.0 && *y > 0.0)
+}
+
+/// Read the EXIF resolution (`XResolution` & `YResolution`, per inch or centimeter) as DPI.
+fn exif_dpi(data: &[u8]) -> Option<(f64, f64)> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+    let resolution = |tag| match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Rational(values) => values.first().map(exif::Rational::to_f64),
+        _ => None,
+    };
+    // Note: the unit 2 (the default) is the inch & 3 is the centimeter
+    let scale = match exif
+        .get_field(exif::Tag::ResolutionUnit, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+    {
+        None | Some(2) => 1.0,
+        Some(3) => 2.54,
+        _ => return None,
+    };
+    Some((
+        resolution(exif::Tag::XResolution)? * scale,
+        resolution(exif::Tag::YResolution)? * scale,
+    ))
+}
+
+/// Read the EXIF fields (if any) as `Exif.<IFD>.<TAG>`.
+fn read_exif(data: &[u8], metadata: &mut Metadata) {
+    let mut cursor = std::io::Cursor::new(data);
+    // Note: images without EXIF data (or with unsupported containers) are treated as having no EXIF fields
+    if let Ok(exif) = exif::Reader::new().read_from_container(&mut cursor) {
+        for field in exif.fields() {
+            metadata.insert(
+                format!("Exif.{}.{}", field.ifd_num, field.tag),
+                field.display_value().with_unit(&exif).to_string(),
+            );
+        }
+    }
+}
+
+/// Read the text chunks (as `PNG.<KEYWORD>`), XMP & ICC profile of a PNG image.
+fn read_png(data: &[u8], metadata: &mut Metadata) -> Result<(), Error> {
+    let error = || Error::decode("Encountered error while reading PNG metadata.");
+
+    for (chunk_type, chunk) in png_chunks(data) {
+        match chunk_type {
+            b"tEXt" => {
+                let (keyword, text) = split_at_nul(chunk).ok_or_else(error)?;
+                insert_png_text(metadata, keyword, latin1(text));
+            }
+            b"zTXt" => {
+                let (keyword, rest) = split_at_nul(chunk).ok_or_else(error)?;
+                let text = inflate(rest.get(1..).ok_or_else(error)?).ok_or_else(error)?;
+                insert_png_text(metadata, keyword, latin1(&text));
+            }
+            b"iTXt" => {
+                let (keyword, rest) = split_at_nul(chunk).ok_or_else(error)?;
+                let compressed = *rest.first().ok_or_else(error)? == 1;
+                let (_language, rest) =
+                    split_at_nul(rest.get(2..).ok_or_else(error)?).ok_or_else(error)?;
+                let (_translated_keyword, text) = split_at_nul(rest).ok_or_else(error)?;
+                let text = if compressed {
+                    inflate(text).ok_or_else(error)?
+                } else {
+                    text.to_vec()
+                };
+                insert_png_text(
+                    metadata,
+                    keyword,
+                    String::from_utf8_lossy(&text).into_owned(),
+                );
+            }
+            b"iCCP" => {
+                let (_name, rest) = split_at_nul(chunk).ok_or_else(error)?;
+                let icc = inflate(rest.get(1..).ok_or_else(error)?).ok_or_else(error)?;
+                metadata.insert(String::from("ICC"), describe_blob(&icc));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert the PNG text chunk as `PNG.<KEYWORD>` (or as `XMP` for XMP packets).
+fn insert_png_text(metadata: &mut Metadata, keyword: &[u8], text: String) {
+    let keyword = latin1(keyword);
+    if keyword == PNG_XMP_KEYWORD {
+        metadata.insert(String::from("XMP"), describe_blob(text.as_bytes()));
+    } else {
+        metadata.insert(format!("PNG.{}", keyword), text);
+    }
+}
+
+/// Get the (type, data) of the PNG chunks.
+fn png_chunks(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut position = PNG_SIGNATURE.len();
+
+    // Note: every chunk consists of length (4 bytes), type (4 bytes), data & CRC (4 bytes)
+    while position + 12 <= data.len() {
+        let length = u32::from_be_bytes([
+            data[position],
+            data[position + 1],
+            data[position + 2],
+            data[position + 3],
+        ]) as usize;
+        let start = position + 8;
+        let end = match start.checked_add(length) {
+            Some(end) if end + 4 <= data.len() => end,
+            _ => break,
+        };
+        chunks.push((&data[position + 4..start], &data[start..end]));
+        position = end + 4;
+    }
+
+    chunks
+}
+
+/// Split the data at the first NUL byte (excluding the NUL byte).
+fn split_at_nul(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let position = data.iter().position(|b| *b == 0)?;
+    Some((&data[..position], &data[position + 1..]))
+}
+
+/// Decode ISO 8859-1 (latin1) text.
+fn latin1(data: &[u8]) -> String {
+    data.iter().map(|b| *b as char).collect()
+}
+
+/// Decompress the zlib compressed data.
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data).ok()
+}
+
+/// Read the XMP & ICC profile of a JPEG image.
+fn read_jpeg(data: &[u8], metadata: &mut Metadata) {
+    let mut icc = Vec::new();
+
+    for (marker, segment) in jpeg_segments(data) {
+        match marker {
+            // APP1
+            0xe1 if segment.starts_with(JPEG_XMP_IDENTIFIER) => {
+                metadata.insert(
+                    String::from("XMP"),
+                    describe_blob(&segment[JPEG_XMP_IDENTIFIER.len()..]),
+                );
+            }
+            // APP2 (ICC profile split into chunks, preceded by the chunk sequence number & count)
+            0xe2 if segment.starts_with(JPEG_ICC_IDENTIFIER) => {
+                if let Some(chunk) = segment.get(JPEG_ICC_IDENTIFIER.len() + 2..) {
+                    icc.extend_from_slice(chunk);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !icc.is_empty() {
+        metadata.insert(String::from("ICC"), describe_blob(&icc));
+    }
+}
+
+/// Get the (marker, payload) of the JPEG segments up to the start of the image data.
+fn jpeg_segments(data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = Vec::new();
+    // Note: skip the SOI marker
+    let mut position = 2;
+
+    while position + 4 <= data.len() && data[position] == 0xff {
+        let marker = data[position + 1];
+        // Note: start of scan (image data) or end of image
+        if marker == 0xda || marker == 0xd9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let end = std::cmp::min(position + 2 + length, data.len());
+        if length < 2 {
+            break;
+        }
+        segments.push((marker, &data[position + 4..end]));
+        position = end;
+    }
+
+    segments
+}
+
+/// Describe binary metadata (for ex. ICC profile) by its size and checksum.
+fn describe_blob(data: &[u8]) -> String {
+    // Note: FNV-1a
+    let checksum = data.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{} bytes (checksum: {:016x})", data.len(), checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(entries: &[(&str, &str)]) -> Metadata {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn should_return_no_changes_for_matching_metadata() {
+        let src = metadata(&[("Exif.primary.Make", "foo")]);
+
+        assert_eq!(Vec::<MetadataChange>::new(), diff(&src, &src.clone()));
+    }
+
+    #[test]
+    fn should_return_added_removed_and_changed_keys_sorted_by_key() {
+        let src = metadata(&[
+            ("Exif.primary.GPSLatitude", "1"),
+            ("Exif.primary.Make", "foo"),
+        ]);
+        let tgt = metadata(&[("Exif.primary.Make", "bar"), ("ICC", "1 bytes")]);
+
+        assert_eq!(
+            vec![
+                MetadataChange::Removed(
+                    String::from("Exif.primary.GPSLatitude"),
+                    String::from("1")
+                ),
+                MetadataChange::Changed(
+                    String::from("Exif.primary.Make"),
+                    String::from("foo"),
+                    String::from("bar")
+                ),
+                MetadataChange::Added(String::from("ICC"), String::from("1 bytes")),
+            ],
+            diff(&src, &tgt)
+        );
+    }
+
+    #[test]
+    fn should_read_text_chunks_from_png() {
+        let mut data = PNG_SIGNATURE.to_vec();
+        for (chunk_type, chunk) in [
+            (b"tEXt", b"Author\0foo".to_vec()),
+            (
+                b"zTXt",
+                [
+                    b"Comment\0\0".as_slice(),
+                    &miniz_oxide::deflate::compress_to_vec_zlib(b"bar", 6),
+                ]
+                .concat(),
+            ),
+            (b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>".to_vec()),
+        ] {
+            data.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            data.extend_from_slice(chunk_type);
+            data.extend_from_slice(&chunk);
+            data.extend_from_slice(&[0; 4]);
+        }
+
+        let mut metadata = Metadata::new();
+        read_png(&data, &mut metadata).unwrap();
+
+        assert_eq!(Some(&String::from("foo")), metadata.get("PNG.Author"));
+        assert_eq!(Some(&String::from("bar")), metadata.get("PNG.Comment"));
+        assert_eq!(Some(&describe_blob(b"<x/>")), metadata.get("XMP"));
+    }
+
+    #[test]
+    fn should_read_xmp_and_icc_from_jpeg_segments() {
+        let mut data = vec![0xff, 0xd8];
+        for (marker, payload) in [
+            (0xe1, [JPEG_XMP_IDENTIFIER, b"<x/>"].concat()),
+            (0xe2, [JPEG_ICC_IDENTIFIER, &[1, 1], b"icc"].concat()),
+        ] {
+            data.extend_from_slice(&[0xff, marker]);
+            data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            data.extend_from_slice(&payload);
+        }
+        data.extend_from_slice(&[0xff, 0xd9]);
+
+        let mut metadata = Metadata::new();
+        read_jpeg(&data, &mut metadata);
+
+        assert_eq!(Some(&describe_blob(b"<x/>")), metadata.get("XMP"));
+        assert_eq!(Some(&describe_blob(b"icc")), metadata.get("ICC"));
+    }
+
+    #[test]
+    fn should_read_the_dpi_of_png_and_jpeg() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        // Note: 5669 pixels per meter (144 DPI) horizontally, 2835 (72 DPI) vertically
+        let chunk = [&5669u32.to_be_bytes()[..], &2835u32.to_be_bytes(), &[1]].concat();
+        png.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        png.extend_from_slice(b"pHYs");
+        png.extend_from_slice(&chunk);
+        png.extend_from_slice(&[0; 4]);
+        let (x, y) = dpi(&png).unwrap();
+        assert_eq!((144, 72), (x.round() as u32, y.round() as u32));
+
+        let jpeg = |unit: u8| {
+            let payload = [b"JFIF\0".as_slice(), &[1, 2, unit, 0, 96, 0, 96, 0, 0]].concat();
+            let mut data = vec![0xff, 0xd8, 0xff, 0xe0];
+            data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            data.extend_from_slice(&payload);
+            data.extend_from_slice(&[0xff, 0xd9]);
+            data
+        };
+        assert_eq!(Some((96.0, 96.0)), dpi(&jpeg(1)));
+        assert_eq!(Some((96.0 * 2.54, 96.0 * 2.54)), dpi(&jpeg(2)));
+        // Note: the unit 0 is only the aspect ratio
+        assert_eq!(None, dpi(&jpeg(0)));
+    }
+}
@@ -0,0 +1,80 @@
+//! Contact sheet of the pairs with difference (of a batch), to show the scope of a regression at a glance.
+
+/// Size (in pixels) of the box the thumbnails are fit in.
+const THUMBNAIL: u32 = 160;
+
+/// Space (in pixels) around the thumbnails.
+const GAP: u32 = 8;
+
+/// Represents a contact sheet with a row (of the source, target & highlighted difference thumbnails) per pair.
+#[derive(Default)]
+pub(crate) struct Montage {
+    rows: Vec<[image::RgbaImage; 3]>,
+}
+
+impl Montage {
+    /// Add a row with the thumbnails of the source, target & highlighted difference.
+    pub(crate) fn add(
+        &mut self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        diff: &image::RgbaImage,
+    ) {
+        self.rows
+            .push([thumbnail(src), thumbnail(tgt), thumbnail(diff)]);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Render the rows (in the order added) over a gray background, with the thumbnails centered in their cells.
+    pub(crate) fn render(&self) -> image::RgbaImage {
+        let cell = THUMBNAIL + GAP;
+        let mut img = image::RgbaImage::from_pixel(
+            3 * cell + GAP,
+            self.rows.len() as u32 * cell + GAP,
+            image::Rgba([64, 64, 64, 255]),
+        );
+        for (row, thumbnails) in self.rows.iter().enumerate() {
+            for (column, thumbnail) in thumbnails.iter().enumerate() {
+                let x = GAP + column as u32 * cell + (THUMBNAIL - thumbnail.width()) / 2;
+                let y = GAP + row as u32 * cell + (THUMBNAIL - thumbnail.height()) / 2;
+                image::imageops::overlay(&mut img, thumbnail, x as i64, y as i64);
+            }
+        }
+        img
+    }
+}
+
+/// Downscale the image to fit the thumbnail box (keeping the aspect ratio, never upscaling).
+pub(crate) fn thumbnail(img: &image::RgbaImage) -> image::RgbaImage {
+    let (width, height) = img.dimensions();
+    if width <= THUMBNAIL && height <= THUMBNAIL {
+        return img.clone();
+    }
+    let scale = THUMBNAIL as f32 / width.max(height) as f32;
+    let size = |length: u32| ((length as f32 * scale).round() as u32).clamp(1, THUMBNAIL);
+    image::imageops::thumbnail(img, size(width), size(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_render_a_row_per_pair() {
+        let wide = image::RgbaImage::from_pixel(640, 320, image::Rgba([255, 0, 0, 255]));
+        let small = image::RgbaImage::from_pixel(10, 10, image::Rgba([0, 0, 255, 255]));
+        let mut montage = Montage::default();
+        assert!(montage.is_empty());
+        montage.add(&wide, &wide, &small);
+        montage.add(&small, &small, &small);
+
+        let img = montage.render();
+        assert_eq!((3 * 168 + 8, 2 * 168 + 8), img.dimensions());
+        // Note: the wide image is fit in the box (160x80, centered vertically)
+        assert_eq!(&image::Rgba([255, 0, 0, 255]), img.get_pixel(8, 8 + 40));
+        assert_eq!(&image::Rgba([64, 64, 64, 255]), img.get_pixel(8, 8 + 39));
+    }
+}
@@ -0,0 +1,146 @@
+//! Observer of the progress of a comparison (for ex. to show a live visualization while embedding the library,
+//! or the progress of the CLI).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::comparator::{BlockDiff, Comparator};
+use crate::Region;
+
+/// Observes the events of a comparison (see `CompareOptionsBuilder::observer`), every method does nothing by
+/// default.
+///
+/// The events are sent from the thread of the comparison (or the threads comparing the blocks), while it runs.
+///
+/// ```no_run
+/// struct Print;
+///
+/// impl idiff::Observer for Print {
+///     fn on_region_found(&self, region: &idiff::Region) {
+///         println!("difference at {}, {}", region.x, region.y);
+///     }
+/// }
+///
+/// let options = idiff::CompareOptions::builder()
+///     .observer(std::sync::Arc::new(Print))
+///     .build()
+///     .unwrap();
+/// idiff::diff_with_options("a.png".as_ref(), "b.png".as_ref(), &options).unwrap();
+/// ```
+pub trait Observer: Send + Sync {
+    /// Called once a block (or a part of one, for ex. a quadrant of the adaptive comparison) is compared, with its
+    /// number of differing pixels.
+    fn on_block_compared(&self, _block: &Region, _differing_pixels: u64) {}
+
+    /// Called with every block with difference found (in order), once the blocks are compared.
+    fn on_region_found(&self, _region: &Region) {}
+
+    /// Called once a stage of the comparison (for ex. `decode` or `diff`, as in `DiffResult::timings`) is
+    /// complete, with the time it took.
+    fn on_stage_complete(&self, _stage: &str, _duration: Duration) {}
+}
+
+/// Represents the observer of the options.
+#[derive(Clone)]
+pub(crate) struct Shared(pub(crate) Arc<dyn Observer>);
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// Notifies the observer of every block compared by the `inner` comparator.
+pub(crate) struct Observed {
+    inner: Arc<dyn Comparator>,
+    observer: Arc<dyn Observer>,
+}
+
+impl Observed {
+    pub(crate) fn new(inner: Arc<dyn Comparator>, observer: Arc<dyn Observer>) -> Observed {
+        Observed { inner, observer }
+    }
+}
+
+impl Comparator for Observed {
+    fn compare_block(
+        &self,
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Region,
+    ) -> BlockDiff {
+        let diff = self.inner.compare_block(src, tgt, bounds);
+        self.observer
+            .on_block_compared(bounds, diff.differing_pixels);
+        diff
+    }
+}
+
+/// Prints the progress of the comparison into stderr (for the `progress` flag of the CLI).
+#[derive(Default)]
+pub(crate) struct Progress {
+    blocks: AtomicU64,
+    regions: AtomicU64,
+}
+
+impl Observer for Progress {
+    fn on_block_compared(&self, _block: &Region, _differing_pixels: u64) {
+        self.blocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_region_found(&self, _region: &Region) {
+        self.regions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_stage_complete(&self, stage: &str, duration: Duration) {
+        eprintln!(
+            "[progress] {} completed in {:.3?} ({} block(s) compared, {} region(s) found so far)",
+            stage,
+            duration,
+            self.blocks.load(Ordering::Relaxed),
+            self.regions.load(Ordering::Relaxed)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(u32, u32, u64)>>);
+
+    impl Observer for Recorder {
+        fn on_block_compared(&self, block: &Region, differing_pixels: u64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((block.x, block.y, differing_pixels));
+        }
+    }
+
+    #[test]
+    fn should_notify_every_compared_block() {
+        let recorder = Arc::new(Recorder::default());
+        let comparator = Observed::new(
+            crate::comparator::get("exact", None).unwrap(),
+            recorder.clone(),
+        );
+        let src = image::RgbaImage::new(4, 4);
+        let mut tgt = src.clone();
+        tgt.put_pixel(3, 3, image::Rgba([1, 0, 0, 0]));
+
+        for (x, y) in [(0, 0), (2, 2)] {
+            let block = Region {
+                x,
+                y,
+                width: 2,
+                height: 2,
+            };
+            comparator.compare_block(&src, &tgt, &block);
+        }
+        assert_eq!(vec![(0, 0, 0), (2, 2, 1)], *recorder.0.lock().unwrap());
+    }
+}
@@ -0,0 +1,97 @@
+//! Recognition of the text of the changed regions (with the `tesseract` command).
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::exit::Error;
+use crate::{cluster, Bounds, Region, TextChange};
+
+/// Height (in pixels) below which a region is upscaled before the recognition (tesseract expects text of about
+/// 30 pixels high).
+const MIN_HEIGHT: u32 = 100;
+
+/// Recognize the text of the changed regions (the clusters of the blocks with difference, padded by a block) in
+/// both the images.
+///
+/// Returns the regions with different text (sorted by position).
+pub(crate) fn text_changes(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    blocks: &[Bounds],
+    step: u32,
+) -> Result<Vec<TextChange>, Error> {
+    let mut changes = Vec::new();
+    for cluster in cluster::cluster(src, tgt, blocks, step, 0, u8::MAX) {
+        // Note: the blocks with difference can cut through the words, so the neighbouring blocks are included
+        let padded = Bounds::new(
+            cluster.bounds.min_width.saturating_sub(step),
+            cluster.bounds.max_width.saturating_add(step),
+            cluster.bounds.min_height.saturating_sub(step),
+            cluster.bounds.max_height.saturating_add(step),
+        )
+        .intersection(bounds);
+
+        let (before, after) = (recognize(src, &padded)?, recognize(tgt, &padded)?);
+        if before != after {
+            changes.push(TextChange {
+                region: Region::from(&padded),
+                before,
+                after,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Recognize the text within the bounds of the image (with the whitespace collapsed).
+fn recognize(img: &image::RgbaImage, bounds: &Bounds) -> Result<String, Error> {
+    let region = Region::from(bounds);
+    let mut crop =
+        image::imageops::crop_imm(img, region.x, region.y, region.width, region.height).to_image();
+    if region.height < MIN_HEIGHT {
+        let scale = MIN_HEIGHT.div_ceil(region.height);
+        crop = image::imageops::resize(
+            &crop,
+            region.width * scale,
+            region.height * scale,
+            image::imageops::FilterType::CatmullRom,
+        );
+    }
+
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgba8(crop)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|_| {
+            Error::io(String::from(
+                "Encountered error while encoding the region for OCR.",
+            ))
+        })?;
+
+    let error = |e: std::io::Error| {
+        Error::io(format!(
+            "Encountered error while running tesseract (required for OCR): {}",
+            e
+        ))
+    };
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(error)?;
+    // Note: tesseract reads the whole image before writing the text, so the pipes cannot block each other
+    child.stdin.take().unwrap().write_all(&png).map_err(error)?;
+    let output = child.wait_with_output().map_err(error)?;
+    if !output.status.success() {
+        return Err(Error::io(String::from(
+            "Encountered error while recognizing the text of a changed region.",
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" "))
+}
@@ -0,0 +1,178 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::AnimationDecoder;
+
+use crate::exit::Error;
+use crate::i18n;
+
+/// Separator between the file and the index of the page / frame within it (for ex. `scan.tif#3`).
+pub(crate) const SEPARATOR: char = '#';
+
+/// Split the path into the file & the index (starting from 0) of the page / frame within it (for ex. `scan.tif#3`).
+///
+/// Returns `None` if the path has no page selector, or if the path (incl. the selector) is an existing file.
+pub(crate) fn split(path: &Path) -> Option<(&Path, usize)> {
+    let (file, page) = path.to_str()?.rsplit_once(SEPARATOR)?;
+    if file.is_empty() || !page.bytes().all(|b| b.is_ascii_digit()) || path.is_file() {
+        return None;
+    }
+    Some((Path::new(file), page.parse().ok()?))
+}
+
+/// Checks if the (tiff / gif / png) image has more than one page / frame.
+pub(crate) fn has_more_pages(data: &[u8]) -> bool {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Tiff) => tiff::decoder::Decoder::new(Cursor::new(data))
+            .is_ok_and(|decoder| decoder.more_images()),
+        Ok(image::ImageFormat::Gif) => image::codecs::gif::GifDecoder::new(Cursor::new(data))
+            .is_ok_and(|decoder| decoder.into_frames().nth(1).is_some()),
+        Ok(image::ImageFormat::Png) => image::codecs::png::PngDecoder::new(Cursor::new(data))
+            .is_ok_and(|decoder| decoder.is_apng()),
+        _ => false,
+    }
+}
+
+/// Count the pages / frames of the (tiff / gif / png) image (1 for the other formats).
+pub(crate) fn count(data: &[u8]) -> usize {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Tiff) => match tiff::decoder::Decoder::new(Cursor::new(data)) {
+            Ok(mut decoder) => {
+                let mut count = 1;
+                while decoder.more_images() && decoder.next_image().is_ok() {
+                    count += 1;
+                }
+                count
+            }
+            Err(_) => 1,
+        },
+        Ok(image::ImageFormat::Gif) => image::codecs::gif::GifDecoder::new(Cursor::new(data))
+            .map_or(1, |decoder| decoder.into_frames().count().max(1)),
+        Ok(image::ImageFormat::Png) => image::codecs::png::PngDecoder::new(Cursor::new(data))
+            .ok()
+            .filter(|decoder| decoder.is_apng())
+            .map_or(1, |decoder| decoder.apng().into_frames().count().max(1)),
+        _ => 1,
+    }
+}
+
+/// Decode the page (tiff) / frame (gif & apng) of the image into RGBA.
+pub(crate) fn decode(data: &[u8], page: usize) -> Result<image::RgbaImage, Error> {
+    let not_found = || Error::decode(format!("Page / frame {} not found in the image.", page));
+    let error = |_| Error::decode(i18n::text("error.open", &[]));
+
+    match image::guess_format(data).map_err(error)? {
+        image::ImageFormat::Tiff => decode_tiff(data, page)?.ok_or_else(not_found),
+        image::ImageFormat::Gif => {
+            let decoder = image::codecs::gif::GifDecoder::new(Cursor::new(data)).map_err(error)?;
+            let frame = decoder.into_frames().nth(page).ok_or_else(not_found)?;
+            Ok(frame.map_err(error)?.into_buffer())
+        }
+        image::ImageFormat::Png => {
+            let decoder = image::codecs::png::PngDecoder::new(Cursor::new(data)).map_err(error)?;
+            let frame = decoder
+                .apng()
+                .into_frames()
+                .nth(page)
+                .ok_or_else(not_found)?;
+            Ok(frame.map_err(error)?.into_buffer())
+        }
+        format => Err(Error::decode(format!(
+            "Page / frame selectors are only supported for tiff, gif & png images (found {:?}).",
+            format
+        ))),
+    }
+}
+
+/// Decode the page (image file directory) of the tiff image.
+///
+/// Returns `None` if the image has fewer pages.
+fn decode_tiff(data: &[u8], page: usize) -> Result<Option<image::RgbaImage>, Error> {
+    use tiff::decoder::DecodingResult;
+    use tiff::ColorType;
+
+    let error = |_| Error::decode(i18n::text("error.open", &[]));
+    let mut decoder = tiff::decoder::Decoder::new(Cursor::new(data)).map_err(error)?;
+    if decoder.seek_to_image(page).is_err() {
+        return Ok(None);
+    }
+
+    let (width, height) = decoder.dimensions().map_err(error)?;
+    let color_type = decoder.colortype().map_err(error)?;
+    let unsupported = || Error::decode(format!("Unsupported tiff color type {:?}.", color_type));
+
+    let image = match (color_type, decoder.read_image().map_err(error)?) {
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            image::GrayImage::from_raw(width, height, buf).map(image::DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            image::GrayAlphaImage::from_raw(width, height, buf)
+                .map(image::DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            image::RgbImage::from_raw(width, height, buf).map(image::DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            image::RgbaImage::from_raw(width, height, buf).map(image::DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            image::ImageBuffer::from_raw(width, height, buf).map(image::DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            image::ImageBuffer::from_raw(width, height, buf).map(image::DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            image::ImageBuffer::from_raw(width, height, buf).map(image::DynamicImage::ImageRgba16)
+        }
+        _ => return Err(unsupported()),
+    };
+
+    image.map(|i| Some(i.to_rgba8())).ok_or_else(unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a tiff with a page per color.
+    fn multi_page_tiff(colors: &[[u8; 3]]) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        let mut encoder = tiff::encoder::TiffEncoder::new(&mut data).unwrap();
+        for color in colors {
+            let pixels: Vec<u8> = color.repeat(4);
+            encoder
+                .write_image::<tiff::encoder::colortype::RGB8>(2, 2, &pixels)
+                .unwrap();
+        }
+        data.into_inner()
+    }
+
+    #[test]
+    fn should_split_page_selector() {
+        assert_eq!(
+            Some((Path::new("/tmp/scan.tif"), 3)),
+            split(Path::new("/tmp/scan.tif#3"))
+        );
+        assert_eq!(None, split(Path::new("/tmp/scan.tif")));
+        assert_eq!(None, split(Path::new("/tmp/scan.tif#a")));
+    }
+
+    #[test]
+    fn should_decode_page_of_multi_page_tiff() {
+        let data = multi_page_tiff(&[[255, 0, 0], [0, 255, 0]]);
+
+        assert_eq!(
+            image::Rgba([255, 0, 0, 255]),
+            *decode(&data, 0).unwrap().get_pixel(0, 0)
+        );
+        assert_eq!(
+            image::Rgba([0, 255, 0, 255]),
+            *decode(&data, 1).unwrap().get_pixel(1, 1)
+        );
+        assert!(decode(&data, 2).is_err());
+        assert!(has_more_pages(&data));
+        assert!(!has_more_pages(&multi_page_tiff(&[[255, 0, 0]])));
+        assert_eq!(2, count(&data));
+        assert_eq!(1, count(&multi_page_tiff(&[[255, 0, 0]])));
+    }
+}
@@ -0,0 +1,112 @@
+//! Running the comparisons of the pairs concurrently, while handling their results in a deterministic order.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+
+/// Default number of jobs (the number of cores, or 1 if unknown).
+pub(crate) fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Apply `work` to the items on up to `jobs` threads, calling `each` (on the calling thread) with every item &
+/// its result in the order of the items, as soon as it & the previous ones are done.
+///
+/// Note: the items are only started within a window (of twice the jobs) after the next one to handle, so that a
+/// slow item does not hold an unbounded number of results (for ex. diff images) in memory.
+pub(crate) fn for_each_ordered<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    work: impl Fn(&T) -> R + Sync,
+    mut each: impl FnMut(&T, R),
+) {
+    // Note: sequentially on the calling thread for a single job (or item), without spawning
+    if jobs <= 1 || items.len() <= 1 {
+        for item in items {
+            each(item, work(item));
+        }
+        return;
+    }
+
+    let window = jobs * 2;
+    let next = AtomicUsize::new(0);
+    let handled = (Mutex::new(0), Condvar::new());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let (sender, next, handled, work) = (sender.clone(), &next, &handled, &work);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else { break };
+                let (lock, changed) = handled;
+                drop(changed.wait_while(lock.lock().unwrap(), |h| index >= *h + window));
+                if sender.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Note: the results finished out of order are held until the previous ones are handled
+        let mut pending = BTreeMap::new();
+        let (lock, changed) = &handled;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            let mut handled = *lock.lock().unwrap();
+            while let Some(result) = pending.remove(&handled) {
+                each(&items[handled], result);
+                handled += 1;
+            }
+            *lock.lock().unwrap() = handled;
+            changed.notify_all();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_handle_the_results_in_order() {
+        let items: Vec<u64> = (0..50).collect();
+        for jobs in [1, 4] {
+            let mut results = Vec::new();
+            for_each_ordered(
+                &items,
+                jobs,
+                |&i| {
+                    // Note: the earlier items finish later
+                    std::thread::sleep(std::time::Duration::from_millis(50 - i));
+                    i * 2
+                },
+                |&i, result| results.push((i, result)),
+            );
+            assert_eq!(
+                items.iter().map(|&i| (i, i * 2)).collect::<Vec<_>>(),
+                results
+            );
+        }
+    }
+
+    #[test]
+    fn should_only_start_the_items_within_the_window() {
+        let items: Vec<usize> = (0..40).collect();
+        let handled = AtomicUsize::new(0);
+        for_each_ordered(
+            &items,
+            2,
+            |&i| {
+                // Note: the first item is slow, the others would all be done (& pending) without the window
+                assert!(i < handled.load(Ordering::SeqCst) + 4);
+                if i == 0 {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+            },
+            |_, _| {
+                handled.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+        assert_eq!(items.len(), handled.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,82 @@
+//! Rendering of the percentage difference in the output (fixed, scientific or ppm, with a precision).
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+static FORMAT: OnceLock<(PercentFormat, Option<usize>)> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum PercentFormat {
+    // decimal percentage (for ex. 0.00012%)
+    #[default]
+    Fixed,
+    // scientific notation percentage (for ex. 1.2e-4%)
+    Scientific,
+    // parts per million (for ex. 1.2 ppm)
+    Ppm,
+}
+
+/// Set the format & precision (number of decimals) of the rendered percentages (once, the later calls are
+/// ignored).
+pub(crate) fn set_format(format: PercentFormat, precision: Option<usize>) {
+    let _ = FORMAT.set((format, precision));
+}
+
+/// Render the percentage difference (with its unit) in the format set.
+///
+/// By default (fixed, without precision), the percentage is rendered as is, truncated to `width` characters if
+/// given.
+pub(crate) fn render(diff: f32, width: Option<usize>) -> String {
+    let (format, precision) = FORMAT.get().copied().unwrap_or_default();
+    render_as(diff, format, precision, width)
+}
+
+fn render_as(
+    diff: f32,
+    format: PercentFormat,
+    precision: Option<usize>,
+    width: Option<usize>,
+) -> String {
+    match (format, precision) {
+        (PercentFormat::Fixed, None) => {
+            let diff = diff.to_string();
+            match width {
+                Some(width) => format!("{:.*}%", width, diff),
+                None => format!("{}%", diff),
+            }
+        }
+        (PercentFormat::Fixed, Some(precision)) => format!("{:.*}%", precision, diff),
+        (PercentFormat::Scientific, precision) => {
+            format!("{:.*e}%", precision.unwrap_or(3), diff)
+        }
+        // Note: 1% is 10000 ppm
+        (PercentFormat::Ppm, precision) => {
+            format!("{:.*} ppm", precision.unwrap_or(2), diff as f64 * 10000.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_render_percentage_in_format() {
+        let diff = 0.000012;
+        assert_eq!(
+            "0.000%",
+            render_as(diff, PercentFormat::Fixed, None, Some(5))
+        );
+        assert_eq!(
+            "0.0000120%",
+            render_as(diff, PercentFormat::Fixed, Some(7), None)
+        );
+        assert_eq!(
+            "1.200e-5%",
+            render_as(diff, PercentFormat::Scientific, None, None)
+        );
+        assert_eq!("0.12 ppm", render_as(diff, PercentFormat::Ppm, None, None));
+        assert_eq!("50%", render_as(50.0, PercentFormat::Fixed, None, None));
+    }
+}
@@ -0,0 +1,67 @@
+//! Classification of the differing pixels by their presence: added (transparent in the source), removed
+//! (transparent in the target) or changed (present in both), for ex. to tell the sprites added to an atlas from
+//! the ones redrawn.
+
+use crate::{row_slice, Bounds, PixelChanges};
+
+impl PixelChanges {
+    /// Classify the differing pixels of the images within the bounds.
+    pub(crate) fn count(
+        src: &image::RgbaImage,
+        tgt: &image::RgbaImage,
+        bounds: &Bounds,
+    ) -> PixelChanges {
+        let mut changes = PixelChanges {
+            added: 0,
+            removed: 0,
+            changed: 0,
+        };
+        for y in bounds.min_height..bounds.max_height {
+            for (s, t) in row_slice(src, y, bounds)
+                .chunks_exact(4)
+                .zip(row_slice(tgt, y, bounds).chunks_exact(4))
+            {
+                match (s[3], t[3]) {
+                    _ if s == t => {}
+                    (0, a) if a != 0 => changes.added += 1,
+                    (a, 0) if a != 0 => changes.removed += 1,
+                    _ => changes.changed += 1,
+                }
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_classify_the_differing_pixels_by_their_presence() {
+        let src = image::RgbaImage::from_fn(4, 2, |x, _| match x {
+            0 | 1 => image::Rgba([0, 0, 0, 0]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        });
+        let mut tgt = src.clone();
+        // Note: a pixel drawn onto the transparent area, a pixel erased, a pixel recolored (& a transparent pixel
+        // of another color, still differing)
+        tgt.put_pixel(0, 0, image::Rgba([0, 255, 0, 255]));
+        tgt.put_pixel(2, 0, image::Rgba([255, 0, 0, 0]));
+        tgt.put_pixel(3, 0, image::Rgba([0, 0, 255, 255]));
+        tgt.put_pixel(1, 1, image::Rgba([9, 9, 9, 0]));
+
+        let changes = PixelChanges::count(&src, &tgt, &Bounds::new(0, 4, 0, 2));
+
+        assert_eq!(
+            PixelChanges {
+                added: 1,
+                removed: 1,
+                changed: 2
+            },
+            changes
+        );
+        let top = PixelChanges::count(&src, &tgt, &Bounds::new(0, 2, 0, 1));
+        assert_eq!((1, 0, 0), (top.added, top.removed, top.changed));
+    }
+}
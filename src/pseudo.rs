@@ -0,0 +1,113 @@
+//! Generated pseudo-targets: a solid color (`color:#RRGGBB`, of the dimensions of the source) or a blank
+//! (fully transparent) image (`blank:WxH`), to check an image against a uniform background.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::exit::Error;
+
+/// Represents a generated target image.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Pseudo {
+    /// solid color (opaque), of the dimensions of the source.
+    Color([u8; 3]),
+    /// fully transparent image of the dimensions (width, height).
+    Blank(u32, u32),
+}
+
+impl Pseudo {
+    /// Parse the pseudo-target of the path.
+    ///
+    /// Returns `None` if the path is not a pseudo-target (or is an existing file), or the error if it is malformed.
+    pub(crate) fn parse(path: &Path) -> Option<Result<Pseudo, String>> {
+        let path = path.to_str()?;
+        let (kind, value) = path.split_once(':')?;
+        if !matches!(kind, "color" | "blank") || Path::new(path).exists() {
+            return None;
+        }
+        let error = || {
+            format!(
+                "Invalid pseudo-target '{}' (expected 'color:#RRGGBB' or 'blank:WxH').",
+                path
+            )
+        };
+        let pseudo = match kind {
+            "color" => {
+                let hex = value.strip_prefix('#').unwrap_or(value);
+                let channel = |i: usize| {
+                    hex.get(i..i + 2)
+                        .and_then(|c| u8::from_str_radix(c, 16).ok())
+                };
+                match (hex.len(), channel(0), channel(2), channel(4)) {
+                    (6, Some(r), Some(g), Some(b)) => Ok(Pseudo::Color([r, g, b])),
+                    _ => Err(error()),
+                }
+            }
+            _ => match value.split_once(['x', 'X']) {
+                Some((width, height)) => match (width.parse(), height.parse()) {
+                    (Ok(width), Ok(height)) if width > 0 && height > 0 => {
+                        Ok(Pseudo::Blank(width, height))
+                    }
+                    _ => Err(error()),
+                },
+                None => Err(error()),
+            },
+        };
+        Some(pseudo)
+    }
+
+    /// Generate the image (a solid color of the dimensions of the source), encoded as png.
+    pub(crate) fn encode(&self, src_dimensions: (u32, u32)) -> Result<Vec<u8>, Error> {
+        let img = match *self {
+            Pseudo::Color([r, g, b]) => {
+                let (width, height) = src_dimensions;
+                image::RgbaImage::from_pixel(width, height, image::Rgba([r, g, b, 255]))
+            }
+            Pseudo::Blank(width, height) => image::RgbaImage::new(width, height),
+        };
+        let mut data = Cursor::new(Vec::new());
+        img.write_to(&mut data, image::ImageOutputFormat::Png)
+            .map_err(|e| {
+                Error::io(format!(
+                    "Encountered error while generating the target: {}",
+                    e
+                ))
+            })?;
+        Ok(data.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_pseudo_targets() {
+        let parse = |path: &str| Pseudo::parse(Path::new(path));
+        assert_eq!(
+            Some(Ok(Pseudo::Color([255, 0, 16]))),
+            parse("color:#ff0010")
+        );
+        assert_eq!(Some(Ok(Pseudo::Color([0, 0, 0]))), parse("color:000000"));
+        assert_eq!(Some(Ok(Pseudo::Blank(640, 480))), parse("blank:640x480"));
+        assert!(matches!(parse("color:#fff"), Some(Err(_))));
+        assert!(matches!(parse("blank:0x10"), Some(Err(_))));
+        assert_eq!(None, parse("/tmp/a.png"));
+        assert_eq!(None, parse("https://example.com/a.png"));
+    }
+
+    #[test]
+    fn should_generate_pseudo_targets() {
+        let decode = |pseudo: Pseudo| {
+            image::load_from_memory(&pseudo.encode((4, 3)).unwrap())
+                .unwrap()
+                .to_rgba8()
+        };
+        let color = decode(Pseudo::Color([1, 2, 3]));
+        assert_eq!((4, 3), color.dimensions());
+        assert!(color.pixels().all(|p| *p == image::Rgba([1, 2, 3, 255])));
+        let blank = decode(Pseudo::Blank(2, 5));
+        assert_eq!((2, 5), blank.dimensions());
+        assert!(blank.pixels().all(|p| p[3] == 0));
+    }
+}
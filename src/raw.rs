@@ -0,0 +1,147 @@
+use crate::Dimensions;
+
+/// Represents the supported raw (headerless) frame formats.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub(crate) enum RawFormat {
+    // 8-bit Y plane followed by an interleaved U/V plane (subsampled 2x2)
+    Nv12,
+    // 8-bit Y, U & V planes (U & V subsampled 2x2)
+    Yuv420p,
+    // 8-bit packed R, G & B
+    Rgb24,
+}
+
+impl RawFormat {
+    /// Number of bytes of a frame with the dimensions.
+    fn frame_size(&self, dimension: Dimensions) -> usize {
+        let Dimensions(width, height) = dimension;
+        let (width, height) = (width as usize, height as usize);
+        let chroma = width.div_ceil(2) * height.div_ceil(2);
+        match self {
+            RawFormat::Nv12 | RawFormat::Yuv420p => width * height + 2 * chroma,
+            RawFormat::Rgb24 => width * height * 3,
+        }
+    }
+}
+
+/// Parse the dimensions of a raw frame from `WIDTHxHEIGHT`.
+pub(crate) fn parse_size(size: &str) -> Result<Dimensions, String> {
+    let error = || {
+        format!(
+            "invalid size '{}' (expected WIDTHxHEIGHT, for ex. 1920x1080)",
+            size
+        )
+    };
+    let (width, height) = size.split_once(['x', 'X']).ok_or_else(error)?;
+    let (width, height) = (
+        width.parse().map_err(|_| error())?,
+        height.parse().map_err(|_| error())?,
+    );
+    if width == 0 || height == 0 {
+        return Err(error());
+    }
+    Ok(Dimensions(width, height))
+}
+
+/// Decode a raw frame into RGBA.
+///
+/// Only the first frame is decoded, if the data contains multiple frames.
+/// YUV is converted with the BT.601 (limited range) coefficients.
+pub(crate) fn decode(
+    data: &[u8],
+    format: RawFormat,
+    dimension: Dimensions,
+) -> Result<image::RgbaImage, String> {
+    let expected = format.frame_size(dimension);
+    if data.len() < expected {
+        return Err(format!(
+            "Raw frame is too small for {:?} {}x{} (expected {} bytes, found {}).",
+            format,
+            dimension.0,
+            dimension.1,
+            expected,
+            data.len()
+        ));
+    }
+
+    let Dimensions(width, height) = dimension;
+    let luma = (width * height) as usize;
+    let chroma_width = width.div_ceil(2) as usize;
+    let chroma = chroma_width * height.div_ceil(2) as usize;
+
+    Ok(image::RgbaImage::from_fn(width, height, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        let (chroma_x, chroma_y) = (x / 2, y / 2);
+        match format {
+            RawFormat::Rgb24 => {
+                let i = (y * width as usize + x) * 3;
+                image::Rgba([data[i], data[i + 1], data[i + 2], 255])
+            }
+            RawFormat::Yuv420p => {
+                let i = chroma_y * chroma_width + chroma_x;
+                yuv_to_rgba(
+                    data[y * width as usize + x],
+                    data[luma + i],
+                    data[luma + chroma + i],
+                )
+            }
+            RawFormat::Nv12 => {
+                let i = luma + (chroma_y * chroma_width + chroma_x) * 2;
+                yuv_to_rgba(data[y * width as usize + x], data[i], data[i + 1])
+            }
+        }
+    }))
+}
+
+/// Convert the (limited range) BT.601 YUV to RGBA.
+fn yuv_to_rgba(y: u8, u: u8, v: u8) -> image::Rgba<u8> {
+    let (c, d, e) = (y as i32 - 16, u as i32 - 128, v as i32 - 128);
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    image::Rgba([
+        clamp(298 * c + 409 * e),
+        clamp(298 * c - 100 * d - 208 * e),
+        clamp(298 * c + 516 * d),
+        255,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_size() {
+        assert_eq!(Ok(Dimensions(1920, 1080)), parse_size("1920x1080"));
+        assert!(parse_size("1920").is_err());
+        assert!(parse_size("0x1080").is_err());
+    }
+
+    #[test]
+    fn should_decode_rgb24() {
+        let data = [1, 2, 3, 4, 5, 6];
+        let img = decode(&data, RawFormat::Rgb24, Dimensions(2, 1)).unwrap();
+
+        assert_eq!(image::Rgba([1, 2, 3, 255]), *img.get_pixel(0, 0));
+        assert_eq!(image::Rgba([4, 5, 6, 255]), *img.get_pixel(1, 0));
+    }
+
+    #[test]
+    fn should_decode_yuv420p_and_nv12_to_the_same_image() {
+        // Note: 2x2 frame - white & black luma, with a single chroma sample
+        let yuv420p = [235, 16, 16, 235, 128, 128];
+        let nv12 = [235, 16, 16, 235, 128, 128];
+
+        let img = decode(&yuv420p, RawFormat::Yuv420p, Dimensions(2, 2)).unwrap();
+        assert_eq!(image::Rgba([255, 255, 255, 255]), *img.get_pixel(0, 0));
+        assert_eq!(image::Rgba([0, 0, 0, 255]), *img.get_pixel(1, 0));
+        assert_eq!(
+            img,
+            decode(&nv12, RawFormat::Nv12, Dimensions(2, 2)).unwrap()
+        );
+    }
+
+    #[test]
+    fn should_fail_for_too_small_frames() {
+        assert!(decode(&[0; 5], RawFormat::Rgb24, Dimensions(2, 1)).is_err());
+    }
+}
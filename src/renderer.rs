@@ -0,0 +1,750 @@
+//! Rendering of the report of a comparison, pluggable via the `ReportRenderer` trait.
+//!
+//! The built-in renderers (`text`, `json`, `junit`, `html` & `github`) are selectable with the `format` option of
+//! the `report` command, and custom renderers can be registered by name (with `register`):
+//!
+//! ```no_run
+//! use idiff::renderer::{Report, ReportRenderer};
+//!
+//! struct Csv;
+//!
+//! impl ReportRenderer for Csv {
+//!     fn render(&self, report: &Report) -> Result<String, String> {
+//!         Ok(format!(
+//!             "{},{},{}\n",
+//!             report.src.display(),
+//!             report.tgt.display(),
+//!             report.result.stats.diff_percentage
+//!         ))
+//!     }
+//! }
+//!
+//! idiff::renderer::register("csv", Csv).unwrap();
+//! idiff::run();
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use colored::*;
+
+use crate::gallery::escape;
+use crate::{cluster, i18n, percent, Bounds, Comparison, DiffResult, Metric, Region, Warning};
+
+/// Names of the built-in renderers.
+pub const BUILT_IN: [&str; 5] = ["text", "json", "junit", "html", "github"];
+
+/// Represents the report of a comparison, as rendered.
+pub struct Report<'a> {
+    /// source file name.
+    pub src: &'a Path,
+    /// target file name.
+    pub tgt: &'a Path,
+    /// result of the comparison.
+    pub result: &'a DiffResult,
+    /// the comparison itself (for the details of the text report, which are not in the result).
+    pub(crate) comparison: &'a Comparison,
+}
+
+impl<'a> Report<'a> {
+    pub(crate) fn new(
+        src: &'a Path,
+        tgt: &'a Path,
+        result: &'a DiffResult,
+        comparison: &'a Comparison,
+    ) -> Report<'a> {
+        Report {
+            src,
+            tgt,
+            result,
+            comparison,
+        }
+    }
+
+    /// Summary of the difference (as the first line of the text report).
+    pub fn summary(&self) -> String {
+        format!(
+            "A difference of '{}' is observed between images.",
+            percent::render(self.result.stats.diff_percentage, None)
+        )
+    }
+}
+
+/// Renders the report of a comparison (printed into stdout by the `report` command).
+pub trait ReportRenderer: Send + Sync {
+    /// Render the report.
+    fn render(&self, report: &Report) -> Result<String, String>;
+}
+
+/// Renders the human readable report (the default): the summary printed by the `compare` command, followed by the
+/// regions (& clusters) with difference.
+pub struct Text;
+
+impl ReportRenderer for Text {
+    fn render(&self, report: &Report) -> Result<String, String> {
+        let comparison = report.comparison;
+        let mut text = Text::summary(comparison);
+        text.push_str(&format!(
+            "{} region(s) with difference (block size: {}):\n",
+            comparison.bounds_with_diff.len(),
+            crate::block_size_text(comparison.block_shape)
+        ));
+        for bound in &comparison.bounds_with_diff {
+            text.push_str(&format!("  {}\n", bound));
+        }
+        if let Some(clusters) = &comparison.clusters {
+            text.push_str(&format!("{} cluster(s) with difference:\n", clusters.len()));
+            for cluster in clusters {
+                text.push_str(&format!("  {}\n", cluster));
+            }
+        }
+        Ok(text)
+    }
+}
+
+impl Text {
+    /// Render the summary of the comparison (printed by the `compare` command).
+    ///
+    /// Note: the difference of a target detected as transformed (and not undone) is left out, as not meaningful
+    pub(crate) fn summary(comparison: &Comparison) -> String {
+        let mut text = warnings_text(&comparison.warnings);
+        if let Some(message) = transform_message(comparison) {
+            text.push_str(&format!("{}\n", message.yellow()));
+            if matches!(comparison.transform, Some((_, false))) {
+                return text;
+            }
+        }
+        if let Some(message) = found_message(comparison) {
+            text.push_str(&format!("{}\n", message));
+        }
+        text.push_str(&approximation_text(comparison));
+        text.push_str(&size_mismatch_text(comparison));
+        text.push_str(&metadata_changes_text(comparison));
+        text.push_str(&metrics_text(comparison));
+        if comparison.diff == 0.0 {
+            let identical = i18n::text("compare.identical", &[]);
+            text.push_str(&format!("{}\n", identical.green()));
+            return text;
+        }
+
+        let diff = percent::render(comparison.diff, Some(5)).red();
+        text.push_str(&format!("{}\n", i18n::text("compare.difference", &[&diff])));
+        text.push_str(&format!(
+            "{}\n",
+            i18n::text("compare.differing-pixels", &[&comparison.differing_pixels])
+        ));
+        if let Some(message) = changed_area_message(comparison) {
+            text.push_str(&format!("{}\n", message));
+        }
+        text.push_str(&details_text(comparison));
+        text
+    }
+
+    /// Render the result of a pair of a batch (or of a mip level / an embedded image), by its name: its warnings &
+    /// percentage difference, followed by the details of the difference (if compared, instead of cached).
+    pub(crate) fn pair(name: &str, diff: f32, comparison: Option<&Comparison>) -> String {
+        let mut text = String::new();
+        if let Some(comparison) = comparison {
+            for warning in &comparison.warnings {
+                let warning = i18n::text("warning", &[warning]);
+                text.push_str(&format!("{}: {}\n", name, warning.yellow()));
+            }
+            for change in comparison.metadata_changes.iter().flatten() {
+                text.push_str(&format!("{}: {}\n", name, change));
+            }
+            if let Some(message) = transform_message(comparison) {
+                text.push_str(&format!("{}: {}\n", name, message.yellow()));
+                if matches!(comparison.transform, Some((_, false))) {
+                    return text;
+                }
+            }
+            if let Some(message) = found_message(comparison) {
+                text.push_str(&format!("{}: {}\n", name, message));
+            }
+        }
+        if diff == 0.0 {
+            let identical = i18n::text("batch.no-difference", &[]);
+            text.push_str(&format!("{}: {}\n", name, identical.green()));
+            return text;
+        }
+
+        text.push_str(&format!(
+            "{}: {}\n",
+            name,
+            percent::render(diff, None).red()
+        ));
+        if let Some(comparison) = comparison {
+            text.push_str(&metrics_text(comparison));
+            text.push_str(&details_text(comparison));
+        }
+        text
+    }
+}
+
+/// Lines of the details of the difference (each only if requested).
+fn details_text(comparison: &Comparison) -> String {
+    [
+        named_regions_text(comparison),
+        text_changes_text(comparison),
+        cluster_counts_text(comparison),
+        pixel_changes_text(comparison),
+        salient_diff_text(comparison),
+        block_histogram_text(comparison),
+        directions_text(comparison),
+    ]
+    .concat()
+}
+
+/// Renders the result as json (requires the `serde` feature).
+pub struct Json;
+
+impl ReportRenderer for Json {
+    #[cfg(feature = "serde")]
+    fn render(&self, report: &Report) -> Result<String, String> {
+        serde_json::to_string_pretty(report.result)
+            .map(|json| format!("{}\n", json))
+            .map_err(|e| format!("Encountered error while writing json: {}", e))
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn render(&self, _report: &Report) -> Result<String, String> {
+        Err(String::from(
+            "Rendering the report as json requires the 'serde' feature.",
+        ))
+    }
+}
+
+/// Renders the report as a JUnit test suite (of a single test case, failed if a difference is observed), for the
+/// test report viewers of CI.
+pub struct Junit;
+
+impl ReportRenderer for Junit {
+    fn render(&self, report: &Report) -> Result<String, String> {
+        let result = report.result;
+        let different = result.stats.diff_percentage != 0.0;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"idiff\" tests=\"1\" failures=\"{}\">\n",
+            different as u8
+        ));
+        xml.push_str(&format!(
+            "  <testcase classname=\"idiff\" name=\"{}\">\n",
+            escape(&pair_name(report))
+        ));
+        if different {
+            let mut details = format!(
+                "{} differing pixel(s), {} region(s) with difference:",
+                result.stats.differing_pixels,
+                result.regions.len()
+            );
+            for region in &result.regions {
+                details.push_str(&format!("\n  {}", describe(region)));
+            }
+            xml.push_str(&format!(
+                "    <failure message=\"{}\">{}</failure>\n",
+                escape(&report.summary()),
+                escape(&details)
+            ));
+        }
+        for warning in &result.warnings {
+            xml.push_str(&format!(
+                "    <system-out>{}</system-out>\n",
+                escape(&i18n::text("warning", &[warning]))
+            ));
+        }
+        xml.push_str("  </testcase>\n</testsuite>\n");
+        Ok(xml)
+    }
+}
+
+/// Renders the report as a standalone html page.
+pub struct Html;
+
+impl ReportRenderer for Html {
+    fn render(&self, report: &Report) -> Result<String, String> {
+        let result = report.result;
+        let stats = &result.stats;
+        let mut rows = String::new();
+        for region in &result.regions {
+            rows.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                region.x, region.y, region.width, region.height
+            ));
+        }
+        let warnings: String = result
+            .warnings
+            .iter()
+            .map(|w| format!("<li>{}</li>\n", escape(&i18n::text("warning", &[w]))))
+            .collect();
+        Ok(format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>idiff report</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+.different {{ color: #c00; }}
+.identical {{ color: #080; }}
+</style>
+</head>
+<body>
+<h1>idiff report</h1>
+<p>{} &rarr; {}</p>
+<p class=\"{}\">{}</p>
+<ul>
+<li>differing pixels: {}</li>
+<li>source dimensions: {}x{}</li>
+<li>target dimensions: {}x{}</li>
+</ul>
+<ul>
+{}</ul>
+<h2>{} region(s) with difference</h2>
+<table>
+<thead><tr><th>X</th><th>Y</th><th>Width</th><th>Height</th></tr></thead>
+<tbody>
+{}</tbody>
+</table>
+</body>
+</html>
+",
+            escape(&report.src.display().to_string()),
+            escape(&report.tgt.display().to_string()),
+            match stats.diff_percentage == 0.0 {
+                true => "identical",
+                false => "different",
+            },
+            escape(&report.summary()),
+            stats.differing_pixels,
+            stats.src_dimensions.0,
+            stats.src_dimensions.1,
+            stats.tgt_dimensions.0,
+            stats.tgt_dimensions.1,
+            warnings,
+            result.regions.len(),
+            rows
+        ))
+    }
+}
+
+/// Renders the report as GitHub Actions workflow commands: an error annotation of the target if a difference is
+/// observed (a notice otherwise) & a warning annotation per warning.
+pub struct GitHub;
+
+impl ReportRenderer for GitHub {
+    fn render(&self, report: &Report) -> Result<String, String> {
+        let result = report.result;
+        let file = github_property(&report.tgt.display().to_string());
+        let mut commands = String::new();
+        for warning in &result.warnings {
+            commands.push_str(&format!(
+                "::warning file={}::{}\n",
+                file,
+                github_data(&i18n::text("warning", &[warning]))
+            ));
+        }
+        let (command, message) = match result.stats.diff_percentage == 0.0 {
+            true => (
+                "notice",
+                format!("No difference observed with {}.", report.src.display()),
+            ),
+            false => (
+                "error",
+                format!(
+                    "{} ({}: {} region(s) with difference)",
+                    report.summary(),
+                    report.src.display(),
+                    result.regions.len()
+                ),
+            ),
+        };
+        commands.push_str(&format!(
+            "::{} file={},title=idiff::{}\n",
+            command,
+            file,
+            github_data(&message)
+        ));
+        Ok(commands)
+    }
+}
+
+/// Name of the compared pair (`SOURCE -> TARGET`).
+fn pair_name(report: &Report) -> String {
+    format!("{} -> {}", report.src.display(), report.tgt.display())
+}
+
+/// Describe the region (its position & size).
+fn describe(region: &Region) -> String {
+    format!(
+        "x: {}, y: {}, width: {}, height: {}",
+        region.x, region.y, region.width, region.height
+    )
+}
+
+/// Escape the message of a workflow command.
+fn github_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape the property (for ex. the file) of a workflow command.
+fn github_property(text: &str) -> String {
+    github_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Message of the detected transform of the target (if any).
+fn transform_message(comparison: &Comparison) -> Option<String> {
+    comparison.transform.map(|(transform, undone)| match undone {
+        true => format!(
+            "The target is the source {}, compared after undoing it.",
+            transform
+        ),
+        false => format!(
+            "The target is the source {} (try with 'undo-transform' flag to compare after undoing it).",
+            transform
+        ),
+    })
+}
+
+/// Message about the position the source is found at in the target (if searched).
+fn found_message(comparison: &Comparison) -> Option<String> {
+    comparison.found_at.map(|(x, y)| {
+        format!(
+            "The source is found in the target at {}, {} (compared with the matched area).",
+            x, y
+        )
+    })
+}
+
+/// Message about the bounding box of all the changes (if any).
+fn changed_area_message(comparison: &Comparison) -> Option<String> {
+    Bounds::enclosing(&comparison.bounds_with_diff).map(|area| {
+        let area = Region::from(&area);
+        format!(
+            "All the changes are within {}x{} at {}, {}.",
+            area.width, area.height, area.x, area.y
+        )
+    })
+}
+
+/// Lines telling that the result is approximate (if previewed, sampled or only the regions were found).
+fn approximation_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    if let Some((rate, margin)) = comparison.sample {
+        let message = format!(
+            "Approximate result (sampled {}% of the pixels, ±{:.3}% at 95% confidence).",
+            rate * 100.0,
+            margin
+        );
+        text.push_str(&format!("{}\n", message.yellow()));
+    }
+    if comparison.regions_only {
+        let message = "Approximate result (regions only, the percentage is of the area of the blocks with difference).";
+        text.push_str(&format!("{}\n", message.yellow()));
+    }
+    if let Some(scale) = comparison.preview_scale {
+        let message = format!(
+            "Approximate result (compared at {}% scale, regions are in the downscaled coordinates).",
+            scale * 100.0
+        );
+        text.push_str(&format!("{}\n", message.yellow()));
+    }
+    text
+}
+
+/// Lines of the requested metrics of the difference.
+fn metrics_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    for (metric, value) in &comparison.metrics {
+        match metric {
+            Metric::Psnr => text.push_str(&format!("{}: {:.2} dB\n", metric, value)),
+            Metric::Mse | Metric::Mae => text.push_str(&format!("{}: {:.4}\n", metric, value)),
+        }
+    }
+    text
+}
+
+/// Lines of the prevailing direction of the difference of every region (block) with difference (if requested).
+fn directions_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    for (i, (bound, direction)) in comparison
+        .bounds_with_diff
+        .iter()
+        .zip(comparison.directions.iter().flatten())
+        .enumerate()
+    {
+        text.push_str(&format!("  region {} ({}): {}\n", i + 1, bound, direction));
+    }
+    text
+}
+
+/// Line of the percentage difference weighted by the visual saliency (if requested).
+fn salient_diff_text(comparison: &Comparison) -> String {
+    comparison
+        .salient_diff
+        .map(|diff| {
+            format!(
+                "Salient difference: {}% (weighted by the visual saliency)\n",
+                diff
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Line of the differing pixels classified by their presence (if requested).
+fn pixel_changes_text(comparison: &Comparison) -> String {
+    comparison
+        .pixel_changes
+        .map(|changes| format!("{}\n", changes))
+        .unwrap_or_default()
+}
+
+/// Lines of the distribution of the percentage of differing pixels of the blocks with difference (if requested).
+fn block_histogram_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    if let Some(histogram) = &comparison.block_histogram {
+        text.push_str("Blocks with difference by percentage of differing pixels:\n");
+        let max = histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (i, count) in histogram.iter().enumerate() {
+            text.push_str(&format!(
+                "  {:>3}-{:>3}%: {:>6} {}\n",
+                i * 10,
+                (i + 1) * 10,
+                count,
+                "#".repeat(count * 40 / max)
+            ));
+        }
+    }
+    text
+}
+
+/// Lines of the regions with different text (if recognized).
+fn text_changes_text(comparison: &Comparison) -> String {
+    comparison
+        .text_changes
+        .iter()
+        .map(|change| format!("  {}\n", change))
+        .collect()
+}
+
+/// Lines of the percentage difference within every named region of the layout (if provided).
+fn named_regions_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    for (name, diff) in comparison.named_regions.iter().flatten() {
+        let changed = format!("{}% changed", diff);
+        if *diff == 0.0 {
+            text.push_str(&format!("  {}: {}\n", name, changed.green()));
+        } else {
+            text.push_str(&format!("  {}: {}\n", name, changed.red()));
+        }
+    }
+    text
+}
+
+/// Lines of the warnings of the comparison.
+pub(crate) fn warnings_text(warnings: &[Warning]) -> String {
+    warnings
+        .iter()
+        .map(|warning| format!("{}\n", i18n::text("warning", &[warning]).yellow()))
+        .collect()
+}
+
+/// Lines of the regions only present in one of the images (when dimensions are different).
+fn size_mismatch_text(comparison: &Comparison) -> String {
+    let mut text = String::new();
+    for (name, bounds) in [
+        ("src", &comparison.only_in_src),
+        ("tgt", &comparison.only_in_tgt),
+    ] {
+        for bound in bounds {
+            text.push_str(&format!(
+                "{}\n",
+                format!("Only in {}: {}", name, bound).yellow()
+            ));
+        }
+    }
+    text
+}
+
+/// Line of the number of clusters per severity (if requested).
+fn cluster_counts_text(comparison: &Comparison) -> String {
+    comparison
+        .clusters
+        .as_ref()
+        .map(|clusters| {
+            let (minor, moderate, major) = cluster::count_by_severity(clusters);
+            format!(
+                "{} cluster(s) with difference (minor: {}, moderate: {}, major: {}).\n",
+                clusters.len(),
+                minor,
+                moderate.to_string().yellow(),
+                major.to_string().red()
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Lines of the differences in the metadata (if compared).
+fn metadata_changes_text(comparison: &Comparison) -> String {
+    match &comparison.metadata_changes {
+        Some(changes) if changes.is_empty() => {
+            format!("{}\n", "No difference observed in the metadata.".green())
+        }
+        Some(changes) => {
+            let mut text = format!("{}\n", "Differences observed in the metadata:".yellow());
+            for change in changes {
+                text.push_str(&format!("  {}\n", change));
+            }
+            text
+        }
+        None => String::new(),
+    }
+}
+
+/// Registry of the custom renderers (by name).
+fn registry() -> &'static RwLock<BTreeMap<String, Arc<dyn ReportRenderer>>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, Arc<dyn ReportRenderer>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(BTreeMap::new()))
+}
+
+/// Register a custom renderer, selectable by its name with the `format` option of the `report` command.
+///
+/// Returns an error if the name is of a built-in renderer.
+pub fn register(name: &str, renderer: impl ReportRenderer + 'static) -> Result<(), String> {
+    if BUILT_IN.contains(&name) {
+        return Err(format!("'{}' is a built-in renderer.", name));
+    }
+    registry()
+        .write()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(renderer));
+    Ok(())
+}
+
+/// Get the renderer by its name.
+pub(crate) fn get(name: &str) -> Result<Arc<dyn ReportRenderer>, String> {
+    let renderer: Arc<dyn ReportRenderer> = match name {
+        "text" => Arc::new(Text),
+        "json" => Arc::new(Json),
+        "junit" => Arc::new(Junit),
+        "html" => Arc::new(Html),
+        "github" => Arc::new(GitHub),
+        _ => {
+            return registry()
+                .read()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .ok_or_else(|| {
+                    let registered = registry()
+                        .read()
+                        .unwrap()
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    format!(
+                        "unknown report format '{}' (built-in: {}; registered: {}).",
+                        name,
+                        BUILT_IN.join(", "),
+                        if registered.is_empty() {
+                            String::from("none")
+                        } else {
+                            registered.join(", ")
+                        }
+                    )
+                })
+        }
+    };
+    Ok(renderer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compare_decoded, diff_result, CompareOptions, Encoded};
+
+    fn rendered(format: &str, tgt: image::RgbaImage) -> String {
+        let options = CompareOptions::builder().block(10).build().unwrap();
+        let src = image::RgbaImage::new(20, 20);
+        let comparison =
+            compare_decoded(src, tgt, &options, Encoded::default(), Vec::new()).unwrap();
+        let result = diff_result(&comparison, &options);
+        let report = Report::new(
+            Path::new("a.png"),
+            Path::new("b,1.png"),
+            &result,
+            &comparison,
+        );
+        get(format).unwrap().render(&report).unwrap()
+    }
+
+    #[test]
+    fn should_render_the_report_in_every_built_in_format() {
+        let mut tgt = image::RgbaImage::new(20, 20);
+        tgt.put_pixel(15, 5, image::Rgba([255, 0, 0, 255]));
+
+        let junit = rendered("junit", tgt.clone());
+        assert!(junit.contains("<testsuite name=\"idiff\" tests=\"1\" failures=\"1\">"));
+        assert!(junit.contains("<testcase classname=\"idiff\" name=\"a.png -&gt; b,1.png\">"));
+        assert!(junit.contains("x: 10, y: 0, width: 10, height: 10</failure>"));
+
+        let github = rendered("github", tgt.clone());
+        assert!(github.starts_with("::error file=b%2C1.png,title=idiff::A difference of"));
+        assert!(github.ends_with("(a.png: 1 region(s) with difference)\n"));
+
+        let html = rendered("html", tgt.clone());
+        assert!(html.contains("<tr><td>10</td><td>0</td><td>10</td><td>10</td></tr>"));
+
+        let text = rendered("text", tgt);
+        assert!(text.contains("1 region(s) with difference (block size: 10):\n"));
+
+        let identical = image::RgbaImage::new(20, 20);
+        assert!(rendered("junit", identical.clone()).contains("failures=\"0\""));
+        assert!(rendered("github", identical).starts_with("::notice file="));
+    }
+
+    #[test]
+    fn should_render_the_summary_and_the_pairs_as_text() {
+        colored::control::set_override(false);
+        let options = CompareOptions::builder().block(10).build().unwrap();
+        let mut tgt = image::RgbaImage::new(20, 20);
+        tgt.put_pixel(15, 5, image::Rgba([255, 0, 0, 255]));
+        let comparison = compare_decoded(
+            image::RgbaImage::new(20, 20),
+            tgt,
+            &options,
+            Encoded::default(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let summary = Text::summary(&comparison);
+        assert!(summary.contains("A difference of '0.25%' is observed between images.\n"));
+        assert!(summary.contains("1 pixel(s) with difference.\n"));
+        assert_eq!(
+            "b.png: 0.25%\n",
+            Text::pair("b.png", comparison.diff, Some(&comparison))
+        );
+        assert_eq!("b.png: no difference\n", Text::pair("b.png", 0.0, None));
+    }
+
+    #[test]
+    fn should_select_registered_renderers() {
+        struct Empty;
+
+        impl ReportRenderer for Empty {
+            fn render(&self, _report: &Report) -> Result<String, String> {
+                Ok(String::new())
+            }
+        }
+
+        assert!(register("text", Empty).is_err());
+        assert!(get("empty").is_err());
+        register("empty", Empty).unwrap();
+        assert!(get("empty").is_ok());
+    }
+}
@@ -0,0 +1,456 @@
+//! Result of comparing two images, shared by the library & the json output of the CLI.
+
+use crate::{Bounds, Comparison, Metric};
+
+/// Version of the schema of the results (`DiffResult::schema_version`), incremented on every incompatible change
+/// (for ex. a removed or renamed field; new fields are compatible).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Represents a rectangular region of an image (in pixels, from the top-left corner).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Region {
+    /// left edge of the region.
+    pub x: u32,
+    /// top edge of the region.
+    pub y: u32,
+    /// width of the region.
+    pub width: u32,
+    /// height of the region.
+    pub height: u32,
+}
+
+impl From<&Bounds> for Region {
+    fn from(bounds: &Bounds) -> Region {
+        Region {
+            x: bounds.min_width,
+            y: bounds.min_height,
+            width: bounds.max_width - bounds.min_width,
+            height: bounds.max_height - bounds.min_height,
+        }
+    }
+}
+
+/// Represents one of the compared images.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Side {
+    /// source image.
+    Src,
+    /// target image.
+    Tgt,
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Src => write!(f, "src"),
+            Side::Tgt => write!(f, "tgt"),
+        }
+    }
+}
+
+/// Represents a transform of the source image the target matches (instead of the source itself).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Transform {
+    /// rotated by 90° clockwise.
+    Rotate90,
+    /// rotated by 180°.
+    Rotate180,
+    /// rotated by 270° clockwise (90° counterclockwise).
+    Rotate270,
+    /// mirrored left to right.
+    FlipHorizontal,
+    /// mirrored top to bottom.
+    FlipVertical,
+    /// mirrored along the top-left to bottom-right diagonal.
+    Transpose,
+    /// mirrored along the top-right to bottom-left diagonal.
+    Transverse,
+}
+
+impl std::fmt::Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transform::Rotate90 => write!(f, "rotated by 90° clockwise"),
+            Transform::Rotate180 => write!(f, "rotated by 180°"),
+            Transform::Rotate270 => write!(f, "rotated by 270° clockwise"),
+            Transform::FlipHorizontal => write!(f, "mirrored horizontally"),
+            Transform::FlipVertical => write!(f, "mirrored vertically"),
+            Transform::Transpose => write!(f, "transposed"),
+            Transform::Transverse => write!(f, "transversed"),
+        }
+    }
+}
+
+/// Represents the prevailing direction of the difference of the changed pixels of a region.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Direction {
+    /// mostly brighter (for ex. a removed shadow).
+    Brighter,
+    /// mostly darker (for ex. a changed shadow).
+    Darker,
+    /// mostly a different color of a similar brightness.
+    HueShifted,
+    /// no prevailing direction (for ex. replaced content).
+    Mixed,
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Brighter => write!(f, "mostly brighter"),
+            Direction::Darker => write!(f, "mostly darker"),
+            Direction::HueShifted => write!(f, "mostly hue shifted"),
+            Direction::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+/// Represents the differing pixels classified by their presence in the images.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PixelChanges {
+    /// number of pixels transparent in the source, but not in the target.
+    pub added: u64,
+    /// number of pixels transparent in the target, but not in the source.
+    pub removed: u64,
+    /// number of the other differing pixels (present in both the images).
+    pub changed: u64,
+}
+
+impl std::fmt::Display for PixelChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} pixel(s) added, {} removed, {} changed",
+            self.added, self.removed, self.changed
+        )
+    }
+}
+
+/// Represents a behavior of the comparison which can change the result without failing it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Warning {
+    /// the images have different dimensions (& only the common region is compared, as not strict).
+    DimensionMismatch {
+        /// dimensions (width, height) of the source image.
+        src: (u32, u32),
+        /// dimensions (width, height) of the target image.
+        tgt: (u32, u32),
+    },
+    /// the image has an ICC profile, which is ignored (the pixels are compared as is).
+    IccProfileIgnored(Side),
+    /// the image has more pages / frames, but only the first one is compared.
+    FramesIgnored(Side),
+    /// the image was scaled to the common physical resolution (DPI aware comparison).
+    Rescaled {
+        /// rescaled image.
+        side: Side,
+        /// dimensions (width, height) of the (upright) image.
+        from: (u32, u32),
+        /// dimensions (width, height) of the rescaled image.
+        to: (u32, u32),
+    },
+    /// the output was written with a different extension than requested (unsupported format).
+    OutputExtensionChanged {
+        /// requested output file name.
+        requested: String,
+        /// written output file name.
+        written: String,
+    },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::DimensionMismatch { src, tgt } => write!(
+                f,
+                "dimensions are different (src: {}x{}, tgt: {}x{}), only the common region is compared",
+                src.0, src.1, tgt.0, tgt.1
+            ),
+            Warning::IccProfileIgnored(side) => write!(
+                f,
+                "ICC profile of the {} image is ignored (pixels are compared as is)",
+                side
+            ),
+            Warning::FramesIgnored(side) => write!(
+                f,
+                "only the first page / frame of the {} image is compared (select another with PATH#INDEX)",
+                side
+            ),
+            Warning::Rescaled { side, from, to } => write!(
+                f,
+                "{} image is rescaled from {}x{} to {}x{} (to the common DPI)",
+                side, from.0, from.1, to.0, to.1
+            ),
+            Warning::OutputExtensionChanged { requested, written } => write!(
+                f,
+                "output {} is written as {} (unsupported extension)",
+                requested, written
+            ),
+        }
+    }
+}
+
+/// Represents a region with different (recognized) text in the images.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TextChange {
+    /// region of the text (the changed region, padded by a block).
+    pub region: Region,
+    /// text in the source image.
+    pub before: String,
+    /// text in the target image.
+    pub after: String,
+}
+
+impl std::fmt::Display for TextChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "text changed from \"{}\" to \"{}\" ({}x{} at {}, {})",
+            self.before,
+            self.after,
+            self.region.width,
+            self.region.height,
+            self.region.x,
+            self.region.y
+        )
+    }
+}
+
+/// Represents the time taken by a step of the comparison.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Timing {
+    /// name of the step (for ex. `decode` or `diff`).
+    pub step: String,
+    /// time taken, in seconds.
+    pub seconds: f64,
+}
+
+/// Represents the summary of a comparison.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DiffStats {
+    /// percentage difference between the images.
+    pub diff_percentage: f32,
+    /// number of pixels with difference (estimated, if sampled; including the regions only present in one of
+    /// the images, if counted).
+    pub differing_pixels: u64,
+    /// number of regions (blocks) with difference.
+    pub regions_with_diff: usize,
+    /// dimensions (width, height) of the source image.
+    pub src_dimensions: (u32, u32),
+    /// dimensions (width, height) of the target image.
+    pub tgt_dimensions: (u32, u32),
+    /// factor the images were downscaled by before the comparison (if previewed, the result is approximate
+    /// & the dimensions / regions are of the downscaled images).
+    pub preview_scale: Option<f32>,
+    /// fraction of the pixels compared (if sampled, the result is extrapolated).
+    pub sample_rate: Option<f32>,
+    /// margin of error of the percentage difference (percentage points, at 95% confidence), if sampled.
+    pub sample_margin: Option<f32>,
+    /// whether only the blocks with difference were found (the differing pixels are the area of the blocks, instead
+    /// of counted).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regions_only: bool,
+    /// mean squared error of the RGB channels (if requested).
+    pub mse: Option<f64>,
+    /// peak signal-to-noise ratio in dB (if requested, infinite for identical images).
+    pub psnr: Option<f64>,
+    /// mean absolute error of the RGB channels (if requested).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mae: Option<f64>,
+    /// differing pixels classified as added, removed or changed (if requested).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pixel_changes: Option<PixelChanges>,
+    /// percentage difference weighted by the visual saliency of the source (if requested): higher than
+    /// `diff_percentage` when the differences are in the prominent areas (near the top & the center, of high
+    /// contrast).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub salient_percentage: Option<f32>,
+}
+
+/// Represents the result of comparing two images.
+///
+/// Every list of regions is sorted top to bottom, then left to right.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DiffResult {
+    /// version of the schema of the result (see `SCHEMA_VERSION`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub schema_version: u32,
+    /// summary of the comparison.
+    pub stats: DiffStats,
+    /// regions (blocks) with difference.
+    pub regions: Vec<Region>,
+    /// regions only present in the source image (when dimensions are different).
+    pub only_in_src: Vec<Region>,
+    /// regions only present in the target image (when dimensions are different).
+    pub only_in_tgt: Vec<Region>,
+    /// differences in the metadata (if compared), formatted as `+ key: value`, `- key: value` or `~ key: old -> new`.
+    pub metadata_changes: Option<Vec<String>>,
+    /// behaviors of the comparison which can change the result (for ex. ignored frames).
+    pub warnings: Vec<Warning>,
+    /// regions with different text (if recognized, with the `ocr` feature).
+    pub text_changes: Vec<TextChange>,
+    /// number of regions (blocks) with difference per 10% bucket of their percentage of differing pixels (if
+    /// requested): the first bucket is above 0% up to 10%, the last above 90% up to 100%.
+    pub block_histogram: Option<Vec<usize>>,
+    /// time taken by every step of the comparison (if requested).
+    pub timings: Option<Vec<Timing>>,
+    /// transform of the source the target matches (if detected); the images are compared after undoing it, if
+    /// requested.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transform: Option<Transform>,
+    /// prevailing direction of the difference of every region with difference (if requested, in the same order
+    /// as `regions`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub directions: Option<Vec<Direction>>,
+    /// position (x, y) of the top-left corner of the source within the target (if searched); the regions are of
+    /// the matched area.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub found_at: Option<(u32, u32)>,
+    /// bounding box of all the regions with difference (if any), to tell roughly where the images changed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub changed_area: Option<Region>,
+}
+
+impl From<&Comparison> for DiffResult {
+    fn from(comparison: &Comparison) -> DiffResult {
+        let regions = |bounds: &[Bounds]| bounds.iter().map(Region::from).collect();
+        let metric = |metric| {
+            comparison
+                .metrics
+                .iter()
+                .find(|(m, _)| *m == metric)
+                .map(|(_, value)| *value)
+        };
+        DiffResult {
+            schema_version: SCHEMA_VERSION,
+            stats: DiffStats {
+                diff_percentage: comparison.diff,
+                differing_pixels: comparison.differing_pixels,
+                regions_with_diff: comparison.bounds_with_diff.len(),
+                src_dimensions: comparison.src.dimensions(),
+                tgt_dimensions: comparison.tgt.dimensions(),
+                preview_scale: comparison.preview_scale,
+                sample_rate: comparison.sample.map(|(rate, _)| rate),
+                sample_margin: comparison.sample.map(|(_, margin)| margin),
+                regions_only: comparison.regions_only,
+                mse: metric(Metric::Mse),
+                psnr: metric(Metric::Psnr),
+                mae: metric(Metric::Mae),
+                pixel_changes: comparison.pixel_changes,
+                salient_percentage: comparison.salient_diff,
+            },
+            regions: regions(&comparison.bounds_with_diff),
+            only_in_src: regions(&comparison.only_in_src),
+            only_in_tgt: regions(&comparison.only_in_tgt),
+            metadata_changes: comparison
+                .metadata_changes
+                .as_ref()
+                .map(|changes| changes.iter().map(|c| c.to_string()).collect()),
+            warnings: comparison.warnings.clone(),
+            text_changes: comparison.text_changes.clone(),
+            block_histogram: comparison.block_histogram.map(|h| h.to_vec()),
+            // Note: the timings are only included if requested (see `CompareOptions::timings`)
+            timings: None,
+            transform: comparison.transform.map(|(t, _)| t),
+            directions: comparison.directions.clone(),
+            found_at: comparison.found_at,
+            changed_area: Bounds::enclosing(&comparison.bounds_with_diff).map(|b| Region::from(&b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_convert_bounds_into_region() {
+        assert_eq!(
+            Region {
+                x: 10,
+                y: 20,
+                width: 5,
+                height: 10
+            },
+            Region::from(&Bounds::new(10, 15, 20, 30))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_serialize_and_deserialize_result() {
+        let result = DiffResult {
+            schema_version: SCHEMA_VERSION,
+            stats: DiffStats {
+                diff_percentage: 0.5,
+                differing_pixels: 1,
+                regions_with_diff: 1,
+                src_dimensions: (10, 10),
+                tgt_dimensions: (10, 10),
+                preview_scale: None,
+                sample_rate: None,
+                sample_margin: None,
+                regions_only: false,
+                mse: None,
+                psnr: None,
+                mae: Some(1.5),
+                pixel_changes: Some(PixelChanges {
+                    added: 1,
+                    removed: 0,
+                    changed: 0,
+                }),
+                salient_percentage: Some(1.5),
+            },
+            regions: vec![Region::from(&Bounds::new(0, 10, 0, 10))],
+            only_in_src: vec![],
+            only_in_tgt: vec![],
+            metadata_changes: None,
+            warnings: vec![Warning::FramesIgnored(Side::Src)],
+            text_changes: vec![TextChange {
+                region: Region::from(&Bounds::new(0, 10, 0, 10)),
+                before: String::from("Sign in"),
+                after: String::from("Log in"),
+            }],
+            block_histogram: Some(vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            timings: Some(vec![Timing {
+                step: String::from("decode"),
+                seconds: 0.25,
+            }]),
+            transform: Some(Transform::Rotate90),
+            directions: Some(vec![Direction::Darker]),
+            found_at: Some((10, 20)),
+            changed_area: Some(Region::from(&Bounds::new(0, 10, 0, 10))),
+        };
+
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(result, serde_json::from_str(&json).unwrap());
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn should_describe_result_in_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(DiffResult)).unwrap();
+        assert!(schema["properties"]["schema_version"].is_object());
+        assert!(schema["definitions"]["DiffStats"]["properties"]["diff_percentage"].is_object());
+    }
+}
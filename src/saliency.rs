@@ -0,0 +1,159 @@
+//! Visual saliency of the pixels: the differences in the prominent areas of the image (near the top & the
+//! horizontal center, where the eye lands first, and of high local contrast, for ex. text & edges) weigh more in
+//! the salient percentage difference than the ones in the flat margins (for ex. of a footer).
+
+use crate::comparator::Comparator;
+use crate::{Bounds, Region};
+
+/// Size (in pixels) of the square cells the local contrast is measured over.
+const CELL: u32 = 16;
+
+/// Weight of the pixels without contrast, relative to the most contrasted ones.
+const MIN_CONTRAST_WEIGHT: f64 = 0.25;
+
+/// Saliency of every pixel of the image within the bounds (row-major, from the origin of the bounds): the weight
+/// of its position (1 at the top center, down to 0.25 at the bottom corners) by the weight of the contrast of its
+/// cell (the standard deviation of the luma, relative to the most contrasted cell).
+fn map(img: &image::RgbaImage, bounds: &Bounds) -> Vec<f64> {
+    let (width, height) = (
+        bounds.max_width - bounds.min_width,
+        bounds.max_height - bounds.min_height,
+    );
+    let (columns, rows) = (width.div_ceil(CELL), height.div_ceil(CELL));
+    let luma = |x: u32, y: u32| {
+        let p = img.get_pixel(bounds.min_width + x, bounds.min_height + y);
+        0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64
+    };
+
+    let mut contrast = vec![0.0; (columns * rows) as usize];
+    for row in 0..rows {
+        for column in 0..columns {
+            let (mut sum, mut squares, mut count) = (0.0, 0.0, 0.0);
+            for y in row * CELL..((row + 1) * CELL).min(height) {
+                for x in column * CELL..((column + 1) * CELL).min(width) {
+                    let l = luma(x, y);
+                    sum += l;
+                    squares += l * l;
+                    count += 1.0;
+                }
+            }
+            let mean = sum / count;
+            contrast[(row * columns + column) as usize] =
+                (squares / count - mean * mean).max(0.0).sqrt();
+        }
+    }
+    let max = contrast.iter().copied().fold(0.0, f64::max);
+
+    let mut saliency = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        // Note: the relative position (0-1) of the center of the pixel
+        let vertical = 1.0 - 0.5 * (y as f64 + 0.5) / height as f64;
+        for x in 0..width {
+            let offset = 2.0 * (x as f64 + 0.5) / width as f64 - 1.0;
+            let horizontal = 1.0 - 0.5 * offset * offset;
+            let cell = contrast[((y / CELL) * columns + x / CELL) as usize];
+            let relative = if max > 0.0 { cell / max } else { 0.0 };
+            let weight = MIN_CONTRAST_WEIGHT + (1.0 - MIN_CONTRAST_WEIGHT) * relative;
+            saliency.push(horizontal * vertical * weight);
+        }
+    }
+    saliency
+}
+
+/// Percentage difference of the differing pixels (within the bounds, as per the comparator) weighted by the
+/// saliency of the source: the same as the percentage of the differing pixels for a uniformly salient image,
+/// higher when the differences are in its prominent areas.
+///
+/// Note: only the pixels with different values are compared (one by one) by the comparator
+pub(crate) fn percentage(
+    src: &image::RgbaImage,
+    tgt: &image::RgbaImage,
+    bounds: &Bounds,
+    comparator: &dyn Comparator,
+) -> f32 {
+    let saliency = map(src, bounds);
+    let total: f64 = saliency.iter().sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    let mut weighted = 0.0;
+    let mut saliency = saliency.iter();
+    for y in bounds.min_height..bounds.max_height {
+        for x in bounds.min_width..bounds.max_width {
+            let weight = saliency.next().copied().unwrap_or_default();
+            if src.get_pixel(x, y) == tgt.get_pixel(x, y) {
+                continue;
+            }
+            let pixel = Region {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            };
+            if comparator.compare_block(src, tgt, &pixel).differing_pixels != 0 {
+                weighted += weight;
+            }
+        }
+    }
+    (weighted / total * 100.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparator;
+
+    #[test]
+    fn should_weigh_the_differences_near_the_top_center_more() {
+        // Note: a page with text-like stripes in the header & the footer
+        let src = image::RgbaImage::from_fn(100, 100, |x, y| {
+            match !(10..90).contains(&y) && x % 2 == 0 {
+                true => image::Rgba([0, 0, 0, 255]),
+                false => image::Rgba([255, 255, 255, 255]),
+            }
+        });
+        let bounds = Bounds::new(0, 100, 0, 100);
+        let changed = |y: u32| {
+            let mut tgt = src.clone();
+            for x in 40..60 {
+                tgt.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+            }
+            tgt
+        };
+        let exact = comparator::get("exact", None).unwrap();
+
+        let at_header = percentage(&src, &changed(5), &bounds, &*exact);
+        let at_footer = percentage(&src, &changed(95), &bounds, &*exact);
+        // Note: 20 of 10000 pixels differ (0.2%) in either
+        assert!(at_header > 0.2, "{}", at_header);
+        assert!(at_header > at_footer * 1.5, "{} / {}", at_header, at_footer);
+        assert_eq!(0.0, percentage(&src, &src, &bounds, &*exact));
+    }
+
+    #[test]
+    fn should_only_count_the_pixels_differing_as_per_the_comparator() {
+        let src = image::RgbaImage::from_pixel(10, 10, image::Rgba([100, 100, 100, 255]));
+        let mut tgt = src.clone();
+        tgt.put_pixel(5, 5, image::Rgba([102, 100, 100, 255]));
+        let bounds = Bounds::new(0, 10, 0, 10);
+
+        let tolerance = comparator::get("tolerance", Some(5.0)).unwrap();
+        assert_eq!(0.0, percentage(&src, &tgt, &bounds, &*tolerance));
+        let exact = comparator::get("exact", None).unwrap();
+        assert!(percentage(&src, &tgt, &bounds, &*exact) > 0.0);
+    }
+
+    #[test]
+    fn should_weigh_flat_images_by_the_position_only() {
+        let src = image::RgbaImage::new(10, 10);
+        let mut tgt = src.clone();
+        tgt.put_pixel(5, 5, image::Rgba([255, 0, 0, 255]));
+        let bounds = Bounds::new(0, 10, 0, 10);
+
+        // Note: the center pixel weighs a little more than the average pixel
+        let exact = comparator::get("exact", None).unwrap();
+        let salient = percentage(&src, &tgt, &bounds, &*exact);
+        assert!((salient - 1.0).abs() < 0.6, "{}", salient);
+    }
+}
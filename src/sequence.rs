@@ -0,0 +1,177 @@
+//! Numbered frame sequences (for ex. `frame_%04d.png` over the frames `1..500`), paired frame by frame.
+
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+use crate::{FilePair, Unpaired};
+
+/// Represents the (inclusive) range of the frame numbers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct FrameRange {
+    start: u32,
+    end: u32,
+}
+
+/// Parse the range of the frames as `START..END` (inclusive, for ex. `1..500`).
+pub(crate) fn parse_range(range: &str) -> Result<FrameRange, String> {
+    let error = || format!("'{}' must be a range of frames as START..END.", range);
+    let (start, end) = range.split_once("..").ok_or_else(error)?;
+    let (start, end) = (
+        start.parse().map_err(|_| error())?,
+        end.trim_start_matches('=').parse().map_err(|_| error())?,
+    );
+    if start > end {
+        return Err(format!(
+            "the first frame ({}) cannot be after the last frame ({}).",
+            start, end
+        ));
+    }
+    Ok(FrameRange { start, end })
+}
+
+/// Parse the pattern of the frame file names, with a single `%d` (or zero padded `%0Nd`) placeholder of the
+/// frame number.
+pub(crate) fn parse_pattern(pattern: &str) -> Result<String, String> {
+    format(pattern, 0)?;
+    Ok(pattern.to_string())
+}
+
+/// Fill the placeholder of the pattern with the frame number.
+fn format(pattern: &str, frame: u32) -> Result<String, String> {
+    let error = || {
+        format!(
+            "'{}' must have a single %d (or zero padded, for ex. %04d) placeholder of the frame number.",
+            pattern
+        )
+    };
+    let (prefix, rest) = pattern.split_once('%').ok_or_else(error)?;
+    let (width, suffix) = rest.split_once('d').ok_or_else(error)?;
+    if suffix.contains('%') || !width.chars().all(|c| c.is_ascii_digit()) {
+        return Err(error());
+    }
+    let width = width.parse().unwrap_or(0);
+    Ok(format!(
+        "{}{:0width$}{}",
+        prefix,
+        frame,
+        suffix,
+        width = width
+    ))
+}
+
+/// Path of the frame: the directory joined with the pattern of the frame file names, or the path itself as the
+/// pattern (for ex. `renders/frame_%04d.png`).
+fn frame_path(path: &Path, pattern: Option<&str>, frame: u32) -> Result<PathBuf, String> {
+    if path.is_dir() {
+        let pattern = pattern.ok_or_else(|| {
+            format!(
+                "'sequence' is required to name the frames of the directory {}.",
+                path.display()
+            )
+        })?;
+        return Ok(path.join(format(pattern, frame)?));
+    }
+    Ok(PathBuf::from(format(&path.to_string_lossy(), frame)?))
+}
+
+/// Pair the frames of the range of the source & target sequences by their number (the frames missing in both
+/// are skipped).
+///
+/// Returns the pairs & the frames only in one of the sequences, along with the names of all the frames in order.
+pub(crate) fn pair_frames(
+    src: &Path,
+    tgt: &Path,
+    pattern: Option<&str>,
+    range: FrameRange,
+) -> Result<(Vec<FilePair>, Unpaired, Vec<String>), Error> {
+    let name = |path: &Path| {
+        path.file_name().map_or_else(
+            || path.display().to_string(),
+            |n| n.to_string_lossy().to_string(),
+        )
+    };
+    let (mut pairs, mut only_in_src, mut only_in_tgt, mut order) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for frame in range.start..=range.end {
+        let (src_file, tgt_file) = (
+            frame_path(src, pattern, frame)?,
+            frame_path(tgt, pattern, frame)?,
+        );
+        let (src_name, tgt_name) = (name(&src_file), name(&tgt_file));
+        match (src_file.is_file(), tgt_file.is_file()) {
+            (true, true) => {
+                let name = match src_name == tgt_name {
+                    true => src_name,
+                    false => format!("{} -> {}", src_name, tgt_name),
+                };
+                order.push(name.clone());
+                pairs.push((name, src_file, tgt_file));
+            }
+            (true, false) => {
+                order.push(src_name.clone());
+                only_in_src.push(src_name);
+            }
+            (false, true) => {
+                order.push(tgt_name.clone());
+                only_in_tgt.push(tgt_name);
+            }
+            (false, false) => {}
+        }
+    }
+    if order.is_empty() {
+        return Err(Error::io(format!(
+            "No frame of the range {}..{} found in src/tgt. Please check and try again.",
+            range.start, range.end
+        )));
+    }
+    Ok((pairs, (only_in_src, only_in_tgt), order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_ranges_and_patterns() {
+        assert_eq!(Ok(FrameRange { start: 1, end: 500 }), parse_range("1..500"));
+        assert_eq!(Ok(FrameRange { start: 3, end: 3 }), parse_range("3..=3"));
+        assert!(parse_range("5..1").is_err());
+        assert!(parse_range("1-5").is_err());
+
+        assert_eq!(
+            Ok(String::from("frame_0042.png")),
+            format("frame_%04d.png", 42)
+        );
+        assert_eq!(Ok(String::from("f7.png")), format("f%d.png", 7));
+        assert!(parse_pattern("frame.png").is_err());
+        assert!(parse_pattern("%d_%d.png").is_err());
+        assert!(parse_pattern("%4x.png").is_err());
+    }
+
+    #[test]
+    fn should_pair_frames_by_number() {
+        let dir = std::env::temp_dir().join(format!("idiff_sequence_{}", std::process::id()));
+        let (src, tgt) = (dir.join("src"), dir.join("tgt"));
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&tgt).unwrap();
+        for frame in [1, 2, 3] {
+            std::fs::write(src.join(format!("f_{:02}.png", frame)), []).unwrap();
+        }
+        for frame in [1, 2, 4] {
+            std::fs::write(tgt.join(format!("f_{:02}.png", frame)), []).unwrap();
+        }
+
+        let range = parse_range("1..5").unwrap();
+        let (pairs, (only_in_src, only_in_tgt), order) =
+            pair_frames(&src, &tgt, Some("f_%02d.png"), range).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            vec!["f_01.png", "f_02.png"],
+            pairs.iter().map(|(n, _, _)| n.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["f_03.png"], only_in_src);
+        assert_eq!(vec!["f_04.png"], only_in_tgt);
+        assert_eq!(vec!["f_01.png", "f_02.png", "f_03.png", "f_04.png"], order);
+    }
+}
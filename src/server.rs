@@ -0,0 +1,485 @@
+//! HTTP server comparing the uploaded images (the `serve` command, with the `server` feature).
+//!
+//! `POST /compare` returns the result (json) & `POST /highlight` the highlighted target (png) of the `src` &
+//! `tgt` files of the (multipart/form-data) body, compared with the options of the query string (named like the
+//! command line options, for ex. `?block=16&comparator=tolerance&comparator-threshold=8`).
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use clap::{Args, FromArgMatches};
+
+use crate::input::Input;
+use crate::{compare_inputs, diff_result, render_output, CompareOptions, OutputFormat, Palette};
+
+/// Represents a (parsed) HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Represents an HTTP response.
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Json response with the error message.
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: serde_json::json!({ "error": message })
+                .to_string()
+                .into_bytes(),
+        }
+    }
+}
+
+/// Represents the limits of the server, so that a client cannot exhaust its memory or its threads.
+pub(crate) struct Limits {
+    /// largest size (in bytes) of a request body (the uploaded images).
+    pub(crate) max_body_size: usize,
+    /// largest number of pixels of an uploaded image (unless lower in the query).
+    pub(crate) max_pixels: u64,
+    /// largest estimated memory (in MB) of a comparison (unless lower in the query).
+    pub(crate) max_memory: u64,
+    /// time to wait for the request to be read / the response to be written.
+    pub(crate) timeout: Duration,
+    /// number of the connections answered at once (the others wait to be accepted).
+    pub(crate) max_connections: usize,
+}
+
+/// Listen on the address & answer the connections (by a fixed number of threads), until the process is stopped.
+pub(crate) fn serve(address: &str, limits: Limits) -> Result<(), String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Encountered error while listening on {}: {}", address, e))?;
+    if let Ok(address) = listener.local_addr() {
+        println!("Listening on http://{}", address);
+    }
+    let _ = std::io::stdout().flush();
+
+    let limits = Arc::new(limits);
+    // Note: the accepted connections wait (up to one per thread) until a thread is free
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(limits.max_connections);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..limits.max_connections.max(1) {
+        let (receiver, limits) = (receiver.clone(), limits.clone());
+        std::thread::spawn(move || loop {
+            let stream = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return,
+            };
+            match stream {
+                Ok(stream) => handle(stream, &limits),
+                Err(_) => return,
+            }
+        });
+    }
+    for stream in listener.incoming().flatten() {
+        if sender.send(stream).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Answer the request of the connection (closed afterwards).
+fn handle(mut stream: TcpStream, limits: &Limits) {
+    let _ = stream.set_read_timeout(Some(limits.timeout));
+    let _ = stream.set_write_timeout(Some(limits.timeout));
+    let response = match read_request(&mut BufReader::new(&stream), limits.max_body_size) {
+        Ok(request) => respond(&request, limits),
+        Err(response) => response,
+    };
+    let _ = write_response(&mut stream, &response);
+}
+
+/// Read the request line, the headers & the body (of `Content-Length` bytes, up to `max_body_size`).
+fn read_request(reader: &mut impl BufRead, max_body_size: usize) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error(400, message);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|_| bad_request("invalid request."))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("invalid request line."));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        content_type: None,
+        body: Vec::new(),
+    };
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader
+            .read_line(&mut line)
+            .map_err(|_| bad_request("invalid header."))?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(bad_request("invalid header."));
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad_request("invalid content length."))?
+            }
+            "content-type" => request.content_type = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if length > max_body_size {
+        return Err(Response::error(
+            413,
+            &format!("body is larger than {} bytes.", max_body_size),
+        ));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|_| bad_request("incomplete body."))?;
+    Ok(request)
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Unprocessable Content",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    for (name, value) in &response.headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    stream.write_all(b"\r\n")?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn respond(request: &Request, limits: &Limits) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/compare" | "/highlight") => match compare(request, limits) {
+            Ok(response) => response,
+            Err(response) => response,
+        },
+        (_, "/compare" | "/highlight") => Response::error(405, "only POST is allowed."),
+        _ => Response::error(404, "not found (POST /compare or /highlight)."),
+    }
+}
+
+/// Compare the uploaded `src` & `tgt` files with the options of the query string (within the limits).
+fn compare(request: &Request, limits: &Limits) -> Result<Response, Response> {
+    let mut options = parse_options(&request.query).map_err(|e| Response::error(400, &e))?;
+    options.max_pixels = Some(
+        options
+            .max_pixels
+            .unwrap_or(u64::MAX)
+            .min(limits.max_pixels),
+    );
+    options.max_memory = Some(
+        options
+            .max_memory
+            .unwrap_or(u64::MAX)
+            .min(limits.max_memory),
+    );
+    let boundary = request
+        .content_type
+        .as_deref()
+        .filter(|t| t.starts_with("multipart/form-data"))
+        .and_then(|t| t.split_once("boundary="))
+        .map(|(_, boundary)| boundary.trim_matches('"'))
+        .ok_or_else(|| Response::error(400, "body must be multipart/form-data."))?;
+    let parts = multipart(&request.body, boundary);
+    let file = |name: &str| {
+        parts
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, data)| Input::Slice(data))
+            .ok_or_else(|| Response::error(400, &format!("missing '{}' file.", name)))
+    };
+    let (src, tgt) = (file("src")?, file("tgt")?);
+
+    let mut comparison = compare_inputs((&src, None), (&tgt, None), &options, Vec::new())
+        .map_err(|e| Response::error(422, &e.message))?;
+    if request.path == "/compare" {
+        let result = serde_json::to_vec(&diff_result(&comparison, &options))
+            .map_err(|e| Response::error(422, &e.to_string()))?;
+        return Ok(Response {
+            status: 200,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: result,
+        });
+    }
+
+    let mut png = Vec::new();
+    render_output(
+        &mut comparison,
+        false,
+        OutputFormat::Highlight,
+        0.5,
+        Palette::Default,
+    )
+    .map_err(|e| Response::error(422, &e.message))?
+    .write_to(
+        &mut std::io::Cursor::new(&mut png),
+        image::ImageOutputFormat::Png,
+    )
+    .map_err(|e| Response::error(422, &e.to_string()))?;
+    Ok(Response {
+        status: 200,
+        content_type: "image/png",
+        headers: vec![("X-Diff-Percentage", comparison.diff.to_string())],
+        body: png,
+    })
+}
+
+/// Parse the options from the query string, as the command line options (`key=true` for the flags).
+///
+/// Note: every pair is decoded before it is split, so that an encoded `=` cannot hide a value within the key
+fn parse_options(query: &str) -> Result<CompareOptions, String> {
+    let command = CompareOptions::augment_args(clap::Command::new("idiff"));
+    let mut args = vec![String::from("idiff")];
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let pair = decode(pair);
+        let (key, value) = pair.split_once('=').unwrap_or((&pair, "true"));
+        if !is_allowed(&command, key) {
+            return Err(format!("option '{}' is not allowed.", key));
+        }
+        match value {
+            "true" => args.push(format!("--{}", key)),
+            "false" => {}
+            value => args.push(format!("--{}={}", key, value)),
+        }
+    }
+    let matches = command.try_get_matches_from(args).map_err(|e| {
+        // Note: only the first line (the error, without the usage)
+        e.to_string().lines().next().unwrap_or_default().to_string()
+    })?;
+    CompareOptions::from_arg_matches(&matches).map_err(|e| e.to_string())
+}
+
+/// Whether the key is (exactly) the long name of an option of the comparison, other than the options reading /
+/// writing the files of the server: the options with a file name value (`*_FILE_NAME`, for ex.
+/// `--mask <MASK_FILE_NAME>`).
+fn is_allowed(command: &clap::Command, key: &str) -> bool {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key))
+        .is_some_and(|arg| {
+            !arg.get_value_names()
+                .unwrap_or_default()
+                .iter()
+                .any(|name| name.ends_with("FILE_NAME"))
+        })
+}
+
+/// Decode the percent-encoded (& `+` for space) component of the query string.
+fn decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let digit = |b: u8| (b as char).to_digit(16);
+                match digit(bytes[i + 1]).zip(digit(bytes[i + 2])) {
+                    Some((high, low)) => {
+                        decoded.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Split the multipart/form-data body into its (named) parts.
+fn multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<(String, &'a [u8])> {
+    let delimiter = format!("--{}", boundary);
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = match find(body, delimiter.as_bytes()) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return parts,
+    };
+    // Note: the last delimiter is followed by `--`
+    while !rest.starts_with(b"--") {
+        let Some(end) = find(rest, delimiter.as_bytes()) else {
+            break;
+        };
+        let part = rest[..end].strip_prefix(b"\r\n").unwrap_or(&rest[..end]);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+        if let Some(separator) = find(part, b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&part[..separator]);
+            let name = headers
+                .split("name=\"")
+                .nth(1)
+                .and_then(|n| n.split('"').next());
+            if let Some(name) = name {
+                parts.push((name.to_string(), &part[separator + 4..]));
+            }
+        }
+        rest = &rest[end + delimiter.len()..];
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(img: &image::RgbaImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+        bytes
+    }
+
+    fn limits() -> Limits {
+        Limits {
+            max_body_size: 1024 * 1024,
+            max_pixels: 10_000,
+            max_memory: 64,
+            timeout: Duration::from_secs(1),
+            max_connections: 1,
+        }
+    }
+
+    fn request(path: &str, src: &[u8], tgt: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, data) in [("src", src), ("tgt", tgt)] {
+            body.extend_from_slice(b"--XYZ\r\n");
+            body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}.png\"\r\n\r\n",
+                    name, name
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--XYZ--\r\n");
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XYZ\r\nContent-Length: {}\r\n\r\n",
+            path,
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+        request
+    }
+
+    #[test]
+    fn should_compare_uploaded_images() {
+        let src = image::RgbaImage::new(20, 20);
+        let mut tgt = src.clone();
+        tgt.put_pixel(5, 5, image::Rgba([255, 0, 0, 255]));
+        let (src, tgt) = (png(&src), png(&tgt));
+
+        let raw = request("/compare?block=5&percentage-base=overlap", &src, &tgt);
+        let request = read_request(&mut &raw[..], 1024 * 1024).unwrap();
+        let response = respond(&request, &limits());
+        assert_eq!(200, response.status);
+        let result: crate::DiffResult = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(0.25, result.stats.diff_percentage);
+        assert_eq!(1, result.regions.len());
+
+        let raw = self::request("/highlight", &src, &tgt);
+        let response = respond(
+            &read_request(&mut &raw[..], 1024 * 1024).unwrap(),
+            &limits(),
+        );
+        assert_eq!("image/png", response.content_type);
+        assert!(image::load_from_memory(&response.body).is_ok());
+
+        let raw = self::request("/compare?block=0", &src, &tgt);
+        let response = respond(
+            &read_request(&mut &raw[..], 1024 * 1024).unwrap(),
+            &limits(),
+        );
+        assert_eq!(400, response.status);
+        assert!(read_request(&mut &raw[..], 10).is_err());
+    }
+
+    #[test]
+    fn should_decode_query_components() {
+        assert_eq!("0,0,10 20", decode("0%2C0%2C10+20"));
+        assert_eq!("100%", decode("100%"));
+    }
+
+    #[test]
+    fn should_reject_the_options_reading_the_files_of_the_server() {
+        for query in [
+            "mask=/etc/passwd",
+            "layout=layout.toml",
+            "history=history.jsonl",
+            "mask%3D%2Fetc%2Fpasswd",
+            "history%3Dhistory.jsonl",
+            "unknown=1",
+            "-h",
+        ] {
+            let error = parse_options(query).unwrap_err();
+            assert!(error.ends_with("is not allowed."), "{}", error);
+        }
+        assert!(parse_options("block=5&strict&roi-a=0%2C0%2C5%2C5").is_ok());
+    }
+
+    #[test]
+    fn should_refuse_the_images_exceeding_the_limits_of_the_server() {
+        let img = png(&image::RgbaImage::new(200, 100));
+        let raw = request("/compare", &img, &img);
+        let response = respond(
+            &read_request(&mut &raw[..], 1024 * 1024).unwrap(),
+            &limits(),
+        );
+        assert_eq!(422, response.status);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("10000"), "{}", body);
+    }
+}
@@ -0,0 +1,95 @@
+//! Highlighting of the blocks with difference colored by their severity (percentage of differing pixels), with
+//! a legend strip.
+
+use crate::{font, Bounds};
+
+/// Upper bounds (exclusive, in percent of the pixels of the block) & colors of the severities, the last one
+/// covering the rest.
+const SEVERITIES: [(f64, [u8; 4], &str); 3] = [
+    (1.0, [255, 215, 0, 255], "<1%"),
+    (10.0, [255, 140, 0, 255], "<10%"),
+    (f64::INFINITY, [255, 0, 0, 255], ">=10%"),
+];
+
+/// Color of the block with the percentage of differing pixels.
+fn color(percent: f64) -> image::Rgba<u8> {
+    let (_, color, _) = SEVERITIES
+        .iter()
+        .find(|(bound, _, _)| percent < *bound)
+        .unwrap_or(&SEVERITIES[2]);
+    image::Rgba(*color)
+}
+
+/// Highlight the bounds in the image with the outline colored by their percentage of differing pixels (in the
+/// same order), and append the legend strip below the image.
+pub(crate) fn highlight(
+    img: &image::RgbaImage,
+    bounds: &[Bounds],
+    percentages: &[f64],
+) -> image::RgbaImage {
+    let scale = 2;
+    let height = font::text_height(scale) + 8;
+    let mut output = image::RgbaImage::from_pixel(
+        img.width(),
+        img.height() + height,
+        image::Rgba([32, 32, 32, 255]),
+    );
+    image::imageops::replace(&mut output, img, 0, 0);
+
+    for (bound, &percent) in bounds.iter().zip(percentages) {
+        let color = color(percent);
+        for x in bound.min_width..bound.max_width {
+            output.put_pixel(x, bound.min_height, color);
+            output.put_pixel(x, bound.max_height - 1, color);
+        }
+        for y in bound.min_height..bound.max_height {
+            output.put_pixel(bound.min_width, y, color);
+            output.put_pixel(bound.max_width - 1, y, color);
+        }
+    }
+
+    // Note: a swatch & the label of every severity, clipped if the image is narrower than the legend
+    let (top, swatch) = (img.height() + 4, font::text_height(scale));
+    let mut left = 4;
+    for (_, color, label) in SEVERITIES {
+        for y in top..top + swatch {
+            for x in left..(left + swatch).min(output.width()) {
+                output.put_pixel(x, y, image::Rgba(color));
+            }
+        }
+        left += swatch + 4;
+        font::draw_text(
+            &mut output,
+            left,
+            top,
+            label,
+            scale,
+            image::Rgba([255, 255, 255, 255]),
+        );
+        left += font::text_width(label, scale) + 12;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_color_blocks_by_severity_and_append_legend() {
+        let img = image::RgbaImage::new(100, 40);
+        let bounds = [
+            Bounds::new(0, 10, 0, 10),
+            Bounds::new(10, 20, 0, 10),
+            Bounds::new(20, 30, 0, 10),
+        ];
+
+        let output = highlight(&img, &bounds, &[0.5, 5.0, 50.0]);
+
+        assert_eq!((100, 58), output.dimensions());
+        assert_eq!(image::Rgba([255, 215, 0, 255]), *output.get_pixel(0, 0));
+        assert_eq!(image::Rgba([255, 140, 0, 255]), *output.get_pixel(10, 0));
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *output.get_pixel(20, 0));
+        assert_eq!(image::Rgba([255, 215, 0, 255]), *output.get_pixel(4, 44));
+    }
+}
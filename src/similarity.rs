@@ -0,0 +1,136 @@
+//! Pairing of the source & target files by perceptual similarity (for ex. screenshots named with random IDs):
+//! every image is reduced to a 64-bit difference hash, and the files are paired by the distance of their hashes.
+
+use crate::NamedFile;
+
+/// Largest distance (in bits, of 64) between the hashes of a pair.
+const MAX_DISTANCE: u32 = 10;
+
+/// Distance (in bits) within which another candidate makes a pair ambiguous.
+const AMBIGUITY_MARGIN: u32 = 2;
+
+/// Difference hash of the image: the grayscale image reduced to 9x8 pixels, every bit telling whether a pixel is
+/// brighter than the next one in its row.
+pub(crate) fn hash(img: &image::RgbaImage) -> u64 {
+    let gray: image::GrayImage = image::buffer::ConvertBuffer::convert(img);
+    let small = image::imageops::resize(&gray, 9, 8, image::imageops::FilterType::Triangle);
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash
+}
+
+/// Represents a pair of the files with the closest hashes, ambiguous if another candidate of either file is
+/// (almost) as close.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Match {
+    pub(crate) src: NamedFile,
+    pub(crate) tgt: NamedFile,
+    pub(crate) distance: u32,
+    /// name & distance of the closest other candidate (if within the ambiguity margin).
+    pub(crate) ambiguous_with: Option<(String, u32)>,
+}
+
+/// Pair the (hashed) source & target files, closest first (each file at most once, by name on a tie), as long as
+/// their distance is at most `MAX_DISTANCE`.
+pub(crate) fn pair(
+    src_files: Vec<(NamedFile, u64)>,
+    tgt_files: Vec<(NamedFile, u64)>,
+) -> Vec<Match> {
+    let mut candidates = Vec::new();
+    for (i, (_, src_hash)) in src_files.iter().enumerate() {
+        for (j, (_, tgt_hash)) in tgt_files.iter().enumerate() {
+            let distance = (src_hash ^ tgt_hash).count_ones();
+            if distance <= MAX_DISTANCE {
+                candidates.push((distance, i, j));
+            }
+        }
+    }
+    candidates.sort();
+
+    let (mut src_paired, mut tgt_paired) =
+        (vec![false; src_files.len()], vec![false; tgt_files.len()]);
+    let mut matches = Vec::new();
+    for &(distance, i, j) in &candidates {
+        if src_paired[i] || tgt_paired[j] {
+            continue;
+        }
+        // Note: the other candidates of either file, still unpaired (the closer ones are all paired already)
+        let ambiguous_with = candidates
+            .iter()
+            .filter(|&&(d, si, tj)| {
+                d <= distance + AMBIGUITY_MARGIN
+                    && (si == i) != (tj == j)
+                    && !src_paired[si]
+                    && !tgt_paired[tj]
+            })
+            .map(|&(d, si, tj)| match si == i {
+                true => (tgt_files[tj].0 .0.clone(), d),
+                false => (src_files[si].0 .0.clone(), d),
+            })
+            .next();
+        src_paired[i] = true;
+        tgt_paired[j] = true;
+        matches.push((i, j, distance, ambiguous_with));
+    }
+
+    // Note: in the order of the source files
+    matches.sort_by_key(|&(i, _, _, _)| i);
+    matches
+        .into_iter()
+        .map(|(i, j, distance, ambiguous_with)| Match {
+            src: src_files[i].0.clone(),
+            tgt: tgt_files[j].0.clone(),
+            distance,
+            ambiguous_with,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(name: &str, hash: u64) -> (NamedFile, u64) {
+        ((name.to_string(), PathBuf::from(name)), hash)
+    }
+
+    #[test]
+    fn should_hash_similar_images_closely() {
+        let gradient = image::RgbaImage::from_fn(90, 80, |x, y| {
+            image::Rgba([(x * 2) as u8, (y * 3) as u8, 0, 255])
+        });
+        let mut changed = gradient.clone();
+        changed.put_pixel(45, 40, image::Rgba([255, 255, 255, 255]));
+        let flipped = image::imageops::flip_horizontal(&gradient);
+
+        let distance = |a, b| (hash(a) ^ hash(b)).count_ones();
+        assert!(distance(&gradient, &changed) <= 2);
+        assert!(distance(&gradient, &flipped) > MAX_DISTANCE);
+    }
+
+    #[test]
+    fn should_pair_the_closest_files_and_report_ambiguous_pairs() {
+        let matches = pair(
+            vec![
+                file("a", 0b0000),
+                file("b", 0xFFFF),
+                file("c", 0xFFFF_0000_0000),
+            ],
+            vec![file("x", 0xFFFE), file("y", 0b0001), file("z", 0b0011)],
+        );
+
+        let pairs: Vec<_> = matches
+            .iter()
+            .map(|m| (m.src.0.as_str(), m.tgt.0.as_str(), m.distance))
+            .collect();
+        assert_eq!(vec![("a", "y", 1), ("b", "x", 1)], pairs);
+        assert_eq!(Some((String::from("z"), 2)), matches[0].ambiguous_with);
+        assert_eq!(None, matches[1].ambiguous_with);
+    }
+}
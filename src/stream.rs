@@ -0,0 +1,107 @@
+//! Streaming decoding: the png, jpeg & gif images are decoded incrementally straight from their readers (for ex.
+//! sockets or archive entries), without intermediate files or buffering the encoded images; the other formats
+//! (which need to seek) are read into memory first.
+
+use std::io::{BufRead, BufReader, Read};
+
+use image::codecs::{gif::GifDecoder, jpeg::JpegDecoder, png::PngDecoder};
+use image::{DynamicImage, ImageDecoder, ImageFormat};
+
+use crate::exit::Error;
+use crate::i18n;
+
+/// Represents an image being decoded from its reader (its header already read).
+pub(crate) enum Stream<R: Read> {
+    Png(Box<PngDecoder<BufReader<R>>>),
+    Jpeg(Box<JpegDecoder<BufReader<R>>>),
+    Gif(Box<GifDecoder<BufReader<R>>>),
+    /// encoded image of a format which cannot be decoded incrementally.
+    Buffered(Vec<u8>, ImageFormat),
+}
+
+impl<R: Read> Stream<R> {
+    /// Read the header of the image of the format (or else the format guessed from its first bytes).
+    pub(crate) fn open(reader: R, format: Option<ImageFormat>) -> Result<Stream<R>, Error> {
+        let mut reader = BufReader::new(reader);
+        let format = match format {
+            Some(format) => format,
+            None => {
+                let head = reader.fill_buf().map_err(|e| Error::io(e.to_string()))?;
+                image::guess_format(head).map_err(|_| {
+                    Error::decode(
+                        "Could not detect the format of the stream (provide its format hint).",
+                    )
+                })?
+            }
+        };
+        let error = |_| Error::decode(i18n::text("error.open", &[]));
+        Ok(match format {
+            ImageFormat::Png => Stream::Png(Box::new(PngDecoder::new(reader).map_err(error)?)),
+            ImageFormat::Jpeg => Stream::Jpeg(Box::new(JpegDecoder::new(reader).map_err(error)?)),
+            ImageFormat::Gif => Stream::Gif(Box::new(GifDecoder::new(reader).map_err(error)?)),
+            _ => {
+                let mut data = Vec::new();
+                reader
+                    .read_to_end(&mut data)
+                    .map_err(|e| Error::io(e.to_string()))?;
+                Stream::Buffered(data, format)
+            }
+        })
+    }
+
+    /// Dimensions (width, height) of the image, read from its header (before it is decoded).
+    pub(crate) fn dimensions(&self) -> Result<(u32, u32), Error> {
+        match self {
+            Stream::Png(decoder) => Ok(decoder.dimensions()),
+            Stream::Jpeg(decoder) => Ok(decoder.dimensions()),
+            Stream::Gif(decoder) => Ok(decoder.dimensions()),
+            Stream::Buffered(data, format) => {
+                image::io::Reader::with_format(std::io::Cursor::new(data), *format)
+                    .into_dimensions()
+                    .map_err(|_| Error::decode(i18n::text("error.open", &[])))
+            }
+        }
+    }
+
+    /// Decode the (rest of the) image.
+    pub(crate) fn decode(self) -> Result<DynamicImage, Error> {
+        let image = match self {
+            Stream::Png(decoder) => DynamicImage::from_decoder(*decoder),
+            Stream::Jpeg(decoder) => DynamicImage::from_decoder(*decoder),
+            Stream::Gif(decoder) => DynamicImage::from_decoder(*decoder),
+            Stream::Buffered(data, format) => image::load_from_memory_with_format(&data, format),
+        };
+        image.map_err(|_| Error::decode(i18n::text("error.open", &[])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(format: image::ImageOutputFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        let img = image::RgbaImage::from_pixel(30, 20, image::Rgba([0, 0, 255, 255]));
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut std::io::Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn should_read_the_header_before_decoding() {
+        let png = encode(image::ImageOutputFormat::Png);
+        let stream = Stream::open(png.as_slice(), None).unwrap();
+        assert!(matches!(stream, Stream::Png(_)));
+        assert_eq!(Ok((30, 20)), stream.dimensions());
+        assert_eq!((30, 20), stream.decode().unwrap().to_rgba8().dimensions());
+
+        let bmp = encode(image::ImageOutputFormat::Bmp);
+        let stream = Stream::open(bmp.as_slice(), Some(ImageFormat::Bmp)).unwrap();
+        assert_eq!(Ok((30, 20)), stream.dimensions());
+        assert_eq!((30, 20), stream.decode().unwrap().to_rgba8().dimensions());
+
+        assert!(Stream::open(&b"not an image"[..], None).is_err());
+        assert!(Stream::open(bmp.as_slice(), Some(ImageFormat::Png)).is_err());
+    }
+}
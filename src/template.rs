@@ -0,0 +1,183 @@
+//! Template matching: finding the best-matching position of the (smaller) source within the target.
+
+/// Largest number of (position, template pixel) pairs compared in the exhaustive search of the coarsest level.
+const MAX_COARSE_COST: u64 = 50_000_000;
+
+/// Smallest size (in pixels) of the template at the coarsest level.
+const MIN_TEMPLATE_SIZE: u32 = 8;
+
+/// Number of best positions of the coarsest level refined (in case the best one is a false match).
+const CANDIDATES: usize = 4;
+
+/// Represents the luma of an image (row-major).
+struct Luma {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+impl Luma {
+    fn new(img: &image::RgbaImage) -> Luma {
+        Luma {
+            width: img.width(),
+            height: img.height(),
+            pixels: img
+                .pixels()
+                .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+                .collect(),
+        }
+    }
+
+    /// Halve the dimensions (averaging every 2x2 pixels, dropping the odd last row / column).
+    fn halve(&self) -> Luma {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let at = |x: u32, y: u32| self.pixels[(y * self.width + x) as usize];
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let (x2, y2) = (x * 2, y * 2);
+                pixels.push(
+                    (at(x2, y2) + at(x2 + 1, y2) + at(x2, y2 + 1) + at(x2 + 1, y2 + 1)) / 4.0,
+                );
+            }
+        }
+        Luma {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Mean absolute difference of the template placed at (x, y), or `None` once it exceeds `limit`.
+    fn difference_at(&self, template: &Luma, x: u32, y: u32, limit: f32) -> Option<f32> {
+        let total_limit = limit * (template.width * template.height) as f32;
+        let mut total = 0.0;
+        for row in 0..template.height {
+            let start = ((y + row) * self.width + x) as usize;
+            let image = &self.pixels[start..start + template.width as usize];
+            let template_start = (row * template.width) as usize;
+            let template =
+                &template.pixels[template_start..template_start + template.width as usize];
+            total += image
+                .iter()
+                .zip(template)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>();
+            if total > total_limit {
+                return None;
+            }
+        }
+        Some(total / (template.width * template.height) as f32)
+    }
+
+    /// Positions (with their difference) of the template within the ranges, best first.
+    fn search(
+        &self,
+        template: &Luma,
+        xs: std::ops::RangeInclusive<u32>,
+        ys: std::ops::RangeInclusive<u32>,
+        count: usize,
+    ) -> Vec<(f32, u32, u32)> {
+        let mut best: Vec<(f32, u32, u32)> = Vec::new();
+        for y in ys {
+            for x in xs.clone() {
+                let limit = match best.len() < count {
+                    true => f32::INFINITY,
+                    false => best[count - 1].0,
+                };
+                if let Some(diff) = self.difference_at(template, x, y, limit) {
+                    let at = best.partition_point(|(d, _, _)| *d <= diff);
+                    best.insert(at, (diff, x, y));
+                    best.truncate(count);
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Find the position (x, y) of the top-left corner of the source within the target, where their luma differs the
+/// least (searched coarse to fine, on halved images).
+///
+/// Fails if the source is larger than the target (in either dimension).
+pub(crate) fn find(src: &image::RgbaImage, tgt: &image::RgbaImage) -> Result<(u32, u32), String> {
+    if src.width() > tgt.width() || src.height() > tgt.height() {
+        return Err(format!(
+            "'src' ({}x{}) must not be larger than 'tgt' ({}x{}) to be found in it.",
+            src.width(),
+            src.height(),
+            tgt.width(),
+            tgt.height()
+        ));
+    }
+
+    let mut levels = vec![(Luma::new(src), Luma::new(tgt))];
+    loop {
+        let (template, image) = levels.last().unwrap();
+        let positions =
+            (image.width - template.width + 1) as u64 * (image.height - template.height + 1) as u64;
+        let cost = positions * (template.width * template.height) as u64;
+        if cost <= MAX_COARSE_COST
+            || template.width / 2 < MIN_TEMPLATE_SIZE
+            || template.height / 2 < MIN_TEMPLATE_SIZE
+        {
+            break;
+        }
+        let halved = (template.halve(), image.halve());
+        levels.push(halved);
+    }
+
+    let (template, image) = levels.last().unwrap();
+    let mut candidates: Vec<(u32, u32)> = image
+        .search(
+            template,
+            0..=image.width - template.width,
+            0..=image.height - template.height,
+            CANDIDATES,
+        )
+        .into_iter()
+        .map(|(_, x, y)| (x, y))
+        .collect();
+
+    // Note: every finer level refines the candidates within 2 pixels of their doubled positions
+    for (template, image) in levels.iter().rev().skip(1) {
+        let (max_x, max_y) = (image.width - template.width, image.height - template.height);
+        let mut refined: Vec<(f32, u32, u32)> = candidates
+            .iter()
+            .flat_map(|&(x, y)| {
+                let (x, y) = (x * 2, y * 2);
+                image.search(
+                    template,
+                    x.saturating_sub(2).min(max_x)..=(x + 2).min(max_x),
+                    y.saturating_sub(2).min(max_y)..=(y + 2).min(max_y),
+                    1,
+                )
+            })
+            .collect();
+        refined.sort_by(|a, b| a.0.total_cmp(&b.0));
+        candidates = refined.into_iter().map(|(_, x, y)| (x, y)).collect();
+    }
+    Ok(candidates[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_the_source_within_the_target() {
+        let tgt = image::RgbaImage::from_fn(600, 400, |x, y| {
+            image::Rgba([
+                (x * 255 / 600) as u8,
+                (y * 255 / 400) as u8,
+                ((x / 20 * 37 + y / 20 * 91) % 255) as u8,
+                255,
+            ])
+        });
+        let src = image::imageops::crop_imm(&tgt, 317, 121, 150, 90).to_image();
+
+        assert_eq!(Ok((317, 121)), find(&src, &tgt));
+        assert_eq!(Ok((0, 0)), find(&tgt, &tgt));
+        assert!(find(&tgt, &src).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Image assertions for the tests of other crates (see `assert_images_eq!`), writing the highlighted
+//! differences of a failing assertion into `target/idiff/`.
+
+use std::path::{Path, PathBuf};
+
+use crate::{compare, render_output, save_image, CompareOptions, OutputFormat, Palette};
+
+/// Assert that the images differ by at most `max_diff` percent (0, by default), or panic with the difference
+/// (& the output with the differences highlighted, written into `target/idiff/`).
+///
+/// ```no_run
+/// idiff::assert_images_eq!("tests/snapshots/home.png", "target/screenshots/home.png");
+/// idiff::assert_images_eq!("tests/snapshots/chart.png", "target/screenshots/chart.png", max_diff = 0.1);
+/// ```
+#[macro_export]
+macro_rules! assert_images_eq {
+    ($src:expr, $tgt:expr $(,)?) => {
+        $crate::assert_images_eq!($src, $tgt, max_diff = 0.0)
+    };
+    ($src:expr, $tgt:expr, max_diff = $max_diff:expr $(,)?) => {
+        if let Err(e) = $crate::testing::check_images(
+            ::std::path::Path::new(&$src),
+            ::std::path::Path::new(&$tgt),
+            $max_diff,
+        ) {
+            panic!("assertion `images are equal` failed: {}", e);
+        }
+    };
+}
+
+/// Compare the images (with the default options), failing if they differ by more than `max_diff` percent.
+///
+/// Writes the target with the differences highlighted into the artifact directory (see `artifact_dir`) on
+/// failure.
+pub fn check_images(src: &Path, tgt: &Path, max_diff: f32) -> Result<(), String> {
+    let mut comparison = compare(src, tgt, &CompareOptions::default()).map_err(|e| e.message)?;
+    if comparison.diff <= max_diff {
+        return Ok(());
+    }
+
+    let dir = artifact_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| {
+        format!(
+            "Encountered error while creating directory {}: {}",
+            dir.display(),
+            e
+        )
+    })?;
+    let name = crate::input::local_path(tgt)
+        .file_stem()
+        .map_or(String::from("tgt"), |s| s.to_string_lossy().into_owned());
+    let output = dir.join(format!("{}_diff.png", name));
+    render_output(
+        &mut comparison,
+        false,
+        OutputFormat::Highlight,
+        0.0,
+        Palette::Default,
+    )
+    .and_then(|img| save_image(&img, &output, None))
+    .map_err(|e| e.message)?;
+
+    Err(format!(
+        "{} and {} differ by {}% (max {}%), differences highlighted in {}",
+        src.display(),
+        tgt.display(),
+        comparison.diff,
+        max_diff,
+        output.display()
+    ))
+}
+
+/// Directory the artifacts of the failing assertions are written into: `idiff/` in the target directory
+/// (`CARGO_TARGET_DIR`, or `target` of the current directory, the package root when run by `cargo test`).
+fn artifact_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map_or(PathBuf::from("target"), PathBuf::from)
+        .join("idiff")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_assert_images_within_max_diff() {
+        let dir = std::env::temp_dir().join(format!("idiff_testing_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (src, tgt) = (dir.join("src.png"), dir.join("changed.png"));
+        let img = image::RgbaImage::new(100, 100);
+        let mut changed = img.clone();
+        *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+        img.save(&src).unwrap();
+        changed.save(&tgt).unwrap();
+
+        crate::assert_images_eq!(src, src);
+        crate::assert_images_eq!(src, tgt, max_diff = 0.1);
+        let e = check_images(&src, &tgt, 0.0).unwrap_err();
+        assert!(e.contains("differ by 0.01%"));
+        assert!(artifact_dir().join("changed_diff.png").is_file());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
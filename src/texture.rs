@@ -0,0 +1,239 @@
+//! Decoding of the mip levels of (dds & ktx2) textures.
+
+// Note: the dxt codec of image is deprecated, but it is the only BC1-3 decoder among the dependencies
+#![allow(deprecated)]
+
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::dxt::{DxtDecoder, DxtVariant};
+use image::ImageDecoder;
+
+use crate::exit::Error;
+
+/// Magic number of dds files.
+const DDS_MAGIC: &[u8] = b"DDS ";
+/// Identifier of ktx2 files.
+const KTX2_IDENTIFIER: &[u8] = b"\xabKTX 20\xbb\r\n\x1a\n";
+
+/// Represents the supported encodings of the texture data.
+#[derive(Clone, Copy, Debug)]
+enum Encoding {
+    Rgba8,
+    Bgra8,
+    Bc(DxtVariant),
+}
+
+impl Encoding {
+    /// Number of bytes of a level with the dimensions.
+    fn level_size(&self, width: u32, height: u32) -> usize {
+        let blocks = || width.div_ceil(4) as usize * height.div_ceil(4) as usize;
+        match self {
+            Encoding::Rgba8 | Encoding::Bgra8 => width as usize * height as usize * 4,
+            Encoding::Bc(DxtVariant::DXT1) => blocks() * 8,
+            Encoding::Bc(_) => blocks() * 16,
+        }
+    }
+
+    /// Decode a level into RGBA.
+    fn decode(&self, data: &[u8], width: u32, height: u32) -> Result<image::RgbaImage, String> {
+        let error = || String::from("Encountered error while decoding the texture level.");
+        let data = data
+            .get(..self.level_size(width, height))
+            .ok_or_else(error)?;
+        match self {
+            Encoding::Rgba8 => {
+                image::RgbaImage::from_raw(width, height, data.to_vec()).ok_or_else(error)
+            }
+            Encoding::Bgra8 => {
+                let pixels = data
+                    .chunks(4)
+                    .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                    .collect();
+                image::RgbaImage::from_raw(width, height, pixels).ok_or_else(error)
+            }
+            Encoding::Bc(variant) => {
+                // Note: the blocks cover 4x4 pixels, so the level is decoded padded & then cropped
+                let (padded_width, padded_height) = (width.div_ceil(4) * 4, height.div_ceil(4) * 4);
+                let decoder =
+                    DxtDecoder::new(Cursor::new(data), padded_width, padded_height, *variant)
+                        .map_err(|_| error())?;
+                let mut pixels = vec![0; decoder.total_bytes() as usize];
+                decoder.read_image(&mut pixels).map_err(|_| error())?;
+                let padded = match variant {
+                    DxtVariant::DXT1 => {
+                        image::RgbImage::from_raw(padded_width, padded_height, pixels)
+                            .map(image::DynamicImage::ImageRgb8)
+                    }
+                    _ => image::RgbaImage::from_raw(padded_width, padded_height, pixels)
+                        .map(image::DynamicImage::ImageRgba8),
+                };
+                Ok(padded
+                    .ok_or_else(error)?
+                    .crop_imm(0, 0, width, height)
+                    .to_rgba8())
+            }
+        }
+    }
+}
+
+/// Decode every mip level of the (dds / ktx2) texture, starting from the largest.
+///
+/// Supports uncompressed 8-bit RGBA / BGRA and BC1-3 (DXT1 / DXT3 / DXT5) textures (of a single 2D image).
+pub(crate) fn mip_levels(path: &Path) -> Result<Vec<image::RgbaImage>, Error> {
+    let data = std::fs::read(path).map_err(|_| {
+        Error::io(format!(
+            "Encountered error while reading {}.",
+            path.display()
+        ))
+    })?;
+    decode_levels(&data)
+        .map_err(|e| Error::decode(format!("Unsupported texture {} ({}).", path.display(), e)))
+}
+
+/// Decode every mip level of the encoded (dds / ktx2) texture.
+fn decode_levels(data: &[u8]) -> Result<Vec<image::RgbaImage>, String> {
+    let (encoding, width, height, levels) = if data.starts_with(DDS_MAGIC) {
+        dds_levels(data)
+    } else if data.starts_with(KTX2_IDENTIFIER) {
+        ktx2_levels(data)
+    } else {
+        Err(String::from("expected a dds or ktx2 texture"))
+    }?;
+
+    levels
+        .iter()
+        .enumerate()
+        .map(|(level, &offset)| {
+            let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+            let data = data.get(offset..).unwrap_or_default();
+            encoding.decode(data, width, height)
+        })
+        .collect()
+}
+
+/// Read the little endian u32 at the offset.
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| String::from("truncated header"))
+}
+
+/// Read the encoding, dimensions & offsets of the levels of the dds texture.
+fn dds_levels(data: &[u8]) -> Result<(Encoding, u32, u32, Vec<usize>), String> {
+    let (height, width) = (u32_at(data, 12)?, u32_at(data, 16)?);
+    let levels = u32_at(data, 28)?.max(1);
+    let format_flags = u32_at(data, 80)?;
+    let four_cc = data.get(84..88).ok_or("truncated header")?;
+
+    let mut offset = 128;
+    let encoding = match four_cc {
+        b"DXT1" => Encoding::Bc(DxtVariant::DXT1),
+        b"DXT3" => Encoding::Bc(DxtVariant::DXT3),
+        b"DXT5" => Encoding::Bc(DxtVariant::DXT5),
+        b"DX10" => {
+            offset += 20;
+            match u32_at(data, 128)? {
+                28 | 29 => Encoding::Rgba8,
+                87 | 91 => Encoding::Bgra8,
+                71 | 72 => Encoding::Bc(DxtVariant::DXT1),
+                74 | 75 => Encoding::Bc(DxtVariant::DXT3),
+                77 | 78 => Encoding::Bc(DxtVariant::DXT5),
+                format => return Err(format!("dxgi format {}", format)),
+            }
+        }
+        // Note: uncompressed (DDPF_RGB), with the order of the channels given by the red mask
+        _ if format_flags & 0x40 != 0 && u32_at(data, 88)? == 32 => match u32_at(data, 92)? {
+            0x0000_00ff => Encoding::Rgba8,
+            0x00ff_0000 => Encoding::Bgra8,
+            mask => return Err(format!("red mask {:#x}", mask)),
+        },
+        _ => return Err(String::from("pixel format")),
+    };
+
+    let offsets = (0..levels)
+        .scan(offset, |offset, level| {
+            let current = *offset;
+            *offset += encoding.level_size((width >> level).max(1), (height >> level).max(1));
+            Some(current)
+        })
+        .collect();
+    Ok((encoding, width, height, offsets))
+}
+
+/// Read the encoding, dimensions & offsets of the levels of the ktx2 texture.
+fn ktx2_levels(data: &[u8]) -> Result<(Encoding, u32, u32, Vec<usize>), String> {
+    let encoding = match u32_at(data, 12)? {
+        37 | 43 => Encoding::Rgba8,
+        44 | 50 => Encoding::Bgra8,
+        131..=134 => Encoding::Bc(DxtVariant::DXT1),
+        135 | 136 => Encoding::Bc(DxtVariant::DXT3),
+        137 | 138 => Encoding::Bc(DxtVariant::DXT5),
+        format => return Err(format!("vk format {}", format)),
+    };
+    let (width, height) = (u32_at(data, 20)?, u32_at(data, 24)?);
+    if u32_at(data, 28)? > 1 || u32_at(data, 32)? > 1 || u32_at(data, 36)? > 1 {
+        return Err(String::from(
+            "only 2D textures of a single image are supported",
+        ));
+    }
+    if u32_at(data, 44)? != 0 {
+        return Err(String::from("supercompression"));
+    }
+
+    // Note: the level index (offset & length of every level, as u64) follows the 80 byte header
+    let levels = u32_at(data, 40)?.max(1);
+    let offsets = (0..levels as usize)
+        .map(|level| u32_at(data, 80 + level * 24).map(|offset| offset as usize))
+        .collect::<Result<_, _>>()?;
+    Ok((encoding, width, height, offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an uncompressed (BGRA) dds with the levels.
+    fn dds(width: u32, height: u32, levels: &[[u8; 4]]) -> Vec<u8> {
+        let mut header = vec![0u8; 128];
+        header[..4].copy_from_slice(DDS_MAGIC);
+        for (offset, value) in [
+            (12, height),
+            (16, width),
+            (28, levels.len() as u32),
+            (80, 0x41),
+            (88, 32),
+            (92, 0x00ff_0000),
+        ] {
+            header[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(value));
+        }
+        for (level, bgra) in levels.iter().enumerate() {
+            let pixels = (width >> level).max(1) * (height >> level).max(1);
+            header.extend(bgra.repeat(pixels as usize));
+        }
+        header
+    }
+
+    #[test]
+    fn should_decode_every_mip_level_of_dds() {
+        let data = dds(4, 2, &[[1, 2, 3, 255], [4, 5, 6, 255], [7, 8, 9, 255]]);
+
+        let levels = decode_levels(&data).unwrap();
+        assert_eq!(
+            vec![(4, 2), (2, 1), (1, 1)],
+            levels.iter().map(|l| l.dimensions()).collect::<Vec<_>>()
+        );
+        assert_eq!(image::Rgba([3, 2, 1, 255]), *levels[0].get_pixel(3, 1));
+        assert_eq!(image::Rgba([9, 8, 7, 255]), *levels[2].get_pixel(0, 0));
+    }
+
+    #[test]
+    fn should_decode_bc1_level_smaller_than_a_block() {
+        // Note: a single block with both the colors white (& every pixel using color 0)
+        let block = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        let img = Encoding::Bc(DxtVariant::DXT1).decode(&block, 2, 1).unwrap();
+
+        assert_eq!((2, 1), img.dimensions());
+        assert_eq!(image::Rgba([255, 255, 255, 255]), *img.get_pixel(1, 0));
+    }
+}
@@ -0,0 +1,94 @@
+//! Thumbnails (small previews) of the source, the target & the output, written next to the output, for ex. for
+//! the chat bots to inline instead of the full size images.
+
+use std::path::{Path, PathBuf};
+
+use crate::exit::Error;
+use crate::{check_overwrite, save_image};
+
+/// Parse the size (in pixels, of the longer side) of the thumbnails.
+pub(crate) fn parse_size(size: &str) -> Result<u32, String> {
+    match size.parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!(
+            "'{}' must be a positive thumbnail size (in pixels).",
+            size
+        )),
+    }
+}
+
+/// Downscale the image to fit the size (keeping its aspect ratio, never upscaled).
+pub(crate) fn resize(img: &image::RgbaImage, size: u32) -> image::RgbaImage {
+    let (width, height) = img.dimensions();
+    if width.max(height) <= size {
+        return img.clone();
+    }
+    let scale = size as f64 / width.max(height) as f64;
+    let scaled = |length: u32| ((length as f64 * scale).round() as u32).max(1);
+    image::imageops::thumbnail(img, scaled(width), scaled(height))
+}
+
+/// Write the thumbnails of the output (of the size) & of the source & target (already downscaled) next to the
+/// output, as `<OUTPUT>_thumb.png`, `<OUTPUT>_thumb_src.png` & `<OUTPUT>_thumb_tgt.png`.
+///
+/// Returns the paths of the thumbnails.
+pub(crate) fn write(
+    output: &Path,
+    img: &image::RgbaImage,
+    (src, tgt): (&image::RgbaImage, &image::RgbaImage),
+    size: u32,
+    force: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let diff = resize(img, size);
+    let mut paths = Vec::new();
+    for (suffix, thumbnail) in [("", &diff), ("_src", src), ("_tgt", tgt)] {
+        let path = output.with_file_name(format!("{}_thumb{}.png", stem, suffix));
+        check_overwrite(&path, force)?;
+        save_image(thumbnail, &path, None)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_downscale_to_the_longer_side() {
+        let img = image::RgbaImage::new(400, 100);
+        assert_eq!((200, 50), resize(&img, 200).dimensions());
+        assert_eq!((400, 100), resize(&img, 1000).dimensions());
+        assert_eq!((3, 1), resize(&img, 3).dimensions());
+        assert!(parse_size("0").is_err());
+        assert_eq!(Ok(128), parse_size("128"));
+    }
+
+    #[test]
+    fn should_write_the_thumbnails_next_to_the_output() {
+        let dir = std::env::temp_dir().join(format!("idiff_thumbnails_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let img = image::RgbaImage::new(100, 50);
+        let small = resize(&img, 10);
+
+        let paths = write(&dir.join("tgt_diff.jpg"), &img, (&small, &small), 10, false).unwrap();
+        let diff = image::open(&paths[0]).unwrap().to_rgba8();
+        let overwrite = write(&dir.join("tgt_diff.jpg"), &img, (&small, &small), 10, false);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            vec![
+                dir.join("tgt_diff_thumb.png"),
+                dir.join("tgt_diff_thumb_src.png"),
+                dir.join("tgt_diff_thumb_tgt.png")
+            ],
+            paths
+        );
+        assert_eq!((10, 5), diff.dimensions());
+        assert!(overwrite.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Tiled output: the output written as a Deep Zoom image (an image pyramid of tiles, as read by web viewers
+//! such as OpenSeadragon) instead of a single file, to pan & zoom the difference of very large images.
+//!
+//! For the descriptor `NAME.dzi`, the tiles of every level are written as `NAME_files/LEVEL/COLUMN_ROW.png`: the
+//! last level is the full resolution image, every level before it is half the size (rounded up) of the next one,
+//! down to the first level of a single pixel.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::exit::Error;
+use crate::{check_overwrite, save_image};
+
+/// Parse the size (in pixels) of the (square) tiles.
+pub(crate) fn parse_tile_size(size: &str) -> Result<u32, String> {
+    match size.parse::<u32>() {
+        Ok(size) if size >= 16 => Ok(size),
+        _ => Err(format!(
+            "'{}' must be a tile size (in pixels) of at least 16.",
+            size
+        )),
+    }
+}
+
+/// Number of the levels of the pyramid of the image (the first level is of a single pixel).
+fn levels(width: u32, height: u32) -> u32 {
+    let largest = width.max(height).max(1);
+    // Note: ceil(log2(largest)) + 1
+    u32::BITS - (largest - 1).leading_zeros() + 1
+}
+
+/// Write the image as a Deep Zoom image `descriptor` (`.dzi`, with its tiles next to it), refusing to overwrite
+/// an existing one unless forced.
+pub(crate) fn write(
+    img: &image::RgbaImage,
+    descriptor: &Path,
+    tile_size: u32,
+    force: bool,
+) -> Result<(), Error> {
+    check_overwrite(descriptor, force)?;
+    let stem = descriptor
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let files = descriptor.with_file_name(format!("{}_files", stem));
+
+    // Note: every level is downscaled from the next one (instead of the full resolution image)
+    let mut current = Cow::Borrowed(img);
+    for level in (0..levels(img.width(), img.height())).rev() {
+        write_level(&current, &files.join(level.to_string()), tile_size)?;
+        let (width, height) = current.dimensions();
+        let next = image::imageops::resize(
+            &*current,
+            width.div_ceil(2),
+            height.div_ceil(2),
+            image::imageops::FilterType::Triangle,
+        );
+        current = Cow::Owned(next);
+    }
+
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"png\" Overlap=\"0\" TileSize=\"{}\">
+  <Size Width=\"{}\" Height=\"{}\"/>
+</Image>
+",
+        tile_size,
+        img.width(),
+        img.height()
+    );
+    std::fs::write(descriptor, xml).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while writing tile descriptor {}: {}",
+            descriptor.display(),
+            e
+        ))
+    })
+}
+
+/// Write the tiles of the level into its directory (as `COLUMN_ROW.png`).
+fn write_level(img: &image::RgbaImage, dir: &Path, tile_size: u32) -> Result<(), Error> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::io(format!(
+            "Encountered error while creating tile directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    let (width, height) = img.dimensions();
+    for row in 0..height.div_ceil(tile_size) {
+        for column in 0..width.div_ceil(tile_size) {
+            let (x, y) = (column * tile_size, row * tile_size);
+            let tile = image::imageops::crop_imm(
+                img,
+                x,
+                y,
+                tile_size.min(width - x),
+                tile_size.min(height - y),
+            )
+            .to_image();
+            save_image(&tile, &dir.join(format!("{}_{}.png", column, row)), None)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_count_the_levels_down_to_a_single_pixel() {
+        assert_eq!(1, levels(1, 1));
+        assert_eq!(2, levels(2, 1));
+        assert_eq!(11, levels(1000, 600));
+        assert_eq!(11, levels(1024, 1024));
+        assert_eq!(12, levels(1025, 10));
+        assert!(parse_tile_size("8").is_err());
+        assert_eq!(Ok(256), parse_tile_size("256"));
+    }
+
+    #[test]
+    fn should_write_the_tiles_of_every_level() {
+        let dir = std::env::temp_dir().join(format!("idiff_tiles_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let descriptor = dir.join("diff.dzi");
+        let img = image::RgbaImage::from_pixel(40, 20, image::Rgba([255, 0, 0, 255]));
+        write(&img, &descriptor, 16, false).unwrap();
+
+        let files = dir.join("diff_files");
+        let tile = |level: &str, name: &str| image::open(files.join(level).join(name)).ok();
+        let (last, corner, first) = (
+            tile("6", "2_1.png"),
+            tile("5", "1_0.png"),
+            tile("0", "0_0.png"),
+        );
+        let xml = std::fs::read_to_string(&descriptor).unwrap();
+        let overwrite = write(&img, &descriptor, 16, false);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Note: the last tiles of a level are cropped to the image
+        assert_eq!((8, 4), last.unwrap().to_rgba8().dimensions());
+        assert_eq!((4, 10), corner.unwrap().to_rgba8().dimensions());
+        assert_eq!((1, 1), first.unwrap().to_rgba8().dimensions());
+        assert!(xml.contains("TileSize=\"16\""));
+        assert!(xml.contains("<Size Width=\"40\" Height=\"20\"/>"));
+        assert!(overwrite.is_err());
+    }
+}
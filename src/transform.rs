@@ -0,0 +1,88 @@
+//! Detection (& undoing) of the rotation / mirroring of the target relative to the source.
+
+use crate::{pixel_difference, Bounds, Dimensions, Transform};
+
+/// Largest fraction of the pixels which can differ between the transformed source & the target, for the
+/// transform to be detected (tolerating for ex. re-encoding).
+const MAX_MISMATCH: f64 = 0.01;
+
+const TRANSFORMS: [Transform; 7] = [
+    Transform::Rotate90,
+    Transform::Rotate180,
+    Transform::Rotate270,
+    Transform::FlipHorizontal,
+    Transform::FlipVertical,
+    Transform::Transpose,
+    Transform::Transverse,
+];
+
+impl Transform {
+    /// Apply the transform to the image.
+    pub(crate) fn apply(&self, img: &image::RgbaImage) -> image::RgbaImage {
+        use image::imageops;
+        match self {
+            Transform::Rotate90 => imageops::rotate90(img),
+            Transform::Rotate180 => imageops::rotate180(img),
+            Transform::Rotate270 => imageops::rotate270(img),
+            Transform::FlipHorizontal => imageops::flip_horizontal(img),
+            Transform::FlipVertical => imageops::flip_vertical(img),
+            Transform::Transpose => imageops::flip_horizontal(&imageops::rotate90(img)),
+            Transform::Transverse => imageops::flip_vertical(&imageops::rotate90(img)),
+        }
+    }
+
+    /// Transform undoing this one.
+    pub(crate) fn inverse(&self) -> Transform {
+        match self {
+            Transform::Rotate90 => Transform::Rotate270,
+            Transform::Rotate270 => Transform::Rotate90,
+            transform => *transform,
+        }
+    }
+}
+
+/// Detect the transform of the source the target matches (with at most 1% of the pixels differing), if the
+/// target does not already match the source as is.
+pub(crate) fn detect(src: &image::RgbaImage, tgt: &image::RgbaImage) -> Option<Transform> {
+    let mismatch = |img: &image::RgbaImage| {
+        if img.dimensions() != tgt.dimensions() {
+            return None;
+        }
+        let bounds = Bounds::get_max_bounds(
+            Dimensions::from(img.dimensions()),
+            Dimensions::from(tgt.dimensions()),
+        )
+        .ok()?;
+        Some(pixel_difference(img, tgt, &bounds) as f64 / bounds.area().max(1) as f64)
+    };
+    if mismatch(src).is_some_and(|m| m <= MAX_MISMATCH) {
+        return None;
+    }
+    TRANSFORMS
+        .iter()
+        .filter_map(|t| Some((*t, mismatch(&t.apply(src))?)))
+        .filter(|(_, m)| *m <= MAX_MISMATCH)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(t, _)| t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_and_undo_transform() {
+        let src = image::RgbaImage::from_fn(4, 3, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+
+        for transform in TRANSFORMS {
+            let tgt = transform.apply(&src);
+            assert_eq!(Some(transform), detect(&src, &tgt));
+            assert_eq!(src, transform.inverse().apply(&tgt));
+        }
+        assert_eq!(None, detect(&src, &src));
+        assert_eq!(
+            image::Rgba([1, 0, 0, 255]),
+            *Transform::Transpose.apply(&src).get_pixel(0, 1)
+        );
+    }
+}
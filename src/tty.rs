@@ -0,0 +1,254 @@
+//! Rendering of images directly in the terminal (for a quick look, for ex. over ssh).
+
+use std::fmt::Write;
+
+use crate::exit::Error;
+
+/// Represents the supported ways of rendering an image in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub(crate) enum TtyProtocol {
+    // kitty / sixel graphics if the terminal is known to support them, otherwise half blocks
+    Auto,
+    // unicode half block characters with 24-bit colors (supported by most terminals)
+    Blocks,
+    // sixel graphics
+    Sixel,
+    // kitty graphics protocol
+    Kitty,
+}
+
+impl TtyProtocol {
+    /// Resolve `Auto` into the protocol supported by the terminal, based on the environment variables.
+    fn resolve(self, var: impl Fn(&str) -> Option<String>) -> TtyProtocol {
+        if self != TtyProtocol::Auto {
+            return self;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || ["WezTerm", "ghostty"].contains(&program.as_str())
+        {
+            TtyProtocol::Kitty
+        } else if term.contains("sixel") || ["foot", "mlterm"].contains(&term.as_str()) {
+            TtyProtocol::Sixel
+        } else {
+            TtyProtocol::Blocks
+        }
+    }
+}
+
+/// Maximum width (in pixels) of the image rendered with sixel / kitty graphics.
+const MAX_GRAPHICS_WIDTH: u32 = 800;
+
+/// Render the image (downscaled to fit the terminal) for printing into the terminal.
+pub(crate) fn render(img: &image::RgbaImage, protocol: TtyProtocol) -> Result<String, Error> {
+    let protocol = protocol.resolve(|name| std::env::var(name).ok());
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(80);
+
+    match protocol {
+        TtyProtocol::Blocks => Ok(blocks(&fit(img, columns))),
+        TtyProtocol::Sixel => Ok(sixel(&fit(img, MAX_GRAPHICS_WIDTH))),
+        _ => kitty(&fit(img, MAX_GRAPHICS_WIDTH)),
+    }
+}
+
+/// Downscale the image (preserving the aspect ratio) to be at most `width` pixels wide.
+fn fit(img: &image::RgbaImage, width: u32) -> image::RgbaImage {
+    if img.width() <= width {
+        return img.clone();
+    }
+    let height = ((img.height() as u64 * width as u64) / img.width() as u64).max(1) as u32;
+    image::imageops::thumbnail(img, width, height)
+}
+
+/// Render the image with a half block character (upper pixel as foreground, lower as background) per 2 pixels.
+fn blocks(img: &image::RgbaImage) -> String {
+    let mut out = String::new();
+    for y in (0..img.height()).step_by(2) {
+        for x in 0..img.width() {
+            let top = img.get_pixel(x, y);
+            let _ = write!(out, "\x1b[38;2;{};{};{}m", top[0], top[1], top[2]);
+            if y + 1 < img.height() {
+                let bottom = img.get_pixel(x, y + 1);
+                let _ = write!(out, "\x1b[48;2;{};{};{}m", bottom[0], bottom[1], bottom[2]);
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Render the image as sixel graphics, with the colors reduced to a 6x6x6 palette.
+fn sixel(img: &image::RgbaImage) -> String {
+    let index = |p: &image::Rgba<u8>| {
+        let level = |c: u8| (c as usize * 5 + 127) / 255;
+        level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])
+    };
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", img.width(), img.height());
+    for i in 0..216 {
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            i / 36 * 20,
+            i / 6 % 6 * 20,
+            i % 6 * 20
+        );
+    }
+
+    for band in (0..img.height()).step_by(6) {
+        let rows = band..(band + 6).min(img.height());
+        let mut used = [false; 216];
+        for y in rows.clone() {
+            for x in 0..img.width() {
+                used[index(img.get_pixel(x, y))] = true;
+            }
+        }
+
+        for color in (0..216).filter(|&c| used[c]) {
+            let _ = write!(out, "#{}", color);
+            let sixels = (0..img.width()).map(|x| {
+                rows.clone()
+                    .filter(|&y| index(img.get_pixel(x, y)) == color)
+                    .fold(0u8, |bits, y| bits | 1 << (y - band))
+            });
+            // Note: run length encode the repeated sixels
+            let mut run: Option<(u8, usize)> = None;
+            for bits in sixels.chain(std::iter::once(u8::MAX)) {
+                match run {
+                    Some((prev, count)) if prev == bits => run = Some((prev, count + 1)),
+                    _ => {
+                        if let Some((prev, count)) = run {
+                            let c = (63 + prev) as char;
+                            match count {
+                                1..=3 => out.extend(std::iter::repeat_n(c, count)),
+                                _ => {
+                                    let _ = write!(out, "!{}{}", count, c);
+                                }
+                            }
+                        }
+                        run = Some((bits, 1));
+                    }
+                }
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    out
+}
+
+/// Render the image with the kitty graphics protocol (as a png, transmitted in chunks).
+fn kitty(img: &image::RgbaImage) -> Result<String, Error> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageOutputFormat::Png)
+        .map_err(|_| {
+            Error::io(String::from(
+                "Encountered error while encoding the terminal preview.",
+            ))
+        })?;
+    let data = base64(png.get_ref());
+
+    let mut out = String::new();
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        match i {
+            0 => write!(out, "\x1b_Ga=T,f=100,m={};{}\x1b\\", more, chunk),
+            _ => write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk),
+        }
+        .unwrap();
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+/// Encode the data as (standard, padded) base64.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_resolve_auto_protocol_from_environment() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        assert_eq!(
+            TtyProtocol::Kitty,
+            TtyProtocol::Auto.resolve(env(&[("TERM", "xterm-kitty")]))
+        );
+        assert_eq!(
+            TtyProtocol::Sixel,
+            TtyProtocol::Auto.resolve(env(&[("TERM", "foot")]))
+        );
+        assert_eq!(
+            TtyProtocol::Blocks,
+            TtyProtocol::Auto.resolve(env(&[("TERM", "xterm-256color")]))
+        );
+        assert_eq!(
+            TtyProtocol::Sixel,
+            TtyProtocol::Sixel.resolve(env(&[("TERM", "xterm-kitty")]))
+        );
+    }
+
+    #[test]
+    fn should_render_two_pixels_per_half_block() {
+        let mut img = image::RgbaImage::new(1, 3);
+        *img.get_pixel_mut(0, 0) = image::Rgba([255, 0, 0, 255]);
+        *img.get_pixel_mut(0, 1) = image::Rgba([0, 0, 255, 255]);
+
+        assert_eq!(
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\n\x1b[38;2;0;0;0m▀\x1b[0m\n",
+            blocks(&img)
+        );
+    }
+
+    #[test]
+    fn should_render_run_length_encoded_sixels() {
+        let img = image::RgbaImage::from_pixel(5, 6, image::Rgba([255, 0, 0, 255]));
+
+        assert!(sixel(&img).ends_with("#180!5~$-\x1b\\\n"));
+    }
+
+    #[test]
+    fn should_encode_base64() {
+        assert_eq!("TWFu", base64(b"Man"));
+        assert_eq!("TWE=", base64(b"Ma"));
+        assert_eq!("TQ==", base64(b"M"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Fast path decoding the uncompressed formats (BMP & TIFF) straight from the encoded image into RGBA, so that huge
+//! scans are not decoded into an intermediate image before being converted.
+
+/// Decode the uncompressed image (8-bit BMP & TIFF, RGB or RGBA) straight into RGBA.
+///
+/// Returns `None` if the image is of another format (or an unsupported variant), to be decoded as usual.
+pub(crate) fn decode(data: &[u8]) -> Option<image::RgbaImage> {
+    if data.starts_with(b"BM") {
+        decode_bmp(data)
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        decode_tiff(data)
+    } else {
+        None
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+    Some(match big_endian {
+        true => u16::from_be_bytes(bytes),
+        false => u16::from_le_bytes(bytes),
+    })
+}
+
+fn u32_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+    Some(match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    })
+}
+
+/// Decode a 24-bit (BGR) or 32-bit (BGRX, or BGRA with the bitfields) uncompressed BMP.
+fn decode_bmp(data: &[u8]) -> Option<image::RgbaImage> {
+    let offset = u32_at(data, 10, false)? as usize;
+    let header_size = u32_at(data, 14, false)?;
+    let width = u32_at(data, 18, false)? as i32;
+    let height = u32_at(data, 22, false)? as i32;
+    let bits = u16_at(data, 28, false)?;
+    let compression = u32_at(data, 30, false)?;
+    if header_size < 40 || width <= 0 || height == 0 {
+        return None;
+    }
+
+    let has_alpha = match (bits, compression) {
+        (24, 0) | (32, 0) => false,
+        // Note: the bitfields follow the 40 bytes of the header (the alpha mask only in the larger headers)
+        (32, 3) => {
+            let masks = [
+                u32_at(data, 54, false)?,
+                u32_at(data, 58, false)?,
+                u32_at(data, 62, false)?,
+            ];
+            let alpha = match header_size >= 56 {
+                true => u32_at(data, 66, false)?,
+                false => 0,
+            };
+            match (masks, alpha) {
+                ([0xff0000, 0xff00, 0xff], 0xff000000) => true,
+                ([0xff0000, 0xff00, 0xff], 0) => false,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let (width, rows) = (width as u32, height.unsigned_abs());
+    let bytes_per_pixel = bits as usize / 8;
+    // Note: the sizes are checked, as they come from the (untrusted) header
+    let stride = (width as usize)
+        .checked_mul(bytes_per_pixel)?
+        .checked_next_multiple_of(4)?;
+    let end = stride.checked_mul(rows as usize)?.checked_add(offset)?;
+    let pixels = data.get(offset..end)?;
+
+    let mut img = image::RgbaImage::new(width, rows);
+    for (y, row) in pixels.chunks_exact(stride).enumerate() {
+        // Note: the rows are stored bottom up, unless the height is negative
+        let y = match height > 0 {
+            true => rows - 1 - y as u32,
+            false => y as u32,
+        };
+        for (x, p) in row
+            .chunks_exact(bytes_per_pixel)
+            .take(width as usize)
+            .enumerate()
+        {
+            let alpha = if has_alpha { p[3] } else { 255 };
+            img.put_pixel(x as u32, y, image::Rgba([p[2], p[1], p[0], alpha]));
+        }
+    }
+    Some(img)
+}
+
+/// Decode an uncompressed 8-bit RGB or RGBA TIFF (of interleaved strips, the first image only).
+fn decode_tiff(data: &[u8]) -> Option<image::RgbaImage> {
+    let big_endian = data.starts_with(b"MM");
+    let ifd = u32_at(data, 4, big_endian)? as usize;
+    let entries = u16_at(data, ifd, big_endian)? as usize;
+
+    // Values of the entry (SHORT or LONG, inline if they fit in 4 bytes).
+    let values = |entry: usize| -> Option<Vec<u32>> {
+        let (kind, count) = (
+            u16_at(data, entry + 2, big_endian)?,
+            u32_at(data, entry + 4, big_endian)? as usize,
+        );
+        let size = match kind {
+            3 => 2,
+            4 => 4,
+            _ => return None,
+        };
+        let start = match count * size <= 4 {
+            true => entry + 8,
+            false => u32_at(data, entry + 8, big_endian)? as usize,
+        };
+        (0..count)
+            .map(|i| match size {
+                2 => u16_at(data, start + 2 * i, big_endian).map(u32::from),
+                _ => u32_at(data, start + 4 * i, big_endian),
+            })
+            .collect()
+    };
+
+    let mut tags = std::collections::HashMap::new();
+    for i in 0..entries {
+        let entry = ifd + 2 + 12 * i;
+        tags.insert(u16_at(data, entry, big_endian)?, entry);
+    }
+    let tag = |id: u16| values(*tags.get(&id)?);
+    let single = |id: u16, default: u32| match tags.contains_key(&id) {
+        true => tag(id).and_then(|v| v.first().copied()),
+        false => Some(default),
+    };
+
+    let (width, height) = (single(256, 0)?, single(257, 0)?);
+    let samples = single(277, 1)?;
+    let compression = single(259, 1)?;
+    let photometric = single(262, u32::MAX)?;
+    let planar = single(284, 1)?;
+    // Note: only the unassociated (or unspecified) alpha is kept as is (as the regular decoder)
+    let extra = single(338, 0)?;
+    let bits = tag(258).unwrap_or_else(|| vec![1]);
+    if width == 0
+        || height == 0
+        || compression != 1
+        || photometric != 2
+        || planar != 1
+        || !(samples == 3 || samples == 4 && extra != 1)
+        || bits.len() != samples as usize
+        || bits.iter().any(|&b| b != 8)
+    {
+        return None;
+    }
+
+    let (offsets, counts) = (tag(273)?, tag(279)?);
+    let rows_per_strip = single(278, height)?.min(height) as usize;
+    let stride = (width as usize).checked_mul(samples as usize)?;
+    // Note: the pixels must fit in the data, before the image is allocated (the header is untrusted)
+    if offsets.len() != counts.len()
+        || offsets.len() != (height as usize).div_ceil(rows_per_strip)
+        || stride.checked_mul(height as usize)? > data.len()
+    {
+        return None;
+    }
+
+    let mut img = image::RgbaImage::new(width, height);
+    for (strip, &offset) in offsets.iter().enumerate() {
+        let first = strip * rows_per_strip;
+        let rows = rows_per_strip.min(height as usize - first);
+        let end = rows.checked_mul(stride)?.checked_add(offset as usize)?;
+        let pixels = data.get(offset as usize..end)?;
+        for (y, row) in pixels.chunks_exact(stride).enumerate() {
+            for (x, p) in row.chunks_exact(samples as usize).enumerate() {
+                let alpha = if samples == 4 { p[3] } else { 255 };
+                img.put_pixel(
+                    x as u32,
+                    (first + y) as u32,
+                    image::Rgba([p[0], p[1], p[2], alpha]),
+                );
+            }
+        }
+    }
+    Some(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode the image in the format (with the regular encoder).
+    fn encode(img: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut data, format).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn should_decode_uncompressed_formats_as_the_regular_decoder() {
+        let rgba = image::RgbaImage::from_fn(13, 7, |x, y| {
+            image::Rgba([
+                (x * 19) as u8,
+                (y * 37) as u8,
+                (x * y) as u8,
+                (x * 7 + y) as u8,
+            ])
+        });
+        let rgb = image::DynamicImage::ImageRgba8(rgba.clone()).to_rgb8();
+
+        for format in [image::ImageFormat::Bmp, image::ImageFormat::Tiff] {
+            for img in [
+                image::DynamicImage::ImageRgba8(rgba.clone()),
+                image::DynamicImage::ImageRgb8(rgb.clone()),
+            ] {
+                let data = encode(img, format);
+                assert_eq!(
+                    image::load_from_memory(&data).unwrap().to_rgba8(),
+                    decode(&data).unwrap(),
+                    "{:?}",
+                    format
+                );
+            }
+        }
+        let png = encode(
+            image::DynamicImage::ImageRgba8(rgba),
+            image::ImageFormat::Png,
+        );
+        assert_eq!(None, decode(&png));
+    }
+
+    #[test]
+    fn should_not_decode_sizes_beyond_the_data() {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(4, 4));
+        let mut bmp = encode(img, image::ImageFormat::Bmp);
+        bmp[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+        bmp[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+        assert_eq!(None, decode(&bmp));
+        bmp[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(None, decode(&bmp));
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use ffmpeg_next as ffmpeg;
+
+use crate::exit::Error;
+use crate::{Bounds, Dimensions};
+
+/// Represents the decoded (RGBA) frames of the video stream of a file.
+pub(crate) struct Frames {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Video,
+    scaler: ffmpeg::software::scaling::Context,
+    stream_index: usize,
+    eof: bool,
+}
+
+impl Frames {
+    /// Open the best video stream of the file.
+    pub(crate) fn open(path: &Path) -> Result<Frames, Error> {
+        let error = |e: ffmpeg::Error| {
+            Error::io(format!(
+                "Encountered error while opening video {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        ffmpeg::init().map_err(error)?;
+        let input = ffmpeg::format::input(path).map_err(error)?;
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| error(ffmpeg::Error::StreamNotFound))?;
+        let stream_index = stream.index();
+
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|c| c.decoder().video())
+            .map_err(error)?;
+        let scaler = ffmpeg::software::scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            ffmpeg::format::Pixel::RGBA,
+            decoder.width(),
+            decoder.height(),
+            ffmpeg::software::scaling::Flags::BILINEAR,
+        )
+        .map_err(error)?;
+
+        Ok(Frames {
+            input,
+            decoder,
+            scaler,
+            stream_index,
+            eof: false,
+        })
+    }
+
+    /// Convert the decoded frame into RGBA.
+    fn convert(&mut self, decoded: &ffmpeg::frame::Video) -> Result<image::RgbaImage, Error> {
+        let mut rgba = ffmpeg::frame::Video::empty();
+        self.scaler.run(decoded, &mut rgba).map_err(|e| {
+            Error::decode(format!(
+                "Encountered error while converting video frame: {}",
+                e
+            ))
+        })?;
+
+        // Note: the rows of the frame can be padded (stride >= width * 4)
+        let (width, height, stride) = (rgba.width(), rgba.height(), rgba.stride(0));
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in rgba.data(0).chunks(stride).take(height as usize) {
+            pixels.extend_from_slice(&row[..(width * 4) as usize]);
+        }
+
+        image::RgbaImage::from_raw(width, height, pixels).ok_or_else(|| {
+            Error::decode(String::from(
+                "Encountered error while converting video frame.",
+            ))
+        })
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<image::RgbaImage, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut decoded = ffmpeg::frame::Video::empty();
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                return Some(self.convert(&decoded));
+            }
+            if self.eof {
+                return None;
+            }
+
+            match self.input.packets().next() {
+                Some((stream, packet)) => {
+                    if stream.index() == self.stream_index {
+                        if let Err(e) = self.decoder.send_packet(&packet) {
+                            return Some(Err(Error::decode(format!(
+                                "Encountered error while decoding video frame: {}",
+                                e
+                            ))));
+                        }
+                    }
+                }
+                None => {
+                    // Note: flush the frames buffered by the decoder
+                    self.eof = true;
+                    let _ = self.decoder.send_eof();
+                }
+            }
+        }
+    }
+}
+
+/// Represents the result of comparing a pair of frames.
+pub(crate) struct FrameComparison {
+    /// index of the frame (starting from 0).
+    pub(crate) index: usize,
+    /// percentage difference between the frames.
+    pub(crate) diff: f32,
+    /// target frame with the blocks with difference highlighted (if requested).
+    pub(crate) highlighted: Option<image::RgbaImage>,
+}
+
+/// Represents the result of comparing two videos.
+pub(crate) struct VideoComparison {
+    /// frames with a difference above the threshold.
+    pub(crate) frames: Vec<FrameComparison>,
+    /// number of compared frames (present in both the videos).
+    pub(crate) compared: usize,
+    /// number of frames only present in the source video.
+    pub(crate) only_in_src: usize,
+    /// number of frames only present in the target video.
+    pub(crate) only_in_tgt: usize,
+    /// mean percentage difference of the compared frames.
+    pub(crate) mean_diff: f32,
+    /// (index, percentage difference) of the frame with the largest difference.
+    pub(crate) max_diff: Option<(usize, f32)>,
+}
+
+/// Compare the frames of the videos.
+///
+/// Frames with a percentage difference greater than `threshold` are returned (highlighted with the palette, if
+/// requested).
+pub(crate) fn compare(
+    src: &Path,
+    tgt: &Path,
+    block: u32,
+    threshold: f32,
+    highlight: Option<crate::Palette>,
+) -> Result<VideoComparison, Error> {
+    let mut src_frames = Frames::open(src)?;
+    let mut tgt_frames = Frames::open(tgt)?;
+
+    let mut comparison = VideoComparison {
+        frames: Vec::new(),
+        compared: 0,
+        only_in_src: 0,
+        only_in_tgt: 0,
+        mean_diff: 0.0,
+        max_diff: None,
+    };
+    let mut total_diff = 0.0;
+
+    loop {
+        let (src_frame, tgt_frame) = match (src_frames.next(), tgt_frames.next()) {
+            (Some(s), Some(t)) => (s?, t?),
+            (Some(_), None) => {
+                comparison.only_in_src = 1 + src_frames.count();
+                break;
+            }
+            (None, Some(_)) => {
+                comparison.only_in_tgt = 1 + tgt_frames.count();
+                break;
+            }
+            (None, None) => break,
+        };
+
+        let index = comparison.compared;
+        let bounds = Bounds::get_max_bounds(
+            Dimensions::from(src_frame.dimensions()),
+            Dimensions::from(tgt_frame.dimensions()),
+        )?;
+        let (diff, bounds_with_diff) = crate::percentage_difference(
+            &src_frame,
+            &tgt_frame,
+            &bounds,
+            block,
+            0,
+            0.0,
+            &crate::comparator::Exact,
+        );
+
+        comparison.compared += 1;
+        total_diff += diff as f64;
+        if comparison.max_diff.map_or(true, |(_, max)| diff > max) {
+            comparison.max_diff = Some((index, diff));
+        }
+
+        if diff > threshold {
+            let highlighted = if let Some(palette) = highlight {
+                let mut frame = tgt_frame;
+                crate::highlight(&mut frame, &bounds_with_diff, palette);
+                Some(frame)
+            } else {
+                None
+            };
+            comparison.frames.push(FrameComparison {
+                index,
+                diff,
+                highlighted,
+            });
+        }
+    }
+
+    if comparison.compared > 0 {
+        comparison.mean_diff = (total_diff / comparison.compared as f64) as f32;
+    }
+
+    Ok(comparison)
+}
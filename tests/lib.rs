@@ -14,17 +14,215 @@ fn insta_test_help_message() -> Result<(), Box<dyn std::error::Error>> {
     insta::assert_snapshot!(&output, @r###"
     diff - for images (compares images pixel by pixel)
 
-    Usage: idiff [OPTIONS] --src <SOURCE_FILE_NAME> --tgt <TARGET_FILE_NAME>
+    Usage: idiff [OPTIONS] [COMMAND]
+
+    Commands:
+      compare          compare the source and target images (default, when no command is specified)
+      batch            compare the images with matching file names in the source and target directories
+      report           compare the source and target images and list the regions with difference
+      check            compare the images in the source and target directories against their budget (fails if any exceeds it)
+      approve          approve the target image as the new source image
+      baseline         store & retrieve the baselines by their content hash
+      mips             compare every mip level of the source and target textures (dds & ktx2)
+      extract-compare  compare the images embedded in the source and target documents (pdf, docx, pptx & xlsx) by their index
+      completions      print the shell completion script
+      man              print the man page (roff)
+      help             Print this message or the help of the given subcommand(s)
+
+    Options:
+          --explain-exit-codes
+              print the exit codes and their meaning
+          --lang <LANG>
+              language of the result & error messages [default: en] [possible values: en, ja]
+          --precision <DECIMALS>
+              number of decimals of the rendered percentage difference
+          --percent-format <PERCENT_FORMAT>
+              how the percentage difference is rendered [default: fixed] [possible values: fixed, scientific, ppm]
+      -h, --help
+              Print help
+      -V, --version
+              Print version
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn insta_test_compare_help_message() -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = Command::cargo_bin("idiff")?;
+    command.arg("compare").arg("--help");
+
+    let assert = command.assert().success();
+    let output = std::str::from_utf8(&assert.get_output().stdout)?;
+
+    insta::assert_snapshot!(&output, @r###"
+    compare the source and target images (default, when no command is specified)
+
+    Usage: idiff compare [OPTIONS] --src <SOURCE_FILE_NAME>
 
     Options:
-          --src <SOURCE_FILE_NAME>     source file name
-          --tgt <TARGET_FILE_NAME>     target file name
-          --strict                     strict comparison (exits if dimensions are different)
-          --highlight                  highlight differences in a new file
-          --block <BLOCK>              pixel block size for highlighting difference [default: 10]
-      -o, --output <OUTPUT_FILE_NAME>  optional output file name (without extension)
-      -h, --help                       Print help
-      -V, --version                    Print version
+          --src <SOURCE_FILE_NAME>
+              source file name
+          --tgt <TARGET_FILE_NAME>
+              target file name (or a generated 'color:#RRGGBB' of the source dimensions / transparent 'blank:WxH' image)
+          --self-compare
+              compare two regions (roi-a & roi-b) of the source image itself, for ex. to verify the symmetry or tiling of a texture
+          --strict
+              strict comparison (exits if dimensions are different)
+          --block <BLOCK>
+              pixel block size for highlighting difference (or auto, for 1% of the smaller dimension) [default: 10]
+          --block-shape <WIDTHxHEIGHT>
+              rectangular blocks (instead of square blocks of the block size)
+          --by-row
+              compare whole rows (scanlines) of the images, as blocks
+          --by-column
+              compare whole columns of the images, as blocks
+          --block-overlap <BLOCK_OVERLAP>
+              number of pixels adjacent blocks overlap by [default: 0]
+          --count-size-mismatch
+              count the regions only present in one of the images (when dimensions are different) as difference
+          --pre-blur <SIGMA>
+              standard deviation (sigma) of the gaussian blur applied to both the images before the comparison (to suppress noise)
+          --ignore-region <X,Y,WIDTH,HEIGHT>
+              region of the images to ignore in the comparison (repeatable)
+          --lang <LANG>
+              language of the result & error messages [default: en] [possible values: en, ja]
+          --mask <MASK_FILE_NAME>
+              mask image weighing the contribution of every pixel to the percentage difference by its alpha channel (or gray value, if opaque), from 0 (ignored) to 255 (fully counted)
+          --precision <DECIMALS>
+              number of decimals of the rendered percentage difference
+          --alpha-mode <ALPHA_MODE>
+              how the alpha channel is compared [default: straight] [possible values: straight, ignore, premultiplied]
+          --percent-format <PERCENT_FORMAT>
+              how the percentage difference is rendered [default: fixed] [possible values: fixed, scientific, ppm]
+          --normalize <NORMALIZE>
+              equalize the brightness / contrast of the target to the source before the comparison (for ex. photos taken under a slightly different exposure) [default: none] [possible values: none, histogram, mean-std]
+          --block-threshold <PERCENT>
+              percentage (0-100) of the pixels of a block which must differ for the block to be reported (& highlighted) as changed [default: 0]
+          --adaptive
+              subdivide (quadtree) only the regions with difference, down to blocks no larger than the block size, instead of comparing a fixed grid (tighter regions, faster on mostly identical images)
+          --detect-transform
+              check whether the target is a rotated (90, 180 or 270°) or mirrored source, to report the transform instead of the difference
+          --undo-transform
+              compare after undoing the detected transform of the target
+          --find-in-tgt
+              search the target for the best-matching position of the (smaller) source, and compare the source with the matched area
+          --roi-a <X,Y,WIDTH,HEIGHT>
+              region of the source to compare (instead of the whole source)
+          --roi-b <X,Y,WIDTH,HEIGHT>
+              region of the target to compare with (instead of the whole target)
+          --shift-tolerance <PIXELS>
+              count a target pixel as matching if an identical source pixel exists within the given number of pixels
+          --min-cluster-size <PIXELS>
+              ignore the regions (of adjacent pixels with difference) smaller than the given number of pixels
+          --min-luma-delta <DELTA>
+              ignore the pixels with a luminance difference (0-255) below the given delta, even if their color changed
+          --comparator <NAME>
+              block comparator (exact, tolerance, ssim, delta-e or a registered one) [default: exact]
+          --comparator-threshold <THRESHOLD>
+              threshold of the comparator (tolerance: largest channel difference, ssim: smallest similarity, delta-e: largest color difference)
+          --tolerance-mode <TOLERANCE_MODE>
+              compare the full precision (float, for ex. EXR) pixels, tolerating a difference of every channel up to float-tolerance [possible values: absolute, relative, ulps]
+          --float-tolerance <TOLERANCE>
+              largest tolerated difference of a channel in the tolerance mode (absolute: in linear units, relative: as a fraction of the larger value, ulps: in units in the last place) [default: 0]
+          --jpeg-aware
+              tolerate the expected re-encoding error of jpeg inputs (estimated per 8x8 block from their quantization tables)
+          --preview-scale <FACTOR>
+              downscale both the images by the factor (0-1) before the comparison, for a fast approximate result
+          --dpi-aware
+              turn the images upright & scale them to their common physical resolution (as per their orientation & DPI metadata, or else 72 DPI) before the comparison
+          --metric <METRIC>
+              additional metrics of the difference (comma separated) [possible values: psnr, mse, mae]
+          --progress
+              print the progress of the comparison (completed stages, compared blocks & found regions) into stderr
+          --sample <RATE>
+              compare only the (pseudo-randomly, but deterministically selected) fraction (0-1) of the pixels of every block, for a fast approximate result
+          --regions-only
+              only find the blocks with difference (by a checksum of every block), instead of counting the differing pixels: the percentage is of the area of the blocks with difference
+          --percentage-base <PERCENTAGE_BASE>
+              denominator of the percentage difference (default: overlap, or union with count-size-mismatch) [possible values: overlap, src, tgt, union]
+          --compare-metadata
+              compare the metadata (EXIF, XMP, ICC profile & PNG text chunks) of the images as well
+          --clusters
+              group the blocks with difference into clusters and classify their severity (minor / moderate / major)
+          --block-histogram
+              print the distribution (in 10% buckets) of the percentage of differing pixels of the blocks with difference
+          --severity-colors
+              color the highlighted blocks by their percentage of differing pixels (yellow below 1%, orange below 10%, red from 10%), with a legend strip below the output
+          --direction
+              classify the changed pixels of every block with difference as mostly brighter, darker, hue shifted or mixed
+          --classify-pixels
+              classify the differing pixels as added (transparent in the source), removed (transparent in the target) or changed
+          --saliency
+              weigh the differing pixels by the visual saliency of the source (near the top & the center, of high contrast) into a salient percentage difference
+          --minor-threshold <MINOR_THRESHOLD>
+              largest channel difference (0-255) of a cluster below which it is minor [default: 32]
+          --major-threshold <MAJOR_THRESHOLD>
+              largest channel difference (0-255) of a cluster from which it is major [default: 128]
+          --max-pixels <N>
+              refuse the images with more pixels (width x height) than the limit, before decoding them
+          --max-memory <MB>
+              refuse the images estimated to require more memory (in MB) than the limit to compare, before decoding them
+          --swap-channels <ORDER>
+              order the channels of the target are stored in, to reorder them into RGBA (for ex. bgr, argb or any permutation of rgba)
+          --swap-src-channels <ORDER>
+              order the channels of the source are stored in (see swap-channels)
+          --raw-format <RAW_FORMAT>
+              format of raw (headerless) src / tgt frames [possible values: nv12, yuv420p, rgb24]
+          --raw-size <WIDTHxHEIGHT>
+              dimensions of raw (headerless) src / tgt frames
+          --format-src <FORMAT>
+              format of the src image (for ex. webp), instead of the format detected from its content
+          --format-tgt <FORMAT>
+              format of the tgt image (for ex. webp), instead of the format detected from its content
+          --timings
+              print the time taken by every step (for ex. decode, diff, highlight & encode) into stderr (& include them in the json output)
+          --highlight
+              highlight differences in a new file
+      -o, --output <OUTPUT_FILE_NAME>
+              optional output file name, next to the target file unless it has directories (created if missing), for ex. artifacts/run42/login_diff (uses the extension of the target file, if not provided)
+          --output-dir <OUTPUT_DIRECTORY_NAME>
+              directory to write the output into (created, if it does not exist), instead of next to the target
+          --tile-output <TILE_DIRECTORY_NAME>
+              directory to write the output into as a Deep Zoom image (a pyramid of tiles, for ex. for OpenSeadragon to pan & zoom), instead of a single file
+          --tile-size <SIZE>
+              size (in pixels) of the square tiles of the tile output [default: 256]
+          --quality <QUALITY>
+              output quality (1-100) for the lossy formats (jpeg, webp & avif)
+          --force
+              overwrite the existing output files (refused, otherwise)
+          --timestamp-output
+              append a timestamp (UTC, YYYYMMDDTHHMMSS) to the names of the output files
+          --stamp
+              append a footer bar (file names, time, difference & version) below the output
+          --thumbnails <SIZE>
+              write thumbnails (of the size, in pixels, of the longer side) of the source, target & output next to the output
+          --output-format <OUTPUT_FORMAT>
+              how the differences are shown in the output [default: highlight] [possible values: highlight, blend, cutout]
+          --blend-alpha <ALPHA>
+              opacity (0-1) of the target composited over the source for the blend output [default: 0.5]
+          --highlight-base <HIGHLIGHT_BASE>
+              image the differences are shown over in the output (both writes an output per image) [default: tgt] [possible values: src, tgt, both]
+          --palette <PALETTE>
+              colors of the differences shown in the output (& the terminal preview) [default: default] [possible values: default, deuteranopia, protanopia, tritanopia, high-contrast]
+          --preview-tty [<PROTOCOL>]
+              render a downscaled output (with the differences shown) in the terminal [possible values: auto, blocks, sixel, kitty]
+          --crop-regions <CROP_DIRECTORY_NAME>
+              save every changed region (padded by a block) cropped from the source & target into the directory
+          --crop-to-changes <CROP_DIRECTORY_NAME>
+              save the bounding box of all the changes cropped from the source & target into the directory
+          --matrix-output <MATRIX_FILE_NAME>
+              write the difference magnitudes (largest channel difference, 0-255) of the common region as a matrix (.csv or .npy)
+          --matrix-level <MATRIX_LEVEL>
+              cells of the matrix output [default: block] [possible values: block, pixel]
+          --wait-for-files <TIMEOUT>
+              wait (up to the number of seconds) for the inputs to exist and stop changing in size, before comparing
+          --dry-run
+              validate the inputs (reading only their headers), print what would be compared & where the output would be written, and exit
+          --info
+              print the format, color type, bit depth, dimensions, frame count & ICC profile presence of both inputs, and exit (without comparing)
+      -h, --help
+              Print help
     "###);
 
     Ok(())
@@ -46,7 +244,7 @@ fn should_fail_when_invalid_file_is_used() -> Result<(), Box<dyn std::error::Err
         .arg(temp_file.as_os_str());
     command
         .assert()
-        .failure()
+        .code(3)
         .stderr(predicate::str::contains(err_msg));
 
     temp_dir.close()?;
@@ -69,9 +267,1973 @@ fn should_fail_when_opening_invalid_file_as_image() -> Result<(), Box<dyn std::e
         .arg(temp_file.as_os_str());
     command
         .assert()
-        .failure()
+        .code(4)
         .stderr(predicate::str::contains(err_msg));
 
     temp_dir.close()?;
     Ok(())
 }
+
+#[test]
+fn should_compare_files_with_matching_names_in_batch() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+
+    img.save(src_dir.child("same.png").path())?;
+    img.save(tgt_dir.child("same.png").path())?;
+    img.save(src_dir.child("changed.png").path())?;
+    changed.save(tgt_dir.child("changed.png").path())?;
+    img.save(src_dir.child("only_in_src.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("changed.png: 0.01%"))
+        .stdout(predicate::str::contains("same.png: no difference"))
+        .stdout(predicate::str::contains("only_in_src.png:").not())
+        .stdout(predicate::str::contains("Only in src: only_in_src.png"))
+        .stdout(predicate::str::contains(
+            "1 of 2 image(s) have differences.",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_pair_files_by_hash_or_order_in_batch() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+
+    img.save(src_dir.child("a.png").path())?;
+    changed.save(src_dir.child("b.png").path())?;
+    changed.save(tgt_dir.child("device_1.png").path())?;
+    changed.save(tgt_dir.child("device_2.png").path())?;
+
+    let batch = |pair_by: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("batch")
+            .arg("--src")
+            .arg(src_dir.as_os_str())
+            .arg("--tgt")
+            .arg(tgt_dir.as_os_str())
+            .arg("--pair-by")
+            .arg(pair_by);
+        Ok(command)
+    };
+    batch("hash")?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "b.png -> device_1.png: no difference",
+        ))
+        .stdout(predicate::str::contains("Only in src: a.png"))
+        .stdout(predicate::str::contains("Only in tgt: device_2.png"))
+        .stdout(predicate::str::contains(
+            "0 of 1 image(s) have differences.",
+        ));
+    batch("order")?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("a.png -> device_1.png: 0.01%"))
+        .stdout(predicate::str::contains(
+            "b.png -> device_2.png: no difference",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_batch_results_in_order_with_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(50, 50);
+    for i in 0..12 {
+        let name = format!("{:02}.png", i);
+        let mut changed = img.clone();
+        for x in 0..i {
+            changed.put_pixel(x, 0, image::Rgba([255, 255, 255, 255]));
+        }
+        img.save(src_dir.child(&name).path())?;
+        changed.save(tgt_dir.child(&name).path())?;
+    }
+
+    let batch = |jobs: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("batch")
+            .arg("--src")
+            .arg(src_dir.as_os_str())
+            .arg("--tgt")
+            .arg(tgt_dir.as_os_str())
+            .arg("--jobs")
+            .arg(jobs);
+        let output = command.assert().code(1).get_output().stdout.clone();
+        Ok(String::from_utf8(output)?)
+    };
+    let sequential = batch("1")?;
+    let names: Vec<&str> = sequential
+        .lines()
+        .filter_map(|line| line.split_once(": ").map(|(name, _)| name))
+        .collect();
+    assert_eq!(
+        (0..12).map(|i| format!("{:02}.png", i)).collect::<Vec<_>>(),
+        names
+    );
+    assert_eq!(sequential, batch("4")?);
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_filter_files_in_batch() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(10, 10);
+    for name in ["login_1.png", "login_2.PNG", "login_draft.png", "home.png"] {
+        img.save(src_dir.child(name).path())?;
+        img.save(tgt_dir.child(name).path())?;
+    }
+    img.save(src_dir.child("login_3.png").path())?;
+    src_dir.child("login_notes.txt").write_str("notes")?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--include")
+        .arg("login_*")
+        .arg("--exclude")
+        .arg("*_draft.*")
+        .arg("--extensions")
+        .arg("png");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("login_1.png: no difference"))
+        .stdout(predicate::str::contains("login_2.PNG: no difference"))
+        .stdout(predicate::str::contains("Only in src: login_3.png"))
+        .stdout(predicate::str::contains("draft").not())
+        .stdout(predicate::str::contains("home").not())
+        .stdout(predicate::str::contains("notes").not())
+        .stdout(predicate::str::contains(
+            "0 of 2 image(s) have differences.",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_classify_the_direction_of_the_regions() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::RgbaImage::from_pixel(100, 50, image::Rgba([120, 120, 120, 255]));
+    img.save(src.path())?;
+    let mut changed = img.clone();
+    for y in 0..10 {
+        for x in 0..10 {
+            changed.put_pixel(x, y, image::Rgba([60, 60, 60, 255]));
+            changed.put_pixel(x + 90, y + 40, image::Rgba([200, 200, 200, 255]));
+        }
+    }
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("report")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--direction");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "region 1 (width: 0..10, height: 0..10): mostly darker",
+        ))
+        .stdout(predicate::str::contains(
+            "region 2 (width: 90..100, height: 40..50): mostly brighter",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_report_mae_of_a_global_shift() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::from_pixel(20, 20, image::Rgba([100, 100, 100, 255])).save(src.path())?;
+    image::RgbaImage::from_pixel(20, 20, image::Rgba([102, 102, 102, 255])).save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--metric")
+        .arg("mae");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '100%'"))
+        .stdout(predicate::str::contains("MAE: 2.0000"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_find_the_source_in_the_target() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("widget.png");
+    let tgt = temp_dir.child("page.png");
+    let page = image::RgbaImage::from_fn(300, 200, |x, y| {
+        image::Rgba([
+            (x * 255 / 300) as u8,
+            (y * 255 / 200) as u8,
+            ((x / 20 * 37 + y / 20 * 91) % 255) as u8,
+            255,
+        ])
+    });
+    page.save(tgt.path())?;
+    let mut widget = image::imageops::crop_imm(&page, 120, 45, 80, 60).to_image();
+    widget.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+    widget.save(src.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--find-in-tgt");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "The source is found in the target at 120, 45",
+        ))
+        .stdout(predicate::str::contains("1 pixel(s) with difference."));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_report_detected_transform() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::RgbaImage::from_fn(60, 40, |x, y| {
+        image::Rgba([x as u8 * 4, y as u8 * 6, 0, 255])
+    });
+    img.save(src.path())?;
+    let mut rotated = image::imageops::rotate90(&img);
+    rotated.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+    rotated.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--detect-transform");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "The target is the source rotated by 90° clockwise",
+        ))
+        .stdout(predicate::str::contains("A difference of").not());
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--detect-transform")
+        .arg("--undo-transform");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("compared after undoing it"))
+        .stdout(predicate::str::contains("1 pixel(s) with difference."));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_append_legend_when_coloring_by_severity() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(100, 100).save(src.path())?;
+    let mut img = image::RgbaImage::new(100, 100);
+    img.put_pixel(55, 55, image::Rgba([255, 255, 255, 255]));
+    img.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--highlight")
+        .arg("--severity-colors");
+    command.assert().code(1);
+
+    let output = image::open(temp_dir.child("tgt_diff.png").path())?.to_rgba8();
+    assert_eq!((100, 118), output.dimensions());
+    assert_eq!(image::Rgba([255, 140, 0, 255]), *output.get_pixel(50, 50));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_create_directories_of_output() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(100, 100).save(src.path())?;
+    image::RgbaImage::from_pixel(100, 100, image::Rgba([255, 0, 0, 255])).save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .current_dir(temp_dir.path())
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--highlight")
+        .arg("--output")
+        .arg("artifacts/run42/login_diff");
+    command.assert().code(1);
+    temp_dir
+        .child("artifacts/run42/login_diff.png")
+        .assert(predicate::path::is_file());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_render_percentage_in_requested_format() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(1000, 1000).save(src.path())?;
+    let mut img = image::RgbaImage::new(1000, 1000);
+    img.put_pixel(10, 10, image::Rgba([255, 255, 255, 255]));
+    img.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--percent-format")
+        .arg("ppm")
+        .arg("--precision")
+        .arg("1");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '1.0 ppm'"))
+        .stdout(predicate::str::contains("1 pixel(s) with difference."));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_messages_in_the_requested_language() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(100, 100).save(src.path())?;
+    image::RgbaImage::new(100, 100).save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--lang")
+        .arg("ja");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("画像間に差分はありません"));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(temp_dir.child("missing.png").path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--lang")
+        .arg("ja");
+    command
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("パスが不正です"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_report_all_errors_at_the_end_when_keeping_going() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    img.save(src_dir.child("a.png").path())?;
+    tgt_dir.child("a.png").write_str("not an image")?;
+    img.save(src_dir.child("b.png").path())?;
+    img.save(tgt_dir.child("b.png").path())?;
+    img.save(src_dir.child("c.png").path())?;
+    image::RgbaImage::new(50, 50).save(tgt_dir.child("c.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--strict")
+        .arg("--keep-going");
+    command
+        .assert()
+        .code(4)
+        .stdout(predicate::str::contains("b.png: no difference"))
+        .stderr(predicate::str::contains("2 error(s):"))
+        .stderr(predicate::str::contains(
+            "a.png: Encountered error while opening",
+        ))
+        .stderr(predicate::str::contains("c.png: 'src'"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_replace_source_with_target_when_approved() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_file = temp_dir.child("src.png");
+    let tgt_file = temp_dir.child("tgt.png");
+    src_file.write_str("source")?;
+    tgt_file.write_str("target")?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("approve")
+        .arg("--src")
+        .arg(src_file.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_file.as_os_str());
+    command.assert().success();
+
+    src_file.assert("target");
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_write_output_in_the_format_of_its_extension() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_file = temp_dir.child("src.png");
+    let tgt_file = temp_dir.child("tgt.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src_file.path())?;
+    changed.save(tgt_file.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src_file.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_file.as_os_str())
+        .arg("--highlight")
+        .arg("--output")
+        .arg("diff.jpg")
+        .arg("--quality")
+        .arg("50");
+    command.assert().code(1);
+
+    assert_eq!(
+        image::ImageFormat::Jpeg,
+        image::io::Reader::open(temp_dir.child("diff.jpg").path())?
+            .with_guessed_format()?
+            .format()
+            .unwrap()
+    );
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_files_within_archives() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let archive = temp_dir.child("baseline.zip");
+    let tgt_dir = temp_dir.child("tgt");
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    changed.save(tgt_dir.child("a.png").path())?;
+
+    let mut png = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut png),
+        image::ImageOutputFormat::Png,
+    )?;
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(archive.path())?);
+    zip.start_file("a.png", zip::write::FileOptions::default())?;
+    std::io::Write::write_all(&mut zip, &png)?;
+    zip.finish()?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(format!("{}::a.png", archive.path().display()))
+        .arg("--tgt")
+        .arg(tgt_dir.child("a.png").as_os_str());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '0.01%'"));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(archive.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("a.png: 0.01%"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_shell_completions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = Command::cargo_bin("idiff")?;
+    command.arg("completions").arg("bash");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("_idiff()"));
+
+    Ok(())
+}
+
+#[test]
+fn should_print_man_page_with_options_and_exit_codes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = Command::cargo_bin("idiff")?;
+    command.arg("man");
+    command.assert().success().stdout(
+        predicate::str::contains(".TH idiff 1")
+            .and(predicate::str::contains("COMPARE OPTIONS"))
+            .and(predicate::str::contains("EXIT STATUS")),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_compare_pages_of_multi_page_tiff() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let scan = temp_dir.child("scan.tif");
+
+    let mut encoder = tiff::encoder::TiffEncoder::new(std::fs::File::create(scan.path())?)?;
+    for page in [[0, 0, 0], [0, 0, 0], [255, 255, 255]] {
+        encoder.write_image::<tiff::encoder::colortype::RGB8>(20, 20, &page.repeat(400))?;
+    }
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(format!("{}#0", scan.path().display()))
+        .arg("--tgt")
+        .arg(format!("{}#1", scan.path().display()));
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No difference observed"));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(format!("{}#1", scan.path().display()))
+        .arg("--tgt")
+        .arg(format!("{}#2", scan.path().display()));
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '100%'"));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(scan.as_os_str())
+        .arg("--tgt")
+        .arg(format!("{}#0", scan.path().display()));
+    command.assert().success().stdout(predicate::str::contains(
+        "Warning: only the first page / frame of the src image is compared",
+    ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_fail_check_when_images_exceed_their_budget() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+
+    for name in ["flaky.png", "exact.png"] {
+        img.save(src_dir.child(name).path())?;
+        changed.save(tgt_dir.child(name).path())?;
+    }
+    let budget = temp_dir.child("budgets.toml");
+    budget.write_str("[files]\n\"flaky.png\" = 0.5\n")?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("check")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--budget")
+        .arg(budget.as_os_str());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("flaky.png: 0.01% (budget: 0.5%)"))
+        .stderr(predicate::str::contains(
+            "1 of 2 image(s) exceed their budget:\n  exact.png: 0.01% (budget: 0%)",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_reuse_cached_results_of_unchanged_pairs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    let cache_dir = temp_dir.child("cache");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    // Note: the pairs need different contents, as the results are cached by content
+    img.save(src_dir.child("a.png").path())?;
+    img.save(tgt_dir.child("a.png").path())?;
+    let other = image::RgbaImage::new(50, 50);
+    other.save(src_dir.child("b.png").path())?;
+    other.save(tgt_dir.child("b.png").path())?;
+
+    let batch = |code: i32, expected: &str| -> Result<(), Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("batch")
+            .arg("--src")
+            .arg(src_dir.as_os_str())
+            .arg("--tgt")
+            .arg(tgt_dir.as_os_str())
+            .arg("--cache-dir")
+            .arg(cache_dir.as_os_str());
+        command
+            .assert()
+            .code(code)
+            .stdout(predicate::str::contains(expected));
+        Ok(())
+    };
+
+    batch(0, "0 of 2 image(s) have differences (0 from the cache).")?;
+    batch(0, "0 of 2 image(s) have differences (2 from the cache).")?;
+    changed.save(tgt_dir.child("a.png").path())?;
+    batch(1, "1 of 2 image(s) have differences (1 from the cache).")?;
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_write_outputs_into_output_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    let output_dir = temp_dir.child("artifacts/diffs");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src_dir.child("a.png").path())?;
+    changed.save(tgt_dir.child("a.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--highlight")
+        .arg("--output-dir")
+        .arg(output_dir.as_os_str());
+    command.assert().code(1);
+
+    output_dir
+        .child("a_diff.png")
+        .assert(predicate::path::is_file());
+    tgt_dir
+        .child("a_diff.png")
+        .assert(predicate::path::missing());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_refuse_to_overwrite_outputs_unless_forced() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_file = temp_dir.child("src.png");
+    let tgt_file = temp_dir.child("tgt.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src_file.path())?;
+    changed.save(tgt_file.path())?;
+    temp_dir.child("tgt_diff.png").write_str("kept")?;
+
+    let compare = |option: Option<&str>| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("--src")
+            .arg(src_file.as_os_str())
+            .arg("--tgt")
+            .arg(tgt_file.as_os_str())
+            .arg("--highlight")
+            .args(option);
+        Ok(command)
+    };
+    compare(None)?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    temp_dir.child("tgt_diff.png").assert("kept");
+
+    compare(Some("--timestamp-output"))?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_match(r"tgt_diff_\d{8}T\d{6}\.png")?);
+    compare(Some("--force"))?.assert().code(1);
+    image::open(temp_dir.child("tgt_diff.png").path())?;
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_exr_renders_with_relative_tolerance() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_file = temp_dir.child("src.exr");
+    let tgt_file = temp_dir.child("tgt.exr");
+
+    let img = image::Rgba32FImage::from_pixel(100, 100, image::Rgba([4.0, 2.0, 1.0, 1.0]));
+    let mut noisy = img.clone();
+    noisy.put_pixel(10, 10, image::Rgba([4.001, 2.0, 1.0, 1.0]));
+    image::DynamicImage::ImageRgba32F(img).save(src_file.path())?;
+    image::DynamicImage::ImageRgba32F(noisy).save(tgt_file.path())?;
+
+    let compare = |tolerance: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("--src")
+            .arg(src_file.as_os_str())
+            .arg("--tgt")
+            .arg(tgt_file.as_os_str())
+            .arg("--tolerance-mode")
+            .arg("relative")
+            .arg("--float-tolerance")
+            .arg(tolerance);
+        Ok(command)
+    };
+    compare("1e-3")?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No difference observed"));
+    compare("1e-4")?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("0.01"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_timings_of_every_step() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_file = temp_dir.child("src.png");
+    let tgt_file = temp_dir.child("tgt.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src_file.path())?;
+    changed.save(tgt_file.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src_file.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_file.as_os_str())
+        .arg("--highlight")
+        .arg("--timings");
+    command
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("[timings] decode: "))
+        .stderr(predicate::str::contains("[timings] diff: "))
+        .stderr(predicate::str::contains("[timings] highlight: "))
+        .stderr(predicate::str::contains("[timings] encode: "));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_images_embedded_in_documents() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+
+    for (name, images) in [("v1.docx", vec![&img]), ("v2.docx", vec![&changed, &img])] {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(temp_dir.child(name).path())?);
+        zip.start_file("word/document.xml", zip::write::FileOptions::default())?;
+        for (index, img) in images.iter().enumerate() {
+            let mut png = Vec::new();
+            img.write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )?;
+            zip.start_file(
+                format!("word/media/image{}.png", index + 1),
+                zip::write::FileOptions::default(),
+            )?;
+            std::io::Write::write_all(&mut zip, &png)?;
+        }
+        zip.finish()?;
+    }
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("extract-compare")
+        .arg("--src")
+        .arg(temp_dir.child("v1.docx").as_os_str())
+        .arg("--tgt")
+        .arg(temp_dir.child("v2.docx").as_os_str())
+        .arg("--images");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "#0 (word/media/image1.png): 0.01%",
+        ))
+        .stdout(predicate::str::contains(
+            "#1 (word/media/image2.png): only in tgt",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_only_print_what_would_be_compared_in_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let output_dir = temp_dir.child("diffs");
+    image::RgbaImage::new(100, 100).save(temp_dir.child("a.png").path())?;
+    image::GrayImage::new(120, 100).save(temp_dir.child("b.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(temp_dir.child("a.png").as_os_str())
+        .arg("--tgt")
+        .arg(temp_dir.child("b.png").as_os_str())
+        .arg("--highlight")
+        .arg("--output-dir")
+        .arg(output_dir.as_os_str())
+        .arg("--dry-run");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(png, 100x100, 8-bit rgba)"))
+        .stdout(predicate::str::contains("(png, 120x100, 8-bit gray)"))
+        .stdout(predicate::str::contains("b_diff.png"));
+    output_dir.assert(predicate::path::missing());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_against_pseudo_targets() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("render.png");
+    let mut img = image::RgbaImage::from_pixel(10, 10, image::Rgba([0, 0, 0, 255]));
+    img.put_pixel(5, 5, image::Rgba([255, 255, 255, 255]));
+    img.save(src.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg("color:#000000")
+        .arg("--highlight");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '1%'"));
+    temp_dir
+        .child("render_diff.png")
+        .assert(predicate::path::exists());
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg("blank:10x10");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '100%'"));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg("color:#00");
+    command
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid pseudo-target"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_info_of_the_inputs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    image::GrayImage::new(120, 100).save(temp_dir.child("a.png").path())?;
+    let mut encoder =
+        image::codecs::gif::GifEncoder::new(std::fs::File::create(temp_dir.child("b.gif").path())?);
+    encoder.encode_frames((0..3).map(|_| image::Frame::new(image::RgbaImage::new(40, 30))))?;
+    drop(encoder);
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(temp_dir.child("a.png").as_os_str())
+        .arg("--tgt")
+        .arg(temp_dir.child("b.gif").as_os_str())
+        .arg("--info");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("format: png"))
+        .stdout(predicate::str::contains("color type: gray"))
+        .stdout(predicate::str::contains("bit depth: 8"))
+        .stdout(predicate::str::contains("dimensions: 120x100"))
+        .stdout(predicate::str::contains("format: gif"))
+        .stdout(predicate::str::contains("dimensions: 40x30"))
+        .stdout(predicate::str::contains("frames: 3"))
+        .stdout(predicate::str::contains("ICC profile: no"))
+        .stdout(predicate::str::contains("difference").not());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_files_matching_glob_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    for dir in ["old", "new"] {
+        temp_dir.child(dir).create_dir_all()?;
+        img.save(temp_dir.child(dir).child("home.png").path())?;
+        img.save(temp_dir.child(dir).child("notes.bmp").path())?;
+    }
+    changed.save(temp_dir.child("new/menü.png").path())?;
+    img.save(temp_dir.child("old/menü.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(temp_dir.child("old/*.png").as_os_str())
+        .arg("--tgt")
+        .arg(temp_dir.child("new/*.png").as_os_str());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("home.png: no difference"))
+        .stdout(predicate::str::contains("menü.png: 0.01%"))
+        .stdout(predicate::str::contains("1 of 2 image(s) have differences"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_exit_with_the_code_of_the_outcome() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let small = temp_dir.child("small.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+    image::RgbaImage::new(50, 50).save(small.path())?;
+
+    let compare =
+        |tgt: &std::path::Path, strict: bool| -> Result<Command, Box<dyn std::error::Error>> {
+            let mut command = Command::cargo_bin("idiff")?;
+            command.arg("--src").arg(src.path()).arg("--tgt").arg(tgt);
+            if strict {
+                command.arg("--strict");
+            }
+            Ok(command)
+        };
+    compare(src.path(), false)?.assert().code(0);
+    compare(tgt.path(), false)?.assert().code(1);
+    compare(small.path(), false)?.assert().code(0);
+    compare(small.path(), true)?.assert().code(5);
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command.arg("--explain-exit-codes");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "3  error reading or writing a file",
+        ))
+        .stdout(predicate::str::contains(
+            "5  the images do not have the same dimensions",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_weigh_the_difference_by_the_mask() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let mask = temp_dir.child("mask.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    *changed.get_pixel_mut(60, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+    // Note: the left half is ignored, the changed pixel of the right half only counts as a fifth
+    let mut weights =
+        image::GrayImage::from_fn(100, 100, |x, _| image::Luma([if x < 50 { 0 } else { 255 }]));
+    weights.put_pixel(60, 10, image::Luma([51]));
+    weights.save(mask.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--mask")
+        .arg(mask.path())
+        .arg("--precision")
+        .arg("3");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A difference of '0.002%'"))
+        .stdout(predicate::str::contains("1 pixel(s) with difference."));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_numbered_frame_sequences() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    for frame in 1..=4 {
+        let name = format!("frame_{:04}.png", frame);
+        img.save(src_dir.child(&name).path())?;
+        match frame {
+            3 => changed.save(tgt_dir.child(&name).path())?,
+            4 => {}
+            _ => img.save(tgt_dir.child(&name).path())?,
+        }
+    }
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--sequence")
+        .arg("frame_%04d.png")
+        .arg("--range")
+        .arg("1..5");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("frame_0002.png: no difference"))
+        .stdout(predicate::str::contains("frame_0003.png: 0.01%"))
+        .stdout(predicate::str::contains("Only in src: frame_0004.png"))
+        .stdout(predicate::str::contains(
+            "First diverging frame: frame_0003.png",
+        ))
+        .stdout(predicate::str::contains(
+            "1 of 3 image(s) have differences.",
+        ));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.child("frame_%04d.png").path())
+        .arg("--tgt")
+        .arg(tgt_dir.child("frame_%04d.png").path())
+        .arg("--range")
+        .arg("1..2");
+    command
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No diverging frame."));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_write_html_gallery_of_batch_results() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    let report_dir = temp_dir.child("report");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src_dir.child("changed.png").path())?;
+    changed.save(tgt_dir.child("changed.png").path())?;
+    img.save(src_dir.child("same.png").path())?;
+    img.save(tgt_dir.child("same.png").path())?;
+    img.save(tgt_dir.child("new.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--report-dir")
+        .arg(report_dir.as_os_str())
+        .arg("--jobs")
+        .arg("1");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Report written into"));
+
+    report_dir
+        .child("index.html")
+        .assert(predicate::str::contains(
+            "<td>changed.png</td><td>different</td><td>0.01%</td><td><a href=\"0000_changed_png.png\">diff</a></td>",
+        ))
+        .assert(predicate::str::contains("<td>same.png</td><td>identical</td>"))
+        .assert(predicate::str::contains("<td>new.png</td><td>only in tgt</td>"));
+    report_dir
+        .child("0000_changed_png.png")
+        .assert(predicate::path::exists());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_print_the_progress_of_the_comparison() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("report")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--progress");
+    command
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("[progress] decode completed in"))
+        .stderr(predicate::str::contains(
+            "(100 block(s) compared, 1 region(s) found so far)",
+        ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_write_the_output_as_tiles() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("scan.png");
+    let tgt = temp_dir.child("scan_v2.png");
+    let tiles = temp_dir.child("tiles");
+
+    let img = image::RgbaImage::new(300, 200);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(280, 150) = image::Rgba([10, 10, 10, 255]);
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("compare")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--highlight")
+        .arg("--tile-output")
+        .arg(tiles.path())
+        .arg("--tile-size")
+        .arg("128");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("scan_v2_diff.dzi"));
+
+    tiles
+        .child("scan_v2_diff.dzi")
+        .assert(predicate::str::contains("TileSize=\"128\""));
+    let files = tiles.child("scan_v2_diff_files");
+    files.child("9/2_1.png").assert(predicate::path::is_file());
+    files.child("9/3_0.png").assert(predicate::path::missing());
+    files.child("0/0_0.png").assert(predicate::path::is_file());
+    temp_dir
+        .child("scan_v2_diff.png")
+        .assert(predicate::path::missing());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_pair_files_by_similarity_in_batch() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src_dir = temp_dir.child("src");
+    let tgt_dir = temp_dir.child("tgt");
+    src_dir.create_dir_all()?;
+    tgt_dir.create_dir_all()?;
+
+    let horizontal =
+        image::RgbaImage::from_fn(100, 100, |x, _| image::Rgba([(x * 2) as u8, 0, 0, 255]));
+    let vertical = image::RgbaImage::from_fn(100, 100, |x, y| {
+        image::Rgba([(((x / 10) % 2) * 255) as u8, y as u8, 0, 255])
+    });
+    let mut changed = horizontal.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+
+    horizontal.save(src_dir.child("login_8f3a.png").path())?;
+    vertical.save(src_dir.child("home_1c2d.png").path())?;
+    changed.save(tgt_dir.child("login_77e0.png").path())?;
+    image::imageops::flip_horizontal(&horizontal).save(tgt_dir.child("other_5b9c.png").path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("batch")
+        .arg("--src")
+        .arg(src_dir.as_os_str())
+        .arg("--tgt")
+        .arg(tgt_dir.as_os_str())
+        .arg("--pair-by")
+        .arg("similarity");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "login_8f3a.png -> login_77e0.png: 0.01%",
+        ))
+        .stdout(predicate::str::contains("Only in src: home_1c2d.png"))
+        .stdout(predicate::str::contains("Only in tgt: other_5b9c.png"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_only_report_the_regions_when_requested() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("a.png");
+    let tgt = temp_dir.child("b.png");
+
+    let img = image::RgbaImage::new(100, 100);
+    let mut changed = img.clone();
+    *changed.get_pixel_mut(10, 10) = image::Rgba([10, 10, 10, 255]);
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("compare")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--regions-only");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("1%"))
+        .stdout(predicate::str::contains("Approximate result (regions only"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_refuse_images_exceeding_the_resource_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("a.png");
+    let tgt = temp_dir.child("b.png");
+    image::RgbaImage::new(100, 100).save(src.path())?;
+    image::RgbaImage::new(1000, 1000).save(tgt.path())?;
+
+    let compare = |option: &str, limit: &str| -> Result<Command, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("compare")
+            .arg("--src")
+            .arg(src.path())
+            .arg("--tgt")
+            .arg(tgt.path())
+            .arg(option)
+            .arg(limit);
+        Ok(command)
+    };
+    compare("--max-pixels", "100000")?
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "target image (1000x1000, 1000000 pixels) exceeds the limit of 100000 pixels",
+        ));
+    compare("--max-memory", "4")?
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("which exceeds the limit of 4 MB"));
+    compare("--max-memory", "16")?.assert().success();
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_two_regions_of_the_same_image() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let texture = temp_dir.child("texture.png");
+
+    let mut img = image::RgbaImage::from_fn(200, 100, |x, y| {
+        image::Rgba([(x % 100) as u8, y as u8, 0, 255])
+    });
+    img.save(texture.path())?;
+
+    let self_compare = || -> Result<Command, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("compare")
+            .arg("--src")
+            .arg(texture.path())
+            .arg("--self-compare")
+            .arg("--roi-a")
+            .arg("0,0,100,100")
+            .arg("--roi-b")
+            .arg("100,0,100,100");
+        Ok(command)
+    };
+    self_compare()?.assert().success();
+
+    *img.get_pixel_mut(150, 50) = image::Rgba([255, 255, 255, 255]);
+    img.save(texture.path())?;
+    self_compare()?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("0.01%"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_two_regions_of_the_same_paletted_image() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = assert_fs::TempDir::new()?;
+    let half = temp_dir.child("half.png");
+
+    // Note: a paletted image, black on the left half & white on the right half
+    let mut encoder = png::Encoder::new(std::fs::File::create(half.path())?, 40, 20);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(vec![0, 0, 0, 255, 255, 255]);
+    let indices: Vec<u8> = (0..40 * 20).map(|i| (i % 40 >= 20) as u8).collect();
+    encoder.write_header()?.write_image_data(&indices)?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("compare")
+        .arg("--src")
+        .arg(half.path())
+        .arg("--self-compare")
+        .arg("--roi-a")
+        .arg("0,0,20,20")
+        .arg("--roi-b")
+        .arg("20,0,20,20");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("100%"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_render_the_report_in_the_requested_format() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(20, 20).save(src.path())?;
+    let mut changed = image::RgbaImage::new(20, 20);
+    changed.put_pixel(5, 5, image::Rgba([255, 0, 0, 255]));
+    changed.save(tgt.path())?;
+
+    let report = |format: &str| -> Result<_, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("report")
+            .arg("--src")
+            .arg(src.path())
+            .arg("--tgt")
+            .arg(tgt.path())
+            .arg("--block")
+            .arg("10")
+            .arg("--format")
+            .arg(format);
+        Ok(command.assert())
+    };
+    report("junit")?
+        .code(1)
+        .stdout(predicate::str::contains("failures=\"1\""))
+        .stdout(predicate::str::contains(
+            "x: 0, y: 0, width: 10, height: 10</failure>",
+        ));
+    report("github")?
+        .code(1)
+        .stdout(predicate::str::starts_with("::error file="));
+    report("csv")?
+        .code(2)
+        .stderr(predicate::str::contains("unknown report format 'csv'"));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_report_and_crop_the_bounding_box_of_the_changes() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::new(100, 100).save(src.path())?;
+    let mut changed = image::RgbaImage::new(100, 100);
+    changed.put_pixel(15, 5, image::Rgba([255, 0, 0, 255]));
+    changed.put_pixel(55, 35, image::Rgba([255, 0, 0, 255]));
+    changed.save(tgt.path())?;
+    let crops = temp_dir.child("crops");
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--block")
+        .arg("10")
+        .arg("--crop-to-changes")
+        .arg(crops.path());
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "All the changes are within 50x40 at 10, 0.",
+        ))
+        .stdout(predicate::str::contains("Changed area cropped into"));
+
+    assert_eq!(
+        (50, 40),
+        image::open(crops.child("changes_tgt.png").path())?
+            .to_rgba8()
+            .dimensions()
+    );
+    assert_eq!(
+        &image::Rgba([255, 0, 0, 255]),
+        image::open(crops.child("changes_tgt.png").path())?
+            .to_rgba8()
+            .get_pixel(45, 35)
+    );
+    crops
+        .child("changes_src.png")
+        .assert(predicate::path::exists());
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_append_a_footer_to_the_output_when_stamped() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::RgbaImage::from_pixel(100, 50, image::Rgba([0, 0, 255, 255]));
+    let mut changed = img.clone();
+    changed.put_pixel(50, 25, image::Rgba([255, 0, 0, 255]));
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--highlight")
+        .arg("--stamp");
+    command.assert().code(1);
+
+    let output = image::open(temp_dir.child("tgt_diff.png").path())?.to_rgba8();
+    assert_eq!((100, 81), output.dimensions());
+    assert_eq!(&image::Rgba([32, 32, 32, 255]), output.get_pixel(99, 80));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_write_thumbnails_next_to_the_output() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::RgbaImage::from_pixel(200, 100, image::Rgba([0, 0, 255, 255]));
+    let mut changed = img.clone();
+    changed.put_pixel(50, 25, image::Rgba([255, 0, 0, 255]));
+    img.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--highlight")
+        .arg("--thumbnails")
+        .arg("50");
+    command
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Thumbnail written into"));
+
+    for name in [
+        "tgt_diff_thumb.png",
+        "tgt_diff_thumb_src.png",
+        "tgt_diff_thumb_tgt.png",
+    ] {
+        let thumbnail = image::open(temp_dir.child(name).path())?.to_rgba8();
+        assert_eq!((50, 25), thumbnail.dimensions());
+    }
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_images_of_different_dpi_at_the_same_physical_size(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.jpg");
+    let tgt = temp_dir.child("tgt.jpg");
+    // Note: a retina screenshot (144 DPI) & a capture (72 DPI) of the same content
+    for (file, (width, height), dpi) in [(&src, (200, 100), 144), (&tgt, (100, 50), 72)] {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([0, 0, 255]));
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+            std::fs::File::create(file.path())?,
+            100,
+        );
+        encoder.set_pixel_density(image::codecs::jpeg::PixelDensity::dpi(dpi));
+        encoder.encode_image(&img)?;
+    }
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--dpi-aware");
+    command.assert().code(0).stdout(predicate::str::contains(
+        "src image is rescaled from 200x100 to 100x50",
+    ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_detect_the_format_from_the_content() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        20,
+        20,
+        image::Rgba([0, 0, 255, 255]),
+    ));
+    img.save(src.path())?;
+    // Note: a gif named .png & a tga (without magic bytes) with an unknown extension
+    img.save_with_format(tgt.path(), image::ImageFormat::Gif)?;
+    let tga = temp_dir.child("tgt.img");
+    img.save_with_format(tga.path(), image::ImageFormat::Tga)?;
+
+    let compare =
+        |tgt: &std::path::Path, format: Option<&str>| -> Result<_, Box<dyn std::error::Error>> {
+            let mut command = Command::cargo_bin("idiff")?;
+            command.arg("--src").arg(src.path()).arg("--tgt").arg(tgt);
+            if let Some(format) = format {
+                command.arg("--format-tgt").arg(format);
+            }
+            Ok(command.assert())
+        };
+    compare(tgt.path(), None)?.code(0);
+    compare(tga.path(), None)?.code(4);
+    compare(tga.path(), Some("tga"))?.code(0);
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_weigh_the_difference_by_saliency() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    let img = image::RgbaImage::from_pixel(100, 100, image::Rgba([255, 255, 255, 255]));
+    img.save(src.path())?;
+
+    // Note: the same change (20 pixels, 0.2%) in the header & in the footer
+    let salient_difference = |y: u32| -> Result<f32, Box<dyn std::error::Error>> {
+        let mut changed = img.clone();
+        for x in 40..60 {
+            changed.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+        }
+        changed.save(tgt.path())?;
+
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("--src")
+            .arg(src.path())
+            .arg("--tgt")
+            .arg(tgt.path())
+            .arg("--saliency");
+        let output = command.assert().code(1).get_output().stdout.clone();
+        let output = String::from_utf8(output)?;
+        let line = output
+            .lines()
+            .find_map(|line| line.strip_prefix("Salient difference: "))
+            .ok_or("missing salient difference")?;
+        Ok(line.split('%').next().unwrap_or_default().parse()?)
+    };
+    let (header, footer) = (salient_difference(5)?, salient_difference(95)?);
+    assert!(header > 0.2, "{}", header);
+    assert!(header > footer * 1.5, "{} / {}", header, footer);
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_ignore_differences_below_the_luminance_delta() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("src.png");
+    let tgt = temp_dir.child("tgt.png");
+    image::RgbaImage::from_pixel(20, 20, image::Rgba([100, 100, 100, 255])).save(src.path())?;
+    // Note: dithered by a hue shift of (almost) the same luminance
+    image::RgbaImage::from_pixel(20, 20, image::Rgba([110, 96, 102, 255])).save(tgt.path())?;
+
+    let compare = |delta: &str| -> Result<_, Box<dyn std::error::Error>> {
+        let mut command = Command::cargo_bin("idiff")?;
+        command
+            .arg("--src")
+            .arg(src.path())
+            .arg("--tgt")
+            .arg(tgt.path())
+            .arg("--min-luma-delta")
+            .arg(delta);
+        Ok(command.assert())
+    };
+    compare("0")?.code(1);
+    compare("2")?.code(0);
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_classify_the_differing_pixels_as_added_removed_or_changed(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("atlas.png");
+    let tgt = temp_dir.child("atlas_v2.png");
+    let mut atlas = image::RgbaImage::new(20, 20);
+    for x in 0..5 {
+        atlas.put_pixel(x, 0, image::Rgba([255, 0, 0, 255]));
+    }
+    let mut changed = atlas.clone();
+    // Note: a sprite of 3 pixels added, a pixel removed & 2 pixels recolored
+    for x in 10..13 {
+        changed.put_pixel(x, 10, image::Rgba([0, 255, 0, 255]));
+    }
+    changed.put_pixel(0, 0, image::Rgba([0, 0, 0, 0]));
+    changed.put_pixel(1, 0, image::Rgba([0, 0, 255, 255]));
+    changed.put_pixel(2, 0, image::Rgba([0, 0, 255, 255]));
+    atlas.save(src.path())?;
+    changed.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("report")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--classify-pixels");
+    command.assert().code(1).stdout(predicate::str::contains(
+        "3 pixel(s) added, 1 removed, 2 changed",
+    ));
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_compare_whole_rows_as_blocks() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let src = temp_dir.child("plot.png");
+    let tgt = temp_dir.child("plot_v2.png");
+    image::RgbaImage::new(30, 20).save(src.path())?;
+    let mut shifted = image::RgbaImage::new(30, 20);
+    for x in 0..30 {
+        shifted.put_pixel(x, 7, image::Rgba([0, 0, 0, 255]));
+    }
+    shifted.save(tgt.path())?;
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("report")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--by-row");
+    command.assert().code(1).stdout(predicate::str::contains(
+        "1 region(s) with difference (block size: 30x1):\n  width: 0..30, height: 7..8",
+    ));
+
+    let mut command = Command::cargo_bin("idiff")?;
+    command
+        .arg("--src")
+        .arg(src.path())
+        .arg("--tgt")
+        .arg(tgt.path())
+        .arg("--by-row")
+        .arg("--block")
+        .arg("5");
+    command.assert().code(2);
+
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn should_push_and_pull_baselines_by_their_content_hash() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = assert_fs::TempDir::new()?;
+    let baselines = temp_dir.child("baselines");
+    let agent = temp_dir.child("agent");
+    let store = temp_dir.child("store");
+    let manifest = temp_dir.child("baselines.toml");
+    baselines.create_dir_all()?;
+    image::RgbaImage::new(10, 10).save(baselines.child("login.png").path())?;
+    image::RgbaImage::new(20, 10).save(baselines.child("home.png").path())?;
+
+    let baseline =
+        |command: &str, dir: &std::path::Path| -> Result<_, Box<dyn std::error::Error>> {
+            let mut cmd = Command::cargo_bin("idiff")?;
+            cmd.arg("baseline")
+                .arg(command)
+                .arg("--dir")
+                .arg(dir)
+                .arg("--store")
+                .arg(store.path())
+                .arg("--manifest")
+                .arg(manifest.path());
+            Ok(cmd.assert())
+        };
+    baseline("push", baselines.path())?
+        .success()
+        .stdout(predicate::str::contains("Stored home.png ("))
+        .stdout(predicate::str::contains("2 baseline(s) recorded in"));
+    baseline("push", baselines.path())?
+        .success()
+        .stdout(predicate::str::contains("(0 newly stored)"));
+    manifest.assert(predicate::str::contains("\"login.png\" = \""));
+
+    baseline("pull", agent.path())?
+        .success()
+        .stdout(predicate::str::contains("(2 retrieved, 0 up to date)"));
+    assert_eq!(
+        std::fs::read(baselines.child("home.png").path())?,
+        std::fs::read(agent.child("home.png").path())?
+    );
+    baseline("pull", agent.path())?
+        .success()
+        .stdout(predicate::str::contains("(0 retrieved, 2 up to date)"));
+
+    temp_dir.close()?;
+    Ok(())
+}